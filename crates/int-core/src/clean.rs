@@ -0,0 +1,281 @@
+/// Cleanup of caches and leftovers
+///
+/// A scope collects files nothing needs any more: cached packages kept for
+/// rollback long after the transaction, extraction directories an
+/// interrupted install left in the temp directory, and temporary or backup
+/// copies of metadata files whose write or package is gone. `scan` finds
+/// them and `remove` deletes them.
+use crate::error::{IntError, IntResult};
+use crate::history;
+use crate::installer::InstallMetadata;
+use crate::manifest::InstallScope;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Prefix of the temporary directories packages are extracted to
+pub(crate) const EXTRACT_PREFIX: &str = "int-extract-";
+
+/// Age after which an extraction directory is taken as abandoned
+const STALE_EXTRACTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Kind of leftover found by `scan`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeftoverKind {
+    /// Cached package older than the retention period
+    CachedPackage,
+    /// Extraction directory of an interrupted install
+    Extraction,
+    /// Temporary file of an interrupted metadata write
+    TempFile,
+    /// Backup of metadata whose package is no longer installed
+    Backup,
+}
+
+/// File or directory that can be cleaned up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Leftover {
+    pub kind: LeftoverKind,
+    pub path: PathBuf,
+    /// Size in bytes (of all files, for directories)
+    pub size: u64,
+}
+
+impl fmt::Display for Leftover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            LeftoverKind::CachedPackage => "Cached package",
+            LeftoverKind::Extraction => "Abandoned extraction",
+            LeftoverKind::TempFile => "Temporary file",
+            LeftoverKind::Backup => "Stale backup",
+        };
+        write!(
+            f,
+            "{} {} ({})",
+            kind,
+            self.path.display(),
+            utils::format_bytes(self.size)
+        )
+    }
+}
+
+/// Find what can be cleaned up in a scope
+///
+/// Cached packages are kept for `retention`, except for the copy of the
+/// installed version, which is always kept.
+pub fn scan(scope: InstallScope, retention: Duration) -> IntResult<Vec<Leftover>> {
+    let metadata_dir = InstallMetadata::metadata_dir(scope);
+    let installed_version = |name: &str| {
+        InstallMetadata::load(name, scope)
+            .ok()
+            .map(|metadata| metadata.package_version)
+    };
+
+    let mut leftovers = scan_cache(
+        &history::package_cache_root(scope),
+        installed_version,
+        retention,
+    )?;
+    leftovers.extend(scan_extractions(&std::env::temp_dir(), STALE_EXTRACTION)?);
+    if let Some(state_dir) = metadata_dir.parent() {
+        leftovers.extend(scan_metadata_dir(state_dir)?);
+    }
+    leftovers.extend(scan_metadata_dir(&metadata_dir)?);
+    Ok(leftovers)
+}
+
+/// Delete a leftover found by `scan`
+pub fn remove(leftover: &Leftover) -> IntResult<()> {
+    let path = &leftover.path;
+    let result = match leftover.kind {
+        LeftoverKind::Extraction => fs::remove_dir_all(path),
+        _ => fs::remove_file(path),
+    };
+    match result {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(IntError::Custom(format!(
+                "Failed to remove {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    }
+
+    // Drop the cache directory of a package once its last copy is gone
+    if leftover.kind == LeftoverKind::CachedPackage {
+        if let Some(parent) = path.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+    }
+    Ok(())
+}
+
+/// Cached packages other than the installed version, older than `retention`
+fn scan_cache(
+    cache_root: &Path,
+    installed_version: impl Fn(&str) -> Option<String>,
+    retention: Duration,
+) -> IntResult<Vec<Leftover>> {
+    let mut leftovers = Vec::new();
+    for package_dir in read_dir(cache_root)? {
+        let Some(name) = package_dir.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let keep = installed_version(name).map(|version| format!("{}.int", version));
+
+        for path in read_dir(&package_dir)? {
+            let file_name = path.file_name().map(|name| name.to_string_lossy());
+            if file_name.as_deref() != keep.as_deref() && older_than(&path, retention) {
+                leftovers.push(leftover(LeftoverKind::CachedPackage, path));
+            }
+        }
+    }
+    Ok(leftovers)
+}
+
+/// Extraction directories in `temp_dir` untouched for `age`
+fn scan_extractions(temp_dir: &Path, age: Duration) -> IntResult<Vec<Leftover>> {
+    Ok(read_dir(temp_dir)?
+        .into_iter()
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(EXTRACT_PREFIX))
+        })
+        .filter(|path| path.is_dir() && older_than(path, age))
+        .map(|path| leftover(LeftoverKind::Extraction, path))
+        .collect())
+}
+
+/// Temporary files, and backups whose file is gone, left by `write_atomic`
+fn scan_metadata_dir(dir: &Path) -> IntResult<Vec<Leftover>> {
+    let mut leftovers = Vec::new();
+    for path in read_dir(dir)? {
+        if !path.is_file() {
+            continue;
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("tmp") => leftovers.push(leftover(LeftoverKind::TempFile, path)),
+            Some("bak") if !path.with_extension("").exists() => {
+                leftovers.push(leftover(LeftoverKind::Backup, path))
+            }
+            _ => {}
+        }
+    }
+    Ok(leftovers)
+}
+
+fn leftover(kind: LeftoverKind, path: PathBuf) -> Leftover {
+    let size = walkdir::WalkDir::new(&path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum();
+    Leftover { kind, path, size }
+}
+
+/// Whether a path was last modified at least `age` ago
+fn older_than(path: &Path, age: Duration) -> bool {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|elapsed| elapsed >= age)
+}
+
+/// Entries of a directory, none if it doesn't exist
+fn read_dir(dir: &Path) -> IntResult<Vec<PathBuf>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Ok(entries.flatten().map(|entry| entry.path()).collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(IntError::IoError(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_cache() {
+        let temp = tempfile::tempdir().unwrap();
+        for (name, version) in [("myapp", "1.0.0"), ("myapp", "1.1.0"), ("gone", "2.0.0")] {
+            let dir = temp.path().join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("{}.int", version)), "package").unwrap();
+        }
+        let installed = |name: &str| (name == "myapp").then(|| "1.1.0".to_string());
+
+        let mut paths: Vec<_> = scan_cache(temp.path(), installed, Duration::ZERO)
+            .unwrap()
+            .into_iter()
+            .map(|leftover| leftover.path)
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                temp.path().join("gone/2.0.0.int"),
+                temp.path().join("myapp/1.0.0.int"),
+            ]
+        );
+
+        // Nothing is old enough within the retention period
+        let recent = scan_cache(temp.path(), installed, Duration::from_secs(3600)).unwrap();
+        assert!(recent.is_empty());
+    }
+
+    #[test]
+    fn test_scan_leftover_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let extraction = temp.path().join(format!("{}abc", EXTRACT_PREFIX));
+        fs::create_dir_all(extraction.join("payload")).unwrap();
+        fs::write(extraction.join("payload/app"), "12345").unwrap();
+        fs::create_dir(temp.path().join("other")).unwrap();
+
+        let found = scan_extractions(temp.path(), Duration::ZERO).unwrap();
+        assert_eq!(
+            found,
+            vec![Leftover {
+                kind: LeftoverKind::Extraction,
+                path: extraction.clone(),
+                size: 5,
+            }]
+        );
+        assert!(scan_extractions(temp.path(), Duration::from_secs(3600))
+            .unwrap()
+            .is_empty());
+
+        for file in [
+            "myapp.json",
+            "myapp.json.bak",
+            "gone.json.bak",
+            "index.json.tmp",
+        ] {
+            fs::write(temp.path().join(file), "{}").unwrap();
+        }
+        let mut found = scan_metadata_dir(temp.path()).unwrap();
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+        let kinds: Vec<_> = found.iter().map(|leftover| leftover.kind).collect();
+        assert_eq!(kinds, vec![LeftoverKind::Backup, LeftoverKind::TempFile]);
+        assert_eq!(found[0].path, temp.path().join("gone.json.bak"));
+
+        remove(&found[0]).unwrap();
+        remove(&found[0]).unwrap();
+        assert!(!temp.path().join("gone.json.bak").exists());
+        remove(&Leftover {
+            kind: LeftoverKind::Extraction,
+            path: extraction.clone(),
+            size: 5,
+        })
+        .unwrap();
+        assert!(!extraction.exists());
+    }
+}
@@ -0,0 +1,282 @@
+/// Compression of package archives
+///
+/// Packages are tar archives compressed with gzip (the default), zstd or
+/// xz, or not compressed at all. Readers detect the compression from the
+/// magic bytes at the start of the file, so the archive itself records it
+/// and packages from before compression could be chosen still open.
+use crate::error::{IntError, IntResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+use tar::Archive;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    Gzip,
+    Zstd,
+    Xz,
+    None,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 4] = [
+        Compression::Gzip,
+        Compression::Zstd,
+        Compression::Xz,
+        Compression::None,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+            Compression::None => "none",
+        }
+    }
+
+    /// Detect the compression from the first bytes of an archive
+    ///
+    /// Anything without a known magic is taken to be a plain tar archive.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if header.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Accepted compression levels (higher is smaller and slower)
+    pub fn levels(&self) -> RangeInclusive<u32> {
+        match self {
+            Compression::Gzip | Compression::Xz => 0..=9,
+            Compression::Zstd => 1..=22,
+            Compression::None => 0..=0,
+        }
+    }
+
+    /// Level used when none is chosen
+    pub fn default_level(&self) -> u32 {
+        match self {
+            Compression::Gzip | Compression::Xz => 6,
+            Compression::Zstd => 3,
+            Compression::None => 0,
+        }
+    }
+
+    pub fn check_level(&self, level: u32) -> IntResult<()> {
+        if self.levels().contains(&level) {
+            return Ok(());
+        }
+        Err(IntError::Custom(format!(
+            "Invalid {} compression level {} (expected {} to {})",
+            self,
+            level,
+            self.levels().start(),
+            self.levels().end()
+        )))
+    }
+
+    /// Compress what is written to `writer`
+    pub fn encoder<W: Write>(self, writer: W, level: u32) -> IntResult<Encoder<W>> {
+        self.check_level(level)?;
+
+        Ok(match self {
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::new(level),
+            )),
+            Compression::Zstd => {
+                Encoder::Zstd(zstd::Encoder::new(writer, level as i32).map_err(IntError::IoError)?)
+            }
+            Compression::Xz => Encoder::Xz(xz2::write::XzEncoder::new(writer, level)),
+            Compression::None => Encoder::None(writer),
+        })
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Compression::ALL
+            .into_iter()
+            .find(|compression| compression.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown compression '{}' (expected gzip, zstd, xz or none)",
+                    s
+                )
+            })
+    }
+}
+
+/// Writer compressing with one of the algorithms
+pub enum Encoder<W: Write> {
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Xz(xz2::write::XzEncoder<W>),
+    None(W),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Write the end of the compressed stream and return the writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+            Encoder::Xz(encoder) => encoder.finish(),
+            Encoder::None(mut writer) => writer.flush().map(|_| writer),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            Encoder::Xz(encoder) => encoder.write(buf),
+            Encoder::None(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+            Encoder::Xz(encoder) => encoder.flush(),
+            Encoder::None(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Reader decompressing a package file
+pub enum Decoder {
+    Gzip(flate2::read::GzDecoder<File>),
+    Zstd(zstd::Decoder<'static, io::BufReader<File>>),
+    Xz(xz2::read::XzDecoder<File>),
+    None(File),
+}
+
+impl Decoder {
+    /// Open a package file, detecting its compression
+    pub fn open<P: AsRef<Path>>(path: P) -> IntResult<Self> {
+        let mut file = File::open(path.as_ref()).map_err(IntError::IoError)?;
+
+        let mut header = [0u8; 6];
+        let mut read = 0;
+        while read < header.len() {
+            match file.read(&mut header[read..]).map_err(IntError::IoError)? {
+                0 => break,
+                count => read += count,
+            }
+        }
+        file.seek(SeekFrom::Start(0)).map_err(IntError::IoError)?;
+
+        Ok(match Compression::detect(&header[..read]) {
+            Compression::Gzip => Decoder::Gzip(flate2::read::GzDecoder::new(file)),
+            Compression::Zstd => Decoder::Zstd(zstd::Decoder::new(file).map_err(|e| {
+                IntError::CorruptedArchive(format!("Failed to read archive: {}", e))
+            })?),
+            Compression::Xz => Decoder::Xz(xz2::read::XzDecoder::new(file)),
+            Compression::None => Decoder::None(file),
+        })
+    }
+
+    pub fn compression(&self) -> Compression {
+        match self {
+            Decoder::Gzip(_) => Compression::Gzip,
+            Decoder::Zstd(_) => Compression::Zstd,
+            Decoder::Xz(_) => Compression::Xz,
+            Decoder::None(_) => Compression::None,
+        }
+    }
+}
+
+impl Read for Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decoder::Gzip(decoder) => decoder.read(buf),
+            Decoder::Zstd(decoder) => decoder.read(buf),
+            Decoder::Xz(decoder) => decoder.read(buf),
+            Decoder::None(file) => file.read(buf),
+        }
+    }
+}
+
+/// Open the tar archive of a package, whatever its compression
+pub fn open_archive<P: AsRef<Path>>(path: P) -> IntResult<Archive<Decoder>> {
+    Decoder::open(path).map(Archive::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_package(path: &Path, compression: Compression) {
+        let file = File::create(path).unwrap();
+        let encoder = compression
+            .encoder(file, compression.default_level())
+            .unwrap();
+        let mut builder = tar::Builder::new(encoder);
+        let content = b"{}";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "manifest.json", &content[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        for compression in Compression::ALL {
+            let path = temp_dir.path().join(format!("{}.int", compression));
+            write_package(&path, compression);
+
+            let decoder = Decoder::open(&path).unwrap();
+            assert_eq!(decoder.compression(), compression);
+
+            let mut archive = Archive::new(decoder);
+            let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            assert_eq!(content, "{}");
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        let sink = Vec::new();
+        assert!(Compression::Gzip.encoder(sink, 10).is_err());
+        assert!(Compression::Zstd.encoder(Vec::new(), 0).is_err());
+        assert!(Compression::Zstd.encoder(Vec::new(), 19).is_ok());
+        assert_eq!("xz".parse::<Compression>().unwrap(), Compression::Xz);
+        assert!("bzip2".parse::<Compression>().is_err());
+    }
+}
@@ -0,0 +1,269 @@
+/// Cron job installation
+///
+/// Scheduled tasks that cannot run as systemd timers are installed as cron
+/// jobs: system packages get their own file in `/etc/cron.d/`, user
+/// packages a marked block in the user's crontab.
+use crate::error::{IntError, IntResult};
+use crate::manifest::{InstallScope, Manifest, ScheduledTask};
+use crate::root;
+use crate::service::{self, ServiceContext};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const CRON_D: &str = "/etc/cron.d";
+
+/// Where a package's cron jobs were installed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CronTable {
+    /// File in `/etc/cron.d/`
+    File(PathBuf),
+    /// Block in the installing user's crontab
+    UserCrontab,
+}
+
+impl CronTable {
+    /// Install cron jobs for the given tasks, replacing any installed earlier
+    pub fn install(
+        manifest: &Manifest,
+        tasks: &[&ScheduledTask],
+        context: &ServiceContext,
+    ) -> IntResult<Self> {
+        let user = match manifest.install_scope {
+            InstallScope::System => context.variables.get("USER").map(String::as_str),
+            InstallScope::User => None,
+        };
+
+        let mut lines = Vec::new();
+        for task in tasks {
+            let command = service::render_template(&task.command, context)?;
+            lines.push(cron_line(task, user, &command));
+        }
+
+        match manifest.install_scope {
+            InstallScope::System => {
+                let path = cron_d_path(&manifest.name);
+                let content = root::target_content(&file_content(&manifest.name, &lines));
+                fs::write(&path, content).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to write cron file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                Ok(CronTable::File(path))
+            }
+            // The user's crontab lives in cron's spool, not in the root
+            InstallScope::User if root::is_set() => {
+                for line in &lines {
+                    root::defer(&format!("# crontab entry of {}: {}", manifest.name, line))?;
+                }
+                Ok(CronTable::UserCrontab)
+            }
+            InstallScope::User => {
+                let crontab = read_crontab()?;
+                write_crontab(&replace_block(&crontab, &manifest.name, &lines))?;
+                Ok(CronTable::UserCrontab)
+            }
+        }
+    }
+
+    /// Remove the package's cron jobs
+    pub fn remove(&self, package_name: &str) -> IntResult<()> {
+        match self {
+            CronTable::File(path) => {
+                if path.exists() {
+                    fs::remove_file(path).map_err(|e| {
+                        IntError::Custom(format!(
+                            "Failed to remove cron file {}: {}",
+                            path.display(),
+                            e
+                        ))
+                    })?;
+                }
+                Ok(())
+            }
+            CronTable::UserCrontab if root::is_set() => {
+                root::defer(&format!("# remove the crontab entries of {}", package_name))
+            }
+            CronTable::UserCrontab => {
+                let crontab = read_crontab()?;
+                let updated = replace_block(&crontab, package_name, &[]);
+                if updated != crontab {
+                    write_crontab(&updated)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Path of the cron.d file for a package
+///
+/// cron ignores files in `/etc/cron.d/` whose names contain dots, so other
+/// characters are replaced.
+fn cron_d_path(package_name: &str) -> PathBuf {
+    let name: String = package_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    root::path(Path::new(CRON_D)).join(name)
+}
+
+/// Render one crontab line
+///
+/// `%` is escaped because cron turns it into a newline. System crontabs
+/// name the user the command runs as.
+fn cron_line(task: &ScheduledTask, user: Option<&str>, command: &str) -> String {
+    let command = command.replace('%', "\\%");
+    match user {
+        Some(user) => format!("{} {} {}", task.schedule, user, command),
+        None => format!("{} {}", task.schedule, command),
+    }
+}
+
+fn file_content(package_name: &str, lines: &[String]) -> String {
+    let mut content = format!(
+        "# Generated by int-installer for {}; removed on uninstall.\n\
+         SHELL=/bin/sh\n\
+         PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin\n",
+        package_name
+    );
+    for line in lines {
+        content.push_str(line);
+        content.push('\n');
+    }
+    content
+}
+
+/// Replace the package's block in a crontab, or remove it if `lines` is empty
+fn replace_block(crontab: &str, package_name: &str, lines: &[String]) -> String {
+    let begin = format!("# BEGIN int-installer {}", package_name);
+    let end = format!("# END int-installer {}", package_name);
+
+    let mut result = String::new();
+    let mut in_block = false;
+    for line in crontab.lines() {
+        if line == begin {
+            in_block = true;
+        } else if in_block && line == end {
+            in_block = false;
+        } else if !in_block {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+
+    if !lines.is_empty() {
+        result.push_str(&begin);
+        result.push('\n');
+        for line in lines {
+            result.push_str(line);
+            result.push('\n');
+        }
+        result.push_str(&end);
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Read the current user's crontab (empty if they have none)
+fn read_crontab() -> IntResult<String> {
+    let output = Command::new("crontab")
+        .arg("-l")
+        .output()
+        .map_err(|e| IntError::Custom(format!("Failed to run crontab: {}", e)))?;
+
+    // `crontab -l` fails with "no crontab for <user>" when there is none
+    if !output.status.success() {
+        return Ok(String::new());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Replace the current user's crontab
+fn write_crontab(content: &str) -> IntResult<()> {
+    let mut child = Command::new("crontab")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| IntError::Custom(format!("Failed to run crontab: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(content.as_bytes())
+            .map_err(|e| IntError::Custom(format!("Failed to write crontab: {}", e)))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| IntError::Custom(format!("Failed to run crontab: {}", e)))?;
+    if !output.status.success() {
+        return Err(IntError::Custom(format!(
+            "Failed to install crontab: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(schedule: &str) -> ScheduledTask {
+        ScheduledTask {
+            name: "backup".to_string(),
+            schedule: schedule.to_string(),
+            command: String::new(),
+            timer: None,
+        }
+    }
+
+    #[test]
+    fn test_cron_line() {
+        assert_eq!(
+            cron_line(&task("0 3 * * *"), Some("root"), "date +%F"),
+            "0 3 * * * root date +\\%F"
+        );
+        assert_eq!(
+            cron_line(&task("@daily"), None, "/opt/myapp/bin/myapp backup"),
+            "@daily /opt/myapp/bin/myapp backup"
+        );
+        assert_eq!(cron_d_path("my.app"), PathBuf::from("/etc/cron.d/my_app"));
+    }
+
+    #[test]
+    fn test_replace_block() {
+        let lines = vec!["@daily backup".to_string()];
+        let crontab = "MAILTO=me\n0 * * * * other\n";
+
+        let installed = replace_block(crontab, "myapp", &lines);
+        assert_eq!(
+            installed,
+            "MAILTO=me\n0 * * * * other\n\
+             # BEGIN int-installer myapp\n@daily backup\n# END int-installer myapp\n"
+        );
+
+        // Reinstalling replaces the block instead of appending another one
+        let lines = vec!["@hourly backup".to_string()];
+        let reinstalled = replace_block(&installed, "myapp", &lines);
+        assert_eq!(reinstalled.matches("# BEGIN").count(), 1);
+        assert!(reinstalled.contains("@hourly backup\n"));
+        assert!(!reinstalled.contains("@daily backup"));
+
+        assert_eq!(replace_block(&reinstalled, "myapp", &[]), crontab);
+    }
+}
@@ -0,0 +1,304 @@
+/// SQLite installed-package database
+///
+/// Optional backend for installation metadata, enabled with the `sqlite`
+/// feature. Once a scope's database exists (see `migrate_json`),
+/// `InstallMetadata` reads and writes it instead of the per-package JSON
+/// files. Besides the full metadata record, installed files, services and
+/// desktop entries get their own tables so they can be queried directly.
+use crate::error::{IntError, IntResult};
+use crate::installer::InstallMetadata;
+use crate::manifest::InstallScope;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS packages (
+        name TEXT PRIMARY KEY,
+        version TEXT NOT NULL,
+        install_date TEXT NOT NULL,
+        install_path TEXT NOT NULL,
+        metadata TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS files (
+        package TEXT NOT NULL REFERENCES packages(name) ON DELETE CASCADE,
+        path TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS files_path ON files(path);
+    CREATE TABLE IF NOT EXISTS services (
+        package TEXT NOT NULL REFERENCES packages(name) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        path TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS desktop_entries (
+        package TEXT NOT NULL REFERENCES packages(name) ON DELETE CASCADE,
+        path TEXT NOT NULL
+    );
+";
+
+/// Installed-package database of one scope
+pub struct PackageDatabase {
+    connection: Connection,
+}
+
+impl PackageDatabase {
+    /// Location of the database for a scope, next to the metadata directory
+    pub fn path(scope: InstallScope) -> PathBuf {
+        InstallMetadata::metadata_dir(scope).with_file_name("installed.db")
+    }
+
+    /// Check whether the scope uses the database
+    pub fn exists(scope: InstallScope) -> bool {
+        Self::path(scope).exists()
+    }
+
+    /// Open the database of a scope, creating it if needed
+    pub fn open(scope: InstallScope) -> IntResult<Self> {
+        let path = Self::path(scope);
+        if let Some(parent) = path.parent() {
+            crate::utils::ensure_dir(parent)?;
+        }
+        Self::from_connection(Connection::open(&path).map_err(db_error)?)
+    }
+
+    fn from_connection(connection: Connection) -> IntResult<Self> {
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(db_error)?;
+        connection.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self { connection })
+    }
+
+    /// Insert or replace the metadata of a package
+    pub fn save(&mut self, metadata: &InstallMetadata) -> IntResult<()> {
+        let json = serde_json::to_string(metadata)
+            .map_err(|e| IntError::Custom(format!("Failed to serialize metadata: {}", e)))?;
+
+        let tx = self.connection.transaction().map_err(db_error)?;
+        tx.execute(
+            "DELETE FROM packages WHERE name = ?1",
+            params![metadata.package_name],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "INSERT INTO packages (name, version, install_date, install_path, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                metadata.package_name,
+                metadata.package_version,
+                metadata.install_date,
+                metadata.install_path.to_string_lossy(),
+                json
+            ],
+        )
+        .map_err(db_error)?;
+
+        for file in metadata.owned_paths() {
+            tx.execute(
+                "INSERT INTO files (package, path) VALUES (?1, ?2)",
+                params![metadata.package_name, file.to_string_lossy()],
+            )
+            .map_err(db_error)?;
+        }
+        for unit in &metadata.service_units {
+            let kind = serde_json::to_value(unit.kind)
+                .ok()
+                .and_then(|kind| kind.as_str().map(str::to_string))
+                .unwrap_or_default();
+            tx.execute(
+                "INSERT INTO services (package, name, kind, path) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    metadata.package_name,
+                    unit.name,
+                    kind,
+                    unit.path.to_string_lossy()
+                ],
+            )
+            .map_err(db_error)?;
+        }
+        if let Some(ref desktop_entry) = metadata.desktop_entry {
+            tx.execute(
+                "INSERT INTO desktop_entries (package, path) VALUES (?1, ?2)",
+                params![metadata.package_name, desktop_entry.to_string_lossy()],
+            )
+            .map_err(db_error)?;
+        }
+
+        tx.commit().map_err(db_error)
+    }
+
+    /// Load the metadata of a package
+    pub fn load(&self, package_name: &str) -> IntResult<Option<InstallMetadata>> {
+        let json: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT metadata FROM packages WHERE name = ?1",
+                params![package_name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)?;
+
+        json.map(|json| parse_metadata(&json)).transpose()
+    }
+
+    /// Remove a package and everything recorded for it
+    pub fn remove(&mut self, package_name: &str) -> IntResult<()> {
+        self.connection
+            .execute(
+                "DELETE FROM packages WHERE name = ?1",
+                params![package_name],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Metadata of all installed packages, ordered by name
+    pub fn list(&self) -> IntResult<Vec<InstallMetadata>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT metadata FROM packages ORDER BY name")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(db_error)?;
+
+        let mut packages = Vec::new();
+        for json in rows {
+            packages.push(parse_metadata(&json.map_err(db_error)?)?);
+        }
+        Ok(packages)
+    }
+
+    /// Name of the package that installed a file
+    pub fn file_owner(&self, path: &Path) -> IntResult<Option<String>> {
+        self.connection
+            .query_row(
+                "SELECT package FROM files WHERE path = ?1",
+                params![path.to_string_lossy()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)
+    }
+}
+
+/// Import the per-package JSON files of a scope into its database
+///
+/// After a successful import the database takes over and the JSON files are
+/// removed. Returns the number of imported packages.
+pub fn migrate_json(scope: InstallScope) -> IntResult<usize> {
+    let mut db = PackageDatabase::open(scope)?;
+    migrate_dir(&InstallMetadata::metadata_dir(scope), &mut db)
+}
+
+fn migrate_dir(metadata_dir: &Path, db: &mut PackageDatabase) -> IntResult<usize> {
+    if !metadata_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(metadata_dir).map_err(IntError::IoError)? {
+        let path = entry.map_err(IntError::IoError)?.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("json") {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| IntError::MetadataCorrupted(e.to_string()))?;
+            db.save(&parse_metadata(&content)?)?;
+            files.push(path);
+        }
+    }
+
+    // Only drop the JSON files once every package made it into the database
+    for file in &files {
+        fs::remove_file(file).map_err(IntError::IoError)?;
+    }
+
+    Ok(files.len())
+}
+
+fn parse_metadata(json: &str) -> IntResult<InstallMetadata> {
+    serde_json::from_str(json).map_err(|e| IntError::MetadataCorrupted(e.to_string()))
+}
+
+fn db_error(error: rusqlite::Error) -> IntError {
+    IntError::DatabaseError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{ServicePreset, ServiceUnit, UnitKind};
+
+    fn metadata(name: &str) -> InstallMetadata {
+        serde_json::from_value(serde_json::json!({
+            "install_id": "test",
+            "package_name": name,
+            "package_version": "1.0.0",
+            "install_date": "2024-01-01T00:00:00Z",
+            "install_path": format!("/opt/{}", name),
+            "install_scope": "user",
+            "installed_files": [format!("/opt/{}/bin/{}", name, name)],
+            "desktop_entry": null,
+            "service_file": null,
+            "service_name": null,
+            "bin_symlink": null,
+        }))
+        .unwrap()
+    }
+
+    fn memory_db() -> PackageDatabase {
+        PackageDatabase::from_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_save_and_query() {
+        let mut db = memory_db();
+        let mut app = metadata("test-app");
+        app.service_units = vec![ServiceUnit {
+            name: "test-app".to_string(),
+            kind: UnitKind::Service,
+            path: PathBuf::from("/etc/systemd/system/test-app.service"),
+            preset: ServicePreset::default(),
+        }];
+        db.save(&app).unwrap();
+        db.save(&metadata("other-app")).unwrap();
+
+        // Saving again replaces the earlier record
+        app.package_version = "1.1.0".to_string();
+        db.save(&app).unwrap();
+
+        let loaded = db.load("test-app").unwrap().unwrap();
+        assert_eq!(loaded.package_version, "1.1.0");
+        assert_eq!(loaded.service_units, app.service_units);
+        assert!(db.load("missing").unwrap().is_none());
+
+        let names: Vec<_> = db
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.package_name)
+            .collect();
+        assert_eq!(names, vec!["other-app", "test-app"]);
+
+        let owned = Path::new("/opt/test-app/bin/test-app");
+        assert_eq!(db.file_owner(owned).unwrap().as_deref(), Some("test-app"));
+
+        db.remove("test-app").unwrap();
+        assert!(db.file_owner(owned).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_migrate_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let json = serde_json::to_string(&metadata("test-app")).unwrap();
+        fs::write(temp.path().join("test-app.json"), json).unwrap();
+        fs::create_dir(temp.path().join("test-app.scripts")).unwrap();
+
+        let mut db = memory_db();
+        assert_eq!(migrate_dir(temp.path(), &mut db).unwrap(), 1);
+        assert!(db.load("test-app").unwrap().is_some());
+        assert!(!temp.path().join("test-app.json").exists());
+        assert!(temp.path().join("test-app.scripts").exists());
+    }
+}
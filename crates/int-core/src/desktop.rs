@@ -3,10 +3,253 @@
 /// This module handles creation of .desktop files for application menu integration
 /// following freedesktop.org standards.
 use crate::error::{IntError, IntResult};
-use crate::manifest::Manifest;
+use crate::installer::InstallMetadata;
+use crate::manifest::{DesktopEntry, IconSet, InstallScope, LaunchCommand, Manifest, Thumbnailer};
+use crate::root;
 use crate::utils;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// GSettings schema directory, relative to a share directory
+const SCHEMA_DIR: &str = "glib-2.0/schemas";
+
+/// Main categories from the freedesktop.org menu specification
+pub const MAIN_CATEGORIES: &[&str] = &[
+    "AudioVideo",
+    "Audio",
+    "Video",
+    "Development",
+    "Education",
+    "Game",
+    "Graphics",
+    "Network",
+    "Office",
+    "Science",
+    "Settings",
+    "System",
+    "Utility",
+];
+
+/// Additional and reserved categories from the freedesktop.org menu specification
+pub const ADDITIONAL_CATEGORIES: &[&str] = &[
+    "Building",
+    "Debugger",
+    "IDE",
+    "GUIDesigner",
+    "Profiling",
+    "RevisionControl",
+    "Translation",
+    "Calendar",
+    "ContactManagement",
+    "Database",
+    "Dictionary",
+    "Chart",
+    "Email",
+    "Finance",
+    "FlowChart",
+    "PDA",
+    "ProjectManagement",
+    "Presentation",
+    "Spreadsheet",
+    "WordProcessor",
+    "2DGraphics",
+    "VectorGraphics",
+    "RasterGraphics",
+    "3DGraphics",
+    "Scanning",
+    "OCR",
+    "Photography",
+    "Publishing",
+    "Viewer",
+    "TextTools",
+    "DesktopSettings",
+    "HardwareSettings",
+    "Printing",
+    "PackageManager",
+    "Dialup",
+    "InstantMessaging",
+    "Chat",
+    "IRCClient",
+    "Feed",
+    "FileTransfer",
+    "HamRadio",
+    "News",
+    "P2P",
+    "RemoteAccess",
+    "Telephony",
+    "TelephonyTools",
+    "VideoConference",
+    "WebBrowser",
+    "WebDevelopment",
+    "Midi",
+    "Mixer",
+    "Sequencer",
+    "Tuner",
+    "TV",
+    "AudioVideoEditing",
+    "Player",
+    "Recorder",
+    "DiscBurning",
+    "ActionGame",
+    "AdventureGame",
+    "ArcadeGame",
+    "BoardGame",
+    "BlocksGame",
+    "CardGame",
+    "KidsGame",
+    "LogicGame",
+    "RolePlaying",
+    "Shooter",
+    "Simulation",
+    "SportsGame",
+    "StrategyGame",
+    "Art",
+    "Construction",
+    "Music",
+    "Languages",
+    "ArtificialIntelligence",
+    "Astronomy",
+    "Biology",
+    "Chemistry",
+    "ComputerScience",
+    "DataVisualization",
+    "Economy",
+    "Electricity",
+    "Geography",
+    "Geology",
+    "Geoscience",
+    "History",
+    "Humanities",
+    "ImageProcessing",
+    "Literature",
+    "Maps",
+    "Math",
+    "NumericalAnalysis",
+    "MedicalSoftware",
+    "Physics",
+    "Robotics",
+    "Spirituality",
+    "Sports",
+    "ParallelComputing",
+    "Amusement",
+    "Archiving",
+    "Compression",
+    "Electronics",
+    "Emulator",
+    "Engineering",
+    "FileTools",
+    "FileManager",
+    "TerminalEmulator",
+    "Filesystem",
+    "Monitor",
+    "Security",
+    "Accessibility",
+    "Calculator",
+    "Clock",
+    "TextEditor",
+    "Documentation",
+    "Adult",
+    "Core",
+    "KDE",
+    "GNOME",
+    "XFCE",
+    "DDE",
+    "GTK",
+    "Qt",
+    "Motif",
+    "Java",
+    "ConsoleOnly",
+    "Screensaver",
+    "TrayIcon",
+    "Applet",
+    "Shell",
+];
+
+/// Validate the syntax of desktop categories
+///
+/// Categories end up in a `;`-separated `Categories=` line, so they must be
+/// non-empty and limited to ASCII letters, digits and `-`. Whether a
+/// category is registered is only a warning, see [`category_warnings`].
+pub fn validate_categories(categories: &[String]) -> IntResult<()> {
+    for category in categories {
+        let well_formed = !category.is_empty()
+            && category
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+        if !well_formed {
+            return Err(IntError::ValidationError(format!(
+                "Invalid desktop category '{}': only ASCII letters, digits and '-' are allowed",
+                category
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check desktop categories against the freedesktop.org registry
+///
+/// Vendor extensions prefixed with `X-` are always accepted. Each unknown
+/// category yields a warning with the closest registered name as a
+/// suggestion.
+pub fn category_warnings(categories: &[String]) -> Vec<String> {
+    categories
+        .iter()
+        .filter(|category| !category.starts_with("X-"))
+        .filter(|category| {
+            !MAIN_CATEGORIES
+                .iter()
+                .chain(ADDITIONAL_CATEGORIES)
+                .any(|known| known == category)
+        })
+        .map(|category| {
+            let hint = suggest_category(category)
+                .map(|s| format!(" (did you mean '{}'?)", s))
+                .unwrap_or_default();
+            format!(
+                "unknown desktop category '{}'{}; use a freedesktop.org category or an X- prefix",
+                category, hint
+            )
+        })
+        .collect()
+}
+
+/// Find the registered category closest to an unknown one
+fn suggest_category(category: &str) -> Option<&'static str> {
+    let lowered = category.to_lowercase();
+
+    MAIN_CATEGORIES
+        .iter()
+        .chain(ADDITIONAL_CATEGORIES)
+        .map(|&known| (known, edit_distance(&lowered, &known.to_lowercase())))
+        .filter(|&(_, distance)| distance <= 3)
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(known, _)| known)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
 
 /// Desktop integration manager
 pub struct DesktopIntegration;
@@ -17,12 +260,22 @@ impl DesktopIntegration {
         Self
     }
 
-    /// Create a desktop entry for an application
-    pub fn create_entry(&self, manifest: &Manifest, install_path: &Path) -> IntResult<PathBuf> {
+    /// Render the desktop entry for an application without writing it
+    ///
+    /// Returns exactly the content `create_entry` would write, so packagers
+    /// can preview the entry before installing.
+    pub fn render_entry(&self, manifest: &Manifest, install_path: &Path) -> IntResult<String> {
         let desktop_config = manifest.desktop.as_ref().ok_or_else(|| {
             IntError::DesktopEntryFailed("No desktop configuration in manifest".to_string())
         })?;
 
+        entry_content(manifest, desktop_config, install_path)
+    }
+
+    /// Create a desktop entry for an application
+    pub fn create_entry(&self, manifest: &Manifest, install_path: &Path) -> IntResult<PathBuf> {
+        let content = self.render_entry(manifest, install_path)?;
+
         // Get desktop entry directory
         let desktop_dir = manifest.install_scope.desktop_entry_path();
         utils::ensure_dir(&desktop_dir)?;
@@ -31,79 +284,8 @@ impl DesktopIntegration {
         let desktop_file_name = format!("{}.desktop", manifest.name);
         let desktop_file_path = desktop_dir.join(&desktop_file_name);
 
-        // Build desktop entry content
-        let mut content = String::new();
-
-        // [Desktop Entry] section
-        content.push_str("[Desktop Entry]\n");
-        content.push_str(&format!("Name={}\n", manifest.display_name()));
-        content.push_str("Type=Application\n");
-
-        if let Some(ref desc) = manifest.description {
-            content.push_str(&format!("Comment={}\n", desc));
-        }
-
-        // Exec line
-        if let Some(ref entry) = manifest.entry {
-            let exec_path = install_path.join("bin").join(entry);
-            content.push_str(&format!("Exec={}\n", exec_path.display()));
-        } else {
-            return Err(IntError::DesktopEntryFailed(
-                "No entry point specified for desktop application".to_string(),
-            ));
-        }
-
-        // Icon
-        if let Some(ref icon) = desktop_config.icon {
-            // Check if icon is absolute path or icon name
-            if icon.starts_with('/') {
-                content.push_str(&format!("Icon={}\n", icon));
-            } else {
-                // Try to find icon in install directory
-                let icon_path = install_path.join("share/icons").join(icon);
-                if icon_path.exists() {
-                    content.push_str(&format!("Icon={}\n", icon_path.display()));
-                } else {
-                    // Use as icon name (theme icon)
-                    content.push_str(&format!("Icon={}\n", icon));
-                }
-            }
-        }
-
-        // Categories
-        if !desktop_config.categories.is_empty() {
-            content.push_str(&format!(
-                "Categories={}\n",
-                desktop_config.categories.join(";")
-            ));
-        }
-
-        // MIME types
-        if !desktop_config.mime_types.is_empty() {
-            content.push_str(&format!(
-                "MimeType={}\n",
-                desktop_config.mime_types.join(";")
-            ));
-        }
-
-        // Keywords
-        if !desktop_config.keywords.is_empty() {
-            content.push_str(&format!("Keywords={}\n", desktop_config.keywords.join(";")));
-        }
-
-        // NoDisplay
-        if !desktop_config.show_in_menu {
-            content.push_str("NoDisplay=true\n");
-        }
-
-        // Terminal
-        content.push_str("Terminal=false\n");
-
-        // Version
-        content.push_str("Version=1.0\n");
-
         // Write desktop file
-        fs::write(&desktop_file_path, content).map_err(|e| {
+        fs::write(&desktop_file_path, root::target_content(&content)).map_err(|e| {
             IntError::DesktopEntryFailed(format!(
                 "Failed to write desktop file {}: {}",
                 desktop_file_path.display(),
@@ -149,6 +331,13 @@ impl DesktopIntegration {
     fn update_database(&self, desktop_dir: &Path) -> IntResult<()> {
         use std::process::Command;
 
+        if root::is_set() {
+            return root::defer(&format!(
+                "update-desktop-database {}",
+                root::target(desktop_dir).display()
+            ));
+        }
+
         // Check if update-desktop-database exists
         let which_output = Command::new("which")
             .arg("update-desktop-database")
@@ -167,45 +356,472 @@ impl DesktopIntegration {
         Ok(())
     }
 
-    /// Install icon files
+    /// Make a desktop entry the default application for MIME types
     ///
-    /// Copies icon files to the appropriate XDG icon directory.
-    pub fn install_icons(
+    /// Returns the previous default of each type so it can be restored on
+    /// uninstall. Types that already default to this entry (on upgrade) keep
+    /// the default recorded by the earlier installation.
+    pub fn set_default_applications(
         &self,
-        source_dir: &Path,
-        _app_name: &str,
-        is_user: bool,
+        desktop_file: &Path,
+        mime_types: &[String],
+        recorded: &BTreeMap<String, Option<String>>,
+    ) -> IntResult<BTreeMap<String, Option<String>>> {
+        let desktop_id = desktop_id(desktop_file)?;
+
+        let mut previous = BTreeMap::new();
+        for mime_type in mime_types {
+            let current = query_default(mime_type);
+            let before = if current.as_deref() == Some(desktop_id) {
+                recorded.get(mime_type).cloned().flatten()
+            } else {
+                current
+            };
+            previous.insert(mime_type.clone(), before);
+        }
+
+        run_xdg_mime(desktop_id, mime_types.iter().map(String::as_str))?;
+
+        Ok(previous)
+    }
+
+    /// Restore the defaults replaced by `set_default_applications`
+    ///
+    /// Types whose default was changed since installation are left alone.
+    pub fn restore_default_applications(
+        &self,
+        desktop_file: &Path,
+        previous: &BTreeMap<String, Option<String>>,
     ) -> IntResult<()> {
-        let icon_base = if is_user {
-            let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
-            PathBuf::from(home).join(".local/share/icons")
+        let desktop_id = desktop_id(desktop_file)?;
+
+        for (mime_type, before) in previous {
+            if query_default(mime_type).as_deref() != Some(desktop_id) {
+                continue;
+            }
+            if let Some(before) = before {
+                run_xdg_mime(before, [mime_type.as_str()])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Install shared-mime-info definitions for custom MIME types
+    ///
+    /// Each XML file is copied from the installed payload to the scope's
+    /// `mime/packages` directory under its own name, then the MIME database
+    /// is rebuilt.
+    pub fn install_mime_definitions(
+        &self,
+        files: &[PathBuf],
+        install_path: &Path,
+        scope: InstallScope,
+    ) -> IntResult<Vec<PathBuf>> {
+        if files.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mime_dir = scope.mime_path();
+        let packages_dir = mime_dir.join("packages");
+        utils::ensure_dir(&packages_dir)?;
+
+        let mut installed = Vec::new();
+        for file in files {
+            let source = install_path.join(file);
+            // Validation guarantees a file name is present
+            let target = packages_dir.join(file.file_name().unwrap_or_default());
+            fs::copy(&source, &target).map_err(|e| IntError::FileCopyFailed {
+                source: source.display().to_string(),
+                dest: target.display().to_string(),
+                reason: e.to_string(),
+            })?;
+            installed.push(target);
+        }
+
+        self.update_mime_database(&mime_dir)?;
+
+        Ok(installed)
+    }
+
+    /// Remove installed MIME definitions and rebuild the MIME database
+    pub fn remove_mime_definitions(&self, files: &[PathBuf]) -> IntResult<()> {
+        for file in files {
+            if file.exists() {
+                fs::remove_file(file).map_err(|e| {
+                    IntError::DesktopEntryFailed(format!(
+                        "Failed to remove MIME definition {}: {}",
+                        file.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+
+        // Definitions live in <mime dir>/packages
+        let mime_dir = files
+            .first()
+            .and_then(|f| f.parent())
+            .and_then(|p| p.parent());
+        if let Some(mime_dir) = mime_dir {
+            self.update_mime_database(mime_dir)?;
+        }
+
+        Ok(())
+    }
+
+    /// Update the shared MIME database
+    ///
+    /// This runs `update-mime-database` so new types are recognized.
+    fn update_mime_database(&self, mime_dir: &Path) -> IntResult<()> {
+        use std::process::Command;
+
+        if root::is_set() {
+            return root::defer(&format!(
+                "update-mime-database {}",
+                root::target(mime_dir).display()
+            ));
+        }
+
+        let which_output = Command::new("which").arg("update-mime-database").output();
+
+        if let Ok(output) = which_output {
+            if output.status.success() {
+                let _ = Command::new("update-mime-database").arg(mime_dir).output();
+                // Ignore errors - this is optional
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Install D-Bus activation files for the package's bus names
+    ///
+    /// The session bus picks up new and removed files on its own. Returns the
+    /// installed files.
+    pub fn install_dbus_services(
+        &self,
+        manifest: &Manifest,
+        install_path: &Path,
+    ) -> IntResult<Vec<PathBuf>> {
+        if manifest.dbus_services.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let services_dir = manifest.install_scope.dbus_services_path();
+        utils::ensure_dir(&services_dir)?;
+
+        let mut installed = Vec::new();
+        for service in &manifest.dbus_services {
+            let command = service
+                .exec
+                .clone()
+                .or_else(|| manifest.launch_command())
+                .ok_or_else(|| {
+                    IntError::DesktopEntryFailed(format!(
+                        "No command to activate D-Bus service {}",
+                        service.name
+                    ))
+                })?;
+
+            let mut content = format!(
+                "[D-BUS Service]\nName={}\nExec={}\n",
+                service.name,
+                dbus_exec_line(&command, install_path)
+            );
+            if let Some(ref unit) = service.systemd_service {
+                content.push_str(&format!("SystemdService={}\n", unit));
+            }
+
+            let path = services_dir.join(format!("{}.service", service.name));
+            fs::write(&path, root::target_content(&content)).map_err(|e| {
+                IntError::DesktopEntryFailed(format!(
+                    "Failed to write D-Bus service file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            installed.push(path);
+        }
+
+        Ok(installed)
+    }
+
+    /// Register the application's thumbnailer
+    ///
+    /// Writes `thumbnailers/<name>.thumbnailer` under the scope's shared data
+    /// directory, where file managers look for thumbnail generators.
+    pub fn install_thumbnailer(
+        &self,
+        manifest: &Manifest,
+        thumbnailer: &Thumbnailer,
+        install_path: &Path,
+    ) -> IntResult<PathBuf> {
+        let thumbnailers_dir = manifest.install_scope.share_path().join("thumbnailers");
+        utils::ensure_dir(&thumbnailers_dir)?;
+
+        let mime_types = match thumbnailer.mime_types.is_empty() {
+            false => &thumbnailer.mime_types,
+            true => manifest
+                .desktop
+                .as_ref()
+                .map(|desktop| &desktop.mime_types)
+                .unwrap_or(&thumbnailer.mime_types),
+        };
+
+        let path = thumbnailers_dir.join(format!("{}.thumbnailer", manifest.name));
+        let content = thumbnailer_content(thumbnailer, mime_types, install_path);
+        fs::write(&path, root::target_content(&content)).map_err(|e| {
+            IntError::DesktopEntryFailed(format!(
+                "Failed to write thumbnailer {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Ok(path)
+    }
+
+    /// Install GSettings schemas and compile them
+    ///
+    /// Without declared schemas, the `*.gschema.xml` and `*.gschema.override`
+    /// files in the payload's `share/glib-2.0/schemas` are installed. Returns
+    /// the installed files.
+    pub fn install_schemas(
+        &self,
+        declared: &[PathBuf],
+        install_path: &Path,
+        scope: InstallScope,
+    ) -> IntResult<Vec<PathBuf>> {
+        let sources = if declared.is_empty() {
+            schema_files(&install_path.join(SCHEMA_DIR))?
         } else {
-            PathBuf::from("/usr/share/icons")
+            declared
+                .iter()
+                .map(|file| install_path.join(file))
+                .collect()
         };
+        if sources.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let schema_dir = scope.share_path().join(SCHEMA_DIR);
+        utils::ensure_dir(&schema_dir)?;
+
+        let mut installed = Vec::new();
+        for source in sources {
+            // Validation guarantees a file name is present
+            let target = schema_dir.join(source.file_name().unwrap_or_default());
+            fs::copy(&source, &target).map_err(|e| IntError::FileCopyFailed {
+                source: source.display().to_string(),
+                dest: target.display().to_string(),
+                reason: e.to_string(),
+            })?;
+            installed.push(target);
+        }
+
+        self.compile_schemas(&schema_dir)?;
+
+        Ok(installed)
+    }
+
+    /// Remove installed GSettings schemas and recompile the rest
+    pub fn remove_schemas(&self, files: &[PathBuf]) -> IntResult<()> {
+        for file in files {
+            if file.exists() {
+                fs::remove_file(file).map_err(|e| {
+                    IntError::DesktopEntryFailed(format!(
+                        "Failed to remove GSettings schema {}: {}",
+                        file.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+
+        if let Some(schema_dir) = files.first().and_then(|f| f.parent()) {
+            // glib-compile-schemas refuses to run on a directory without
+            // schemas, which would leave the old compiled file behind
+            if schema_files(schema_dir)?.is_empty() {
+                let _ = fs::remove_file(schema_dir.join("gschemas.compiled"));
+            } else {
+                self.compile_schemas(schema_dir)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compile the GSettings schemas of a directory
+    ///
+    /// This runs `glib-compile-schemas`, which applications need to find
+    /// their settings.
+    fn compile_schemas(&self, schema_dir: &Path) -> IntResult<()> {
+        if root::is_set() {
+            return root::defer(&format!(
+                "glib-compile-schemas {}",
+                root::target(schema_dir).display()
+            ));
+        }
+
+        let which_output = Command::new("which").arg("glib-compile-schemas").output();
 
-        // Common icon sizes
-        let sizes = ["16x16", "32x32", "48x48", "64x64", "128x128", "256x256"];
-
-        for size in &sizes {
-            let source_icon_dir = source_dir.join("hicolor").join(size).join("apps");
-            if source_icon_dir.exists() {
-                let target_icon_dir = icon_base.join("hicolor").join(size).join("apps");
-                utils::ensure_dir(&target_icon_dir)?;
-
-                // Copy all icon files
-                for entry in fs::read_dir(&source_icon_dir).map_err(IntError::IoError)? {
-                    let entry = entry.map_err(IntError::IoError)?;
-                    let source = entry.path();
-                    if source.is_file() {
-                        let target = target_icon_dir.join(entry.file_name());
-                        fs::copy(&source, &target).map_err(IntError::IoError)?;
+        if let Ok(output) = which_output {
+            if output.status.success() {
+                let _ = Command::new("glib-compile-schemas")
+                    .arg(schema_dir)
+                    .output();
+                // Ignore errors - this is optional
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Install icon files into the hicolor icon theme
+    ///
+    /// A theme tree is copied as-is; icons declared per size are installed
+    /// as `<size>/apps/<icon_name>.<ext>`. Returns the installed files.
+    pub fn install_icons(
+        &self,
+        icons: &IconSet,
+        icon_name: &str,
+        install_path: &Path,
+        scope: InstallScope,
+    ) -> IntResult<Vec<PathBuf>> {
+        let theme_dir = scope.share_path().join("icons/hicolor");
+        self.install_icons_into(icons, icon_name, install_path, &theme_dir)
+    }
+
+    fn install_icons_into(
+        &self,
+        icons: &IconSet,
+        icon_name: &str,
+        install_path: &Path,
+        theme_dir: &Path,
+    ) -> IntResult<Vec<PathBuf>> {
+        let mut copies = Vec::new();
+
+        match icons {
+            IconSet::Tree(tree) => {
+                let tree = install_path.join(tree);
+                for size in read_dirs(&tree)? {
+                    for context in read_dirs(&size)? {
+                        for entry in fs::read_dir(&context).map_err(IntError::IoError)? {
+                            let source = entry.map_err(IntError::IoError)?.path();
+                            if source.is_file() {
+                                // Both levels come from read_dir, so they have names
+                                let target = theme_dir
+                                    .join(size.file_name().unwrap_or_default())
+                                    .join(context.file_name().unwrap_or_default())
+                                    .join(source.file_name().unwrap_or_default());
+                                copies.push((source, target));
+                            }
+                        }
                     }
                 }
             }
+            IconSet::Sizes(files) => {
+                for (size, file) in files {
+                    let mut file_name = icon_name.to_string();
+                    if let Some(extension) = file.extension() {
+                        file_name.push('.');
+                        file_name.push_str(&extension.to_string_lossy());
+                    }
+                    let target = theme_dir.join(size).join("apps").join(file_name);
+                    copies.push((install_path.join(file), target));
+                }
+            }
+        }
+
+        let mut installed = Vec::new();
+        for (source, target) in copies {
+            if let Some(parent) = target.parent() {
+                utils::ensure_dir(parent)?;
+            }
+            fs::copy(&source, &target).map_err(|e| IntError::FileCopyFailed {
+                source: source.display().to_string(),
+                dest: target.display().to_string(),
+                reason: e.to_string(),
+            })?;
+            installed.push(target);
         }
 
         // Update icon cache
-        self.update_icon_cache(&icon_base)?;
+        self.update_icon_cache(theme_dir)?;
+
+        Ok(installed)
+    }
+
+    /// Remove installed icon files and refresh the icon cache
+    pub fn remove_icons(&self, files: &[PathBuf]) -> IntResult<()> {
+        for file in files {
+            if file.exists() {
+                fs::remove_file(file).map_err(|e| {
+                    IntError::DesktopEntryFailed(format!(
+                        "Failed to remove icon {}: {}",
+                        file.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+
+        // Icons live in <theme>/<size>/<context>
+        let theme_dir = files
+            .first()
+            .and_then(|f| f.ancestors().nth(3))
+            .map(Path::to_path_buf);
+        if let Some(theme_dir) = theme_dir {
+            self.update_icon_cache(&theme_dir)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reverse every desktop-side change recorded in the metadata
+    ///
+    /// Restores replaced default applications, then removes the desktop
+    /// entry, icons, thumbnailer, D-Bus activation files, GSettings schemas
+    /// and MIME definitions, refreshing the caches each of them feeds.
+    pub fn remove_all(&self, metadata: &InstallMetadata) -> IntResult<()> {
+        if let Some(ref desktop_entry) = metadata.desktop_entry {
+            // Hand MIME types back to their previous default applications
+            if !metadata.mime_defaults.is_empty() {
+                let _ = self.restore_default_applications(desktop_entry, &metadata.mime_defaults);
+            }
+            self.remove_entry(desktop_entry)?;
+        }
+
+        if !metadata.icons.is_empty() {
+            self.remove_icons(&metadata.icons)?;
+        }
+
+        let files = metadata.thumbnailer.iter().chain(&metadata.dbus_services);
+        for file in files {
+            if file.exists() {
+                fs::remove_file(file).map_err(|e| {
+                    IntError::DesktopEntryFailed(format!(
+                        "Failed to remove {}: {}",
+                        file.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+
+        if !metadata.gschemas.is_empty() {
+            self.remove_schemas(&metadata.gschemas)?;
+        }
+
+        if !metadata.mime_definitions.is_empty() {
+            self.remove_mime_definitions(&metadata.mime_definitions)?;
+            // The desktop database caches which applications handle each type
+            let desktop_dir = metadata.install_scope.desktop_entry_path();
+            if desktop_dir.exists() {
+                self.update_database(&desktop_dir)?;
+            }
+        }
 
         Ok(())
     }
@@ -214,6 +830,13 @@ impl DesktopIntegration {
     fn update_icon_cache(&self, icon_dir: &Path) -> IntResult<()> {
         use std::process::Command;
 
+        if root::is_set() {
+            return root::defer(&format!(
+                "gtk-update-icon-cache -f -t {}",
+                root::target(icon_dir).display()
+            ));
+        }
+
         let which_output = Command::new("which").arg("gtk-update-icon-cache").output();
 
         if let Ok(output) = which_output {
@@ -236,45 +859,704 @@ impl Default for DesktopIntegration {
     }
 }
 
+/// Build the contents of an application's desktop file
+fn entry_content(
+    manifest: &Manifest,
+    desktop_config: &DesktopEntry,
+    install_path: &Path,
+) -> IntResult<String> {
+    let mut content = String::new();
+
+    // [Desktop Entry] section
+    content.push_str("[Desktop Entry]\n");
+    content.push_str(&format!("Name={}\n", manifest.display_name()));
+    for (locale, strings) in &manifest.localized {
+        if let Some(ref name) = strings.display_name {
+            content.push_str(&format!("Name[{}]={}\n", locale, name));
+        }
+    }
+    if let Some(ref generic_name) = desktop_config.generic_name {
+        content.push_str(&format!("GenericName={}\n", generic_name));
+    }
+    content.push_str("Type=Application\n");
+
+    if let Some(ref desc) = manifest.description {
+        content.push_str(&format!("Comment={}\n", desc));
+    }
+    for (locale, strings) in &manifest.localized {
+        if let Some(ref desc) = strings.description {
+            content.push_str(&format!("Comment[{}]={}\n", locale, desc));
+        }
+    }
+
+    // Exec line
+    let command = manifest.launch_command().ok_or_else(|| {
+        IntError::DesktopEntryFailed("No entry point specified for desktop application".to_string())
+    })?;
+    content.push_str(&format!("Exec={}\n", exec_line(&command, install_path)));
+
+    if command.working_dir().is_some() {
+        content.push_str(&format!(
+            "Path={}\n",
+            command.resolve_working_dir(install_path).display()
+        ));
+    }
+
+    // Icon
+    if let Some(ref icon) = desktop_config.icon {
+        content.push_str(&format!("Icon={}\n", icon_value(icon, install_path)));
+    }
+
+    // Categories
+    if !desktop_config.categories.is_empty() {
+        content.push_str(&format!(
+            "Categories={}\n",
+            desktop_config.categories.join(";")
+        ));
+    }
+
+    // MIME types
+    if !desktop_config.mime_types.is_empty() {
+        content.push_str(&format!(
+            "MimeType={}\n",
+            desktop_config.mime_types.join(";")
+        ));
+    }
+
+    // Keywords
+    if !desktop_config.keywords.is_empty() {
+        content.push_str(&format!("Keywords={}\n", desktop_config.keywords.join(";")));
+    }
+
+    // NoDisplay
+    if !desktop_config.show_in_menu {
+        content.push_str("NoDisplay=true\n");
+    }
+
+    // Terminal
+    content.push_str(&format!("Terminal={}\n", desktop_config.terminal));
+
+    // Startup notification and window grouping
+    if let Some(startup_notify) = desktop_config.startup_notify {
+        content.push_str(&format!("StartupNotify={}\n", startup_notify));
+    }
+    if let Some(ref wm_class) = desktop_config.startup_wm_class {
+        content.push_str(&format!("StartupWMClass={}\n", wm_class));
+    }
+
+    // Version
+    content.push_str("Version=1.0\n");
+
+    // Actions
+    if !desktop_config.actions.is_empty() {
+        let ids: Vec<&str> = desktop_config
+            .actions
+            .iter()
+            .map(|a| a.id.as_str())
+            .collect();
+        content.push_str(&format!("Actions={};\n", ids.join(";")));
+    }
+    for action in &desktop_config.actions {
+        content.push_str(&format!("\n[Desktop Action {}]\n", action.id));
+        content.push_str(&format!("Name={}\n", action.name));
+        content.push_str(&format!("Exec={}\n", exec_line(&action.exec, install_path)));
+        if let Some(ref icon) = action.icon {
+            content.push_str(&format!("Icon={}\n", icon_value(icon, install_path)));
+        }
+    }
+
+    Ok(content)
+}
+
+/// Resolve an Icon value
+///
+/// Absolute paths are used as-is; other values refer to a file under
+/// `share/icons` of the installation if one exists, or to a theme icon.
+fn icon_value(icon: &str, install_path: &Path) -> String {
+    if icon.starts_with('/') {
+        return icon.to_string();
+    }
+
+    let icon_path = install_path.join("share/icons").join(icon);
+    if icon_path.exists() {
+        icon_path.display().to_string()
+    } else {
+        icon.to_string()
+    }
+}
+
+/// Theme name under which per-size icons are installed
+///
+/// This is the desktop entry's `icon` when it names a theme icon, so the
+/// entry finds the installed files, and the package name otherwise.
+pub fn icon_name(manifest: &Manifest) -> String {
+    manifest
+        .desktop
+        .as_ref()
+        .and_then(|desktop| desktop.icon.as_deref())
+        .filter(|icon| !icon.starts_with('/'))
+        .map(|icon| {
+            Path::new(icon)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| icon.to_string())
+        })
+        .unwrap_or_else(|| manifest.name.clone())
+}
+
+/// List the GSettings schema and override files in a directory
+fn schema_files(dir: &Path) -> IntResult<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(IntError::IoError)? {
+        let path = entry.map_err(IntError::IoError)?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.ends_with(".gschema.xml") || name.ends_with(".gschema.override") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// List the subdirectories of a directory
+fn read_dirs(dir: &Path) -> IntResult<Vec<PathBuf>> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        IntError::InvalidPackage(format!(
+            "Cannot read icon directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(IntError::IoError)?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Desktop file ID (file name) used by `xdg-mime`
+fn desktop_id(desktop_file: &Path) -> IntResult<&str> {
+    desktop_file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            IntError::DesktopEntryFailed(format!(
+                "Invalid desktop file path: {}",
+                desktop_file.display()
+            ))
+        })
+}
+
+/// Query the default application of a MIME type
+fn query_default(mime_type: &str) -> Option<String> {
+    // No desktop session runs inside an alternate root to ask
+    if root::is_set() {
+        return None;
+    }
+
+    let output = Command::new("xdg-mime")
+        .args(["query", "default", mime_type])
+        .output()
+        .ok()?;
+    let default = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    (output.status.success() && !default.is_empty()).then_some(default)
+}
+
+/// Set the default application for MIME types with `xdg-mime default`
+fn run_xdg_mime<'a>(
+    desktop_id: &str,
+    mime_types: impl IntoIterator<Item = &'a str>,
+) -> IntResult<()> {
+    if root::is_set() {
+        let mime_types: Vec<&str> = mime_types.into_iter().collect();
+        return root::defer(&format!(
+            "xdg-mime default {} {}",
+            desktop_id,
+            mime_types.join(" ")
+        ));
+    }
+
+    let output = Command::new("xdg-mime")
+        .arg("default")
+        .arg(desktop_id)
+        .args(mime_types)
+        .output()
+        .map_err(|e| IntError::DesktopEntryFailed(format!("Failed to run xdg-mime: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(IntError::DesktopEntryFailed(format!(
+            "xdg-mime default failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Build the Exec value for a launch command
+///
+/// Environment variables are set through `env`, as Exec has no syntax for
+/// them.
+fn exec_line(command: &LaunchCommand, install_path: &Path) -> String {
+    let program = command.resolve_program(install_path);
+    let mut exec = env_prefix(command, escape_exec_arg);
+    exec.push_str(&escape_exec_arg(&program.to_string_lossy()));
+    for arg in command.args() {
+        exec.push(' ');
+        exec.push_str(&escape_exec_arg(arg));
+    }
+    exec
+}
+
+/// `env NAME=value ... ` for the environment of a command, quoted by `quote`
+fn env_prefix(command: &LaunchCommand, quote: impl Fn(&str) -> String) -> String {
+    if command.env().is_empty() {
+        return String::new();
+    }
+    let mut prefix = String::from("env ");
+    for (key, value) in command.env() {
+        prefix.push_str(&quote(&format!("{}={}", key, value)));
+        prefix.push(' ');
+    }
+    prefix
+}
+
+/// Build the contents of a `.thumbnailer` file
+///
+/// Arguments are written as-is so field codes like `%o` reach the file
+/// manager; only the program path is quoted if needed.
+fn thumbnailer_content(
+    thumbnailer: &Thumbnailer,
+    mime_types: &[String],
+    install_path: &Path,
+) -> String {
+    let program = thumbnailer.exec.resolve_program(install_path);
+    let program = escape_exec_arg(&program.to_string_lossy());
+
+    let mut exec = env_prefix(&thumbnailer.exec, escape_exec_arg);
+    exec.push_str(&program);
+    for arg in thumbnailer.exec.args() {
+        exec.push(' ');
+        exec.push_str(arg);
+    }
+
+    format!(
+        "[Thumbnailer Entry]\nTryExec={}\nExec={}\nMimeType={};\n",
+        program,
+        exec,
+        mime_types.join(";")
+    )
+}
+
+/// Build the Exec value of a D-Bus service file
+///
+/// D-Bus splits the value like a shell, so arguments with spaces or quotes
+/// are single-quoted.
+fn dbus_exec_line(command: &LaunchCommand, install_path: &Path) -> String {
+    let program = command.resolve_program(install_path);
+    let quote = |arg: &str| {
+        if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "'\"\\".contains(c)) {
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        } else {
+            arg.to_string()
+        }
+    };
+    let mut exec = env_prefix(command, quote);
+    exec.push_str(
+        &std::iter::once(program.to_string_lossy().into_owned())
+            .chain(command.args().iter().cloned())
+            .map(|arg| quote(&arg))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    exec
+}
+
+/// Escape an argument for the Exec key following the Desktop Entry spec
+///
+/// Arguments containing reserved characters are double-quoted, `%` is
+/// doubled so it is not taken as a field code, and the result is escaped
+/// once more as a desktop string value.
+fn escape_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(',
+        ')', '`',
+    ];
+
+    let arg = arg.replace('%', "%%");
+
+    let quoted = if arg.is_empty() || arg.contains(RESERVED) {
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    } else {
+        arg
+    };
+
+    quoted.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::manifest::{DesktopEntry, InstallScope};
+    use crate::manifest::{DesktopAction, LocalizedStrings};
+    use std::collections::BTreeMap;
 
     fn create_test_manifest() -> Manifest {
         Manifest {
             version: "1.0".to_string(),
             name: "test-app".to_string(),
+            kind: Default::default(),
             display_name: Some("Test Application".to_string()),
             package_version: "1.0.0".to_string(),
             description: Some("A test application".to_string()),
+            localized: BTreeMap::new(),
             author: None,
             install_scope: InstallScope::User,
             install_path: PathBuf::from("/tmp/test-app"),
             entry: Some("test-app".to_string()),
+            entries: vec![],
             service: false,
             service_name: None,
+            hardening: false,
+            schedules: vec![],
+            service_preset: None,
+            units: vec![],
+            tmpfiles: vec![],
+            environment: BTreeMap::new(),
+            variables: BTreeMap::new(),
+            health_check: None,
             post_install: None,
             pre_uninstall: None,
+            scripts: Default::default(),
+            docs: Default::default(),
+            completions: Default::default(),
+            man_pages: vec![],
+            library: None,
+            plugin: None,
+            gschemas: vec![],
+            dbus_services: vec![],
             desktop: Some(DesktopEntry {
                 categories: vec!["Development".to_string()],
                 mime_types: vec![],
+                mime_definitions: vec![],
                 icon: Some("test-app".to_string()),
+                icons: None,
                 show_in_menu: true,
                 keywords: vec!["test".to_string()],
+                set_default_for_mime_types: false,
+                thumbnailer: None,
+                generic_name: None,
+                terminal: false,
+                startup_wm_class: None,
+                startup_notify: None,
+                actions: vec![],
             }),
             dependencies: vec![],
             required_space: None,
             architecture: None,
             license: None,
             homepage: None,
+            update_url: None,
             auto_launch: false,
             launch_command: None,
+            changelog: None,
+            changelog_file: None,
+            eula: None,
+            data_dirs: vec![],
+            cache_dirs: vec![],
+            log_dirs: vec![],
             signature: None,
             file_hashes: None,
+            compression: None,
         }
     }
 
+    #[test]
+    fn test_category_validation() {
+        let valid = vec![
+            "Development".to_string(),
+            "IDE".to_string(),
+            "X-Custom".to_string(),
+        ];
+        assert!(validate_categories(&valid).is_ok());
+        assert!(category_warnings(&valid).is_empty());
+
+        // Unknown categories are only warnings
+        let typo = vec!["Developement".to_string()];
+        assert!(validate_categories(&typo).is_ok());
+        let warnings = category_warnings(&typo);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("did you mean 'Development'"));
+
+        let nonsense = vec!["Nonsense".to_string()];
+        assert!(validate_categories(&nonsense).is_ok());
+        assert_eq!(category_warnings(&nonsense).len(), 1);
+
+        // Malformed categories are still rejected
+        assert!(validate_categories(&["Game;Utility".to_string()]).is_err());
+        assert!(validate_categories(&["".to_string()]).is_err());
+        assert!(validate_categories(&["Audio Video".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_exec_line_escaping() {
+        let command = LaunchCommand::Structured {
+            program: "test-app".to_string(),
+            args: vec![
+                "--open".to_string(),
+                "my file".to_string(),
+                "100%".to_string(),
+            ],
+            working_dir: None,
+            env: BTreeMap::new(),
+        };
+        assert_eq!(
+            exec_line(&command, Path::new("/opt/test-app")),
+            r#"/opt/test-app/bin/test-app --open "my file" 100%%"#
+        );
+
+        let command = LaunchCommand::Structured {
+            program: "test-app".to_string(),
+            args: vec![],
+            working_dir: None,
+            env: BTreeMap::from([("GREETING".to_string(), "hello world".to_string())]),
+        };
+        assert_eq!(
+            exec_line(&command, Path::new("/opt/test-app")),
+            r#"env "GREETING=hello world" /opt/test-app/bin/test-app"#
+        );
+        assert_eq!(
+            dbus_exec_line(&command, Path::new("/opt/test-app")),
+            "env 'GREETING=hello world' /opt/test-app/bin/test-app"
+        );
+
+        assert_eq!(escape_exec_arg(r#"say "hi""#), r#""say \\"hi\\"""#);
+        assert_eq!(escape_exec_arg("$HOME"), r#""\\$HOME""#);
+    }
+
+    #[test]
+    fn test_optional_entry_fields() {
+        let manifest = create_test_manifest();
+        let mut desktop = manifest.desktop.clone().unwrap();
+        let install_path = Path::new("/opt/test-app");
+
+        let content = entry_content(&manifest, &desktop, install_path).unwrap();
+        assert!(content.contains("\nTerminal=false\n"));
+        assert!(!content.contains("GenericName="));
+        assert!(!content.contains("StartupWMClass="));
+
+        desktop.generic_name = Some("Test Tool".to_string());
+        desktop.terminal = true;
+        desktop.startup_wm_class = Some("TestApp".to_string());
+        desktop.startup_notify = Some(false);
+        let content = entry_content(&manifest, &desktop, install_path).unwrap();
+        assert!(content.contains("\nName=Test Application\nGenericName=Test Tool\n"));
+        assert!(content.contains("\nTerminal=true\n"));
+        assert!(content.contains("\nStartupNotify=false\n"));
+        assert!(content.contains("\nStartupWMClass=TestApp\n"));
+    }
+
+    #[test]
+    fn test_render_entry() {
+        let mut manifest = create_test_manifest();
+        let integration = DesktopIntegration::new();
+        let install_path = Path::new("/opt/test-app");
+
+        let content = integration.render_entry(&manifest, install_path).unwrap();
+        assert!(content.starts_with("[Desktop Entry]\n"));
+        assert!(content.contains("\nName=Test Application\n"));
+
+        manifest.desktop = None;
+        assert!(integration.render_entry(&manifest, install_path).is_err());
+    }
+
+    #[test]
+    fn test_localized_entry() {
+        let mut manifest = create_test_manifest();
+        manifest.localized.insert(
+            "de".to_string(),
+            LocalizedStrings {
+                display_name: Some("Test-Anwendung".to_string()),
+                description: None,
+            },
+        );
+        manifest.localized.insert(
+            "fr".to_string(),
+            LocalizedStrings {
+                display_name: None,
+                description: Some("Une application de test".to_string()),
+            },
+        );
+        let desktop = manifest.desktop.clone().unwrap();
+
+        let content = entry_content(&manifest, &desktop, Path::new("/opt/test-app")).unwrap();
+        assert!(content.contains("Name=Test Application\nName[de]=Test-Anwendung\n"));
+        assert!(!content.contains("Comment[de]"));
+        assert!(
+            content.contains("Comment=A test application\nComment[fr]=Une application de test\n")
+        );
+    }
+
+    #[test]
+    fn test_dbus_exec_line() {
+        let command = LaunchCommand::Structured {
+            program: "test-app".to_string(),
+            args: vec!["--gapplication-service".to_string(), "it's".to_string()],
+            working_dir: None,
+            env: BTreeMap::new(),
+        };
+        assert_eq!(
+            dbus_exec_line(&command, Path::new("/opt/test app")),
+            r"'/opt/test app/bin/test-app' --gapplication-service 'it'\''s'"
+        );
+    }
+
+    #[test]
+    fn test_thumbnailer_content() {
+        let thumbnailer = Thumbnailer {
+            exec: LaunchCommand::Structured {
+                program: "test-app-thumbnailer".to_string(),
+                args: vec![
+                    "-s".to_string(),
+                    "%s".to_string(),
+                    "%u".to_string(),
+                    "%o".to_string(),
+                ],
+                working_dir: None,
+                env: BTreeMap::new(),
+            },
+            mime_types: vec![],
+        };
+        let mime_types = vec!["application/x-test".to_string(), "image/x-test".to_string()];
+
+        assert_eq!(
+            thumbnailer_content(&thumbnailer, &mime_types, Path::new("/opt/test-app")),
+            "[Thumbnailer Entry]\n\
+             TryExec=/opt/test-app/bin/test-app-thumbnailer\n\
+             Exec=/opt/test-app/bin/test-app-thumbnailer -s %s %u %o\n\
+             MimeType=application/x-test;image/x-test;\n"
+        );
+    }
+
+    #[test]
+    fn test_desktop_actions() {
+        let mut manifest = create_test_manifest();
+        let mut desktop = manifest.desktop.take().unwrap();
+        desktop.actions = vec![DesktopAction {
+            id: "new-window".to_string(),
+            name: "New Window".to_string(),
+            exec: LaunchCommand::Structured {
+                program: "test-app".to_string(),
+                args: vec!["--new-window".to_string()],
+                working_dir: None,
+                env: BTreeMap::new(),
+            },
+            icon: None,
+        }];
+
+        let content = entry_content(&manifest, &desktop, Path::new("/opt/test-app")).unwrap();
+        assert!(content.contains("\nActions=new-window;\n"));
+        assert!(content.ends_with(
+            "\n[Desktop Action new-window]\n\
+             Name=New Window\n\
+             Exec=/opt/test-app/bin/test-app --new-window\n"
+        ));
+    }
+
+    #[test]
+    fn test_install_icons() {
+        let temp = tempfile::tempdir().unwrap();
+        let install_path = temp.path().join("opt/test-app");
+        let tree = install_path.join("share/icons/hicolor/48x48/apps");
+        fs::create_dir_all(&tree).unwrap();
+        fs::write(tree.join("test-app.png"), "png").unwrap();
+        fs::write(install_path.join("logo.svg"), "svg").unwrap();
+
+        let integration = DesktopIntegration::new();
+        let theme = temp.path().join("icons/hicolor");
+
+        let icons = IconSet::Tree(PathBuf::from("share/icons/hicolor"));
+        let installed = integration
+            .install_icons_into(&icons, "test-app", &install_path, &theme)
+            .unwrap();
+        assert_eq!(installed, vec![theme.join("48x48/apps/test-app.png")]);
+
+        let icons = IconSet::Sizes([("scalable".to_string(), PathBuf::from("logo.svg"))].into());
+        let installed = integration
+            .install_icons_into(&icons, "test-app", &install_path, &theme)
+            .unwrap();
+        assert_eq!(installed, vec![theme.join("scalable/apps/test-app.svg")]);
+
+        integration.remove_icons(&installed).unwrap();
+        assert!(!installed[0].exists());
+        assert_eq!(icon_name(&create_test_manifest()), "test-app");
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let temp = tempfile::tempdir().unwrap();
+        let icon = temp.path().join("icons/hicolor/48x48/apps/test-app.png");
+        let thumbnailer = temp.path().join("thumbnailers/test-app.thumbnailer");
+        let dbus_service = temp.path().join("dbus-1/services/test-app.service");
+        for file in [&icon, &thumbnailer, &dbus_service] {
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, "").unwrap();
+        }
+
+        let mut metadata: InstallMetadata = serde_json::from_value(serde_json::json!({
+            "install_id": "test",
+            "package_name": "test-app",
+            "package_version": "1.0.0",
+            "install_date": "2024-01-01T00:00:00Z",
+            "install_path": temp.path().join("opt/test-app"),
+            "install_scope": "user",
+            "installed_files": [],
+            "desktop_entry": null,
+            "service_file": null,
+            "service_name": null,
+            "bin_symlink": null,
+        }))
+        .unwrap();
+        metadata.icons = vec![icon.clone()];
+        metadata.thumbnailer = Some(thumbnailer.clone());
+        metadata.dbus_services = vec![dbus_service.clone()];
+
+        DesktopIntegration::new().remove_all(&metadata).unwrap();
+        assert!(!icon.exists());
+        assert!(!thumbnailer.exists());
+        assert!(!dbus_service.exists());
+    }
+
+    #[test]
+    fn test_schema_files() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("org.example.App.gschema.xml"), "").unwrap();
+        fs::write(temp.path().join("10_org.example.App.gschema.override"), "").unwrap();
+        fs::write(temp.path().join("gschemas.compiled"), "").unwrap();
+
+        let files = schema_files(temp.path()).unwrap();
+        assert_eq!(files.len(), 2);
+        let missing = schema_files(&temp.path().join("missing")).unwrap();
+        assert!(missing.is_empty());
+    }
+
     #[test]
     fn test_desktop_entry_creation() {
         use tempfile::TempDir;
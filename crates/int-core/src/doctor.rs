@@ -0,0 +1,629 @@
+/// Detection and repair of orphaned installation state
+///
+/// Installations can drift from their metadata: install directories deleted
+/// by hand, files removed behind the installer's back, or integration files
+/// left behind by a half-finished uninstall. `scan` reports such problems for
+/// a scope and `repair` cleans up the ones that can be fixed without
+/// reinstalling.
+///
+/// `diagnose` looks at the host as well: the init system, the tools
+/// installs rely on, and whether the scope's directories are usable.
+use crate::error::{IntError, IntResult};
+use crate::installer::InstallMetadata;
+use crate::manifest::InstallScope;
+use crate::platform;
+use crate::service::{InitSystem, ServiceManager};
+use crate::Uninstaller;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Problem found in the installation state of a scope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// Metadata file that cannot be read
+    CorruptedMetadata { path: PathBuf, error: String },
+    /// Package whose install directory no longer exists
+    OrphanedPackage {
+        package: String,
+        install_path: PathBuf,
+    },
+    /// Installed files missing from an otherwise intact installation
+    MissingFiles {
+        package: String,
+        files: Vec<PathBuf>,
+    },
+    /// Symlink whose target no longer exists
+    DanglingSymlink {
+        package: Option<String>,
+        path: PathBuf,
+    },
+    /// Unit file of a package that is gone or no longer installed
+    LeftoverUnit {
+        package: Option<String>,
+        path: PathBuf,
+    },
+    /// Desktop entry of a package that is gone or no longer installed
+    LeftoverDesktopEntry {
+        package: Option<String>,
+        path: PathBuf,
+    },
+}
+
+impl Issue {
+    /// Whether `repair` can fix the issue
+    ///
+    /// Missing files can only be restored by reinstalling the package.
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Issue::MissingFiles { .. })
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let owner = |package: &Option<String>| match package {
+            Some(package) => format!(" of {}", package),
+            None => String::new(),
+        };
+
+        match self {
+            Issue::CorruptedMetadata { path, error } => {
+                write!(f, "Corrupted metadata {}: {}", path.display(), error)
+            }
+            Issue::OrphanedPackage {
+                package,
+                install_path,
+            } => write!(
+                f,
+                "{} is registered but {} no longer exists",
+                package,
+                install_path.display()
+            ),
+            Issue::MissingFiles { package, files } => {
+                write!(
+                    f,
+                    "{} is missing {} installed file(s)",
+                    package,
+                    files.len()
+                )
+            }
+            Issue::DanglingSymlink { package, path } => {
+                write!(f, "Dangling symlink{} {}", owner(package), path.display())
+            }
+            Issue::LeftoverUnit { package, path } => {
+                write!(f, "Leftover unit{} {}", owner(package), path.display())
+            }
+            Issue::LeftoverDesktopEntry { package, path } => {
+                write!(
+                    f,
+                    "Leftover desktop entry{} {}",
+                    owner(package),
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+/// Outcome of a host check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    /// Some packages or features won't work
+    Warning,
+    /// Installing in the scope will fail
+    Error,
+}
+
+/// Result of one check made by `diagnose`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Check {
+    /// What was checked
+    pub name: String,
+    pub status: CheckStatus,
+    /// What was found
+    pub detail: String,
+    /// How to fix a failed check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn failed(
+        name: &str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Tools installs rely on, with what is missing without them
+const TOOLS: &[(&str, &str, &str)] = &[
+    (
+        "gpg",
+        "package signatures cannot be verified",
+        "install GnuPG (e.g. the gnupg package)",
+    ),
+    (
+        "update-desktop-database",
+        "new desktop entries may not show up in menus until the next login",
+        "install desktop-file-utils",
+    ),
+];
+
+/// Check whether the host can install packages in a scope
+///
+/// Covers the init system, the tools installs rely on, the metadata
+/// directory, PATH and the installation state found by `scan`.
+pub fn diagnose(scope: InstallScope) -> IntResult<Vec<Check>> {
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    let mut checks = Vec::new();
+
+    let init_system = platform::init_system();
+    checks.push(match init_system {
+        Some(init_system) => Check::ok("Init system", init_system.to_string()),
+        None => Check::failed(
+            "Init system",
+            CheckStatus::Warning,
+            "none running, packages with services can't be installed",
+            "install on a system booted with systemd, OpenRC or runit",
+        ),
+    });
+    if init_system == Some(InitSystem::Systemd) {
+        checks.push(tool_check(
+            "systemctl",
+            "services cannot be managed",
+            "install systemd's command line tools",
+            &path_var,
+        ));
+    }
+    for (tool, missing, fix) in TOOLS {
+        checks.push(tool_check(tool, missing, fix, &path_var));
+    }
+
+    checks.push(metadata_dir_check(scope));
+    checks.push(path_check(&scope.bin_path(), &path_var));
+
+    let issues = scan(scope)?;
+    if issues.is_empty() {
+        checks.push(Check::ok("Installed packages", "no problems found"));
+    }
+    for issue in issues {
+        let fix = if issue.is_repairable() {
+            "run `int-engine repair`".to_string()
+        } else {
+            "reinstall the package".to_string()
+        };
+        checks.push(Check::failed(
+            "Installed packages",
+            CheckStatus::Warning,
+            issue.to_string(),
+            fix,
+        ));
+    }
+
+    Ok(checks)
+}
+
+fn tool_check(tool: &str, missing: &str, fix: &str, path_var: &OsStr) -> Check {
+    match find_in_path(tool, path_var) {
+        Some(path) => Check::ok(tool, path.display().to_string()),
+        None => Check::failed(
+            tool,
+            CheckStatus::Warning,
+            format!("not found in PATH, {}", missing),
+            fix,
+        ),
+    }
+}
+
+/// Whether the scope's metadata can be written
+fn metadata_dir_check(scope: InstallScope) -> Check {
+    const NAME: &str = "Metadata directory";
+    let dir = InstallMetadata::metadata_dir(scope);
+    // The directory is created on the first install
+    let Some(existing) = dir.ancestors().find(|dir| dir.exists()) else {
+        return Check::ok(NAME, dir.display().to_string());
+    };
+
+    match tempfile::tempfile_in(existing) {
+        Ok(_) => Check::ok(NAME, dir.display().to_string()),
+        Err(e) => {
+            let fix = match scope {
+                InstallScope::System => "run as root, or install with --scope user".to_string(),
+                InstallScope::User => format!("make {} writable by you", existing.display()),
+            };
+            Check::failed(
+                NAME,
+                CheckStatus::Error,
+                format!("{} is not writable: {}", existing.display(), e),
+                fix,
+            )
+        }
+    }
+}
+
+/// Whether commands linked into the scope's bin directory can be run by name
+fn path_check(bin_dir: &Path, path_var: &OsStr) -> Check {
+    const NAME: &str = "PATH";
+    if std::env::split_paths(path_var).any(|dir| dir == bin_dir) {
+        return Check::ok(NAME, format!("contains {}", bin_dir.display()));
+    }
+    Check::failed(
+        NAME,
+        CheckStatus::Warning,
+        format!(
+            "{} is missing, installed commands can't be run by name",
+            bin_dir.display()
+        ),
+        format!(
+            "add `export PATH=\"{}:$PATH\"` to your shell profile",
+            bin_dir.display()
+        ),
+    )
+}
+
+/// Look up an executable in a PATH value
+fn find_in_path(name: &str, path_var: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(path_var)
+        .map(|dir| dir.join(name))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Scan the installation state of a scope
+pub fn scan(scope: InstallScope) -> IntResult<Vec<Issue>> {
+    let mut issues = Vec::new();
+    let packages = load_packages(scope, &mut issues)?;
+
+    for metadata in &packages {
+        check_package(metadata, &mut issues);
+    }
+
+    // Files nobody owns any more that point into the scope's install root
+    let owned: HashSet<&Path> = packages.iter().flat_map(|m| m.owned_paths()).collect();
+    let root = scope.default_install_path("");
+    for path in unowned_files(&scope.bin_path(), &owned) {
+        if let Ok(target) = fs::read_link(&path) {
+            if !path.exists() && target.starts_with(&root) {
+                issues.push(Issue::DanglingSymlink {
+                    package: None,
+                    path,
+                });
+            }
+        }
+    }
+    for path in unowned_files(&scope.desktop_entry_path(), &owned) {
+        if path.extension().and_then(|s| s.to_str()) == Some("desktop")
+            && points_to_missing(&path, "Exec=", &root)
+        {
+            issues.push(Issue::LeftoverDesktopEntry {
+                package: None,
+                path,
+            });
+        }
+    }
+    for path in unowned_files(&scope.systemd_service_path(), &owned) {
+        if points_to_missing(&path, "ExecStart=", &root) {
+            issues.push(Issue::LeftoverUnit {
+                package: None,
+                path,
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Check the installation state of one package
+pub fn check(metadata: &InstallMetadata) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    check_package(metadata, &mut issues);
+    issues
+}
+
+/// Fix an issue found by `scan`
+///
+/// Orphaned packages are uninstalled without running their maintainer
+/// scripts, which would fail with the install directory gone. Corrupted
+/// metadata is renamed aside so the remaining packages can be listed again.
+pub fn repair(issue: &Issue, scope: InstallScope) -> IntResult<()> {
+    match issue {
+        Issue::CorruptedMetadata { path, .. } => {
+            let mut aside = path.clone().into_os_string();
+            aside.push(".corrupt");
+            fs::rename(path, &aside).map_err(|e| {
+                IntError::Custom(format!(
+                    "Failed to move corrupted metadata {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+        }
+        Issue::OrphanedPackage { package, .. } => {
+            Uninstaller::new().skip_scripts().uninstall(package, scope)
+        }
+        Issue::MissingFiles { package, .. } => Err(IntError::Custom(format!(
+            "Missing files of {} can only be restored by reinstalling it",
+            package
+        ))),
+        Issue::LeftoverUnit { path, .. } => {
+            if !path.exists() {
+                return Ok(());
+            }
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            ServiceManager::new().unregister(path, &name, scope)
+        }
+        Issue::DanglingSymlink { path, .. } | Issue::LeftoverDesktopEntry { path, .. } => {
+            if path.symlink_metadata().is_err() {
+                return Ok(());
+            }
+            fs::remove_file(path).map_err(|e| {
+                IntError::Custom(format!("Failed to remove {}: {}", path.display(), e))
+            })
+        }
+    }
+}
+
+/// Load the metadata of all packages, recording files that fail to parse
+fn load_packages(scope: InstallScope, issues: &mut Vec<Issue>) -> IntResult<Vec<InstallMetadata>> {
+    #[cfg(feature = "sqlite")]
+    if crate::db::PackageDatabase::exists(scope) {
+        return InstallMetadata::list(scope);
+    }
+
+    let metadata_dir = InstallMetadata::metadata_dir(scope);
+    if !metadata_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut packages = Vec::new();
+    for entry in fs::read_dir(&metadata_dir).map_err(IntError::IoError)? {
+        let path = entry.map_err(IntError::IoError)?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+
+        match InstallMetadata::read_file(&path) {
+            Ok(metadata) => packages.push(metadata),
+            Err(error) => issues.push(Issue::CorruptedMetadata {
+                path,
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    Ok(packages)
+}
+
+/// Check one package against the files on disk
+fn check_package(metadata: &InstallMetadata, issues: &mut Vec<Issue>) {
+    let package = Some(metadata.package_name.clone());
+    let symlinks = metadata
+        .bin_symlink
+        .iter()
+        .chain(&metadata.bin_symlinks)
+        .chain(&metadata.plugin_link);
+
+    if !metadata.install_path.exists() {
+        issues.push(Issue::OrphanedPackage {
+            package: metadata.package_name.clone(),
+            install_path: metadata.install_path.clone(),
+        });
+
+        let units = metadata
+            .service_units
+            .iter()
+            .map(|unit| &unit.path)
+            .chain(&metadata.service_file);
+        for path in units.filter(|path| path.exists()) {
+            issues.push(Issue::LeftoverUnit {
+                package: package.clone(),
+                path: path.clone(),
+            });
+        }
+        for path in metadata.desktop_entry.iter().filter(|path| path.exists()) {
+            issues.push(Issue::LeftoverDesktopEntry {
+                package: package.clone(),
+                path: path.clone(),
+            });
+        }
+    } else {
+        let files: Vec<_> = metadata
+            .installed_files
+            .iter()
+            .filter(|file| file.symlink_metadata().is_err())
+            .cloned()
+            .collect();
+        if !files.is_empty() {
+            issues.push(Issue::MissingFiles {
+                package: metadata.package_name.clone(),
+                files,
+            });
+        }
+    }
+
+    for path in symlinks {
+        if path.symlink_metadata().is_ok() && !path.exists() {
+            issues.push(Issue::DanglingSymlink {
+                package: package.clone(),
+                path: path.clone(),
+            });
+        }
+    }
+}
+
+/// Files in a directory not owned by any installed package
+fn unowned_files(dir: &Path, owned: &HashSet<&Path>) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| !path.is_dir() && !owned.contains(path.as_path()))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Whether the program a desktop entry or unit file runs is missing from
+/// the install root
+fn points_to_missing(path: &Path, key: &str, root: &Path) -> bool {
+    let Ok(content) = fs::read_to_string(path) else {
+        return false;
+    };
+
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(key))
+        .filter_map(|value| value.split_whitespace().next())
+        // systemd prefixes such as `-` or `@` modify how the command runs
+        .map(|program| program.trim_start_matches(['-', '@', '+', '!', ':']))
+        .map(|program| Path::new(program.trim_matches('"')))
+        .any(|program| program.starts_with(root) && !program.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(install_path: &Path, files: &[PathBuf]) -> InstallMetadata {
+        serde_json::from_value(serde_json::json!({
+            "install_id": "test",
+            "package_name": "test-app",
+            "package_version": "1.0.0",
+            "install_date": "2024-01-01T00:00:00Z",
+            "install_path": install_path,
+            "install_scope": "user",
+            "installed_files": files,
+            "desktop_entry": null,
+            "service_file": null,
+            "service_name": null,
+            "bin_symlink": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_package() {
+        let temp = tempfile::tempdir().unwrap();
+        let install_path = temp.path().join("test-app");
+        let binary = install_path.join("bin/test-app");
+        fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        fs::write(&binary, "").unwrap();
+        let mut metadata = metadata(&install_path, std::slice::from_ref(&binary));
+
+        // Healthy installation
+        let mut issues = Vec::new();
+        check_package(&metadata, &mut issues);
+        assert!(issues.is_empty());
+
+        // Missing payload and a symlink to it
+        let link = temp.path().join("test-app-link");
+        std::os::unix::fs::symlink(&binary, &link).unwrap();
+        metadata.bin_symlinks = vec![link.clone()];
+        fs::remove_file(&binary).unwrap();
+        check_package(&metadata, &mut issues);
+        assert_eq!(
+            issues,
+            vec![
+                Issue::MissingFiles {
+                    package: "test-app".to_string(),
+                    files: vec![binary.clone()],
+                },
+                Issue::DanglingSymlink {
+                    package: Some("test-app".to_string()),
+                    path: link.clone(),
+                },
+            ]
+        );
+        assert!(!issues[0].is_repairable());
+
+        // Install directory removed by hand
+        fs::remove_dir_all(&install_path).unwrap();
+        let desktop = temp.path().join("test-app.desktop");
+        fs::write(
+            &desktop,
+            format!("[Desktop Entry]\nExec={}\n", binary.display()),
+        )
+        .unwrap();
+        metadata.desktop_entry = Some(desktop.clone());
+        issues.clear();
+        check_package(&metadata, &mut issues);
+        assert_eq!(
+            issues[0],
+            Issue::OrphanedPackage {
+                package: "test-app".to_string(),
+                install_path,
+            }
+        );
+        assert!(issues.contains(&Issue::LeftoverDesktopEntry {
+            package: Some("test-app".to_string()),
+            path: desktop.clone(),
+        }));
+
+        assert!(points_to_missing(&desktop, "Exec=", temp.path()));
+        assert!(!points_to_missing(&desktop, "Exec=", Path::new("/opt")));
+        for issue in &issues[1..] {
+            repair(issue, InstallScope::User).unwrap();
+        }
+        assert!(!desktop.exists());
+        assert!(link.symlink_metadata().is_err());
+    }
+
+    #[test]
+    fn test_host_checks() {
+        let temp = tempfile::tempdir().unwrap();
+        let bin = temp.path().join("bin");
+        fs::create_dir(&bin).unwrap();
+        let gpg = bin.join("gpg");
+        fs::write(&gpg, "").unwrap();
+        let path_var = std::env::join_paths([Path::new("/nonexistent"), &bin]).unwrap();
+
+        // Only executables count
+        assert_eq!(find_in_path("gpg", &path_var), None);
+        crate::utils::make_executable(&gpg).unwrap();
+        assert_eq!(find_in_path("gpg", &path_var), Some(gpg));
+
+        assert_eq!(path_check(&bin, &path_var).status, CheckStatus::Ok);
+        let missing = path_check(&temp.path().join(".local/bin"), &path_var);
+        assert_eq!(missing.status, CheckStatus::Warning);
+        assert!(missing.fix.unwrap().contains(".local/bin:$PATH"));
+    }
+}
@@ -35,6 +35,9 @@ pub enum IntError {
     /// Installation directory creation failed
     DirectoryCreationFailed(String),
 
+    /// Required dependency is not available on the system
+    MissingDependency(String),
+
     /// File copy operation failed
     FileCopyFailed {
         source: String,
@@ -52,6 +55,14 @@ pub enum IntError {
     /// MIME type registration failed
     MimeRegistrationFailed(String),
 
+    /// The package needs an init system that is not running here
+    InitSystemUnavailable {
+        /// What the package needs (e.g. "systemd")
+        required: String,
+        /// Init system detected on this machine, if any
+        found: Option<String>,
+    },
+
     // ===== Security Errors =====
     /// Path traversal attempt detected
     PathTraversalAttempt(PathBuf),
@@ -79,6 +90,22 @@ pub enum IntError {
     /// systemd interaction error
     SystemdError(String),
 
+    /// Service failed to become healthy after start
+    ServiceUnhealthy {
+        /// Service name
+        service: String,
+        /// Why the service is considered unhealthy
+        reason: String,
+    },
+
+    /// Error returned by the service manager over D-Bus
+    ServiceBusError {
+        /// D-Bus error name (e.g. `org.freedesktop.systemd1.NoSuchUnit`)
+        name: String,
+        /// Error message from the service manager
+        message: String,
+    },
+
     /// Permission setting error
     PermissionError(String),
 
@@ -102,6 +129,33 @@ pub enum IntError {
     /// Installation metadata corrupted
     MetadataCorrupted(String),
 
+    /// Installed-package database could not be read or written
+    DatabaseError(String),
+
+    /// Another installation or uninstallation holds the scope's lock
+    OperationInProgress(PathBuf),
+
+    /// Package is held at its installed version
+    PackageHeld {
+        /// Package name
+        package: String,
+        /// Version the package is held at
+        version: String,
+    },
+
+    /// Package is older than the installed version
+    DowngradeRefused {
+        /// Package name
+        package: String,
+        /// Installed version
+        installed: String,
+        /// Version of the package being installed
+        version: String,
+    },
+
+    /// Package declares a license agreement that was not accepted
+    EulaNotAccepted(String),
+
     // ===== Generic Errors =====
     /// Generic error with custom message
     Custom(String),
@@ -164,6 +218,7 @@ impl fmt::Display for IntError {
             IntError::DirectoryCreationFailed(s) => {
                 write!(f, "Failed to create installation directory: {}", s)
             }
+            IntError::MissingDependency(s) => write!(f, "Missing required dependency: {}", s),
             IntError::FileCopyFailed {
                 source,
                 dest,
@@ -181,6 +236,18 @@ impl fmt::Display for IntError {
             }
             IntError::DesktopEntryFailed(s) => write!(f, "Failed to create desktop entry: {}", s),
             IntError::MimeRegistrationFailed(s) => write!(f, "Failed to register MIME type: {}", s),
+            IntError::InitSystemUnavailable { required, found } => match found {
+                Some(found) => write!(
+                    f,
+                    "This package requires {}, but this system uses {}",
+                    required, found
+                ),
+                None => write!(
+                    f,
+                    "This package requires {}, but no supported init system was detected",
+                    required
+                ),
+            },
 
             IntError::PathTraversalAttempt(p) => {
                 write!(f, "Path traversal attempt detected: {}", p.display())
@@ -200,6 +267,12 @@ impl fmt::Display for IntError {
 
             IntError::IoError(e) => write!(f, "I/O error: {}", e),
             IntError::SystemdError(s) => write!(f, "systemd error: {}", s),
+            IntError::ServiceUnhealthy { service, reason } => {
+                write!(f, "Service {} is not healthy: {}", service, reason)
+            }
+            IntError::ServiceBusError { name, message } => {
+                write!(f, "Service manager error ({}): {}", name, message)
+            }
             IntError::PermissionError(s) => write!(f, "Failed to set permissions: {}", s),
             IntError::UserLookupError(s) => write!(f, "Failed to lookup user/group: {}", s),
 
@@ -219,6 +292,31 @@ impl fmt::Display for IntError {
 
             IntError::PackageNotInstalled(s) => write!(f, "Package not installed: {}", s),
             IntError::MetadataCorrupted(s) => write!(f, "Installation metadata corrupted: {}", s),
+            IntError::DatabaseError(s) => write!(f, "Package database error: {}", s),
+            IntError::OperationInProgress(path) => write!(
+                f,
+                "Another installation or uninstallation is in progress (lock: {})",
+                path.display()
+            ),
+            IntError::PackageHeld { package, version } => write!(
+                f,
+                "{} is held at version {} (use unhold to allow other versions)",
+                package, version
+            ),
+            IntError::DowngradeRefused {
+                package,
+                installed,
+                version,
+            } => write!(
+                f,
+                "Refusing to downgrade {} from {} to {} (use --allow-downgrade to override)",
+                package, installed, version
+            ),
+            IntError::EulaNotAccepted(package) => write!(
+                f,
+                "License agreement of {} was not accepted (use --accept-eula to accept it)",
+                package
+            ),
 
             IntError::Custom(s) => write!(f, "{}", s),
             IntError::Unexpected(s) => write!(f, "Unexpected error: {}", s),
@@ -289,6 +387,7 @@ impl IntError {
         matches!(
             self,
             IntError::TargetPathExists(_)
+                | IntError::OperationInProgress(_)
                 | IntError::ScriptExecutionFailed { .. }
                 | IntError::ValidationError(_)
         )
@@ -296,10 +395,75 @@ impl IntError {
 
     /// Check if error requires elevated permissions
     pub fn requires_elevation(&self) -> bool {
-        matches!(
-            self,
-            IntError::InsufficientPermissions(_) | IntError::PermissionError(_)
-        )
+        match self {
+            IntError::InsufficientPermissions(_) | IntError::PermissionError(_) => true,
+            IntError::ServiceBusError { name, .. } => {
+                name.ends_with("AccessDenied") || name.ends_with("InteractiveAuthorizationRequired")
+            }
+            _ => false,
+        }
+    }
+
+    /// Stable numeric code of the error's class
+    ///
+    /// Used as the process exit code by the CLI, so scripts can tell
+    /// failures apart. Codes stay the same across releases; errors without a
+    /// more specific class use 1.
+    pub fn code(&self) -> i32 {
+        match self {
+            IntError::InvalidPackage(_)
+            | IntError::ManifestParseError(_)
+            | IntError::CorruptedArchive(_)
+            | IntError::MissingField(_)
+            | IntError::ValidationError(_)
+            | IntError::UnsupportedVersion { .. }
+            | IntError::InvalidScope(_) => 10,
+            IntError::PathTraversalAttempt(_)
+            | IntError::InvalidSignature(_)
+            | IntError::UntrustedPublisher(_)
+            | IntError::InvalidScript(_) => 11,
+            _ if self.requires_elevation() => 12,
+            IntError::DiskSpaceInsufficient { .. } => 13,
+            IntError::TargetPathExists(_)
+            | IntError::OperationInProgress(_)
+            | IntError::PackageHeld { .. }
+            | IntError::DowngradeRefused { .. } => 14,
+            IntError::PackageNotInstalled(_) => 15,
+            IntError::MissingDependency(_) | IntError::InitSystemUnavailable { .. } => 16,
+            IntError::ScriptExecutionFailed { .. } | IntError::ScriptTimeout(_) => 17,
+            IntError::ServiceRegistrationFailed(_)
+            | IntError::SystemdError(_)
+            | IntError::ServiceUnhealthy { .. }
+            | IntError::ServiceBusError { .. } => 18,
+            IntError::MetadataCorrupted(_) | IntError::DatabaseError(_) => 19,
+            IntError::DirectoryCreationFailed(_)
+            | IntError::FileCopyFailed { .. }
+            | IntError::DesktopEntryFailed(_)
+            | IntError::MimeRegistrationFailed(_)
+            | IntError::UserLookupError(_)
+            | IntError::IoError(_) => 20,
+            IntError::EulaNotAccepted(_) => 21,
+            _ => 1,
+        }
+    }
+
+    /// Machine-readable name of the error's class, matching `code`
+    pub fn class(&self) -> &'static str {
+        match self.code() {
+            10 => "invalid_package",
+            11 => "security",
+            12 => "permission",
+            13 => "disk_space",
+            14 => "conflict",
+            15 => "not_installed",
+            16 => "dependency",
+            17 => "script",
+            18 => "service",
+            19 => "metadata",
+            20 => "filesystem",
+            21 => "license",
+            _ => "error",
+        }
     }
 
     /// Get user-friendly error message
@@ -330,9 +494,61 @@ impl IntError {
                     available / 1_000_000
                 )
             }
+            IntError::MissingDependency(name) => {
+                format!(
+                    "Dependensi yang dibutuhkan tidak ditemukan: {}. Install terlebih dahulu.",
+                    name
+                )
+            }
             IntError::ServiceRegistrationFailed(_) => {
                 "Gagal mendaftarkan service. Periksa konfigurasi systemd.".to_string()
             }
+            IntError::InitSystemUnavailable { required, .. } => {
+                format!(
+                    "Package ini membutuhkan {}, yang tidak tersedia di sistem ini.",
+                    required
+                )
+            }
+            IntError::ServiceUnhealthy { service, .. } => {
+                format!(
+                    "Service {} gagal berjalan setelah dijalankan. Periksa log service tersebut.",
+                    service
+                )
+            }
+            IntError::ServiceBusError { name, .. } if name.ends_with("AccessDenied") => {
+                "Tidak memiliki izin untuk mengelola service. Jalankan dengan hak akses root."
+                    .to_string()
+            }
+            IntError::DatabaseError(_) => {
+                "Database package terpasang tidak dapat dibaca. Periksa izin akses berkasnya."
+                    .to_string()
+            }
+            IntError::OperationInProgress(_) => {
+                "Proses instalasi lain sedang berjalan. Tunggu hingga selesai lalu coba lagi."
+                    .to_string()
+            }
+            IntError::PackageHeld { package, version } => {
+                format!(
+                    "{} ditahan pada versi {}. Jalankan unhold terlebih dahulu untuk memasang versi lain.",
+                    package, version
+                )
+            }
+            IntError::DowngradeRefused {
+                package,
+                installed,
+                version,
+            } => {
+                format!(
+                    "Versi {} yang terpasang ({}) lebih baru dari package ini ({}). Gunakan --allow-downgrade untuk tetap memasang.",
+                    package, installed, version
+                )
+            }
+            IntError::EulaNotAccepted(package) => {
+                format!(
+                    "Perjanjian lisensi {} belum disetujui. Baca dan setujui lisensinya untuk melanjutkan instalasi.",
+                    package
+                )
+            }
             IntError::PathTraversalAttempt(_) => {
                 "Package mengandung path berbahaya. Instalasi dibatalkan untuk keamanan."
                     .to_string()
@@ -370,5 +586,23 @@ mod tests {
         let msg = err.user_message();
         assert!(msg.contains("Ruang disk tidak cukup"));
     }
+
+    #[test]
+    fn test_error_code() {
+        let err = IntError::PackageNotInstalled("test".to_string());
+        assert_eq!((err.code(), err.class()), (15, "not_installed"));
+
+        // Permission problems reported by the service manager
+        let err = IntError::ServiceBusError {
+            name: "org.freedesktop.DBus.Error.AccessDenied".to_string(),
+            message: "denied".to_string(),
+        };
+        assert_eq!(err.code(), 12);
+
+        let err = IntError::EulaNotAccepted("test".to_string());
+        assert_eq!((err.code(), err.class()), (21, "license"));
+
+        assert_eq!(IntError::custom("test").code(), 1);
+    }
 }
 // ...existing code...
@@ -0,0 +1,205 @@
+/// Export and import of the installed-package set
+///
+/// The installed packages of one or more scopes can be written to a JSON or
+/// TOML file (chosen by the file extension) and installed again from it,
+/// e.g. to provision another machine or restore a backup.
+use crate::error::{IntError, IntResult};
+use crate::history;
+use crate::installer::{InstallConfig, InstallMetadata, Installer};
+use crate::manifest::InstallScope;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Installed package in an exported set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedPackage {
+    /// Package name
+    pub name: String,
+    /// Installed version
+    pub version: String,
+    /// Scope the package is installed in
+    pub scope: InstallScope,
+    /// Package file it was installed from, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
+}
+
+/// Set of installed packages
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageSet {
+    #[serde(default)]
+    pub packages: Vec<ExportedPackage>,
+}
+
+/// Outcome of installing a package set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Packages that were installed
+    pub installed: Vec<String>,
+    /// Packages already installed at the exported version
+    pub skipped: Vec<String>,
+    /// Packages held at another version
+    #[serde(default)]
+    pub held: Vec<String>,
+    /// Packages that could not be installed, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl PackageSet {
+    /// Collect the packages installed in the given scopes
+    pub fn from_installed(scopes: &[InstallScope]) -> IntResult<Self> {
+        let mut packages = Vec::new();
+        for &scope in scopes {
+            for metadata in InstallMetadata::list(scope)? {
+                packages.push(ExportedPackage {
+                    name: metadata.package_name,
+                    version: metadata.package_version,
+                    scope,
+                    source: metadata.source,
+                });
+            }
+        }
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self { packages })
+    }
+
+    /// Read a package set from a `.json` or `.toml` file
+    pub fn load(path: &Path) -> IntResult<Self> {
+        let content = fs::read_to_string(path).map_err(IntError::IoError)?;
+        let parse_error = |e: String| {
+            IntError::Custom(format!(
+                "Failed to parse package set {}: {}",
+                path.display(),
+                e
+            ))
+        };
+
+        if is_toml(path) {
+            toml::from_str(&content).map_err(|e| parse_error(e.to_string()))
+        } else {
+            serde_json::from_str(&content).map_err(|e| parse_error(e.to_string()))
+        }
+    }
+
+    /// Write the package set to a `.json` or `.toml` file
+    pub fn save(&self, path: &Path) -> IntResult<()> {
+        let content = if is_toml(path) {
+            toml::to_string_pretty(self).map_err(|e| e.to_string())
+        } else {
+            serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+        }
+        .map_err(|e| IntError::Custom(format!("Failed to serialize package set: {}", e)))?;
+
+        fs::write(path, content).map_err(|e| {
+            IntError::Custom(format!(
+                "Failed to write package set {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Install every package of the set that isn't installed yet
+    ///
+    /// Package files are looked up at their recorded source, then as
+    /// `<name>-<version>.int` in `packages_dir`, then in the package cache.
+    /// Held packages are left at their version. A package that fails
+    /// doesn't stop the others. Packages with a license agreement are only
+    /// installed with `accept_eula`.
+    pub fn install(
+        &self,
+        installer: &Installer,
+        packages_dir: &Path,
+        accept_eula: bool,
+    ) -> ImportReport {
+        let mut report = ImportReport::default();
+        for package in &self.packages {
+            match InstallMetadata::load(&package.name, package.scope) {
+                Ok(installed) if installed.package_version == package.version => {
+                    report.skipped.push(package.name.clone());
+                    continue;
+                }
+                Ok(installed) if installed.held => {
+                    report.held.push(package.name.clone());
+                    continue;
+                }
+                _ => {}
+            }
+
+            let Some(file) = package_file(package, packages_dir) else {
+                report.failed.push((
+                    package.name.clone(),
+                    format!("No package file found for version {}", package.version),
+                ));
+                continue;
+            };
+            let config = InstallConfig {
+                eula_accepted: accept_eula,
+                ..InstallConfig::default()
+            };
+            match installer.install(&file, config) {
+                Ok(_) => report.installed.push(package.name.clone()),
+                Err(e) => report.failed.push((package.name.clone(), e.to_string())),
+            }
+        }
+        report
+    }
+}
+
+/// Locate the package file to install an exported package from
+fn package_file(package: &ExportedPackage, packages_dir: &Path) -> Option<PathBuf> {
+    let file_name = format!("{}-{}.int", package.name, package.version);
+    let cached = history::cached_package(package.scope, &package.name, &package.version);
+
+    package
+        .source
+        .iter()
+        .cloned()
+        .chain([packages_dir.join(file_name), cached])
+        .find(|path| path.is_file())
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_set_formats() {
+        let temp = tempfile::tempdir().unwrap();
+        let set = PackageSet {
+            packages: vec![
+                ExportedPackage {
+                    name: "myapp".to_string(),
+                    version: "1.0.0".to_string(),
+                    scope: InstallScope::User,
+                    source: Some(PathBuf::from("/tmp/myapp-1.0.0.int")),
+                },
+                ExportedPackage {
+                    name: "mytool".to_string(),
+                    version: "2.1.0".to_string(),
+                    scope: InstallScope::System,
+                    source: None,
+                },
+            ],
+        };
+
+        for file in ["packages.json", "packages.toml"] {
+            let path = temp.path().join(file);
+            set.save(&path).unwrap();
+            assert_eq!(PackageSet::load(&path).unwrap(), set);
+        }
+        let toml = fs::read_to_string(temp.path().join("packages.toml")).unwrap();
+        assert!(toml.contains("[[packages]]"));
+
+        // Package files next to the set are found by name and version
+        let file = temp.path().join("mytool-2.1.0.int");
+        fs::write(&file, "").unwrap();
+        assert_eq!(package_file(&set.packages[1], temp.path()), Some(file));
+        assert_eq!(package_file(&set.packages[0], temp.path()), None);
+    }
+}
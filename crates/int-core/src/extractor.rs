@@ -2,14 +2,15 @@
 ///
 /// This module handles the extraction of .int packages (tar.gz archives)
 /// with security validation and progress tracking.
+use crate::compression::open_archive;
 use crate::error::{IntError, IntResult};
-use crate::manifest::Manifest;
+use crate::manifest::{Manifest, ScriptHook};
 use crate::security::SecurityValidator;
-use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 use std::io::{self, Read};
-use std::path::{Path, PathBuf};
-use tar::Archive;
+use std::path::{Component, Path, PathBuf};
+use tar::EntryType;
 
 /// Extracted package structure
 ///
@@ -39,24 +40,34 @@ impl ExtractedPackage {
         self.services_dir.as_ref().map(|dir| dir.join(service_name))
     }
 
+    /// Check if the script for a lifecycle hook exists
+    pub fn has_script(&self, hook: ScriptHook) -> bool {
+        self.manifest
+            .script(hook)
+            .map(|script| self.extract_dir.join(script.path).exists())
+            .unwrap_or(false)
+    }
+
     /// Check if post-install script exists
     pub fn has_post_install(&self) -> bool {
-        if let Some(ref script_path) = self.manifest.post_install {
-            let full_path = self.extract_dir.join(script_path);
-            full_path.exists()
-        } else {
-            false
+        self.has_script(ScriptHook::PostInstall)
+    }
+
+    /// Get changelog text, either inline or from the packaged changelog file
+    pub fn changelog(&self) -> Option<String> {
+        if let Some(ref changelog) = self.manifest.changelog {
+            return Some(changelog.clone());
         }
+
+        self.manifest
+            .changelog_file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(self.extract_dir.join(file)).ok())
     }
 
     /// Check if pre-uninstall script exists
     pub fn has_pre_uninstall(&self) -> bool {
-        if let Some(ref script_path) = self.manifest.pre_uninstall {
-            let full_path = self.extract_dir.join(script_path);
-            full_path.exists()
-        } else {
-            false
-        }
+        self.has_script(ScriptHook::PreUninstall)
     }
 }
 
@@ -69,6 +80,45 @@ impl Drop for ExtractedPackage {
     }
 }
 
+/// Kind of an entry in a package archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    /// Hard links, devices and other special files
+    Other,
+}
+
+/// Entry of a package archive, as listed by `PackageExtractor::list_entries`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageEntry {
+    /// Path inside the package, e.g. `payload/bin/myapp`
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    /// Size in bytes (0 for directories and links)
+    pub size: u64,
+    /// Unix permission bits
+    pub mode: u32,
+    /// Target of a symlink
+    pub link_target: Option<PathBuf>,
+}
+
+impl PackageEntry {
+    /// Where a payload entry ends up when installed to `install_path`
+    ///
+    /// Entries outside the payload (manifest, scripts, services) are not
+    /// copied as is and have no destination.
+    pub fn destination(&self, install_path: &Path) -> Option<PathBuf> {
+        self.path
+            .strip_prefix("payload")
+            .ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .map(|relative| install_path.join(relative))
+    }
+}
+
 /// Package extractor
 pub struct PackageExtractor {
     /// Security validator
@@ -77,6 +127,8 @@ pub struct PackageExtractor {
     progress_callback: Option<Box<dyn Fn(u64, u64) + Send>>,
     /// Log callback
     log_callback: Option<Box<dyn Fn(String) + Send>>,
+    /// Hash verification progress callback
+    verify_callback: Option<Box<dyn Fn(usize, usize) + Send>>,
     /// Whether to verify GPG signature
     pub verify_signature: bool,
 }
@@ -88,6 +140,7 @@ impl PackageExtractor {
             validator: SecurityValidator::new(),
             progress_callback: None,
             log_callback: None,
+            verify_callback: None,
             verify_signature: false,
         }
     }
@@ -112,6 +165,17 @@ impl PackageExtractor {
         self
     }
 
+    /// Set hash verification progress callback
+    ///
+    /// The callback receives (verified_files, total_files)
+    pub fn with_verify_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(usize, usize) + Send + 'static,
+    {
+        self.verify_callback = Some(Box::new(callback));
+        self
+    }
+
     /// Extract a .int package to a temporary directory
     ///
     /// Returns an ExtractedPackage with parsed manifest and component paths.
@@ -141,7 +205,9 @@ impl PackageExtractor {
         self.validator.validate_total_size(package_size)?;
 
         // Create temporary extraction directory
-        let temp_dir = tempfile::tempdir()
+        let temp_dir = tempfile::Builder::new()
+            .prefix(crate::clean::EXTRACT_PREFIX)
+            .tempdir()
             .map_err(|e| IntError::Custom(format!("Failed to create temp dir: {}", e)))?;
 
         // keep() returns PathBuf on some versions or when certain features are enabled.
@@ -212,10 +278,7 @@ impl PackageExtractor {
         extract_dir: &Path,
         total_size: u64,
     ) -> IntResult<()> {
-        let file = File::open(archive_path).map_err(IntError::IoError)?;
-
-        let decoder = GzDecoder::new(file);
-        let mut archive = Archive::new(decoder);
+        let mut archive = open_archive(archive_path)?;
 
         let mut extracted_size = 0u64;
 
@@ -252,6 +315,7 @@ impl PackageExtractor {
             }
 
             // Report log
+            tracing::debug!("Extracting: {}", entry_path.display());
             if let Some(ref callback) = self.log_callback {
                 callback(format!("Extracting: {}", entry_path.display()));
             }
@@ -318,9 +382,7 @@ impl PackageExtractor {
             ));
         }
 
-        let file = File::open(package_path).map_err(IntError::IoError)?;
-        let decoder = GzDecoder::new(file);
-        let mut archive = Archive::new(decoder);
+        let mut archive = open_archive(package_path)?;
 
         // Find and parse manifest
         for entry_result in archive
@@ -351,6 +413,96 @@ impl PackageExtractor {
         ))
     }
 
+    /// Read the changelog of a package without extracting it
+    ///
+    /// Returns the inline `changelog` from the manifest, or the contents of
+    /// the packaged `changelog_file`.
+    pub fn read_changelog<P: AsRef<Path>>(&self, package_path: P) -> IntResult<Option<String>> {
+        let package_path = package_path.as_ref();
+        let manifest = self.validate_package(package_path)?;
+
+        if manifest.changelog.is_some() {
+            return Ok(manifest.changelog);
+        }
+
+        match manifest.changelog_file {
+            Some(ref file) => read_packaged_file(package_path, file),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the license agreement of a package without extracting it
+    ///
+    /// Returns `None` if the package declares no `eula`. A declared file
+    /// missing from the package is an error, as it could not be accepted.
+    pub fn read_eula<P: AsRef<Path>>(&self, package_path: P) -> IntResult<Option<String>> {
+        let package_path = package_path.as_ref();
+        let manifest = self.validate_package(package_path)?;
+
+        let Some(ref eula) = manifest.eula else {
+            return Ok(None);
+        };
+        match read_packaged_file(package_path, eula)? {
+            Some(content) => Ok(Some(content)),
+            None => Err(IntError::InvalidPackage(format!(
+                "License agreement {} not found in package",
+                eula.display()
+            ))),
+        }
+    }
+
+    /// List the entries of a package without extracting it
+    pub fn list_entries<P: AsRef<Path>>(&self, package_path: P) -> IntResult<Vec<PackageEntry>> {
+        let mut archive = open_archive(package_path)?;
+
+        let mut entries = Vec::new();
+        for entry_result in archive
+            .entries()
+            .map_err(|e| IntError::CorruptedArchive(format!("Failed to read archive: {}", e)))?
+        {
+            let entry = entry_result
+                .map_err(|e| IntError::CorruptedArchive(format!("Failed to read entry: {}", e)))?;
+
+            let path = entry
+                .path()
+                .map_err(|e| IntError::CorruptedArchive(format!("Invalid entry path: {}", e)))?
+                .components()
+                .filter(|component| *component != Component::CurDir)
+                .collect::<PathBuf>();
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+                return Err(IntError::PathTraversalAttempt(path));
+            }
+
+            let header = entry.header();
+            let kind = match header.entry_type() {
+                EntryType::Regular | EntryType::Continuous => EntryKind::File,
+                EntryType::Directory => EntryKind::Directory,
+                EntryType::Symlink => EntryKind::Symlink,
+                _ => EntryKind::Other,
+            };
+            let link_target = match kind {
+                EntryKind::Symlink => entry
+                    .link_name()
+                    .map_err(|e| IntError::CorruptedArchive(format!("Invalid link target: {}", e)))?
+                    .map(|target| target.into_owned()),
+                _ => None,
+            };
+
+            entries.push(PackageEntry {
+                path,
+                kind,
+                size: header.size().unwrap_or(0),
+                mode: header.mode().unwrap_or(0),
+                link_target,
+            });
+        }
+
+        Ok(entries)
+    }
+
     /// Verify GPG signature of a package (detached)
     fn verify_gpg_signature(&self, package_path: &Path) -> IntResult<()> {
         let sig_path = package_path.with_extension("int.sig");
@@ -361,12 +513,10 @@ impl PackageExtractor {
             )));
         }
 
-        if let Some(ref callback) = self.log_callback {
-            callback(format!(
-                "Verifying external GPG signature for {}...",
-                package_path.display()
-            ));
-        }
+        self.log(format!(
+            "Verifying external GPG signature for {}...",
+            package_path.display()
+        ));
 
         use std::process::Command;
         let output = Command::new("gpg")
@@ -384,9 +534,7 @@ impl PackageExtractor {
             )));
         }
 
-        if let Some(ref callback) = self.log_callback {
-            callback("GPG signature verified successfully.".to_string());
-        }
+        self.log("GPG signature verified successfully.".to_string());
 
         Ok(())
     }
@@ -398,9 +546,7 @@ impl PackageExtractor {
             None => return Ok(()),
         };
 
-        if let Some(ref callback) = self.log_callback {
-            callback("Verifying embedded GPG signature...".to_string());
-        }
+        self.log("Verifying embedded GPG signature...".to_string());
 
         // Create a manifest copy without the signature to verify it
         let mut manifest_to_verify = manifest.clone();
@@ -439,9 +585,7 @@ impl PackageExtractor {
             )));
         }
 
-        if let Some(ref callback) = self.log_callback {
-            callback("Embedded GPG signature verified successfully.".to_string());
-        }
+        self.log("Embedded GPG signature verified successfully.".to_string());
 
         Ok(())
     }
@@ -452,11 +596,9 @@ impl PackageExtractor {
         extract_dir: &Path,
         hashes: &std::collections::BTreeMap<String, String>,
     ) -> IntResult<()> {
-        if let Some(ref callback) = self.log_callback {
-            callback(format!("Verifying hashes for {} files...", hashes.len()));
-        }
+        self.log(format!("Verifying hashes for {} files...", hashes.len()));
 
-        for (rel_path, expected_hash) in hashes {
+        for (index, (rel_path, expected_hash)) in hashes.iter().enumerate() {
             let full_path = extract_dir.join(rel_path);
             if !full_path.exists() {
                 return Err(IntError::InvalidPackage(format!(
@@ -466,38 +608,30 @@ impl PackageExtractor {
             }
 
             // Calculate SHA256
-            let hash = self.calculate_sha256(&full_path)?;
+            let hash = crate::utils::sha256_file(&full_path)?;
             if hash != *expected_hash {
                 return Err(IntError::InvalidSignature(format!(
                     "Hash mismatch for file {}: expected {}, found {}",
                     rel_path, expected_hash, hash
                 )));
             }
-        }
 
-        if let Some(ref callback) = self.log_callback {
-            callback("All file hashes verified successfully.".to_string());
+            if let Some(ref callback) = self.verify_callback {
+                callback(index + 1, hashes.len());
+            }
         }
 
+        self.log("All file hashes verified successfully.".to_string());
+
         Ok(())
     }
 
-    /// Calculate SHA256 hash of a file
-    fn calculate_sha256(&self, path: &Path) -> IntResult<String> {
-        use sha2::{Digest, Sha256};
-        let mut file = File::open(path).map_err(IntError::IoError)?;
-        let mut hasher = Sha256::new();
-        let mut buffer = [0u8; 8192];
-
-        loop {
-            let count = file.read(&mut buffer).map_err(IntError::IoError)?;
-            if count == 0 {
-                break;
-            }
-            hasher.update(&buffer[..count]);
+    /// Record a log message as a tracing event and pass it to the log callback
+    fn log(&self, message: String) {
+        tracing::info!("{}", message);
+        if let Some(ref callback) = self.log_callback {
+            callback(message);
         }
-
-        Ok(format!("{:x}", hasher.finalize()))
     }
 }
 
@@ -507,6 +641,33 @@ impl Default for PackageExtractor {
     }
 }
 
+/// Read a text file from a package archive, `None` if it isn't there
+fn read_packaged_file(package_path: &Path, path: &Path) -> IntResult<Option<String>> {
+    let mut archive = open_archive(package_path)?;
+
+    for entry_result in archive
+        .entries()
+        .map_err(|e| IntError::CorruptedArchive(format!("Failed to read archive: {}", e)))?
+    {
+        let mut entry = entry_result
+            .map_err(|e| IntError::CorruptedArchive(format!("Failed to read entry: {}", e)))?;
+
+        let entry_path = entry
+            .path()
+            .map_err(|e| IntError::CorruptedArchive(format!("Invalid entry path: {}", e)))?;
+
+        if entry_path == path {
+            let mut content = String::new();
+            entry
+                .read_to_string(&mut content)
+                .map_err(|e| IntError::CorruptedArchive(e.to_string()))?;
+            return Ok(Some(content));
+        }
+    }
+
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -515,14 +676,6 @@ mod tests {
     use tempfile::TempDir;
 
     fn create_test_package() -> (TempDir, PathBuf) {
-        use flate2::write::GzEncoder;
-        use flate2::Compression;
-        use tar::Builder;
-
-        let temp_dir = TempDir::new().unwrap();
-        let package_path = temp_dir.path().join("test.int");
-
-        // Create package content
         let manifest = r#"{
             "version": "1.0",
             "name": "test-app",
@@ -531,6 +684,17 @@ mod tests {
             "install_path": "/home/user/.local/share/test-app"
         }"#;
 
+        create_test_package_with(manifest, &[("payload/test.txt", b"test file content")])
+    }
+
+    fn create_test_package_with(manifest: &str, files: &[(&str, &[u8])]) -> (TempDir, PathBuf) {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use tar::Builder;
+
+        let temp_dir = TempDir::new().unwrap();
+        let package_path = temp_dir.path().join("test.int");
+
         // Create tar.gz
         let file = File::create(&package_path).unwrap();
         let encoder = GzEncoder::new(file, Compression::default());
@@ -553,14 +717,15 @@ mod tests {
         header.set_cksum();
         builder.append(&header, &[][..]).unwrap();
 
-        // Add a test file
-        let test_content = b"test file content";
-        let mut header = tar::Header::new_gnu();
-        header.set_path("payload/test.txt").unwrap();
-        header.set_size(test_content.len() as u64);
-        header.set_mode(0o644);
-        header.set_cksum();
-        builder.append(&header, &test_content[..]).unwrap();
+        // Add package files
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, *content).unwrap();
+        }
 
         builder.finish().unwrap();
 
@@ -590,6 +755,81 @@ mod tests {
         assert_eq!(manifest.package_version, "1.0.0");
     }
 
+    #[test]
+    fn test_read_changelog() {
+        let manifest = r#"{
+            "version": "1.0",
+            "name": "test-app",
+            "package_version": "1.1.0",
+            "install_scope": "user",
+            "install_path": "/home/user/.local/share/test-app",
+            "changelog_file": "CHANGELOG.md"
+        }"#;
+        let (_temp, package_path) =
+            create_test_package_with(manifest, &[("CHANGELOG.md", b"## 1.1.0\n- Fixed things\n")]);
+
+        let extractor = PackageExtractor::new();
+        let changelog = extractor.read_changelog(&package_path).unwrap();
+        assert_eq!(changelog.as_deref(), Some("## 1.1.0\n- Fixed things\n"));
+
+        let (_temp, package_path) = create_test_package();
+        assert_eq!(extractor.read_changelog(&package_path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_eula() {
+        let manifest = r#"{
+            "version": "1.0",
+            "name": "test-app",
+            "package_version": "1.0.0",
+            "install_scope": "user",
+            "install_path": "/home/user/.local/share/test-app",
+            "eula": "EULA.txt"
+        }"#;
+        let (_temp, package_path) =
+            create_test_package_with(manifest, &[("EULA.txt", b"You agree to...\n")]);
+
+        let extractor = PackageExtractor::new();
+        let eula = extractor.read_eula(&package_path).unwrap();
+        assert_eq!(eula.as_deref(), Some("You agree to...\n"));
+
+        let (_temp, package_path) = create_test_package_with(manifest, &[]);
+        assert!(matches!(
+            extractor.read_eula(&package_path),
+            Err(IntError::InvalidPackage(_))
+        ));
+
+        let (_temp, package_path) = create_test_package();
+        assert_eq!(extractor.read_eula(&package_path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_list_entries() {
+        let (_temp, package_path) = create_test_package();
+
+        let entries = PackageExtractor::new().list_entries(&package_path).unwrap();
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("manifest.json"),
+                Path::new("payload"),
+                Path::new("payload/test.txt"),
+            ]
+        );
+
+        let file = &entries[2];
+        assert_eq!(file.kind, EntryKind::File);
+        assert_eq!(file.size, 17);
+        assert_eq!(file.mode, 0o644);
+        assert_eq!(
+            file.destination(Path::new("/opt/test-app")),
+            Some(PathBuf::from("/opt/test-app/test.txt"))
+        );
+        assert_eq!(entries[1].destination(Path::new("/opt/test-app")), None);
+        assert_eq!(entries[0].destination(Path::new("/opt/test-app")), None);
+    }
+
     #[test]
     fn test_progress_callback() {
         let (_temp, package_path) = create_test_package();
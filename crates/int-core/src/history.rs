@@ -0,0 +1,381 @@
+/// Transaction history
+///
+/// Every install, upgrade and uninstall is recorded as a transaction in a
+/// JSON Lines file next to the metadata directory, one transaction per
+/// line, so recording never has to rewrite earlier entries.
+///
+/// Installed packages are also kept in a package cache (the installed
+/// version and the one it replaced), so a transaction can be rolled back by
+/// reinstalling the earlier version.
+use crate::error::{IntError, IntResult};
+use crate::installer::{InstallConfig, InstallMetadata, Installer};
+use crate::manifest::InstallScope;
+use crate::service::{InitSystem, ServiceManager};
+use crate::Uninstaller;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const HISTORY_FILE: &str = "history.jsonl";
+const PACKAGE_CACHE: &str = "packages";
+
+/// Kind of operation a transaction performed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Install,
+    Upgrade,
+    Uninstall,
+    Purge,
+}
+
+/// Version change of one package in a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageChange {
+    /// Package name
+    pub name: String,
+    /// Version installed before the transaction
+    pub old_version: Option<String>,
+    /// Version installed by the transaction
+    pub new_version: Option<String>,
+}
+
+/// Outcome of a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TransactionResult {
+    Success,
+    Failed { error: String },
+}
+
+/// One recorded install, upgrade or uninstall
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    /// Unique transaction ID
+    pub id: String,
+    /// When the transaction finished (RFC 3339)
+    pub timestamp: String,
+    /// Operation performed
+    pub operation: Operation,
+    /// Scope the operation ran in
+    pub scope: InstallScope,
+    /// Packages changed by the transaction
+    pub packages: Vec<PackageChange>,
+    /// Whether the transaction succeeded
+    pub result: TransactionResult,
+}
+
+impl Transaction {
+    /// Start a transaction, assumed successful until `finish` says otherwise
+    pub fn new(operation: Operation, scope: InstallScope, packages: Vec<PackageChange>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            operation,
+            scope,
+            packages,
+            result: TransactionResult::Success,
+        }
+    }
+
+    /// Set the outcome and record the transaction in the scope's history
+    ///
+    /// A history that cannot be written doesn't fail the operation itself.
+    pub fn finish<T>(mut self, result: &IntResult<T>) {
+        self.timestamp = Utc::now().to_rfc3339();
+        if let Err(e) = result {
+            self.result = TransactionResult::Failed {
+                error: e.to_string(),
+            };
+        }
+        if let Err(e) = record(&self) {
+            tracing::warn!("Failed to record transaction {}: {}", self.id, e);
+        }
+    }
+}
+
+/// Location of the history for a scope
+pub fn path(scope: InstallScope) -> PathBuf {
+    InstallMetadata::metadata_dir(scope).with_file_name(HISTORY_FILE)
+}
+
+/// Append a transaction to its scope's history
+pub fn record(transaction: &Transaction) -> IntResult<()> {
+    append(&path(transaction.scope), transaction)
+}
+
+/// All transactions of a scope, oldest first
+pub fn list(scope: InstallScope) -> IntResult<Vec<Transaction>> {
+    read(&path(scope))
+}
+
+/// Find a transaction by its ID or a unique prefix of it
+pub fn get(scope: InstallScope, id: &str) -> IntResult<Option<Transaction>> {
+    let mut matches = list(scope)?
+        .into_iter()
+        .filter(|transaction| transaction.id.starts_with(id));
+
+    match (matches.next(), matches.next()) {
+        (Some(_), Some(_)) => Err(IntError::Custom(format!(
+            "Transaction ID '{}' is ambiguous",
+            id
+        ))),
+        (transaction, _) => Ok(transaction),
+    }
+}
+
+/// Undo a successful transaction
+///
+/// Installs are undone by uninstalling the package; upgrades and
+/// uninstalls by reinstalling the previous version from the package cache,
+/// after which integration files and units only the newer version had are
+/// removed. Only the most recent transaction of a package can be rolled
+/// back, and data removed by a purge is not restored.
+pub fn rollback(scope: InstallScope, id: &str) -> IntResult<()> {
+    let transaction = get(scope, id)?
+        .ok_or_else(|| IntError::Custom(format!("No transaction with ID {}", id)))?;
+    if transaction.result != TransactionResult::Success {
+        return Err(IntError::Custom(format!(
+            "Transaction {} failed and has nothing to roll back",
+            transaction.id
+        )));
+    }
+
+    let transactions = list(scope)?;
+    for change in &transaction.packages {
+        let latest = transactions.iter().rev().find(|t| {
+            t.result == TransactionResult::Success
+                && t.packages.iter().any(|c| c.name == change.name)
+        });
+        if latest.map(|t| &t.id) != Some(&transaction.id) {
+            return Err(IntError::Custom(format!(
+                "{} changed after transaction {}; roll back its later transactions first",
+                change.name, transaction.id
+            )));
+        }
+    }
+
+    for change in &transaction.packages {
+        rollback_change(scope, change)?;
+    }
+    Ok(())
+}
+
+fn rollback_change(scope: InstallScope, change: &PackageChange) -> IntResult<()> {
+    let Some(ref old_version) = change.old_version else {
+        return Uninstaller::new().uninstall(&change.name, scope);
+    };
+
+    let package = cached_package(scope, &change.name, old_version);
+    if !package.exists() {
+        return Err(IntError::Custom(format!(
+            "No cached copy of {} {} to roll back to",
+            change.name, old_version
+        )));
+    }
+
+    let current = InstallMetadata::load(&change.name, scope).ok();
+    let config = InstallConfig {
+        install_path: current.as_ref().map(|m| m.install_path.clone()),
+        // Rolling back an upgrade goes to an older version on purpose
+        allow_downgrade: true,
+        // The old version's license agreement was accepted when installing it
+        eula_accepted: true,
+        ..InstallConfig::default()
+    };
+    let restored = Installer::new().install(&package, config)?;
+
+    match current {
+        Some(current) => remove_leftovers(&current, &restored),
+        None => Ok(()),
+    }
+}
+
+/// Remove what the rolled-back version installed and the restored one didn't
+fn remove_leftovers(previous: &InstallMetadata, restored: &InstallMetadata) -> IntResult<()> {
+    let units: Vec<_> = previous
+        .service_units
+        .iter()
+        .filter(|unit| !restored.service_units.iter().any(|r| r.path == unit.path))
+        .cloned()
+        .collect();
+    if !units.is_empty() {
+        let init_system = previous.init_system.unwrap_or_else(InitSystem::detect);
+        ServiceManager::with_init_system(init_system)
+            .unregister_units(&units, previous.install_scope)?;
+    }
+
+    let kept: HashSet<&Path> = restored.owned_paths().into_iter().collect();
+    for path in previous.owned_paths() {
+        if kept.contains(path) || path.starts_with(&restored.install_path) {
+            continue;
+        }
+        if path.symlink_metadata().is_ok_and(|m| !m.is_dir()) {
+            fs::remove_file(path).map_err(|e| {
+                IntError::Custom(format!("Failed to remove {}: {}", path.display(), e))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Cached copy of a package version
+pub fn cached_package(scope: InstallScope, name: &str, version: &str) -> PathBuf {
+    package_cache_dir(scope, name).join(format!("{}.int", version))
+}
+
+/// Directory holding the cached versions of a package
+pub(crate) fn package_cache_dir(scope: InstallScope, name: &str) -> PathBuf {
+    package_cache_root(scope).join(name)
+}
+
+/// Directory holding the package cache of a scope
+pub(crate) fn package_cache_root(scope: InstallScope) -> PathBuf {
+    InstallMetadata::metadata_dir(scope).with_file_name(PACKAGE_CACHE)
+}
+
+/// Keep a copy of a package installed by a transaction
+///
+/// Only the installed version and the version it replaced stay cached.
+pub(crate) fn cache_package(
+    scope: InstallScope,
+    package_path: &Path,
+    change: &PackageChange,
+) -> IntResult<()> {
+    let Some(ref version) = change.new_version else {
+        return Ok(());
+    };
+    let dir = package_cache_dir(scope, &change.name);
+    crate::utils::ensure_dir(&dir)?;
+
+    // Reinstalling from the cache must not copy the file onto itself
+    let target = cached_package(scope, &change.name, version);
+    if package_path.canonicalize().ok() != target.canonicalize().ok() {
+        fs::copy(package_path, &target).map_err(|e| IntError::FileCopyFailed {
+            source: package_path.display().to_string(),
+            dest: target.display().to_string(),
+            reason: e.to_string(),
+        })?;
+    }
+
+    let keep: Vec<String> = change
+        .new_version
+        .iter()
+        .chain(&change.old_version)
+        .map(|version| format!("{}.int", version))
+        .collect();
+    prune_cache(&dir, &keep);
+    Ok(())
+}
+
+/// Remove cached files other than the ones to keep
+fn prune_cache(dir: &Path, keep: &[String]) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !keep.contains(&name) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn append(path: &Path, transaction: &Transaction) -> IntResult<()> {
+    if let Some(parent) = path.parent() {
+        crate::utils::ensure_dir(parent)?;
+    }
+
+    let mut line = serde_json::to_string(transaction)
+        .map_err(|e| IntError::Custom(format!("Failed to serialize transaction: {}", e)))?;
+    line.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| IntError::Custom(format!("Failed to write history {}: {}", path.display(), e)))
+}
+
+fn read(path: &Path) -> IntResult<Vec<Transaction>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = fs::read_to_string(path).map_err(IntError::IoError)?;
+    // A line cut short by a crash is skipped rather than hiding the rest
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(name: &str, old: Option<&str>, new: Option<&str>) -> PackageChange {
+        PackageChange {
+            name: name.to_string(),
+            old_version: old.map(str::to_string),
+            new_version: new.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join(HISTORY_FILE);
+
+        let install = Transaction::new(
+            Operation::Install,
+            InstallScope::User,
+            vec![change("myapp", None, Some("1.0.0"))],
+        );
+        let mut upgrade = Transaction::new(
+            Operation::Upgrade,
+            InstallScope::User,
+            vec![change("myapp", Some("1.0.0"), Some("1.1.0"))],
+        );
+        upgrade.result = TransactionResult::Failed {
+            error: "Script failed".to_string(),
+        };
+        append(&path, &install).unwrap();
+        append(&path, &upgrade).unwrap();
+
+        // A partially written last line doesn't hide earlier transactions
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"id\":")
+            .unwrap();
+
+        assert_eq!(read(&path).unwrap(), vec![install, upgrade]);
+        assert!(read(&temp.path().join("missing.jsonl")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prune_cache() {
+        let temp = tempfile::tempdir().unwrap();
+        for version in ["1.0.0", "1.1.0", "1.2.0"] {
+            fs::write(temp.path().join(format!("{}.int", version)), "").unwrap();
+        }
+
+        prune_cache(
+            temp.path(),
+            &["1.2.0.int".to_string(), "1.1.0.int".to_string()],
+        );
+        let mut cached: Vec<_> = fs::read_dir(temp.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        cached.sort();
+        assert_eq!(cached, vec!["1.1.0.int", "1.2.0.int"]);
+    }
+}
@@ -0,0 +1,190 @@
+/// Installed file ownership index
+///
+/// Maps every file recorded in installation metadata to the package that
+/// owns it, so ownership lookups don't have to read the metadata of every
+/// package. Each scope keeps its index in a JSON file next to the metadata
+/// directory; it is updated whenever metadata is saved or removed and
+/// rebuilt from the metadata if missing.
+use crate::error::{IntError, IntResult};
+use crate::installer::InstallMetadata;
+use crate::manifest::InstallScope;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_FILE: &str = "files.json";
+
+/// Package owning an installed file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOwner {
+    /// Package name
+    pub package: String,
+    /// Scope the package is installed in
+    pub scope: InstallScope,
+}
+
+/// File ownership index of one scope
+#[derive(Debug)]
+pub struct FileIndex {
+    path: PathBuf,
+    owners: BTreeMap<PathBuf, String>,
+}
+
+impl FileIndex {
+    /// Location of the index for a scope
+    pub fn path(scope: InstallScope) -> PathBuf {
+        InstallMetadata::metadata_dir(scope).with_file_name(INDEX_FILE)
+    }
+
+    /// Load the index of a scope, rebuilding it from the metadata if missing
+    pub fn load(scope: InstallScope) -> IntResult<Self> {
+        let path = Self::path(scope);
+        if path.exists() {
+            return Self::read(path);
+        }
+
+        let mut index = Self {
+            path,
+            owners: BTreeMap::new(),
+        };
+        for metadata in InstallMetadata::list(scope)? {
+            index.update(&metadata);
+        }
+        // Readers without write access to the scope still get a usable index
+        if !index.owners.is_empty() {
+            let _ = index.save();
+        }
+        Ok(index)
+    }
+
+    fn read(path: PathBuf) -> IntResult<Self> {
+        let content =
+            fs::read_to_string(&path).map_err(|e| IntError::MetadataCorrupted(e.to_string()))?;
+        let owners = serde_json::from_str(&content)
+            .map_err(|e| IntError::MetadataCorrupted(e.to_string()))?;
+        Ok(Self { path, owners })
+    }
+
+    /// Name of the package owning a file
+    pub fn owner(&self, path: &Path) -> Option<&str> {
+        self.owners.get(path).map(String::as_str)
+    }
+
+    /// Record the files of a package, replacing what was recorded before
+    pub fn update(&mut self, metadata: &InstallMetadata) {
+        self.remove_package(&metadata.package_name);
+        for path in metadata.owned_paths() {
+            self.owners
+                .insert(path.to_path_buf(), metadata.package_name.clone());
+        }
+    }
+
+    /// Forget the files of a package
+    pub fn remove_package(&mut self, package_name: &str) {
+        self.owners.retain(|_, owner| owner != package_name);
+    }
+
+    /// Write the index to disk
+    pub fn save(&self) -> IntResult<()> {
+        if let Some(parent) = self.path.parent() {
+            crate::utils::ensure_dir(parent)?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.owners)
+            .map_err(|e| IntError::Custom(format!("Failed to serialize file index: {}", e)))?;
+        crate::utils::write_atomic(&self.path, json.as_bytes())
+    }
+}
+
+/// Find the installed package that owns a file
+///
+/// The user scope is searched before the system scope. If the path itself
+/// is not recorded, its canonical form (symlinks resolved) is tried.
+pub fn who_owns(path: &Path) -> IntResult<Option<FileOwner>> {
+    let mut candidates = vec![path.to_path_buf()];
+    if let Ok(canonical) = path.canonicalize() {
+        if canonical != path {
+            candidates.push(canonical);
+        }
+    }
+
+    for scope in [InstallScope::User, InstallScope::System] {
+        for candidate in &candidates {
+            if let Some(package) = owner_in(scope, candidate)? {
+                return Ok(Some(FileOwner { package, scope }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn owner_in(scope: InstallScope, path: &Path) -> IntResult<Option<String>> {
+    #[cfg(feature = "sqlite")]
+    if crate::db::PackageDatabase::exists(scope) {
+        return crate::db::PackageDatabase::open(scope)?.file_owner(path);
+    }
+
+    if !InstallMetadata::metadata_dir(scope).exists() {
+        return Ok(None);
+    }
+    Ok(FileIndex::load(scope)?.owner(path).map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(name: &str, files: &[&str]) -> InstallMetadata {
+        serde_json::from_value(serde_json::json!({
+            "install_id": "test",
+            "package_name": name,
+            "package_version": "1.0.0",
+            "install_date": "2024-01-01T00:00:00Z",
+            "install_path": format!("/opt/{}", name),
+            "install_scope": "user",
+            "installed_files": files,
+            "desktop_entry": format!("/usr/share/applications/{}.desktop", name),
+            "service_file": null,
+            "service_name": null,
+            "bin_symlink": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_file_index() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut index = FileIndex {
+            path: temp.path().join(INDEX_FILE),
+            owners: BTreeMap::new(),
+        };
+
+        index.update(&metadata(
+            "test-app",
+            &["/opt/test-app/bin/a", "/opt/test-app/bin/b"],
+        ));
+        index.update(&metadata("other-app", &["/opt/other-app/bin/c"]));
+        let desktop = Path::new("/usr/share/applications/test-app.desktop");
+        assert_eq!(index.owner(desktop), Some("test-app"));
+
+        // An upgrade that drops a file no longer owns it
+        index.update(&metadata("test-app", &["/opt/test-app/bin/a"]));
+        assert_eq!(
+            index.owner(Path::new("/opt/test-app/bin/a")),
+            Some("test-app")
+        );
+        assert_eq!(index.owner(Path::new("/opt/test-app/bin/b")), None);
+
+        index.save().unwrap();
+        let mut index = FileIndex::read(temp.path().join(INDEX_FILE)).unwrap();
+        assert_eq!(
+            index.owner(Path::new("/opt/other-app/bin/c")),
+            Some("other-app")
+        );
+
+        index.remove_package("other-app");
+        assert_eq!(index.owner(Path::new("/opt/other-app/bin/c")), None);
+    }
+}
@@ -6,18 +6,34 @@
 /// - Setting permissions
 /// - Executing scripts
 /// - System integration
-use crate::desktop::DesktopIntegration;
+use crate::cron::CronTable;
+#[cfg(feature = "sqlite")]
+use crate::db::PackageDatabase;
+use crate::desktop::{self, DesktopIntegration};
 use crate::error::{IntError, IntResult};
 use crate::extractor::{ExtractedPackage, PackageExtractor};
-use crate::manifest::{InstallScope, Manifest};
-use crate::service::ServiceManager;
+use crate::history::{self, Operation, PackageChange, Transaction};
+use crate::index::{self, FileIndex};
+use crate::lock::OperationLock;
+use crate::manifest::{
+    self, Dependency, InstallScope, LibraryConfig, MaintainerScript, Manifest, PackageKind,
+    PluginConfig, ScriptHook,
+};
+use crate::platform;
+use crate::root;
+use crate::service::{
+    self, InitSystem, ServiceContext, ServiceManager, ServicePreset, ServiceUnit, UnitKind,
+};
 use crate::utils;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 /// Installation configuration
@@ -31,6 +47,23 @@ pub struct InstallConfig {
     pub create_desktop_entry: bool,
     /// Dry run (don't actually install)
     pub dry_run: bool,
+    /// What to do with services that were running before an upgrade
+    pub restart_policy: RestartPolicy,
+    /// Enablement preset for all units, overriding the manifest and system presets
+    pub service_preset: Option<ServicePreset>,
+    /// Install the package only as a dependency of other packages, so
+    /// autoremove can remove it once nothing depends on it
+    pub as_dependency: bool,
+    /// Install even if the package is older than the installed version
+    pub allow_downgrade: bool,
+    /// Override safety checks: allows downgrades and installs despite
+    /// missing required dependencies
+    pub force: bool,
+    /// The user accepted the package's license agreement, if it has one
+    pub eula_accepted: bool,
+    /// The package file is temporary (e.g. spooled from stdin), so it is not
+    /// recorded as the package's source
+    pub temporary_package: bool,
 }
 
 impl Default for InstallConfig {
@@ -40,17 +73,42 @@ impl Default for InstallConfig {
             start_service: false,
             create_desktop_entry: true,
             dry_run: false,
+            restart_policy: RestartPolicy::default(),
+            service_preset: None,
+            as_dependency: false,
+            allow_downgrade: false,
+            force: false,
+            eula_accepted: false,
+            temporary_package: false,
         }
     }
 }
 
+/// What to do with services that were running before an upgrade
+///
+/// Running services are always stopped before their files are replaced;
+/// the policy decides whether they are started again afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    /// Start them again once the upgrade is complete
+    #[default]
+    Restart,
+    /// Leave them stopped
+    LeaveStopped,
+    /// Ask through the confirmation callback (restarts if none is set)
+    Ask,
+}
+
 /// Installation progress state
 #[derive(Debug, Clone)]
 pub enum InstallProgress {
     Extracting { current: u64, total: u64 },
+    Verifying { current: usize, total: usize },
     CopyingFiles { current: usize, total: usize },
     SettingPermissions,
     ExecutingScript { script: String },
+    ScriptOutput { line: String },
     RegisteringService,
     CreatingDesktopEntry,
     Finalizing,
@@ -58,6 +116,117 @@ pub enum InstallProgress {
     Completed,
 }
 
+/// Kind of a file recorded for an installed package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    /// Payload file in the install directory
+    File,
+    /// Symlink created for an entry or plugin
+    Symlink,
+    /// Service or other unit file
+    Unit,
+    /// Desktop entry
+    DesktopEntry,
+    /// Other system integration file (icons, completions, man pages, ...)
+    Integration,
+}
+
+impl FileKind {
+    /// Name of the kind, as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileKind::File => "file",
+            FileKind::Symlink => "symlink",
+            FileKind::Unit => "unit",
+            FileKind::DesktopEntry => "desktop_entry",
+            FileKind::Integration => "integration",
+        }
+    }
+}
+
+/// File recorded for an installed package, as currently found on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledFile {
+    pub path: PathBuf,
+    pub kind: FileKind,
+    /// Size in bytes, `None` if the file is missing
+    pub size: Option<u64>,
+    /// SHA256 of the content, for regular files that can be read
+    pub sha256: Option<String>,
+}
+
+/// What installing a package would do, as reported by `Installer::plan`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallPlan {
+    /// Package name
+    pub package_name: String,
+    /// Package version
+    pub package_version: String,
+    /// Installation scope
+    pub install_scope: InstallScope,
+    /// Installation path
+    pub install_path: PathBuf,
+    /// Version replaced by the installation, if the package is installed
+    pub installed_version: Option<String>,
+    /// Payload files and where they are installed
+    pub files: Vec<PlannedFile>,
+    /// Maintainer scripts run during installation, in order
+    pub scripts: Vec<PlannedScript>,
+    /// Units registered
+    pub services: Vec<String>,
+    /// Desktop entries created
+    pub desktop_entries: Vec<PathBuf>,
+    /// Problems found, see `PlanConflict`
+    pub conflicts: Vec<PlanConflict>,
+    /// Total size of the payload files in bytes
+    pub disk_usage: u64,
+    /// Free space at the install path in bytes, if it could be determined
+    pub available_space: Option<u64>,
+}
+
+impl InstallPlan {
+    /// Whether a conflict would stop the installation
+    pub fn is_blocked(&self) -> bool {
+        self.conflicts
+            .iter()
+            .any(|conflict| matches!(conflict, PlanConflict::Blocked { .. }))
+    }
+}
+
+/// Payload file of an install plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedFile {
+    /// Where the file is installed
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Maintainer script of an install plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedScript {
+    /// Hook the script runs for
+    pub hook: String,
+    /// Script path (relative to package root)
+    pub path: PathBuf,
+    /// Script content, with invalid UTF-8 replaced
+    pub content: String,
+}
+
+/// Problem found while planning an installation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PlanConflict {
+    /// A check failed that makes the installation fail
+    Blocked { message: String },
+    /// The install path exists without being an installation of the
+    /// package, and is replaced entirely
+    InstallPathExists { path: PathBuf },
+    /// A file that would be written belongs to another package
+    FileOwned { path: PathBuf, package: String },
+}
+
 /// Installation metadata
 ///
 /// This is saved to track installed packages for uninstallation.
@@ -83,20 +252,133 @@ pub struct InstallMetadata {
     pub service_file: Option<PathBuf>,
     /// Service name (if service)
     pub service_name: Option<String>,
-    /// Binary symlink path (if created)
+    /// Binary symlink path of the main entry (if created)
     pub bin_symlink: Option<PathBuf>,
+    /// Binary symlink paths of all executable entries
+    #[serde(default)]
+    pub bin_symlinks: Vec<PathBuf>,
+    /// Package kind
+    #[serde(default)]
+    pub kind: PackageKind,
+    /// Dynamic linker configuration file (library packages)
+    #[serde(default)]
+    pub ld_config: Option<PathBuf>,
+    /// Symlink into the host's plugin directory (plugin packages)
+    #[serde(default)]
+    pub plugin_link: Option<PathBuf>,
+    /// Persistent data directories (preserved on uninstall unless purged)
+    #[serde(default)]
+    pub data_dirs: Vec<PathBuf>,
+    /// Cache directories (preserved on uninstall unless purged)
+    #[serde(default)]
+    pub cache_dirs: Vec<PathBuf>,
+    /// Log directories (preserved on uninstall unless purged)
+    #[serde(default)]
+    pub log_dirs: Vec<PathBuf>,
+    /// Previous default applications of MIME types taken over by the package
+    #[serde(default)]
+    pub mime_defaults: BTreeMap<String, Option<String>>,
+    /// Installed shell completion files
+    #[serde(default)]
+    pub completions: Vec<PathBuf>,
+    /// Installed man pages
+    #[serde(default)]
+    pub man_pages: Vec<PathBuf>,
+    /// Installed thumbnailer registration
+    #[serde(default)]
+    pub thumbnailer: Option<PathBuf>,
+    /// Installed D-Bus activation files
+    #[serde(default)]
+    pub dbus_services: Vec<PathBuf>,
+    /// Installed GSettings schemas
+    #[serde(default)]
+    pub gschemas: Vec<PathBuf>,
+    /// Installed icon theme files
+    #[serde(default)]
+    pub icons: Vec<PathBuf>,
+    /// Installed shared-mime-info definitions
+    #[serde(default)]
+    pub mime_definitions: Vec<PathBuf>,
+    /// Installed tmpfiles.d fragments
+    #[serde(default)]
+    pub tmpfiles: Vec<PathBuf>,
+    /// All units registered for the package (timers, sockets and services)
+    #[serde(default)]
+    pub service_units: Vec<ServiceUnit>,
+    /// Init system the service was registered with
+    #[serde(default)]
+    pub init_system: Option<InitSystem>,
+    /// Environment file generated for the service
+    #[serde(default)]
+    pub env_file: Option<PathBuf>,
+    /// Where scheduled tasks were installed as cron jobs
+    #[serde(default)]
+    pub cron: Option<CronTable>,
+    /// Pre-uninstall script, copied into the scripts directory at install time
+    #[serde(default)]
+    pub pre_uninstall: Option<MaintainerScript>,
+    /// Post-uninstall script, copied into the scripts directory at install time
+    #[serde(default)]
+    pub post_uninstall: Option<MaintainerScript>,
+    /// Directories created outside the install path (removed when empty)
+    #[serde(default)]
+    pub created_dirs: Vec<PathBuf>,
+    /// Package file the installation came from
+    #[serde(default)]
+    pub source: Option<PathBuf>,
+    /// Manifest of the installed version
+    #[serde(default, deserialize_with = "deserialize_manifest")]
+    pub manifest: Option<Manifest>,
+    /// Total size of the installed files in bytes
+    #[serde(default)]
+    pub installed_size: u64,
+    /// Installed only as a dependency of other packages
+    #[serde(default)]
+    pub installed_as_dependency: bool,
+    /// Held at the installed version: other versions are not installed over
+    /// it (see `Installer::set_held`)
+    #[serde(default)]
+    pub held: bool,
+    /// SHA256 of the installed package archive, identifying its cached copy
+    #[serde(default)]
+    pub package_sha256: Option<String>,
+}
+
+/// Stored manifests that no longer parse (e.g. written by a newer version)
+/// are dropped instead of making the whole metadata unreadable
+fn deserialize_manifest<'de, D>(deserializer: D) -> Result<Option<Manifest>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| serde_json::from_value(value).ok()))
 }
 
 impl InstallMetadata {
-    /// Save metadata to disk
-    pub fn save(&self, scope: InstallScope) -> IntResult<()> {
-        let metadata_dir = match scope {
+    /// Directory holding the metadata of installed packages
+    pub fn metadata_dir(scope: InstallScope) -> PathBuf {
+        root::path(&match scope {
             InstallScope::User => {
                 let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
                 PathBuf::from(home).join(".local/share/int-installer/installed")
             }
             InstallScope::System => PathBuf::from("/var/lib/int-installer/installed"),
-        };
+        })
+    }
+
+    /// Directory holding the maintainer scripts kept for uninstallation
+    pub fn scripts_dir(package_name: &str, scope: InstallScope) -> PathBuf {
+        Self::metadata_dir(scope).join(format!("{}.scripts", package_name))
+    }
+
+    /// Save metadata to disk
+    pub fn save(&self, scope: InstallScope) -> IntResult<()> {
+        #[cfg(feature = "sqlite")]
+        if PackageDatabase::exists(scope) {
+            return PackageDatabase::open(scope)?.save(self);
+        }
+
+        let metadata_dir = Self::metadata_dir(scope);
 
         utils::ensure_dir(&metadata_dir)?;
 
@@ -105,44 +387,258 @@ impl InstallMetadata {
         let json = serde_json::to_string_pretty(self)
             .map_err(|e| IntError::Custom(format!("Failed to serialize metadata: {}", e)))?;
 
-        fs::write(&metadata_file, json).map_err(|e| {
-            IntError::Custom(format!(
-                "Failed to write metadata to {}: {}",
-                metadata_file.display(),
-                e
-            ))
-        })?;
+        utils::write_atomic(&metadata_file, json.as_bytes())?;
 
-        Ok(())
+        let mut index = FileIndex::load(scope)?;
+        index.update(self);
+        index.save()
     }
 
     /// Load metadata from disk
     pub fn load(package_name: &str, scope: InstallScope) -> IntResult<Self> {
-        let metadata_dir = match scope {
-            InstallScope::User => {
-                let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
-                PathBuf::from(home).join(".local/share/int-installer/installed")
-            }
-            InstallScope::System => PathBuf::from("/var/lib/int-installer/installed"),
-        };
+        #[cfg(feature = "sqlite")]
+        if PackageDatabase::exists(scope) {
+            return PackageDatabase::open(scope)?
+                .load(package_name)?
+                .ok_or_else(|| IntError::PackageNotInstalled(package_name.to_string()));
+        }
 
-        let metadata_file = metadata_dir.join(format!("{}.json", package_name));
+        let metadata_file = Self::metadata_dir(scope).join(format!("{}.json", package_name));
 
         if !metadata_file.exists() {
             return Err(IntError::PackageNotInstalled(package_name.to_string()));
         }
 
-        let content = fs::read_to_string(&metadata_file)
-            .map_err(|e| IntError::MetadataCorrupted(e.to_string()))?;
+        Self::read_file(&metadata_file)
+    }
+
+    /// Read a metadata file, falling back to its backup if it is damaged
+    pub(crate) fn read_file(path: &Path) -> IntResult<Self> {
+        let parse = |path: &Path| -> IntResult<Self> {
+            let content =
+                fs::read_to_string(path).map_err(|e| IntError::MetadataCorrupted(e.to_string()))?;
+            serde_json::from_str(&content).map_err(|e| IntError::MetadataCorrupted(e.to_string()))
+        };
+
+        parse(path).or_else(|error| {
+            let backup = utils::backup_path(path);
+            if backup.exists() {
+                parse(&backup)
+            } else {
+                Err(error)
+            }
+        })
+    }
+
+    /// Remove saved metadata from disk
+    pub fn remove(package_name: &str, scope: InstallScope) -> IntResult<()> {
+        #[cfg(feature = "sqlite")]
+        if PackageDatabase::exists(scope) {
+            return PackageDatabase::open(scope)?.remove(package_name);
+        }
+
+        let metadata_file = Self::metadata_dir(scope).join(format!("{}.json", package_name));
+        for file in [utils::backup_path(&metadata_file), metadata_file] {
+            if file.exists() {
+                fs::remove_file(&file).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to remove metadata {}: {}",
+                        file.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+
+        let mut index = FileIndex::load(scope)?;
+        index.remove_package(package_name);
+        index.save()
+    }
+
+    /// Display name of the package
+    pub fn display_name(&self) -> &str {
+        match self.manifest {
+            Some(ref manifest) => manifest.display_name(),
+            None => &self.package_name,
+        }
+    }
+
+    /// Data, cache and log directories created by the package
+    ///
+    /// Only recorded directories strictly below the scope's data, cache or
+    /// log base directory are returned, so purging can never remove a base
+    /// directory or anything outside of them.
+    pub fn managed_dirs(&self) -> Vec<PathBuf> {
+        let scope = self.install_scope;
+        let bases = [scope.data_home(), scope.cache_home(), scope.log_home()];
+
+        self.data_dirs
+            .iter()
+            .chain(&self.cache_dirs)
+            .chain(&self.log_dirs)
+            .filter(|dir| {
+                bases
+                    .iter()
+                    .any(|base| dir.strip_prefix(base).is_ok_and(manifest::is_subdir_path))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Installed size in bytes
+    ///
+    /// Metadata written before sizes were recorded is measured on disk.
+    pub fn size(&self) -> u64 {
+        match self.installed_size {
+            0 => self.disk_usage(),
+            size => size,
+        }
+    }
+
+    /// Current total size of the files the installation put on the system
+    ///
+    /// Symlinks count with their own size; missing files are skipped.
+    pub fn disk_usage(&self) -> u64 {
+        self.owned_paths()
+            .into_iter()
+            .filter_map(|path| path.symlink_metadata().ok())
+            .filter(|metadata| !metadata.is_dir())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Every file the installation put on the system, with its current size
+    /// and hash
+    pub fn files(&self) -> Vec<InstalledFile> {
+        let payload: HashSet<&Path> = self.installed_files.iter().map(PathBuf::as_path).collect();
+
+        self.owned_paths()
+            .into_iter()
+            .map(|path| {
+                let metadata = path.symlink_metadata().ok();
+                let is_symlink = metadata.as_ref().is_some_and(|m| m.is_symlink());
+                let kind = if self.desktop_entry.as_deref() == Some(path) {
+                    FileKind::DesktopEntry
+                } else if self.service_file.as_deref() == Some(path)
+                    || self.service_units.iter().any(|unit| unit.path == path)
+                {
+                    FileKind::Unit
+                } else if is_symlink {
+                    FileKind::Symlink
+                } else if payload.contains(path) {
+                    FileKind::File
+                } else {
+                    FileKind::Integration
+                };
+                let sha256 = metadata
+                    .as_ref()
+                    .filter(|m| m.is_file())
+                    .and_then(|_| utils::sha256_file(path).ok());
+
+                InstalledFile {
+                    path: path.to_path_buf(),
+                    kind,
+                    size: metadata.map(|m| m.len()),
+                    sha256,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether this package needs another installed package
+    ///
+    /// Declared dependencies and the host of a plugin count.
+    pub fn depends_on(&self, package_name: &str) -> bool {
+        let Some(ref manifest) = self.manifest else {
+            return false;
+        };
+        manifest.dependencies.iter().any(|d| d.name == package_name)
+            || manifest
+                .plugin
+                .as_ref()
+                .is_some_and(|plugin| plugin.host == package_name)
+    }
+
+    /// Every file the installation put on the system
+    ///
+    /// The installed payload plus the files created for system integration
+    /// (symlinks, unit files, desktop files, ...).
+    pub fn owned_paths(&self) -> Vec<&Path> {
+        let single = [
+            &self.desktop_entry,
+            &self.service_file,
+            &self.bin_symlink,
+            &self.ld_config,
+            &self.plugin_link,
+            &self.thumbnailer,
+            &self.env_file,
+        ];
+        let lists = [
+            &self.installed_files,
+            &self.bin_symlinks,
+            &self.completions,
+            &self.man_pages,
+            &self.dbus_services,
+            &self.gschemas,
+            &self.icons,
+            &self.mime_definitions,
+            &self.tmpfiles,
+        ];
+
+        let mut paths: Vec<&Path> = single
+            .into_iter()
+            .flatten()
+            .chain(lists.into_iter().flatten())
+            .map(PathBuf::as_path)
+            .chain(self.service_units.iter().map(|unit| unit.path.as_path()))
+            .collect();
+        if let Some(CronTable::File(ref cron_file)) = self.cron {
+            paths.push(cron_file);
+        }
+
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Load the metadata of all packages installed in a scope
+    pub fn list(scope: InstallScope) -> IntResult<Vec<Self>> {
+        #[cfg(feature = "sqlite")]
+        if PackageDatabase::exists(scope) {
+            return PackageDatabase::open(scope)?.list();
+        }
+
+        let metadata_dir = Self::metadata_dir(scope);
+        if !metadata_dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut packages = Vec::new();
+
+        for entry in fs::read_dir(&metadata_dir).map_err(IntError::IoError)? {
+            let entry = entry.map_err(IntError::IoError)?;
+            let path = entry.path();
+
+            if path.extension().and_then(|s| s.to_str()) == Some("json") {
+                packages.push(Self::read_file(&path)?);
+            }
+        }
 
-        serde_json::from_str(&content).map_err(|e| IntError::MetadataCorrupted(e.to_string()))
+        Ok(packages)
     }
 }
 
+/// Callback answering a yes/no question
+type ConfirmCallback = dyn Fn(&str) -> bool + Send + Sync + 'static;
+
 /// Package installer
 pub struct Installer {
     /// Progress callback
     progress_callback: Option<Arc<dyn Fn(InstallProgress) + Send + Sync + 'static>>,
+    /// Callback answering yes/no questions during installation
+    confirm_callback: Option<Arc<ConfirmCallback>>,
+    /// Whether to wait for a concurrent operation instead of failing
+    wait_for_lock: bool,
 }
 
 impl Installer {
@@ -150,6 +646,8 @@ impl Installer {
     pub fn new() -> Self {
         Self {
             progress_callback: None,
+            confirm_callback: None,
+            wait_for_lock: false,
         }
     }
 
@@ -162,14 +660,188 @@ impl Installer {
         self
     }
 
+    /// Set confirmation callback
+    ///
+    /// Used for questions such as whether to restart services after an
+    /// upgrade with `RestartPolicy::Ask`.
+    pub fn with_confirm<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.confirm_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Wait for a concurrent install or uninstall in the same scope to
+    /// finish instead of failing with `IntError::OperationInProgress`
+    pub fn wait_for_lock(mut self, wait: bool) -> Self {
+        self.wait_for_lock = wait;
+        self
+    }
+
+    /// Hold an installed package at its version, or release the hold
+    ///
+    /// Installing another version of a held package fails with
+    /// `IntError::PackageHeld` unless forced, and imports skip it.
+    pub fn set_held(
+        &self,
+        package_name: &str,
+        scope: InstallScope,
+        held: bool,
+    ) -> IntResult<InstallMetadata> {
+        let _lock = OperationLock::acquire(scope, self.wait_for_lock)?;
+        let mut metadata = InstallMetadata::load(package_name, scope)?;
+        if metadata.held != held {
+            metadata.held = held;
+            metadata.save(scope)?;
+        }
+        Ok(metadata)
+    }
+
+    /// Report what installing a package would do, without installing it
+    ///
+    /// The package is extracted and verified like for an installation, but
+    /// failing checks are reported as conflicts instead of errors. Nothing
+    /// is locked, so the plan may be outdated by the time it is installed.
+    pub fn plan<P: AsRef<Path>>(
+        &self,
+        package_path: P,
+        config: &InstallConfig,
+    ) -> IntResult<InstallPlan> {
+        let mut extractor = PackageExtractor::new();
+        extractor.verify_signature = true;
+        let extracted = extractor.extract(package_path.as_ref())?;
+        let manifest = &extracted.manifest;
+        let scope = manifest.install_scope;
+        let install_path = root::path(
+            config
+                .install_path
+                .as_ref()
+                .unwrap_or(&manifest.install_path),
+        );
+
+        let mut conflicts = Vec::new();
+        let mut check = |result: IntResult<()>| {
+            if let Err(e) = result {
+                conflicts.push(PlanConflict::Blocked {
+                    message: e.to_string(),
+                });
+            }
+        };
+        check(self.check_permissions(manifest, &install_path));
+        check(manifest.check_init_system(platform::init_system()));
+        // Check commands are package code and wait for the confirmation
+        check(self.check_dependencies(manifest, config.force, false));
+        check(self.check_installed_version(manifest, config));
+        check(check_eula(manifest, config));
+
+        let files = planned_files(&extracted.payload_dir, &install_path)?;
+        let disk_usage = files.iter().map(|file| file.size).sum();
+        let available_space = utils::get_available_space(&install_path).ok();
+        if let Some(available) = available_space {
+            let required = manifest.required_space.unwrap_or(disk_usage);
+            if available < required {
+                check(Err(IntError::DiskSpaceInsufficient {
+                    required,
+                    available,
+                }));
+            }
+        }
+
+        let previous = InstallMetadata::load(&manifest.name, scope).ok();
+        let replaces_previous = previous
+            .as_ref()
+            .is_some_and(|previous| previous.install_path == install_path);
+        if install_path.exists() && !replaces_previous {
+            conflicts.push(PlanConflict::InstallPathExists {
+                path: install_path.clone(),
+            });
+        }
+
+        let desktop_entries: Vec<PathBuf> = match manifest.desktop {
+            Some(_) if config.create_desktop_entry => vec![scope
+                .desktop_entry_path()
+                .join(format!("{}.desktop", manifest.name))],
+            _ => vec![],
+        };
+        let written = files.iter().map(|file| &file.path).chain(&desktop_entries);
+        for path in written.filter(|path| path.symlink_metadata().is_ok()) {
+            if let Some(owner) = index::who_owns(path)? {
+                if owner.package != manifest.name {
+                    conflicts.push(PlanConflict::FileOwned {
+                        path: path.clone(),
+                        package: owner.package,
+                    });
+                }
+            }
+        }
+
+        let upgrading = previous.is_some();
+        let hooks = [
+            (ScriptHook::PreInstall, ScriptHook::PreUpgrade),
+            (ScriptHook::PostInstall, ScriptHook::PostUpgrade),
+        ];
+        let mut scripts = Vec::new();
+        for (install_hook, upgrade_hook) in hooks {
+            let hook = select_hook(manifest, upgrading, install_hook, upgrade_hook);
+            let Some(script) = manifest.script(hook) else {
+                continue;
+            };
+            // Scripts missing from the package are skipped when installing
+            let Ok(content) = fs::read(extracted.extract_dir.join(&script.path)) else {
+                continue;
+            };
+            scripts.push(PlannedScript {
+                hook: hook.as_str().to_string(),
+                path: script.path,
+                content: String::from_utf8_lossy(&content).into_owned(),
+            });
+        }
+
+        let services = if manifest.service {
+            manifest.service_units()
+        } else {
+            vec![]
+        };
+
+        Ok(InstallPlan {
+            package_name: manifest.name.clone(),
+            package_version: manifest.package_version.clone(),
+            install_scope: scope,
+            install_path,
+            installed_version: previous.map(|previous| previous.package_version),
+            files,
+            scripts,
+            services,
+            desktop_entries,
+            conflicts,
+            disk_usage,
+            available_space,
+        })
+    }
+
     /// Install a package
+    ///
+    /// Installs and upgrades are recorded in the scope's transaction history.
     pub fn install<P: AsRef<Path>>(
         &self,
         package_path: P,
         config: InstallConfig,
     ) -> IntResult<InstallMetadata> {
-        let package_path = package_path.as_ref();
+        let mut transaction = None;
+        let result = self.install_package(package_path.as_ref(), config, &mut transaction);
+        if let Some(transaction) = transaction {
+            transaction.finish(&result);
+        }
+        result
+    }
 
+    fn install_package(
+        &self,
+        package_path: &Path,
+        config: InstallConfig,
+        transaction: &mut Option<Transaction>,
+    ) -> IntResult<InstallMetadata> {
         // Extract package
         self.report_progress(InstallProgress::Log {
             message: "Initializing package extraction...".to_string(),
@@ -185,6 +857,11 @@ impl Installer {
                 extractor = extractor.with_progress(move |current, total| {
                     cb_progress(InstallProgress::Extracting { current, total });
                 });
+
+                let cb_verify = Arc::clone(callback);
+                extractor = extractor.with_verify_progress(move |current, total| {
+                    cb_verify(InstallProgress::Verifying { current, total });
+                });
             }
 
             // Connect log callback for extraction logs
@@ -198,10 +875,19 @@ impl Installer {
         };
         let extracted = extractor.extract(package_path)?;
 
+        for warning in extracted.manifest.warnings() {
+            self.report_progress(InstallProgress::Log {
+                message: format!("Warning: {}", warning),
+            });
+        }
+
         // Determine install path
-        let install_path = config
-            .install_path
-            .unwrap_or_else(|| extracted.manifest.install_path.clone());
+        let install_path = root::path(
+            config
+                .install_path
+                .as_ref()
+                .unwrap_or(&extracted.manifest.install_path),
+        );
 
         // Check permissions
         self.report_progress(InstallProgress::Log {
@@ -212,6 +898,23 @@ impl Installer {
         });
         self.check_permissions(&extracted.manifest, &install_path)?;
 
+        // Check that the package's services can be managed here
+        extracted
+            .manifest
+            .check_init_system(platform::init_system())?;
+
+        // Refuse to replace a held or newer installed version unless asked to
+        self.check_installed_version(&extracted.manifest, &config)?;
+
+        // Vendors can require their license agreement to be accepted first
+        if !config.dry_run {
+            check_eula(&extracted.manifest, &config)?;
+        }
+
+        // Check dependencies; their check commands run no earlier than
+        // maintainer scripts would
+        self.check_dependencies(&extracted.manifest, config.force, !config.dry_run)?;
+
         // Check disk space
         if let Some(required) = extracted.manifest.required_space {
             self.report_progress(InstallProgress::Log {
@@ -223,6 +926,50 @@ impl Installer {
             utils::check_disk_space(&install_path, required)?;
         }
 
+        // Keep other installs and uninstalls out of the scope until done
+        let _lock = if config.dry_run {
+            None
+        } else {
+            let scope = extracted.manifest.install_scope;
+            Some(OperationLock::acquire(scope, self.wait_for_lock)?)
+        };
+
+        // Run pre-install (or pre-upgrade) script before touching the target
+        let previous = if config.dry_run {
+            None
+        } else {
+            InstallMetadata::load(&extracted.manifest.name, extracted.manifest.install_scope).ok()
+        };
+        let upgrading = previous.is_some();
+        if !config.dry_run {
+            let operation = if upgrading {
+                Operation::Upgrade
+            } else {
+                Operation::Install
+            };
+            let change = PackageChange {
+                name: extracted.manifest.name.clone(),
+                old_version: previous.as_ref().map(|p| p.package_version.clone()),
+                new_version: Some(extracted.manifest.package_version.clone()),
+            };
+            let scope = extracted.manifest.install_scope;
+            *transaction = Some(Transaction::new(operation, scope, vec![change]));
+
+            let pre_hook = select_hook(
+                &extracted.manifest,
+                upgrading,
+                ScriptHook::PreInstall,
+                ScriptHook::PreUpgrade,
+            );
+            self.run_hook(&extracted, pre_hook, &extracted.extract_dir, &install_path)?;
+        }
+
+        // Stop running services before their files are replaced
+        let stopped_services = match previous {
+            Some(ref previous) => self.stop_services(previous),
+            None => None,
+        };
+
         // Check if already installed - if exists, remove it (overwrite)
         if install_path.exists() && !config.dry_run {
             self.report_progress(InstallProgress::Log {
@@ -245,37 +992,52 @@ impl Installer {
             return Ok(self.create_metadata(&extracted.manifest, &install_path, vec![]));
         }
 
-        // Copy payload files
-        self.report_progress(InstallProgress::CopyingFiles {
-            current: 0,
-            total: 1,
-        });
+        // Record the directories created outside the install path, so
+        // uninstallation can remove them again
+        let dir_tracker = utils::DirTracker::start();
 
+        // Copy payload files
         utils::ensure_dir(&install_path)?;
         self.report_progress(InstallProgress::Log {
             message: format!("Copying payload files to {}...", install_path.display()),
         });
         let installed_files = self.copy_payload(&extracted.payload_dir, &install_path)?;
 
+        // Install documentation and license files
+        let mut installed_files = installed_files;
+        installed_files.extend(self.install_docs(&extracted, &install_path)?);
+        let man_pages = self.install_man_pages(&extracted.manifest, &install_path)?;
+        let completions = self.install_completions(&extracted.manifest, &install_path)?;
+
         // Set permissions
         self.report_progress(InstallProgress::SettingPermissions);
         self.set_permissions(&install_path, &extracted.manifest)?;
 
-        // Execute post-install script
-        if extracted.has_post_install() {
-            if let Some(ref script_path) = extracted.manifest.post_install {
-                let script_name = script_path.display().to_string();
-                self.report_progress(InstallProgress::Log {
-                    message: format!("Executing post-install script: {}...", script_name),
-                });
-                self.report_progress(InstallProgress::ExecutingScript {
-                    script: script_name,
-                });
-
-                let full_script_path = extracted.extract_dir.join(script_path);
-                self.execute_script(&full_script_path, &install_path)?;
-            }
-        }
+        // Create data, cache and log directories
+        let scope = extracted.manifest.install_scope;
+        let owned_dirs = previous
+            .as_ref()
+            .map(|p| p.managed_dirs())
+            .unwrap_or_default();
+        let data_dirs = extracted.manifest.resolved_data_dirs(scope);
+        let data_dirs = self.create_managed_dirs(data_dirs, scope, &owned_dirs)?;
+        let cache_dirs = extracted.manifest.resolved_cache_dirs(scope);
+        let cache_dirs = self.create_managed_dirs(cache_dirs, scope, &owned_dirs)?;
+        let log_dirs = extracted.manifest.resolved_log_dirs(scope);
+        let log_dirs = self.create_managed_dirs(log_dirs, scope, &owned_dirs)?;
+
+        // Install tmpfiles.d fragments so runtime directories exist before
+        // scripts and services need them
+        let tmpfiles = self.install_tmpfiles(&extracted, &install_path)?;
+
+        // Execute post-install (or post-upgrade) script
+        let post_hook = select_hook(
+            &extracted.manifest,
+            upgrading,
+            ScriptHook::PostInstall,
+            ScriptHook::PostUpgrade,
+        );
+        self.run_hook(&extracted, post_hook, &install_path, &install_path)?;
 
         // Create desktop entry
         let desktop_entry = if config.create_desktop_entry && extracted.manifest.desktop.is_some() {
@@ -288,57 +1050,159 @@ impl Installer {
             None
         };
 
-        // Register service
-        let (service_file, service_name) = if extracted.manifest.service {
+        // Become the default application for the declared MIME types
+        let mime_defaults = match (&desktop_entry, &extracted.manifest.desktop) {
+            (Some(entry), Some(desktop)) if desktop.set_default_for_mime_types => self
+                .set_default_applications(
+                    entry,
+                    &desktop.mime_types,
+                    extracted.manifest.install_scope,
+                    previous.as_ref(),
+                ),
+            _ => BTreeMap::new(),
+        };
+
+        // Install custom MIME type definitions
+        let mime_definitions = match extracted.manifest.desktop {
+            Some(ref desktop) if !desktop.mime_definitions.is_empty() => {
+                self.report_progress(InstallProgress::Log {
+                    message: "Installing MIME type definitions...".to_string(),
+                });
+                DesktopIntegration::new().install_mime_definitions(
+                    &desktop.mime_definitions,
+                    &install_path,
+                    extracted.manifest.install_scope,
+                )?
+            }
+            _ => vec![],
+        };
+
+        // Install icons into the icon theme
+        let icons = extracted
+            .manifest
+            .desktop
+            .as_ref()
+            .and_then(|d| d.icons.as_ref());
+        let icons = match icons {
+            Some(icons) => {
+                self.report_progress(InstallProgress::Log {
+                    message: "Installing icons...".to_string(),
+                });
+                DesktopIntegration::new().install_icons(
+                    icons,
+                    &desktop::icon_name(&extracted.manifest),
+                    &install_path,
+                    extracted.manifest.install_scope,
+                )?
+            }
+            None => vec![],
+        };
+
+        // Register the thumbnailer for the application's file types
+        let thumbnailer = match extracted.manifest.desktop {
+            Some(ref desktop) => match desktop.thumbnailer {
+                Some(ref thumbnailer) => Some(DesktopIntegration::new().install_thumbnailer(
+                    &extracted.manifest,
+                    thumbnailer,
+                    &install_path,
+                )?),
+                None => None,
+            },
+            None => None,
+        };
+
+        // Make the application activatable over D-Bus
+        let dbus_services =
+            DesktopIntegration::new().install_dbus_services(&extracted.manifest, &install_path)?;
+
+        // Install and compile GSettings schemas
+        let gschemas = DesktopIntegration::new().install_schemas(
+            &extracted.manifest.gschemas,
+            &install_path,
+            extracted.manifest.install_scope,
+        )?;
+        if !gschemas.is_empty() {
+            self.report_progress(InstallProgress::Log {
+                message: format!("Installed {} GSettings schema file(s).", gschemas.len()),
+            });
+        }
+
+        // Register services
+        let (service_units, init_system) = if extracted.manifest.service {
+            let service_manager = ServiceManager::new();
+            let scope = extracted.manifest.install_scope;
             self.report_progress(InstallProgress::Log {
-                message: "Registering systemd service...".to_string(),
+                message: format!("Registering {} service...", service_manager.init_system()),
             });
             self.report_progress(InstallProgress::RegisteringService);
-            let (file, name) = self.register_service(&extracted, &install_path)?;
+            let units =
+                service_manager.register(&extracted, &install_path, config.service_preset)?;
+
+            // Start services if requested or their preset says so; nothing runs
+            // inside an alternate root, where enabling is all that is recorded
+            let to_start: Vec<&ServiceUnit> = ServiceUnit::startable(&units)
+                .into_iter()
+                .filter(|unit| config.start_service || unit.preset == ServicePreset::EnableNow)
+                .filter(|_| !root::is_set())
+                .collect();
+            for unit in &to_start {
+                self.report_progress(InstallProgress::Log {
+                    message: format!("Starting service {}...", unit.name),
+                });
+                service_manager.start(&unit.name, scope)?;
+            }
 
-            // Start service if requested
-            if config.start_service {
+            for unit in to_start.iter().filter(|u| u.kind == UnitKind::Service) {
                 self.report_progress(InstallProgress::Log {
-                    message: format!("Starting service {}...", name),
+                    message: format!("Waiting for service {} to become healthy...", unit.name),
                 });
-                ServiceManager::new().start(&name, extracted.manifest.install_scope)?;
+                // The health check describes the main service only
+                let health_check = extracted
+                    .manifest
+                    .health_check
+                    .as_ref()
+                    .filter(|_| unit.name == extracted.manifest.service_name());
+                service_manager.wait_healthy(&unit.name, scope, health_check)?;
             }
 
-            (Some(file), Some(name))
+            (units, Some(service_manager.init_system()))
         } else {
-            (None, None)
+            (Vec::new(), None)
         };
+        if let Some(ref previous) = previous {
+            self.remove_dropped_units(previous, &service_units)?;
+        }
 
-        // Create binary symlink if entry is specified
-        let bin_symlink = if let Some(ref entry) = extracted.manifest.entry {
-            let entry_path = install_path.join("bin").join(entry);
-            if entry_path.exists() {
-                let bin_dir = extracted.manifest.install_scope.bin_path();
-                utils::ensure_dir(&bin_dir)?;
-                let symlink_path = bin_dir.join(entry);
-
-                // Create symlink (remove existing if any)
-                if symlink_path.exists() {
-                    fs::remove_file(&symlink_path).ok();
-                }
+        // Install cron jobs for scheduled tasks not run by a systemd timer
+        let cron = self.install_cron(
+            &extracted.manifest,
+            &install_path,
+            previous.as_ref().and_then(|p| p.cron.as_ref()),
+            init_system.or_else(platform::init_system),
+        )?;
+
+        // Create binary symlinks for all executable entries
+        let mut bin_symlinks = Vec::new();
+        for entry in extracted.manifest.executable_entries() {
+            if let Some(symlink_path) =
+                self.create_bin_symlink(&extracted.manifest, &install_path, entry)?
+            {
+                bin_symlinks.push(symlink_path);
+            }
+        }
 
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::symlink;
-                    symlink(&entry_path, &symlink_path).map_err(|e| {
-                        IntError::Custom(format!("Failed to create symlink: {}", e))
-                    })?;
-                    Some(symlink_path)
-                }
-                #[cfg(not(unix))]
-                {
-                    None // Symlinks not supported/implemented for this platform yet
-                }
-            } else {
-                None
+        // Register shared libraries or link into the host application
+        let ld_config = match extracted.manifest.library {
+            Some(ref library) => {
+                self.register_library(&extracted.manifest, library, &install_path)?
             }
-        } else {
-            None
+            None => None,
+        };
+        let plugin_link = match extracted.manifest.plugin {
+            Some(ref plugin) => {
+                Some(self.link_plugin(&extracted.manifest, plugin, &install_path)?)
+            }
+            None => None,
         };
 
         // Create and save metadata
@@ -349,12 +1213,91 @@ impl Installer {
         let mut metadata =
             self.create_metadata(&extracted.manifest, &install_path, installed_files);
         metadata.desktop_entry = desktop_entry;
-        metadata.service_file = service_file;
-        metadata.service_name = service_name;
-        metadata.bin_symlink = bin_symlink;
+        if !config.temporary_package {
+            metadata.source = package_path.canonicalize().ok();
+        }
+        // The main service stays addressable through service_file/service_name
+        let main_service = service_units
+            .iter()
+            .find(|unit| unit.name == extracted.manifest.service_name())
+            .or_else(|| service_units.iter().find(|u| u.kind == UnitKind::Service))
+            .or_else(|| service_units.first());
+        metadata.service_file = main_service.map(|unit| unit.path.clone());
+        metadata.service_name = main_service.map(|unit| unit.name.clone());
+        metadata.service_units = service_units;
+        metadata.mime_defaults = mime_defaults;
+        metadata.completions = completions;
+        metadata.man_pages = man_pages;
+        metadata.thumbnailer = thumbnailer;
+        metadata.dbus_services = dbus_services;
+        metadata.gschemas = gschemas;
+        metadata.icons = icons;
+        metadata.mime_definitions = mime_definitions;
+        metadata.tmpfiles = tmpfiles;
+        metadata.init_system = init_system;
+        metadata.cron = cron;
+        if metadata.service_name.is_some() {
+            let scope = extracted.manifest.install_scope;
+            metadata.env_file = Some(extracted.manifest.env_file(scope));
+        }
+        metadata.bin_symlink = extracted
+            .manifest
+            .entry
+            .as_ref()
+            .and_then(|entry| bin_symlinks.iter().find(|p| p.ends_with(entry)).cloned());
+        metadata.bin_symlinks = bin_symlinks;
+        metadata.ld_config = ld_config;
+        metadata.plugin_link = plugin_link;
+        metadata.data_dirs = data_dirs;
+        metadata.cache_dirs = cache_dirs;
+        metadata.log_dirs = log_dirs;
+        // Directories created by an earlier version are still the package's
+        let was_dependency = previous.as_ref().is_some_and(|p| p.installed_as_dependency);
+        let held = previous.as_ref().is_some_and(|p| p.held);
+        let mut created_dirs = previous.map(|p| p.created_dirs).unwrap_or_default();
+        created_dirs.extend(dir_tracker.finish());
+        // Data, cache and log directories are preserved on uninstall
+        let managed: Vec<_> = metadata
+            .data_dirs
+            .iter()
+            .chain(&metadata.cache_dirs)
+            .chain(&metadata.log_dirs)
+            .collect();
+        created_dirs.retain(|dir| {
+            !dir.starts_with(&install_path) && !managed.iter().any(|m| dir.starts_with(m))
+        });
+        created_dirs.sort();
+        created_dirs.dedup();
+        metadata.created_dirs = created_dirs;
+        metadata.installed_size = metadata.disk_usage();
+        metadata.installed_as_dependency = config.as_dependency || was_dependency;
+        metadata.held = held;
+        metadata.package_sha256 = utils::sha256_file(package_path).ok();
+        self.persist_uninstall_scripts(&extracted, &mut metadata)?;
+
+        // Keep the package so this version can be restored by a rollback
+        if let Some(ref transaction) = transaction {
+            let scope = extracted.manifest.install_scope;
+            for change in &transaction.packages {
+                if let Err(e) = history::cache_package(scope, package_path, change) {
+                    self.report_progress(InstallProgress::Log {
+                        message: format!("Warning: failed to cache package: {}", e),
+                    });
+                }
+            }
+        }
 
         metadata.save(extracted.manifest.install_scope)?;
 
+        if let Some(stopped) = stopped_services {
+            self.restart_services(
+                stopped.finish(),
+                &metadata,
+                &extracted.manifest,
+                config.restart_policy,
+            )?;
+        }
+
         self.report_progress(InstallProgress::Log {
             message: "Installation completed successfully.".to_string(),
         });
@@ -363,29 +1306,316 @@ impl Installer {
         Ok(metadata)
     }
 
-    /// Check if we have sufficient permissions
-    fn check_permissions(&self, manifest: &Manifest, install_path: &Path) -> IntResult<()> {
-        use crate::security;
+    /// Stop the running services of the installed version
+    ///
+    /// Returns `None` if none of its units are running.
+    fn stop_services(&self, previous: &InstallMetadata) -> Option<StoppedServices> {
+        let mut units: Vec<(String, UnitKind)> = previous
+            .service_units
+            .iter()
+            .map(|unit| (unit.name.clone(), unit.kind))
+            .collect();
+        if units.is_empty() {
+            // Metadata written before multiple units were tracked
+            units.extend(
+                previous
+                    .service_name
+                    .clone()
+                    .map(|name| (name, UnitKind::Service)),
+            );
+        }
 
-        if manifest.install_scope == InstallScope::System {
-            // System install requires root or polkit
-            if !security::has_root_privileges() {
-                // Check if we can write to system directories
-                if !security::can_write_system_dir(install_path) {
-                    return Err(IntError::InsufficientPermissions(
-                        "System installation requires administrator privileges".to_string(),
-                    ));
+        let scope = previous.install_scope;
+        let manager = ServiceManager::with_init_system(
+            previous.init_system.unwrap_or_else(InitSystem::detect),
+        );
+
+        // Timers and sockets first so they cannot start their services again
+        units.sort_by_key(|(_, kind)| *kind == UnitKind::Service);
+        let running: Vec<String> = units
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| manager.is_active(name, scope))
+            .collect();
+        if running.is_empty() {
+            return None;
+        }
+
+        for name in &running {
+            self.report_progress(InstallProgress::Log {
+                message: format!("Stopping service {} for upgrade...", name),
+            });
+            let _ = manager.stop(name, scope);
+        }
+
+        Some(StoppedServices {
+            manager,
+            scope,
+            units: running,
+            restore: true,
+        })
+    }
+
+    /// Unregister the units of the installed version that the new one no
+    /// longer ships, so they don't stay enabled
+    fn remove_dropped_units(
+        &self,
+        previous: &InstallMetadata,
+        units: &[ServiceUnit],
+    ) -> IntResult<()> {
+        let kept = |path: &Path| units.iter().any(|unit| unit.path == path);
+        let scope = previous.install_scope;
+        let manager = ServiceManager::with_init_system(
+            previous.init_system.unwrap_or_else(InitSystem::detect),
+        );
+
+        if previous.service_units.is_empty() {
+            // Metadata written before multiple units were tracked
+            if let (Some(file), Some(name)) = (&previous.service_file, &previous.service_name) {
+                if !kept(file) {
+                    self.report_progress(InstallProgress::Log {
+                        message: format!("Removing service {} dropped by the upgrade...", name),
+                    });
+                    manager.unregister(file, name, scope)?;
                 }
             }
+            return Ok(());
         }
 
-        Ok(())
+        let dropped: Vec<ServiceUnit> = previous
+            .service_units
+            .iter()
+            .filter(|unit| !kept(&unit.path))
+            .cloned()
+            .collect();
+        if dropped.is_empty() {
+            return Ok(());
+        }
+
+        let names: Vec<&str> = dropped.iter().map(|unit| unit.name.as_str()).collect();
+        self.report_progress(InstallProgress::Log {
+            message: format!("Removing {} dropped by the upgrade...", names.join(", ")),
+        });
+        manager.unregister_units(&dropped, scope)
+    }
+
+    /// Start services stopped for an upgrade, according to the restart policy
+    fn restart_services(
+        &self,
+        stopped: Vec<String>,
+        metadata: &InstallMetadata,
+        manifest: &Manifest,
+        policy: RestartPolicy,
+    ) -> IntResult<()> {
+        let scope = metadata.install_scope;
+        let manager = ServiceManager::with_init_system(
+            metadata.init_system.unwrap_or_else(InitSystem::detect),
+        );
+
+        // Skip units dropped by the new version or already started by it
+        let units: Vec<&ServiceUnit> = stopped
+            .iter()
+            .filter_map(|name| metadata.service_units.iter().find(|u| &u.name == name))
+            .filter(|unit| !manager.is_active(&unit.name, scope))
+            .collect();
+        if units.is_empty() {
+            return Ok(());
+        }
+
+        let names: Vec<&str> = units.iter().map(|unit| unit.name.as_str()).collect();
+        let restart = match policy {
+            RestartPolicy::Restart => true,
+            RestartPolicy::LeaveStopped => false,
+            RestartPolicy::Ask => {
+                self.confirm(&format!("Restart {} after the upgrade?", names.join(", ")))
+            }
+        };
+        if !restart {
+            self.report_progress(InstallProgress::Log {
+                message: format!("Leaving {} stopped after upgrade.", names.join(", ")),
+            });
+            return Ok(());
+        }
+
+        for unit in &units {
+            self.report_progress(InstallProgress::Log {
+                message: format!("Restarting service {}...", unit.name),
+            });
+            manager.start(&unit.name, scope)?;
+        }
+        for unit in units.iter().filter(|u| u.kind == UnitKind::Service) {
+            let health_check = manifest
+                .health_check
+                .as_ref()
+                .filter(|_| unit.name == manifest.service_name());
+            manager.wait_healthy(&unit.name, scope, health_check)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ask a yes/no question, answering yes without a confirmation callback
+    fn confirm(&self, question: &str) -> bool {
+        self.confirm_callback
+            .as_ref()
+            .is_none_or(|callback| callback(question))
+    }
+
+    /// Check if we have sufficient permissions
+    fn check_permissions(&self, manifest: &Manifest, install_path: &Path) -> IntResult<()> {
+        use crate::security;
+
+        if manifest.install_scope == InstallScope::System {
+            // System install requires root or polkit
+            if !security::has_root_privileges() {
+                // Check if we can write to system directories
+                if !security::can_write_system_dir(install_path) {
+                    return Err(IntError::InsufficientPermissions(
+                        "System installation requires administrator privileges".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that declared dependencies are available
+    ///
+    /// Missing required dependencies abort the installation (unless forced),
+    /// while missing optional dependencies are only reported as warnings.
+    ///
+    /// A `check_command` is code from the package, run with the installer's
+    /// privileges like a maintainer script. Without `run_check_commands`,
+    /// dependencies that declare one are skipped.
+    fn check_dependencies(
+        &self,
+        manifest: &Manifest,
+        force: bool,
+        run_check_commands: bool,
+    ) -> IntResult<()> {
+        // Checks run on the host, which says nothing about the root's contents
+        if root::is_set() && !manifest.dependencies.is_empty() {
+            self.report_progress(InstallProgress::Log {
+                message: "Skipping dependency checks for alternate root".to_string(),
+            });
+            return Ok(());
+        }
+
+        for dependency in &manifest.dependencies {
+            if dependency.check_command.is_some() && !run_check_commands {
+                continue;
+            }
+            self.report_progress(InstallProgress::Log {
+                message: format!("Checking dependency: {}...", dependency.name),
+            });
+
+            if self.is_dependency_available(dependency) {
+                continue;
+            }
+
+            let reason = dependency
+                .reason
+                .as_deref()
+                .map(|r| format!(" ({})", r))
+                .unwrap_or_default();
+
+            if dependency.optional {
+                self.report_progress(InstallProgress::Log {
+                    message: format!(
+                        "Warning: optional dependency '{}' not found{}",
+                        dependency.name, reason
+                    ),
+                });
+            } else if force {
+                self.report_progress(InstallProgress::Log {
+                    message: format!(
+                        "Warning: required dependency '{}' not found{}, installing anyway",
+                        dependency.name, reason
+                    ),
+                });
+            } else {
+                return Err(IntError::MissingDependency(format!(
+                    "{}{}",
+                    dependency.name, reason
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the installed version may be replaced by the package
+    ///
+    /// Held packages keep their version and downgrades are refused, unless
+    /// overridden by `force` (or `allow_downgrade` for downgrades).
+    fn check_installed_version(
+        &self,
+        manifest: &Manifest,
+        config: &InstallConfig,
+    ) -> IntResult<()> {
+        let Ok(installed) = InstallMetadata::load(&manifest.name, manifest.install_scope) else {
+            return Ok(());
+        };
+        let version = &manifest.package_version;
+        if installed.held && installed.package_version != *version {
+            if !config.force {
+                return Err(IntError::PackageHeld {
+                    package: manifest.name.clone(),
+                    version: installed.package_version,
+                });
+            }
+            self.report_progress(InstallProgress::Log {
+                message: format!(
+                    "Warning: {} is held at {}, replacing it anyway",
+                    manifest.name, installed.package_version
+                ),
+            });
+        }
+
+        let installed = installed.package_version;
+        if utils::compare_versions(version, &installed) != std::cmp::Ordering::Less {
+            return Ok(());
+        }
+
+        if !(config.allow_downgrade || config.force) {
+            return Err(IntError::DowngradeRefused {
+                package: manifest.name.clone(),
+                installed,
+                version: version.clone(),
+            });
+        }
+        self.report_progress(InstallProgress::Log {
+            message: format!(
+                "Warning: downgrading {} from {} to {}",
+                manifest.name, installed, version
+            ),
+        });
+        Ok(())
+    }
+
+    /// Run a dependency's check command, or look it up in PATH
+    fn is_dependency_available(&self, dependency: &Dependency) -> bool {
+        let output = match dependency.check_command {
+            Some(ref check) => Command::new("sh").arg("-c").arg(check).output(),
+            None => Command::new("which").arg(&dependency.name).output(),
+        };
+
+        output.map(|o| o.status.success()).unwrap_or(false)
     }
 
     /// Copy payload to installation directory
     fn copy_payload(&self, payload_dir: &Path, install_path: &Path) -> IntResult<Vec<PathBuf>> {
         use walkdir::WalkDir;
 
+        let total = WalkDir::new(payload_dir)
+            .follow_links(false)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| !entry.file_type().is_dir())
+            .count();
+        self.report_progress(InstallProgress::CopyingFiles { current: 0, total });
+
         let mut installed_files = Vec::new();
 
         for entry in WalkDir::new(payload_dir).follow_links(false) {
@@ -413,6 +1643,57 @@ impl Installer {
                     reason: e.to_string(),
                 })?;
 
+                installed_files.push(dst_path);
+                self.report_progress(InstallProgress::CopyingFiles {
+                    current: installed_files.len(),
+                    total,
+                });
+            }
+        }
+
+        Ok(installed_files)
+    }
+
+    /// Copy declared documentation and license files to their standard locations
+    fn install_docs(
+        &self,
+        extracted: &ExtractedPackage,
+        install_path: &Path,
+    ) -> IntResult<Vec<PathBuf>> {
+        let manifest = &extracted.manifest;
+        let scope = manifest.install_scope;
+        let targets = [
+            (&manifest.docs.files, manifest.doc_dir(scope)),
+            (&manifest.docs.licenses, manifest.license_dir(scope)),
+        ];
+
+        let mut installed_files = Vec::new();
+
+        for (files, target_dir) in targets {
+            for file in files {
+                let src_path = install_path.join(file);
+                if !src_path.exists() {
+                    return Err(IntError::InvalidPackage(format!(
+                        "Documentation file not found in payload: {}",
+                        file.display()
+                    )));
+                }
+
+                // Validation guarantees a file name is present
+                let file_name = file.file_name().unwrap_or_default();
+                let dst_path = target_dir.join(file_name);
+
+                self.report_progress(InstallProgress::Log {
+                    message: format!("Installing {}...", dst_path.display()),
+                });
+
+                utils::ensure_dir(&target_dir)?;
+                fs::copy(&src_path, &dst_path).map_err(|e| IntError::FileCopyFailed {
+                    source: src_path.display().to_string(),
+                    dest: dst_path.display().to_string(),
+                    reason: e.to_string(),
+                })?;
+
                 installed_files.push(dst_path);
             }
         }
@@ -420,10 +1701,104 @@ impl Installer {
         Ok(installed_files)
     }
 
+    /// Install man pages into the scope's man directory
+    ///
+    /// Declared pages go to `man<section>/`; without declarations the
+    /// payload's `share/man` tree (including translations) is copied as-is.
+    fn install_man_pages(
+        &self,
+        manifest: &Manifest,
+        install_path: &Path,
+    ) -> IntResult<Vec<PathBuf>> {
+        use walkdir::WalkDir;
+
+        let man_dir = manifest.install_scope.man_path();
+        let mut pages = Vec::new();
+
+        if manifest.man_pages.is_empty() {
+            let source_dir = install_path.join("share/man");
+            if !source_dir.is_dir() {
+                return Ok(vec![]);
+            }
+            for entry in WalkDir::new(&source_dir).follow_links(false) {
+                let entry = entry.map_err(|e| {
+                    IntError::Custom(format!("Failed to walk man page directory: {}", e))
+                })?;
+                if entry.file_type().is_file() {
+                    let relative = entry.path().strip_prefix(&source_dir).map_err(|e| {
+                        IntError::Custom(format!("Failed to get relative path: {}", e))
+                    })?;
+                    pages.push((entry.path().to_path_buf(), man_dir.join(relative)));
+                }
+            }
+        } else {
+            for page in &manifest.man_pages {
+                // Validation guarantees a file name with a section
+                let section = manifest::man_section(page).unwrap_or('1');
+                let target = man_dir
+                    .join(format!("man{}", section))
+                    .join(page.file_name().unwrap_or_default());
+                pages.push((install_path.join(page), target));
+            }
+        }
+
+        let mut installed = Vec::new();
+        for (source, target) in pages {
+            if let Some(parent) = target.parent() {
+                utils::ensure_dir(parent)?;
+            }
+            fs::copy(&source, &target).map_err(|e| IntError::FileCopyFailed {
+                source: source.display().to_string(),
+                dest: target.display().to_string(),
+                reason: e.to_string(),
+            })?;
+            installed.push(target);
+        }
+
+        if !installed.is_empty() {
+            self.report_progress(InstallProgress::Log {
+                message: format!("Installed {} man page(s).", installed.len()),
+            });
+            utils::run_mandb();
+        }
+
+        Ok(installed)
+    }
+
+    /// Install shell completion files where each shell loads them
+    fn install_completions(
+        &self,
+        manifest: &Manifest,
+        install_path: &Path,
+    ) -> IntResult<Vec<PathBuf>> {
+        let scope = manifest.install_scope;
+        let mut installed = Vec::new();
+
+        for (shell, file) in manifest.completions.files() {
+            let source = install_path.join(file);
+            let target_dir = scope.completion_path(shell);
+            let target = target_dir.join(shell.completion_file_name(file));
+
+            self.report_progress(InstallProgress::Log {
+                message: format!("Installing {}...", target.display()),
+            });
+
+            utils::ensure_dir(&target_dir)?;
+            fs::copy(&source, &target).map_err(|e| IntError::FileCopyFailed {
+                source: source.display().to_string(),
+                dest: target.display().to_string(),
+                reason: e.to_string(),
+            })?;
+            installed.push(target);
+        }
+
+        Ok(installed)
+    }
+
     /// Set permissions on installed files
     fn set_permissions(&self, install_path: &Path, manifest: &Manifest) -> IntResult<()> {
-        // Make entry executable if specified
-        if let Some(ref entry) = manifest.entry {
+        // Make all executable entries executable
+        for entry in manifest.executable_entries() {
             let entry_path = install_path.join("bin").join(entry);
             if entry_path.exists() {
                 utils::make_executable(&entry_path)?;
@@ -433,24 +1808,352 @@ impl Installer {
         Ok(())
     }
 
-    /// Execute installation script
-    fn execute_script(&self, script_path: &Path, install_path: &Path) -> IntResult<()> {
-        // Make script executable
-        utils::make_executable(script_path)?;
+    /// Symlink an executable entry into the scope's bin directory
+    ///
+    /// Returns `None` if the entry does not exist in the payload.
+    fn create_bin_symlink(
+        &self,
+        manifest: &Manifest,
+        install_path: &Path,
+        entry: &str,
+    ) -> IntResult<Option<PathBuf>> {
+        let entry_path = install_path.join("bin").join(entry);
+        if !entry_path.exists() {
+            return Ok(None);
+        }
+
+        let bin_dir = manifest.install_scope.bin_path();
+        utils::ensure_dir(&bin_dir)?;
+        let symlink_path = bin_dir.join(entry);
+
+        // Create symlink (remove existing if any)
+        if symlink_path.exists() {
+            fs::remove_file(&symlink_path).ok();
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+            symlink(root::target(&entry_path), &symlink_path)
+                .map_err(|e| IntError::Custom(format!("Failed to create symlink: {}", e)))?;
+            Ok(Some(symlink_path))
+        }
+        #[cfg(not(unix))]
+        {
+            Ok(None) // Symlinks not supported/implemented for this platform yet
+        }
+    }
+
+    /// Register a library's directories with the dynamic linker
+    ///
+    /// Only system installs can be registered; user installs rely on the
+    /// application setting its own library path.
+    fn register_library(
+        &self,
+        manifest: &Manifest,
+        library: &LibraryConfig,
+        install_path: &Path,
+    ) -> IntResult<Option<PathBuf>> {
+        if library.lib_dirs.is_empty() {
+            return Ok(None);
+        }
+
+        if manifest.install_scope == InstallScope::User {
+            self.report_progress(InstallProgress::Log {
+                message: "Skipping dynamic linker registration for user installation".to_string(),
+            });
+            return Ok(None);
+        }
+
+        let conf_dir = root::path(Path::new("/etc/ld.so.conf.d"));
+        utils::ensure_dir(&conf_dir)?;
+        let conf_path = conf_dir.join(format!("{}.conf", manifest.name));
+
+        let content: String = library
+            .lib_dirs
+            .iter()
+            .map(|dir| format!("{}\n", root::target(&install_path.join(dir)).display()))
+            .collect();
+
+        self.report_progress(InstallProgress::Log {
+            message: format!("Registering shared libraries in {}...", conf_path.display()),
+        });
+        fs::write(&conf_path, content).map_err(|e| {
+            IntError::Custom(format!(
+                "Failed to write linker configuration {}: {}",
+                conf_path.display(),
+                e
+            ))
+        })?;
+
+        utils::run_ldconfig();
+
+        Ok(Some(conf_path))
+    }
+
+    /// Install cron jobs for the package's scheduled tasks
+    ///
+    /// Jobs left by the previous version are removed when none are needed
+    /// any more.
+    fn install_cron(
+        &self,
+        manifest: &Manifest,
+        install_path: &Path,
+        previous: Option<&CronTable>,
+        init_system: Option<InitSystem>,
+    ) -> IntResult<Option<CronTable>> {
+        let tasks = manifest.cron_tasks(init_system);
+        if tasks.is_empty() {
+            if let Some(previous) = previous {
+                previous.remove(&manifest.name)?;
+            }
+            return Ok(None);
+        }
 
-        // Execute script with install_path as working directory
-        let output = Command::new(script_path)
-            .current_dir(install_path)
-            .env("INSTALL_PATH", install_path)
-            .output()
-            .map_err(|e| IntError::Custom(format!("Failed to execute script: {}", e)))?;
+        self.report_progress(InstallProgress::Log {
+            message: format!("Installing {} cron job(s)...", tasks.len()),
+        });
+        let context = ServiceContext::new(manifest, install_path);
+        CronTable::install(manifest, &tasks, &context).map(Some)
+    }
+
+    /// Install the package's tmpfiles.d fragments and apply them
+    ///
+    /// Fragments are rendered with the same `{{NAME}}` variables as service
+    /// definitions.
+    fn install_tmpfiles(
+        &self,
+        extracted: &ExtractedPackage,
+        install_path: &Path,
+    ) -> IntResult<Vec<PathBuf>> {
+        let manifest = &extracted.manifest;
+        if manifest.tmpfiles.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let scope = manifest.install_scope;
+        let conf_dir = scope.tmpfiles_path();
+        utils::ensure_dir(&conf_dir)?;
+        let context = ServiceContext::new(manifest, install_path);
+
+        let mut installed = Vec::new();
+        for fragment in &manifest.tmpfiles {
+            let content = service::read_service_source(extracted, fragment)?.ok_or_else(|| {
+                IntError::InvalidPackage(format!(
+                    "tmpfiles fragment not found in services/: {}",
+                    fragment
+                ))
+            })?;
+            let conf_path = conf_dir.join(fragment);
 
-        if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(-1);
-            return Err(IntError::ScriptExecutionFailed {
-                script: script_path.display().to_string(),
-                exit_code,
+            self.report_progress(InstallProgress::Log {
+                message: format!("Installing {}...", conf_path.display()),
             });
+            let content = root::target_content(&service::render_template(&content, &context)?);
+            fs::write(&conf_path, content).map_err(|e| {
+                IntError::Custom(format!(
+                    "Failed to write tmpfiles fragment {}: {}",
+                    conf_path.display(),
+                    e
+                ))
+            })?;
+            installed.push(conf_path);
+        }
+
+        if root::is_set() {
+            let user = match scope {
+                InstallScope::User => " --user",
+                InstallScope::System => "",
+            };
+            let fragments: Vec<String> = installed
+                .iter()
+                .map(|path| root::target(path).display().to_string())
+                .collect();
+            root::defer(&format!(
+                "systemd-tmpfiles{} --create {}",
+                user,
+                fragments.join(" ")
+            ))?;
+            return Ok(installed);
+        }
+
+        // Create the declared files and directories now instead of at next boot
+        let mut cmd = Command::new("systemd-tmpfiles");
+        if scope == InstallScope::User {
+            cmd.arg("--user");
+        }
+        cmd.arg("--create").args(&installed);
+        match cmd.output() {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => self.report_progress(InstallProgress::Log {
+                message: format!(
+                    "Warning: systemd-tmpfiles reported errors: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            }),
+            Err(_) => self.report_progress(InstallProgress::Log {
+                message: "Warning: systemd-tmpfiles not found; fragments apply at next boot"
+                    .to_string(),
+            }),
+        }
+
+        Ok(installed)
+    }
+
+    /// Link the plugin into its host application's plugin directory
+    fn link_plugin(
+        &self,
+        manifest: &Manifest,
+        plugin: &PluginConfig,
+        install_path: &Path,
+    ) -> IntResult<PathBuf> {
+        // Validation keeps the plugin directory inside the host's install path
+        let host = InstallMetadata::load(&plugin.host, manifest.install_scope)
+            .map_err(|_| IntError::MissingDependency(format!("{} (plugin host)", plugin.host)))?;
+        let plugin_dir = host.install_path.join(&plugin.plugin_dir);
+
+        utils::ensure_dir(&plugin_dir)?;
+        let link_path = plugin_dir.join(&manifest.name);
+
+        self.report_progress(InstallProgress::Log {
+            message: format!("Linking plugin into {}...", plugin_dir.display()),
+        });
+
+        if link_path.symlink_metadata().is_ok() {
+            fs::remove_file(&link_path).ok();
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+            symlink(root::target(install_path), &link_path)
+                .map_err(|e| IntError::Custom(format!("Failed to link plugin: {}", e)))?;
+        }
+        #[cfg(not(unix))]
+        {
+            utils::copy_dir_recursive(install_path, &link_path)?;
+        }
+
+        Ok(link_path)
+    }
+
+    /// Create data, cache or log directories
+    ///
+    /// Returns the directories the package owns: the ones created now and
+    /// the ones an earlier version created. A directory that already existed
+    /// belongs to someone else and is not recorded, so purging leaves it.
+    fn create_managed_dirs(
+        &self,
+        dirs: Vec<PathBuf>,
+        scope: InstallScope,
+        owned: &[PathBuf],
+    ) -> IntResult<Vec<PathBuf>> {
+        let mut created = Vec::new();
+        for dir in dirs {
+            let existed = dir.exists();
+            self.report_progress(InstallProgress::Log {
+                message: format!("Creating directory {}...", dir.display()),
+            });
+            self.create_managed_dir(&dir, scope)?;
+            if !existed || owned.contains(&dir) {
+                created.push(dir);
+            }
+        }
+        Ok(created)
+    }
+
+    /// Create a data/cache/log directory owned by the installing user
+    fn create_managed_dir(&self, dir: &Path, scope: InstallScope) -> IntResult<()> {
+        utils::ensure_dir(dir)?;
+
+        // An elevated user-scope install must not leave root-owned directories
+        // in the user's home
+        if scope == InstallScope::User {
+            utils::chown_to_invoking_user(dir)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the script attached to a lifecycle hook, if the package ships one
+    fn run_hook(
+        &self,
+        extracted: &ExtractedPackage,
+        hook: ScriptHook,
+        working_dir: &Path,
+        install_path: &Path,
+    ) -> IntResult<()> {
+        let script = match extracted.manifest.script(hook) {
+            Some(script) => script,
+            None => return Ok(()),
+        };
+
+        let full_script_path = extracted.extract_dir.join(&script.path);
+        if !full_script_path.exists() {
+            return Ok(());
+        }
+
+        let script_name = script.path.display().to_string();
+        self.report_progress(InstallProgress::Log {
+            message: format!("Executing {} script: {}...", hook.as_str(), script_name),
+        });
+        self.report_progress(InstallProgress::ExecutingScript {
+            script: script_name,
+        });
+
+        execute_script(
+            &full_script_path,
+            &script,
+            hook,
+            working_dir,
+            install_path,
+            &|line| {
+                self.report_progress(InstallProgress::ScriptOutput {
+                    line: line.to_string(),
+                })
+            },
+        )
+    }
+
+    /// Keep the uninstall scripts next to the installation metadata
+    ///
+    /// The package is gone by the time it is uninstalled, so the scripts are
+    /// copied now. Copies left by an earlier version are replaced.
+    fn persist_uninstall_scripts(
+        &self,
+        extracted: &ExtractedPackage,
+        metadata: &mut InstallMetadata,
+    ) -> IntResult<()> {
+        let scripts_dir =
+            InstallMetadata::scripts_dir(&metadata.package_name, metadata.install_scope);
+        utils::remove_dir_safe(&scripts_dir)?;
+
+        for hook in [ScriptHook::PreUninstall, ScriptHook::PostUninstall] {
+            let script = match extracted.manifest.script(hook) {
+                Some(script) => script,
+                None => continue,
+            };
+            let source = extracted.extract_dir.join(&script.path);
+            if !source.exists() {
+                continue;
+            }
+
+            utils::ensure_dir(&scripts_dir)?;
+            let target = scripts_dir.join(hook.as_str());
+            fs::copy(&source, &target).map_err(|e| IntError::FileCopyFailed {
+                source: source.display().to_string(),
+                dest: target.display().to_string(),
+                reason: e.to_string(),
+            })?;
+
+            let persisted = MaintainerScript {
+                path: target,
+                ..script
+            };
+            match hook {
+                ScriptHook::PreUninstall => metadata.pre_uninstall = Some(persisted),
+                _ => metadata.post_uninstall = Some(persisted),
+            }
         }
 
         Ok(())
@@ -462,14 +2165,42 @@ impl Installer {
         desktop_integration.create_entry(manifest, install_path)
     }
 
-    /// Register systemd service
-    fn register_service(
+    /// Register the desktop entry as default application for MIME types
+    ///
+    /// Defaults are per user, so this only applies to user installations.
+    /// Failures are reported as warnings.
+    fn set_default_applications(
         &self,
-        extracted: &ExtractedPackage,
-        install_path: &Path,
-    ) -> IntResult<(PathBuf, String)> {
-        let service_manager = ServiceManager::new();
-        service_manager.register(extracted, install_path)
+        desktop_entry: &Path,
+        mime_types: &[String],
+        scope: InstallScope,
+        previous: Option<&InstallMetadata>,
+    ) -> BTreeMap<String, Option<String>> {
+        if scope != InstallScope::User {
+            self.report_progress(InstallProgress::Log {
+                message: "Warning: default applications can only be set for user installations"
+                    .to_string(),
+            });
+            return BTreeMap::new();
+        }
+
+        self.report_progress(InstallProgress::Log {
+            message: format!(
+                "Setting default application for {}...",
+                mime_types.join(", ")
+            ),
+        });
+        let recorded = previous
+            .map(|p| p.mime_defaults.clone())
+            .unwrap_or_default();
+        DesktopIntegration::new()
+            .set_default_applications(desktop_entry, mime_types, &recorded)
+            .unwrap_or_else(|e| {
+                self.report_progress(InstallProgress::Log {
+                    message: format!("Warning: {}", e),
+                });
+                BTreeMap::new()
+            })
     }
 
     /// Create installation metadata
@@ -491,19 +2222,329 @@ impl Installer {
             service_file: None,
             service_name: None,
             bin_symlink: None,
+            bin_symlinks: vec![],
+            kind: manifest.kind,
+            ld_config: None,
+            plugin_link: None,
+            data_dirs: vec![],
+            cache_dirs: vec![],
+            log_dirs: vec![],
+            mime_defaults: BTreeMap::new(),
+            completions: vec![],
+            man_pages: vec![],
+            thumbnailer: None,
+            dbus_services: vec![],
+            gschemas: vec![],
+            icons: vec![],
+            mime_definitions: vec![],
+            tmpfiles: vec![],
+            service_units: vec![],
+            init_system: None,
+            env_file: None,
+            cron: None,
+            pre_uninstall: None,
+            post_uninstall: None,
+            created_dirs: vec![],
+            source: None,
+            manifest: Some(manifest.clone()),
+            installed_size: 0,
+            installed_as_dependency: false,
+            held: false,
+            package_sha256: None,
         }
     }
 
-    /// Report progress
+    /// Report progress, also recording it as a tracing event
     fn report_progress(&self, progress: InstallProgress) {
+        match progress {
+            InstallProgress::Log { ref message } => tracing::info!("{}", message),
+            InstallProgress::ScriptOutput { ref line } => tracing::info!("| {}", line),
+            InstallProgress::Extracting { .. }
+            | InstallProgress::Verifying { .. }
+            | InstallProgress::CopyingFiles { .. } => tracing::trace!(?progress),
+            _ => tracing::debug!(?progress),
+        }
+
         if let Some(ref callback) = self.progress_callback {
             callback(progress);
         }
     }
 }
 
+/// Services stopped before an upgrade
+///
+/// Records which units were running. If the upgrade fails before
+/// `finish` is called, dropping the guard starts them again so the
+/// previous state is restored.
+struct StoppedServices {
+    manager: ServiceManager,
+    scope: InstallScope,
+    units: Vec<String>,
+    restore: bool,
+}
+
+impl StoppedServices {
+    /// Mark the upgrade as done and hand back the stopped units
+    fn finish(mut self) -> Vec<String> {
+        self.restore = false;
+        std::mem::take(&mut self.units)
+    }
+}
+
+impl Drop for StoppedServices {
+    fn drop(&mut self) {
+        if self.restore {
+            // Services before their triggers
+            for name in self.units.iter().rev() {
+                let _ = self.manager.start(name, self.scope);
+            }
+        }
+    }
+}
+
+/// Execute a maintainer script
+///
+/// The script runs in `working_dir` with `INSTALL_PATH` and
+/// `INT_SCRIPT_HOOK` set, and fails if it exits unsuccessfully or exceeds
+/// its timeout. Each line the script prints is passed to `output` as it is
+/// printed.
+pub(crate) fn execute_script(
+    script_path: &Path,
+    script: &MaintainerScript,
+    hook: ScriptHook,
+    working_dir: &Path,
+    install_path: &Path,
+    output: &dyn Fn(&str),
+) -> IntResult<()> {
+    // Scripts would act on the host rather than the alternate root
+    if root::is_set() {
+        return root::defer(&format!(
+            "# {} script {} was not run",
+            hook.as_str(),
+            script.path.display()
+        ));
+    }
+
+    let mut cmd = match script.interpreter {
+        Some(ref interpreter) => {
+            let mut cmd = Command::new(interpreter);
+            cmd.arg(script_path);
+            cmd
+        }
+        None => {
+            // Make script executable
+            utils::make_executable(script_path)?;
+            Command::new(script_path)
+        }
+    };
+
+    // A timeout must also stop whatever the script started
+    utils::new_process_group(&mut cmd);
+
+    let mut child = cmd
+        .current_dir(working_dir)
+        .env("INSTALL_PATH", install_path)
+        .env("INT_SCRIPT_HOOK", hook.as_str())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| IntError::Custom(format!("Failed to execute script: {}", e)))?;
+
+    // Pipes are read on their own threads, so a background process started
+    // by the script and keeping them open can't hold up the installation
+    let (sender, lines) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        forward_lines(stdout, sender.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_lines(stderr, sender);
+    }
+
+    let deadline = script
+        .timeout
+        .map(|seconds| Instant::now() + Duration::from_secs(seconds));
+    let status = loop {
+        match lines.recv_timeout(Duration::from_millis(50)) {
+            Ok(line) => output(&line),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => std::thread::sleep(Duration::from_millis(50)),
+        }
+        if let Some(status) = child.try_wait().map_err(IntError::IoError)? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            utils::kill_process_group(&mut child);
+            let _ = child.wait();
+            return Err(IntError::ScriptTimeout(format!(
+                "{} exceeded {} seconds",
+                script_path.display(),
+                script.timeout.unwrap_or_default()
+            )));
+        }
+    };
+    // Output printed just before the script exited; a background process
+    // that keeps writing to the pipes must not hold up the installation
+    let drain_deadline = Instant::now() + Duration::from_secs(1);
+    while let Ok(line) = lines.recv_timeout(Duration::from_millis(100)) {
+        output(&line);
+        if Instant::now() >= drain_deadline {
+            break;
+        }
+    }
+
+    if !status.success() {
+        let exit_code = status.code().unwrap_or(-1);
+        return Err(IntError::ScriptExecutionFailed {
+            script: script_path.display().to_string(),
+            exit_code,
+        });
+    }
+
+    Ok(())
+}
+
+/// Send the lines read from a script's pipe until it is closed
+fn forward_lines(pipe: impl std::io::Read + Send + 'static, sender: mpsc::Sender<String>) {
+    use std::io::BufRead;
+
+    std::thread::spawn(move || {
+        let reader = std::io::BufReader::new(pipe);
+        for line in reader.lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Payload files and their destinations, sorted by destination
+fn planned_files(payload_dir: &Path, install_path: &Path) -> IntResult<Vec<PlannedFile>> {
+    use walkdir::WalkDir;
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(payload_dir).follow_links(false) {
+        let entry = entry
+            .map_err(|e| IntError::Custom(format!("Failed to walk payload directory: {}", e)))?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(payload_dir)
+            .map_err(|e| IntError::Custom(format!("Failed to get relative path: {}", e)))?;
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        files.push(PlannedFile {
+            path: install_path.join(relative),
+            size,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Check that a declared license agreement was accepted
+fn check_eula(manifest: &Manifest, config: &InstallConfig) -> IntResult<()> {
+    if manifest.eula.is_some() && !config.eula_accepted {
+        return Err(IntError::EulaNotAccepted(manifest.name.clone()));
+    }
+    Ok(())
+}
+
+/// Pick the upgrade hook when upgrading and the package declares it
+fn select_hook(
+    manifest: &Manifest,
+    upgrading: bool,
+    install_hook: ScriptHook,
+    upgrade_hook: ScriptHook,
+) -> ScriptHook {
+    if upgrading && manifest.script(upgrade_hook).is_some() {
+        upgrade_hook
+    } else {
+        install_hook
+    }
+}
+
 impl Default for Installer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_managed_dirs_stay_below_base() {
+        let manifest = Manifest::from_str(
+            r#"{
+                "name": "test-app",
+                "package_version": "1.0.0",
+                "install_scope": "system",
+                "install_path": "/opt/test-app"
+            }"#,
+        )
+        .unwrap();
+        let mut metadata =
+            Installer::new().create_metadata(&manifest, Path::new("/opt/test-app"), vec![]);
+
+        let scope = InstallScope::System;
+        metadata.data_dirs = vec![scope.data_home().join("test-app")];
+        metadata.log_dirs = vec![
+            PathBuf::from("/var/log"),
+            scope.log_home().join("."),
+            scope.log_home().join("test-app/.."),
+            PathBuf::from("/etc/ssh"),
+        ];
+
+        // Purging removes exactly these directories
+        assert_eq!(
+            metadata.managed_dirs(),
+            vec![scope.data_home().join("test-app")]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_script_timeout_kills_process_group() {
+        let temp = tempfile::tempdir().unwrap();
+        let script_path = temp.path().join("hang.sh");
+        let pid_file = temp.path().join("child.pid");
+        fs::write(
+            &script_path,
+            format!("sleep 30 &\necho $! > {}\nsleep 30\n", pid_file.display()),
+        )
+        .unwrap();
+        let script = MaintainerScript {
+            path: PathBuf::from("hang.sh"),
+            interpreter: Some("sh".to_string()),
+            timeout: Some(1),
+        };
+
+        let result = execute_script(
+            &script_path,
+            &script,
+            ScriptHook::PostInstall,
+            temp.path(),
+            temp.path(),
+            &|_| {},
+        );
+        assert!(matches!(result, Err(IntError::ScriptTimeout(_))));
+
+        // The background child is gone too (at most a zombie awaiting reaping)
+        let pid = fs::read_to_string(&pid_file).unwrap();
+        let stat_path = format!("/proc/{}/stat", pid.trim());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let killed = loop {
+            let stat = fs::read_to_string(&stat_path).unwrap_or_default();
+            if stat.is_empty() || stat.contains(") Z ") {
+                break true;
+            }
+            if Instant::now() >= deadline {
+                break false;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert!(killed);
+    }
+}
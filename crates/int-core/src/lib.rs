@@ -13,10 +13,24 @@
 ///
 /// - `manifest`: Package manifest parsing and validation
 /// - `extractor`: Secure tar.gz archive extraction
+/// - `compression`: Compression of package archives (gzip, zstd, xz)
 /// - `installer`: Installation orchestration
+/// - `export`: Export and import of the installed-package set
+/// - `history`: Transaction history of installs and uninstalls
+/// - `index`: Installed file ownership index
+/// - `lock`: Per-scope lock serializing installs and uninstalls
 /// - `service`: systemd service management
+/// - `cron`: Cron jobs for scheduled tasks
+/// - `clean`: Cleanup of caches and leftovers
+/// - `doctor`: Detection and repair of orphaned installation state
+/// - `repair`: Repair of installed files from the package cache
+/// - `updates`: Update checks against vendor update feeds
+/// - `db`: SQLite installed-package database (`sqlite` feature)
+/// - `root`: Installing into an alternate root filesystem
+/// - `platform`: Detection of the running system (init system)
 /// - `desktop`: Desktop entry creation
 /// - `security`: Security validation and sandboxing
+/// - `signature`: Signature inspection of packages
 /// - `error`: Error types and handling
 /// - `utils`: Utility functions
 ///
@@ -44,23 +58,59 @@
 /// # }
 /// ```
 // Public modules
+pub mod clean;
+pub mod compression;
+pub mod cron;
+#[cfg(feature = "sqlite")]
+pub mod db;
 pub mod desktop;
+pub mod doctor;
 pub mod error;
+pub mod export;
 pub mod extractor;
+pub mod history;
+pub mod index;
 pub mod installer;
+pub mod lock;
 pub mod manifest;
+pub mod platform;
+pub mod repair;
+pub mod root;
 pub mod security;
 pub mod service;
+pub mod signature;
+pub mod updates;
 pub mod utils;
 
 // Re-export commonly used types
+pub use compression::Compression;
+pub use cron::CronTable;
+#[cfg(feature = "sqlite")]
+pub use db::PackageDatabase;
 pub use desktop::DesktopIntegration;
 pub use error::{IntError, IntResult};
-pub use extractor::{ExtractedPackage, PackageExtractor};
-pub use installer::{InstallConfig, InstallMetadata, InstallProgress, Installer};
-pub use manifest::{Dependency, DesktopEntry, InstallScope, Manifest};
+pub use export::{ExportedPackage, ImportReport, PackageSet};
+pub use extractor::{EntryKind, ExtractedPackage, PackageEntry, PackageExtractor};
+pub use history::{Operation, PackageChange, Transaction, TransactionResult};
+pub use index::{who_owns, FileOwner};
+pub use installer::{
+    FileKind, InstallConfig, InstallMetadata, InstallPlan, InstallProgress, InstalledFile,
+    Installer, PlanConflict, PlannedFile, PlannedScript, RestartPolicy,
+};
+pub use lock::OperationLock;
+pub use manifest::{
+    DbusService, Dependency, DesktopAction, DesktopEntry, HealthCheck, HealthProbe, IconSet,
+    InstallScope, LaunchCommand, LocalizedStrings, MaintainerScript, Manifest, PackageKind,
+    ScheduledTask, ScriptHook, Thumbnailer,
+};
 pub use security::SecurityValidator;
-pub use service::ServiceManager;
+pub use service::{
+    InitSystem, ServiceBackend, ServiceManager, ServicePreset, ServiceStatus, ServiceUnit, UnitKind,
+};
+pub use signature::{SignatureInfo, SignatureTrust};
+
+use std::io::ErrorKind;
+use std::sync::Arc;
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -68,39 +118,246 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Supported manifest version
 pub const MANIFEST_VERSION: &str = manifest::MANIFEST_VERSION;
 
+/// What uninstalling a package would remove
+///
+/// Only paths that currently exist are listed.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct UninstallReport {
+    /// Package name
+    pub package_name: String,
+    /// Package version
+    pub package_version: String,
+    /// Installed files
+    pub files: Vec<std::path::PathBuf>,
+    /// Directories removed entirely (install path; data directories when purging)
+    pub directories: Vec<std::path::PathBuf>,
+    /// Units stopped and unregistered
+    pub services: Vec<String>,
+    /// Binary and plugin symlinks
+    pub symlinks: Vec<std::path::PathBuf>,
+    /// Desktop entries
+    pub desktop_entries: Vec<std::path::PathBuf>,
+    /// Other integration files (icons, completions, man pages, ...)
+    pub integration_files: Vec<std::path::PathBuf>,
+    /// Maintainer scripts run during uninstallation
+    pub scripts: Vec<String>,
+}
+
+/// Installed package found by `Uninstaller::list_all_installed`
+#[derive(Debug, Clone)]
+pub struct InstalledPackage {
+    /// Scope the package is installed in
+    pub scope: InstallScope,
+    /// Installation metadata
+    pub metadata: InstallMetadata,
+    /// Other scope that has a package of the same name
+    pub also_installed_in: Option<InstallScope>,
+}
+
+/// Uninstallation progress state
+#[derive(Debug, Clone)]
+pub enum UninstallProgress {
+    ExecutingScript { script: String },
+    StoppingService,
+    RemovingIntegration,
+    RemovingFiles { current: usize, total: usize },
+    Completed,
+}
+
 /// Uninstaller for removing installed packages
-pub struct Uninstaller;
+pub struct Uninstaller {
+    /// Progress callback
+    progress_callback: Option<Arc<dyn Fn(UninstallProgress) + Send + Sync + 'static>>,
+    /// Whether maintainer scripts are skipped
+    skip_scripts: bool,
+    /// Whether to wait for a concurrent operation instead of failing
+    wait_for_lock: bool,
+}
 
 impl Uninstaller {
     /// Create a new uninstaller
     pub fn new() -> Self {
-        Self
+        Self {
+            progress_callback: None,
+            skip_scripts: false,
+            wait_for_lock: false,
+        }
+    }
+
+    /// Set progress callback
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(UninstallProgress) + Send + Sync + 'static,
+    {
+        self.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Wait for a concurrent install or uninstall in the same scope to
+    /// finish instead of failing with `IntError::OperationInProgress`
+    pub fn wait_for_lock(mut self, wait: bool) -> Self {
+        self.wait_for_lock = wait;
+        self
+    }
+
+    /// Don't run the package's maintainer scripts
+    pub(crate) fn skip_scripts(mut self) -> Self {
+        self.skip_scripts = true;
+        self
     }
 
     /// Uninstall a package
     ///
     /// This removes all installed files, services, and desktop entries.
     pub fn uninstall(&self, package_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.remove(package_name, scope, false)
+    }
+
+    /// Remove a package, recording the transaction in the scope's history
+    fn remove(&self, package_name: &str, scope: InstallScope, purge: bool) -> IntResult<()> {
+        let mut transaction = None;
+        let result = self.remove_package(package_name, scope, purge, &mut transaction);
+        if let Some(transaction) = transaction {
+            transaction.finish(&result);
+        }
+        result
+    }
+
+    fn remove_package(
+        &self,
+        package_name: &str,
+        scope: InstallScope,
+        purge: bool,
+        transaction: &mut Option<Transaction>,
+    ) -> IntResult<()> {
+        let _lock = OperationLock::acquire(scope, self.wait_for_lock)?;
+
         // Load installation metadata
         let metadata = InstallMetadata::load(package_name, scope)?;
+        let operation = if purge {
+            Operation::Purge
+        } else {
+            Operation::Uninstall
+        };
+        let change = PackageChange {
+            name: package_name.to_string(),
+            old_version: Some(metadata.package_version.clone()),
+            new_version: None,
+        };
+        *transaction = Some(Transaction::new(operation, scope, vec![change]));
+        let scripts_dir = InstallMetadata::scripts_dir(package_name, scope);
 
-        // Stop and remove service if exists
-        if let (Some(service_file), Some(service_name)) =
+        let scripts =
+            |script: &Option<MaintainerScript>| script.clone().filter(|_| !self.skip_scripts);
+
+        // Run the pre-uninstall script while the package is still in place
+        if let Some(ref script) = scripts(&metadata.pre_uninstall) {
+            self.report_progress(UninstallProgress::ExecutingScript {
+                script: ScriptHook::PreUninstall.as_str().to_string(),
+            });
+            let working_dir = if metadata.install_path.exists() {
+                &metadata.install_path
+            } else {
+                &scripts_dir
+            };
+            installer::execute_script(
+                &script.path,
+                script,
+                ScriptHook::PreUninstall,
+                working_dir,
+                &metadata.install_path,
+                &|line| tracing::info!("| {}", line),
+            )?;
+        }
+
+        // Stop and remove services if they exist
+        let init_system = metadata.init_system.unwrap_or_else(InitSystem::detect);
+        if !metadata.service_units.is_empty() || metadata.service_name.is_some() {
+            self.report_progress(UninstallProgress::StoppingService);
+        }
+        if !metadata.service_units.is_empty() {
+            let service_manager = ServiceManager::with_init_system(init_system);
+            service_manager.unregister_units(&metadata.service_units, scope)?;
+        } else if let (Some(service_file), Some(service_name)) =
             (&metadata.service_file, &metadata.service_name)
         {
-            let service_manager = ServiceManager::new();
+            // Metadata written before multiple units were tracked
+            let service_manager = ServiceManager::with_init_system(init_system);
             service_manager.unregister(service_file, service_name, scope)?;
         }
 
-        // Remove desktop entry if exists
-        if let Some(ref desktop_entry) = metadata.desktop_entry {
-            let desktop_integration = DesktopIntegration::new();
-            desktop_integration.remove_entry(desktop_entry)?;
+        // Remove the service environment file and its directory if now empty
+        if let Some(ref env_file) = metadata.env_file {
+            if env_file.exists() {
+                std::fs::remove_file(env_file).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to remove environment file {}: {}",
+                        env_file.display(),
+                        e
+                    ))
+                })?;
+            }
+            if let Some(parent) = env_file.parent() {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+
+        self.report_progress(UninstallProgress::RemovingIntegration);
+
+        // Remove cron jobs
+        if let Some(ref cron) = metadata.cron {
+            cron.remove(package_name)?;
+        }
+
+        // Remove tmpfiles.d fragments (the files they created are left alone)
+        for fragment in &metadata.tmpfiles {
+            if fragment.exists() {
+                std::fs::remove_file(fragment).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to remove tmpfiles fragment {}: {}",
+                        fragment.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+
+        // Remove desktop entry, icons, MIME registrations and other
+        // desktop integration
+        DesktopIntegration::new().remove_all(&metadata)?;
+
+        // Remove shell completions
+        for completion in &metadata.completions {
+            if completion.exists() {
+                std::fs::remove_file(completion).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to remove completion file {}: {}",
+                        completion.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+
+        // Remove man pages
+        for page in &metadata.man_pages {
+            if page.exists() {
+                std::fs::remove_file(page).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to remove man page {}: {}",
+                        page.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+        if !metadata.man_pages.is_empty() {
+            utils::run_mandb();
         }
 
-        // Remove binary symlink if exists
-        if let Some(ref bin_symlink) = metadata.bin_symlink {
-            if bin_symlink.exists() {
+        // Remove binary symlinks if they exist
+        for bin_symlink in metadata.bin_symlink.iter().chain(&metadata.bin_symlinks) {
+            if bin_symlink.symlink_metadata().is_ok() {
                 std::fs::remove_file(bin_symlink).map_err(|e| {
                     IntError::Custom(format!(
                         "Failed to remove symlink {}: {}",
@@ -111,16 +368,53 @@ impl Uninstaller {
             }
         }
 
-        // Execute pre-uninstall script if it was recorded
-        // Note: We don't have access to the original package, so we skip this
+        // Unregister shared libraries
+        if let Some(ref ld_config) = metadata.ld_config {
+            if ld_config.exists() {
+                std::fs::remove_file(ld_config).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to remove linker configuration {}: {}",
+                        ld_config.display(),
+                        e
+                    ))
+                })?;
+                utils::run_ldconfig();
+            }
+        }
+
+        // Remove plugin link from the host application
+        if let Some(ref plugin_link) = metadata.plugin_link {
+            if plugin_link.symlink_metadata().is_ok() {
+                std::fs::remove_file(plugin_link).map_err(|e| {
+                    IntError::Custom(format!(
+                        "Failed to remove plugin link {}: {}",
+                        plugin_link.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
 
         // Remove installed files
-        for file in &metadata.installed_files {
+        let total = metadata.installed_files.len();
+        for (i, file) in metadata.installed_files.iter().enumerate() {
+            self.report_progress(UninstallProgress::RemovingFiles {
+                current: i + 1,
+                total,
+            });
             if file.exists() {
                 std::fs::remove_file(file).map_err(|e| {
                     IntError::Custom(format!("Failed to remove file {}: {}", file.display(), e))
                 })?;
             }
+
+            // Files outside the install path (docs, licenses) live in their own
+            // directory, which is removed once it becomes empty
+            if !file.starts_with(&metadata.install_path) {
+                if let Some(parent) = file.parent() {
+                    let _ = std::fs::remove_dir(parent);
+                }
+            }
         }
 
         // Remove installation directory
@@ -128,59 +422,216 @@ impl Uninstaller {
             utils::remove_dir_safe(&metadata.install_path)?;
         }
 
-        // Remove metadata file
-        let metadata_path = metadata
-            .install_path
-            .parent()
-            .and_then(|p| Some(p.join(format!("{}.json", package_name))))
-            .ok_or_else(|| IntError::Custom("Invalid metadata path".to_string()))?;
+        // Remove the directories the installation created, if now empty
+        utils::remove_empty_dirs(&metadata.created_dirs);
 
-        if metadata_path.exists() {
-            std::fs::remove_file(&metadata_path).map_err(|e| {
-                IntError::Custom(format!(
-                    "Failed to remove metadata {}: {}",
-                    metadata_path.display(),
-                    e
-                ))
-            })?;
+        // Run the post-uninstall script, then drop the kept scripts
+        if let Some(ref script) = scripts(&metadata.post_uninstall) {
+            self.report_progress(UninstallProgress::ExecutingScript {
+                script: ScriptHook::PostUninstall.as_str().to_string(),
+            });
+            installer::execute_script(
+                &script.path,
+                script,
+                ScriptHook::PostUninstall,
+                &scripts_dir,
+                &metadata.install_path,
+                &|line| tracing::info!("| {}", line),
+            )?;
+        }
+        utils::remove_dir_safe(&scripts_dir)?;
+
+        // Remove metadata
+        InstallMetadata::remove(package_name, scope)?;
+
+        // A plain uninstall keeps the cached packages so it can be rolled back
+        if purge {
+            for dir in metadata.managed_dirs() {
+                utils::remove_dir_safe(&dir)?;
+            }
+            utils::remove_dir_safe(&history::package_cache_dir(scope, package_name))?;
         }
 
+        self.report_progress(UninstallProgress::Completed);
+
         Ok(())
     }
 
-    /// List all installed packages
-    pub fn list_installed(&self, scope: InstallScope) -> IntResult<Vec<InstallMetadata>> {
-        let metadata_dir = match scope {
-            InstallScope::User => {
-                let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
-                std::path::PathBuf::from(home).join(".local/share/int-installer/installed")
-            }
-            InstallScope::System => std::path::PathBuf::from("/var/lib/int-installer/installed"),
-        };
+    /// Report what uninstalling a package would remove, without removing it
+    ///
+    /// With `purge`, the data, cache and log directories are included.
+    pub fn dry_run(
+        &self,
+        package_name: &str,
+        scope: InstallScope,
+        purge: bool,
+    ) -> IntResult<UninstallReport> {
+        let metadata = InstallMetadata::load(package_name, scope)?;
+        let exists = |path: &&std::path::PathBuf| path.symlink_metadata().is_ok();
 
-        if !metadata_dir.exists() {
-            return Ok(vec![]);
+        let mut directories: Vec<_> = [&metadata.install_path]
+            .into_iter()
+            .filter(exists)
+            .cloned()
+            .collect();
+        if purge {
+            let managed = metadata.managed_dirs();
+            directories.extend(managed.iter().filter(exists).cloned());
+            let cache = history::package_cache_dir(scope, package_name);
+            directories.extend(Some(cache).filter(|dir| dir.exists()));
         }
 
-        let mut packages = Vec::new();
+        let mut services: Vec<_> = metadata
+            .service_units
+            .iter()
+            .map(|unit| unit.name.clone())
+            .collect();
+        if services.is_empty() {
+            services.extend(metadata.service_name.clone());
+        }
+
+        let symlinks = metadata
+            .bin_symlink
+            .iter()
+            .chain(&metadata.bin_symlinks)
+            .chain(&metadata.plugin_link);
+        let mut symlinks: Vec<_> = symlinks.filter(exists).cloned().collect();
+        symlinks.sort();
+        symlinks.dedup();
+
+        let integration_files = metadata
+            .icons
+            .iter()
+            .chain(&metadata.completions)
+            .chain(&metadata.man_pages)
+            .chain(&metadata.thumbnailer)
+            .chain(&metadata.dbus_services)
+            .chain(&metadata.gschemas)
+            .chain(&metadata.mime_definitions)
+            .chain(&metadata.tmpfiles)
+            .chain(&metadata.env_file)
+            .chain(&metadata.ld_config)
+            .chain(metadata.service_units.iter().map(|unit| &unit.path))
+            .filter(exists)
+            .cloned()
+            .collect();
+
+        let scripts = [
+            (ScriptHook::PreUninstall, &metadata.pre_uninstall),
+            (ScriptHook::PostUninstall, &metadata.post_uninstall),
+        ]
+        .into_iter()
+        .filter(|(_, script)| script.is_some())
+        .map(|(hook, _)| hook.as_str().to_string())
+        .collect();
+
+        let files = metadata.installed_files.iter().filter(exists);
+        let desktop_entries = metadata.desktop_entry.iter().filter(exists);
+
+        Ok(UninstallReport {
+            files: files.cloned().collect(),
+            directories,
+            services,
+            symlinks,
+            desktop_entries: desktop_entries.cloned().collect(),
+            integration_files,
+            scripts,
+            package_name: metadata.package_name,
+            package_version: metadata.package_version,
+        })
+    }
+
+    /// Uninstall a package and remove its data, cache and log directories
+    ///
+    /// A regular uninstall preserves these directories so user data survives
+    /// reinstallation; purging removes them as well.
+    pub fn purge(&self, package_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.remove(package_name, scope, true)
+    }
+
+    /// List all installed packages
+    pub fn list_installed(&self, scope: InstallScope) -> IntResult<Vec<InstallMetadata>> {
+        InstallMetadata::list(scope)
+    }
 
-        for entry in std::fs::read_dir(&metadata_dir).map_err(IntError::IoError)? {
-            let entry = entry.map_err(IntError::IoError)?;
-            let path = entry.path();
+    /// Packages installed only as dependencies that nothing depends on
+    ///
+    /// Removing one can orphan the dependencies it had in turn, so those are
+    /// included too, listed after the packages that needed them. Only
+    /// packages in the same scope count as dependents.
+    pub fn autoremove_candidates(&self, scope: InstallScope) -> IntResult<Vec<InstallMetadata>> {
+        let mut remaining = InstallMetadata::list(scope)?;
+        let mut candidates = Vec::new();
 
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                let content = std::fs::read_to_string(&path)
-                    .map_err(|e| IntError::MetadataCorrupted(e.to_string()))?;
+        loop {
+            let unneeded = remaining.iter().position(|package| {
+                package.installed_as_dependency
+                    && !remaining
+                        .iter()
+                        .any(|other| other.depends_on(&package.package_name))
+            });
+            match unneeded {
+                Some(index) => candidates.push(remaining.remove(index)),
+                None => return Ok(candidates),
+            }
+        }
+    }
+
+    /// Uninstall the packages found by `autoremove_candidates`
+    ///
+    /// Returns the names of the removed packages.
+    pub fn autoremove(&self, scope: InstallScope) -> IntResult<Vec<String>> {
+        let mut removed = Vec::new();
+        for package in self.autoremove_candidates(scope)? {
+            self.uninstall(&package.package_name, scope)?;
+            removed.push(package.package_name);
+        }
+        Ok(removed)
+    }
 
-                let metadata: InstallMetadata = serde_json::from_str(&content)
-                    .map_err(|e| IntError::MetadataCorrupted(e.to_string()))?;
+    /// List the installed packages of both scopes, sorted by name
+    ///
+    /// A scope whose metadata can't be read for lack of permissions is
+    /// skipped. A package installed in both scopes is listed once, with the
+    /// user installation taking precedence as it does on `PATH`.
+    pub fn list_all_installed(&self) -> IntResult<Vec<InstalledPackage>> {
+        let mut packages: Vec<InstalledPackage> = Vec::new();
+        for scope in [InstallScope::User, InstallScope::System] {
+            let installed = match InstallMetadata::list(scope) {
+                Err(IntError::IoError(e)) if e.kind() == ErrorKind::PermissionDenied => continue,
+                result => result?,
+            };
 
-                packages.push(metadata);
+            for metadata in installed {
+                let existing = packages
+                    .iter_mut()
+                    .find(|p| p.metadata.package_name == metadata.package_name);
+                match existing {
+                    Some(existing) => existing.also_installed_in = Some(scope),
+                    None => packages.push(InstalledPackage {
+                        scope,
+                        metadata,
+                        also_installed_in: None,
+                    }),
+                }
             }
         }
 
+        packages.sort_by(|a, b| a.metadata.package_name.cmp(&b.metadata.package_name));
         Ok(packages)
     }
+
+    /// Report progress, also recording it as a tracing event
+    fn report_progress(&self, progress: UninstallProgress) {
+        match progress {
+            UninstallProgress::RemovingFiles { .. } => tracing::trace!(?progress),
+            _ => tracing::debug!(?progress),
+        }
+
+        if let Some(ref callback) = self.progress_callback {
+            callback(progress);
+        }
+    }
 }
 
 impl Default for Uninstaller {
@@ -0,0 +1,88 @@
+/// Per-scope operation lock
+///
+/// Installations and uninstallations in the same scope touch the same
+/// metadata and often the same paths, so only one may run at a time. The
+/// lock is an `flock` on a file next to the metadata directory; the kernel
+/// releases it when the holder exits, even after a crash.
+use crate::error::{IntError, IntResult};
+use crate::installer::InstallMetadata;
+use crate::manifest::InstallScope;
+use nix::fcntl::{flock, FlockArg};
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+
+/// Exclusive lock on a scope, held until dropped
+#[derive(Debug)]
+pub struct OperationLock {
+    _file: File,
+}
+
+impl OperationLock {
+    /// Location of the lock file for a scope
+    pub fn path(scope: InstallScope) -> PathBuf {
+        InstallMetadata::metadata_dir(scope).with_file_name("lock")
+    }
+
+    /// Lock a scope
+    ///
+    /// If another operation holds the lock, either wait for it to finish or
+    /// fail with `IntError::OperationInProgress`.
+    pub fn acquire(scope: InstallScope, wait: bool) -> IntResult<Self> {
+        Self::acquire_at(Self::path(scope), wait)
+    }
+
+    fn acquire_at(path: PathBuf, wait: bool) -> IntResult<Self> {
+        if let Some(parent) = path.parent() {
+            crate::utils::ensure_dir(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| {
+                IntError::Custom(format!(
+                    "Failed to open lock file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+        let arg = if wait {
+            FlockArg::LockExclusive
+        } else {
+            FlockArg::LockExclusiveNonblock
+        };
+        match flock(file.as_raw_fd(), arg) {
+            Ok(()) => Ok(Self { _file: file }),
+            Err(nix::errno::Errno::EWOULDBLOCK) => Err(IntError::OperationInProgress(path)),
+            Err(e) => Err(IntError::Custom(format!(
+                "Failed to lock {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_lock() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("int-installer/lock");
+
+        let lock = OperationLock::acquire_at(path.clone(), false).unwrap();
+        assert!(matches!(
+            OperationLock::acquire_at(path.clone(), false),
+            Err(IntError::OperationInProgress(_))
+        ));
+
+        drop(lock);
+        OperationLock::acquire_at(path, false).unwrap();
+    }
+}
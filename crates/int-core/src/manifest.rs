@@ -2,9 +2,12 @@
 ///
 /// This module handles the manifest.json file that describes an INT package.
 /// It provides type-safe parsing, validation, and access to package metadata.
+use crate::compression::Compression;
 use crate::error::{IntError, IntResult};
+use crate::root;
+use crate::service::{InitSystem, ServicePreset, UnitKind, BUILTIN_VARIABLES};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
 
 /// Current supported manifest version
@@ -23,7 +26,7 @@ pub enum InstallScope {
 impl InstallScope {
     /// Get default installation path for this scope
     pub fn default_install_path(&self, app_name: &str) -> PathBuf {
-        match self {
+        root::path(&match self {
             InstallScope::User => {
                 let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
                 PathBuf::from(home)
@@ -32,12 +35,12 @@ impl InstallScope {
                     .join(app_name)
             }
             InstallScope::System => PathBuf::from("/opt").join(app_name),
-        }
+        })
     }
 
     /// Get desktop entry path for this scope
     pub fn desktop_entry_path(&self) -> PathBuf {
-        match self {
+        root::path(&match self {
             InstallScope::User => {
                 let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
                 PathBuf::from(home)
@@ -46,32 +49,149 @@ impl InstallScope {
                     .join("applications")
             }
             InstallScope::System => PathBuf::from("/usr/share/applications"),
-        }
+        })
     }
 
     /// Get systemd service path for this scope
     pub fn systemd_service_path(&self) -> PathBuf {
-        match self {
+        root::path(&match self {
             InstallScope::User => {
                 let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
                 PathBuf::from(home).join(".config/systemd/user")
             }
             InstallScope::System => PathBuf::from("/etc/systemd/system"),
+        })
+    }
+
+    /// Get launchd plist directory for this scope (macOS)
+    pub fn launchd_path(&self) -> PathBuf {
+        root::path(&match self {
+            InstallScope::User => {
+                let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
+                PathBuf::from(home).join("Library/LaunchAgents")
+            }
+            InstallScope::System => PathBuf::from("/Library/LaunchDaemons"),
+        })
+    }
+
+    /// Get tmpfiles.d directory for this scope
+    pub fn tmpfiles_path(&self) -> PathBuf {
+        root::path(&match self {
+            InstallScope::User => self.config_home().join("user-tmpfiles.d"),
+            InstallScope::System => PathBuf::from("/etc/tmpfiles.d"),
+        })
+    }
+
+    /// Get shared data path (docs, licenses) for this scope
+    pub fn share_path(&self) -> PathBuf {
+        root::path(&match self {
+            InstallScope::User => xdg_home("XDG_DATA_HOME", ".local/share"),
+            InstallScope::System => PathBuf::from("/usr/local/share"),
+        })
+    }
+
+    /// Get shared MIME database path for this scope
+    pub fn mime_path(&self) -> PathBuf {
+        self.share_path().join("mime")
+    }
+
+    /// Get man page path for this scope
+    pub fn man_path(&self) -> PathBuf {
+        self.share_path().join("man")
+    }
+
+    /// Get the directory a shell loads completions from for this scope
+    pub fn completion_path(&self, shell: Shell) -> PathBuf {
+        match (shell, self) {
+            (Shell::Bash, _) => self.share_path().join("bash-completion/completions"),
+            (Shell::Zsh, _) => self.share_path().join("zsh/site-functions"),
+            (Shell::Fish, InstallScope::User) => self.config_home().join("fish/completions"),
+            (Shell::Fish, InstallScope::System) => {
+                self.share_path().join("fish/vendor_completions.d")
+            }
         }
     }
 
+    /// Get D-Bus session service directory for this scope
+    pub fn dbus_services_path(&self) -> PathBuf {
+        self.share_path().join("dbus-1/services")
+    }
+
     /// Get binary symlink path for this scope
     pub fn bin_path(&self) -> PathBuf {
-        match self {
+        root::path(&match self {
             InstallScope::User => {
                 let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
                 PathBuf::from(home).join(".local/bin")
             }
             InstallScope::System => PathBuf::from("/usr/local/bin"),
+        })
+    }
+
+    /// Get base directory for persistent application data
+    pub fn data_home(&self) -> PathBuf {
+        root::path(&match self {
+            InstallScope::User => xdg_home("XDG_DATA_HOME", ".local/share"),
+            InstallScope::System => PathBuf::from("/var/lib"),
+        })
+    }
+
+    /// Get base directory for application caches
+    pub fn cache_home(&self) -> PathBuf {
+        root::path(&match self {
+            InstallScope::User => xdg_home("XDG_CACHE_HOME", ".cache"),
+            InstallScope::System => PathBuf::from("/var/cache"),
+        })
+    }
+
+    /// Get base directory for configuration files
+    pub fn config_home(&self) -> PathBuf {
+        root::path(&match self {
+            InstallScope::User => xdg_home("XDG_CONFIG_HOME", ".config"),
+            InstallScope::System => PathBuf::from("/etc"),
+        })
+    }
+
+    /// Get base directory for application logs
+    pub fn log_home(&self) -> PathBuf {
+        root::path(&match self {
+            InstallScope::User => xdg_home("XDG_STATE_HOME", ".local/state"),
+            InstallScope::System => PathBuf::from("/var/log"),
+        })
+    }
+}
+
+/// Resolve an XDG base directory, falling back to a path under $HOME
+fn xdg_home(var: &str, fallback: &str) -> PathBuf {
+    match std::env::var(var) {
+        Ok(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
+            PathBuf::from(home).join(fallback)
         }
     }
 }
 
+/// Package kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageKind {
+    /// Standalone application (desktop entry, bin symlinks, services)
+    #[default]
+    Application,
+    /// Shared library without launchable entries
+    Library,
+    /// Extension installed into a host application
+    Plugin,
+}
+
+impl PackageKind {
+    /// Check if this is the default application kind
+    pub fn is_application(&self) -> bool {
+        *self == PackageKind::Application
+    }
+}
+
 /// Package manifest structure
 ///
 /// This represents the complete metadata for an INT package.
@@ -84,6 +204,10 @@ pub struct Manifest {
     /// Package name (used as identifier)
     pub name: String,
 
+    /// Package kind (application, library or plugin)
+    #[serde(default, skip_serializing_if = "PackageKind::is_application")]
+    pub kind: PackageKind,
+
     /// Package display name (optional)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub display_name: Option<String>,
@@ -95,6 +219,10 @@ pub struct Manifest {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 
+    /// Translated display names and descriptions, keyed by locale (e.g. "de", "pt_BR")
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub localized: BTreeMap<String, LocalizedStrings>,
+
     /// Package author/vendor
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub author: Option<String>,
@@ -109,6 +237,10 @@ pub struct Manifest {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub entry: Option<String>,
 
+    /// Additional executable names (relative to install_path/bin)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<String>,
+
     /// Whether to install as systemd service
     #[serde(default)]
     pub service: bool,
@@ -117,6 +249,38 @@ pub struct Manifest {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub service_name: Option<String>,
 
+    /// Add sandboxing directives to systemd services
+    #[serde(default)]
+    pub hardening: bool,
+
+    /// Whether units are enabled and started on registration (defaults to enable)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_preset: Option<ServicePreset>,
+
+    /// Unit files in `services/` to register (defaults to `<service_name>.service`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub units: Vec<String>,
+
+    /// Environment variables for the service, written to its environment file
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+
+    /// tmpfiles.d fragments in `services/` to install
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tmpfiles: Vec<String>,
+
+    /// Extra `{{NAME}}` template variables for service definitions
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+
+    /// Health check run after the service is started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheck>,
+
+    /// Scheduled commands, installed as cron jobs where timers are unavailable
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<ScheduledTask>,
+
     /// Post-install script path (relative to package root)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub post_install: Option<PathBuf>,
@@ -125,10 +289,42 @@ pub struct Manifest {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pre_uninstall: Option<PathBuf>,
 
+    /// Maintainer scripts (takes precedence over post_install/pre_uninstall)
+    #[serde(default, skip_serializing_if = "Scripts::is_empty")]
+    pub scripts: Scripts,
+
+    /// Documentation and license files to install
+    #[serde(default, skip_serializing_if = "DocFiles::is_empty")]
+    pub docs: DocFiles,
+
+    /// Shell completion files
+    #[serde(default, skip_serializing_if = "Completions::is_empty")]
+    pub completions: Completions,
+
+    /// Man pages (relative to install_path, defaults to the share/man tree)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub man_pages: Vec<PathBuf>,
+
+    /// Library settings (kind = "library")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<LibraryConfig>,
+
+    /// Plugin settings (kind = "plugin")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<PluginConfig>,
+
     /// Desktop integration settings
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub desktop: Option<DesktopEntry>,
 
+    /// D-Bus names the application can be activated on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dbus_services: Vec<DbusService>,
+
+    /// GSettings schemas (relative to install_path, defaults to share/glib-2.0/schemas)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gschemas: Vec<PathBuf>,
+
     /// Required dependencies
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub dependencies: Vec<Dependency>,
@@ -149,13 +345,42 @@ pub struct Manifest {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub homepage: Option<String>,
 
+    /// HTTPS URL of the vendor's update feed (see `updates`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_url: Option<String>,
+
     /// Whether to auto-launch after installation
     #[serde(default)]
     pub auto_launch: bool,
 
     /// Command to launch the application (optional, defaults to entry)
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub launch_command: Option<String>,
+    pub launch_command: Option<LaunchCommand>,
+
+    /// Release notes for this version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+
+    /// Changelog file path (relative to package root, e.g. "CHANGELOG.md")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog_file: Option<PathBuf>,
+
+    /// License agreement the user has to accept before installing
+    /// (relative to package root, e.g. "EULA.txt")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eula: Option<PathBuf>,
+
+    /// Persistent data directories, relative to the scope's data home
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_dirs: Vec<PathBuf>,
+
+    /// Cache directories, relative to the scope's cache home
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cache_dirs: Vec<PathBuf>,
+
+    /// Log directories, relative to the scope's log home
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_dirs: Vec<PathBuf>,
 
     /// Embedded GPG signature of the manifest (v0.3.0+)
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -165,16 +390,33 @@ pub struct Manifest {
     /// Using BTreeMap instead of HashMap to ensure deterministic serialization order
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub file_hashes: Option<BTreeMap<String, String>>,
+
+    /// Compression the package archive was built with, set by int-pack.
+    /// Readers detect it from the archive, so this is informational.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
 }
 
 fn default_version() -> String {
     MANIFEST_VERSION.to_string()
 }
 
+/// Translations of user-visible manifest fields
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalizedStrings {
+    /// Translated display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    /// Translated description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
 /// Desktop entry configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DesktopEntry {
-    /// Categories (e.g., "Development;IDE;")
+    /// Categories (e.g., "Development", "IDE")
     #[serde(default)]
     pub categories: Vec<String>,
 
@@ -182,10 +424,18 @@ pub struct DesktopEntry {
     #[serde(default)]
     pub mime_types: Vec<String>,
 
+    /// shared-mime-info XML files defining custom MIME types (relative to install_path)
+    #[serde(default)]
+    pub mime_definitions: Vec<PathBuf>,
+
     /// Icon name or path
     #[serde(default)]
     pub icon: Option<String>,
 
+    /// Icon files to install into the hicolor icon theme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icons: Option<IconSet>,
+
     /// Whether to show in application menu
     #[serde(default = "default_true")]
     pub show_in_menu: bool,
@@ -193,166 +443,1441 @@ pub struct DesktopEntry {
     /// Keywords for search
     #[serde(default)]
     pub keywords: Vec<String>,
-}
 
-fn default_true() -> bool {
-    true
-}
+    /// Make the application the default handler for its MIME types
+    #[serde(default)]
+    pub set_default_for_mime_types: bool,
 
-/// Package dependency
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Dependency {
-    /// Dependency name
-    pub name: String,
+    /// Generic name of the application (e.g. "Web Browser")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generic_name: Option<String>,
 
-    /// Minimum version
+    /// Whether the application runs in a terminal
     #[serde(default)]
-    pub min_version: Option<String>,
+    pub terminal: bool,
 
-    /// Check command (e.g., "which docker")
-    #[serde(default)]
-    pub check_command: Option<String>,
+    /// Window class used by the window manager to group the application's windows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup_wm_class: Option<String>,
+
+    /// Whether the application supports startup notification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup_notify: Option<bool>,
+
+    /// Thumbnail generator for the application's file types
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnailer: Option<Thumbnailer>,
+
+    /// Additional actions offered by the launcher (e.g. "New Window")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<DesktopAction>,
 }
 
-impl Manifest {
-    /// Parse manifest from JSON string
-    pub fn from_str(json: &str) -> IntResult<Self> {
-        serde_json::from_str(json).map_err(|e| IntError::ManifestParseError(e.to_string()))
-    }
+/// Icon files shipped in the payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IconSet {
+    /// Directory laid out like the hicolor theme (`<size>/<context>/<file>`),
+    /// relative to install_path
+    Tree(PathBuf),
+    /// Icon file per size (e.g. "48x48", "scalable"), relative to install_path
+    Sizes(BTreeMap<String, PathBuf>),
+}
 
-    /// Parse manifest from file
-    pub fn from_file<P: AsRef<Path>>(path: P) -> IntResult<Self> {
-        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| {
-            IntError::ManifestParseError(format!("Failed to read manifest file: {}", e))
-        })?;
-        Self::from_str(&content)
+impl IconSet {
+    /// Get all declared paths
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            IconSet::Tree(dir) => vec![dir.as_path()],
+            IconSet::Sizes(files) => files.values().map(PathBuf::as_path).collect(),
+        }
     }
+}
 
-    /// Validate manifest
-    ///
-    /// Performs comprehensive validation to ensure the manifest is valid and safe.
-    pub fn validate(&self) -> IntResult<()> {
-        // Check version compatibility
-        if self.version != MANIFEST_VERSION {
-            return Err(IntError::UnsupportedVersion {
-                found: self.version.clone(),
-                expected: MANIFEST_VERSION.to_string(),
-            });
-        }
+/// Thumbnail generator registration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Thumbnailer {
+    /// Command writing a thumbnail, with the field codes `%i`/`%u` (input
+    /// path/URI), `%o` (output PNG) and `%s` (size)
+    pub exec: LaunchCommand,
 
-        // Validate package name
-        if self.name.is_empty() {
-            return Err(IntError::MissingField("name".to_string()));
-        }
+    /// MIME types to thumbnail (defaults to the desktop entry's mime_types)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mime_types: Vec<String>,
+}
 
-        if !is_valid_package_name(&self.name) {
-            return Err(IntError::ValidationError(format!(
-                "Invalid package name: {}. Must contain only alphanumeric characters, hyphens, and underscores",
-                self.name
-            )));
-        }
+/// D-Bus activatable service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DbusService {
+    /// Well-known bus name (e.g. "org.example.MyApp")
+    pub name: String,
 
-        // Validate version
-        if self.package_version.is_empty() {
-            return Err(IntError::MissingField("package_version".to_string()));
-        }
+    /// Command that claims the name (defaults to the launch command)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec: Option<LaunchCommand>,
 
-        // Validate install path
-        if !self.install_path.is_absolute() {
-            return Err(IntError::ValidationError(
-                "install_path must be absolute".to_string(),
-            ));
-        }
+    /// systemd unit to start instead of running the command directly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub systemd_service: Option<String>,
+}
 
-        // Check for path traversal in install path
-        if has_path_traversal(&self.install_path) {
-            return Err(IntError::PathTraversalAttempt(self.install_path.clone()));
-        }
+/// Desktop action shown in the application's context menu
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DesktopAction {
+    /// Action identifier (letters, digits and `-`)
+    pub id: String,
 
-        // Validate script paths
-        if let Some(ref script) = self.post_install {
-            if script.is_absolute() {
-                return Err(IntError::ValidationError(
-                    "post_install script path must be relative".to_string(),
-                ));
-            }
-            if has_path_traversal(script) {
-                return Err(IntError::PathTraversalAttempt(script.to_path_buf()));
-            }
-        }
+    /// Label shown to the user
+    pub name: String,
 
-        if let Some(ref script) = self.pre_uninstall {
-            if script.is_absolute() {
-                return Err(IntError::ValidationError(
-                    "pre_uninstall script path must be relative".to_string(),
-                ));
-            }
-            if has_path_traversal(script) {
-                return Err(IntError::PathTraversalAttempt(script.to_path_buf()));
-            }
-        }
+    /// Command run by the action
+    pub exec: LaunchCommand,
 
-        // Validate auto-launch
-        if self.auto_launch && self.launch_command.is_none() && self.entry.is_none() {
-            return Err(IntError::ValidationError(
-                "auto_launch is true but neither launch_command nor entry is specified".to_string(),
-            ));
+    /// Icon name or path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Command used to launch an installed application
+///
+/// Accepts either a bare program string (legacy form) or a structured object
+/// with arguments and a working directory. The original form is preserved on
+/// serialization so signed manifests still verify.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LaunchCommand {
+    /// Program name or path, without arguments
+    Program(String),
+    /// Program with arguments and working directory
+    Structured {
+        /// Program name (relative to install_path/bin) or absolute path
+        program: String,
+        /// Arguments passed to the program
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        /// Working directory (relative to install_path or absolute)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        working_dir: Option<PathBuf>,
+        /// Environment variables set for the program
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        env: BTreeMap<String, String>,
+    },
+}
+
+static NO_ENV: BTreeMap<String, String> = BTreeMap::new();
+
+impl LaunchCommand {
+    /// Get the program name or path
+    pub fn program(&self) -> &str {
+        match self {
+            LaunchCommand::Program(program) => program,
+            LaunchCommand::Structured { program, .. } => program,
         }
+    }
 
-        Ok(())
+    /// Get the program arguments
+    pub fn args(&self) -> &[String] {
+        match self {
+            LaunchCommand::Program(_) => &[],
+            LaunchCommand::Structured { args, .. } => args,
+        }
     }
 
-    /// Get display name or fallback to name
-    pub fn display_name(&self) -> &str {
-        self.display_name.as_deref().unwrap_or(&self.name)
+    /// Get the environment variables set for the program
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        match self {
+            LaunchCommand::Program(_) => &NO_ENV,
+            LaunchCommand::Structured { env, .. } => env,
+        }
     }
 
-    /// Get service name or fallback to name
-    pub fn service_name(&self) -> &str {
-        self.service_name.as_deref().unwrap_or(&self.name)
+    /// Get the configured working directory
+    pub fn working_dir(&self) -> Option<&Path> {
+        match self {
+            LaunchCommand::Program(_) => None,
+            LaunchCommand::Structured { working_dir, .. } => working_dir.as_deref(),
+        }
     }
 
-    /// Check if package requires system-level installation
-    pub fn requires_system_install(&self) -> bool {
-        self.install_scope == InstallScope::System
+    /// Resolve the program against the installation directory
+    pub fn resolve_program(&self, install_path: &Path) -> PathBuf {
+        let program = Path::new(self.program());
+        if program.is_absolute() {
+            program.to_path_buf()
+        } else {
+            install_path.join("bin").join(program)
+        }
     }
 
-    /// Get installation metadata path for this package
-    pub fn metadata_path(&self, scope: InstallScope) -> PathBuf {
-        match scope {
-            InstallScope::User => {
-                let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
-                PathBuf::from(home)
-                    .join(".local/share/int-installer/installed")
-                    .join(format!("{}.json", self.name))
-            }
-            InstallScope::System => PathBuf::from("/var/lib/int-installer/installed")
-                .join(format!("{}.json", self.name)),
+    /// Resolve the working directory, defaulting to the installation directory
+    pub fn resolve_working_dir(&self, install_path: &Path) -> PathBuf {
+        match self.working_dir() {
+            Some(dir) => install_path.join(dir),
+            None => install_path.to_path_buf(),
         }
     }
 
-    /// Serialize to JSON string (pretty)
-    pub fn to_string(&self) -> IntResult<String> {
-        serde_json::to_string_pretty(self)
-            .map_err(|e| IntError::Custom(format!("Failed to serialize manifest: {}", e)))
+    /// Build a process command ready to spawn
+    pub fn to_command(&self, install_path: &Path) -> std::process::Command {
+        let mut cmd = std::process::Command::new(self.resolve_program(install_path));
+        cmd.args(self.args())
+            .envs(self.env())
+            .current_dir(self.resolve_working_dir(install_path));
+        cmd
     }
+}
 
-    /// Serialize to compact canonical JSON string for signing/verification
-    pub fn to_canonical_string(&self) -> IntResult<String> {
-        serde_json::to_string(self)
-            .map_err(|e| IntError::Custom(format!("Failed to serialize manifest: {}", e)))
+impl std::fmt::Display for LaunchCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program())?;
+        for arg in self.args() {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
     }
 }
 
-/// Validate package name format
-fn is_valid_package_name(name: &str) -> bool {
-    !name.is_empty()
-        && name
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+/// Library package configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LibraryConfig {
+    /// Directories with shared libraries (relative to install_path),
+    /// registered with the dynamic linker on system installs
+    #[serde(default)]
+    pub lib_dirs: Vec<PathBuf>,
 }
 
-/// Check if path contains traversal attempts (..)
+/// Plugin package configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginConfig {
+    /// Package name of the host application
+    pub host: String,
+
+    /// Host plugin directory, relative to the host's install path
+    pub plugin_dir: PathBuf,
+}
+
+/// Documentation and license file declarations
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocFiles {
+    /// Documentation files (relative to payload), installed to share/doc/<name>/
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<PathBuf>,
+
+    /// License files (relative to payload), installed to share/licenses/<name>/
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<PathBuf>,
+}
+
+impl DocFiles {
+    /// Check if no documentation is declared
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.licenses.is_empty()
+    }
+}
+
+/// Shells with completion support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Name a completion file must have to be found by the shell
+    ///
+    /// bash looks completions up by command name, zsh by `_<command>` and
+    /// fish by `<command>.fish`.
+    pub fn completion_file_name(&self, source: &Path) -> String {
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        match self {
+            Shell::Bash => name.strip_suffix(".bash").unwrap_or(&name).to_string(),
+            Shell::Zsh => {
+                let name = name.strip_suffix(".zsh").unwrap_or(&name);
+                if name.starts_with('_') {
+                    name.to_string()
+                } else {
+                    format!("_{}", name)
+                }
+            }
+            Shell::Fish if name.ends_with(".fish") => name,
+            Shell::Fish => format!("{}.fish", name),
+        }
+    }
+}
+
+/// Shell completion files (relative to install_path)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Completions {
+    /// bash completion scripts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bash: Vec<PathBuf>,
+
+    /// zsh completion functions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub zsh: Vec<PathBuf>,
+
+    /// fish completion scripts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fish: Vec<PathBuf>,
+}
+
+impl Completions {
+    /// Check if no completions are declared
+    pub fn is_empty(&self) -> bool {
+        self.bash.is_empty() && self.zsh.is_empty() && self.fish.is_empty()
+    }
+
+    /// Get all declared files with their shell
+    pub fn files(&self) -> Vec<(Shell, &Path)> {
+        let shells = [
+            (Shell::Bash, &self.bash),
+            (Shell::Zsh, &self.zsh),
+            (Shell::Fish, &self.fish),
+        ];
+        shells
+            .into_iter()
+            .flat_map(|(shell, files)| files.iter().map(move |file| (shell, file.as_path())))
+            .collect()
+    }
+}
+
+/// Lifecycle hooks a maintainer script can be attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptHook {
+    PreInstall,
+    PostInstall,
+    PreUninstall,
+    PostUninstall,
+    PreUpgrade,
+    PostUpgrade,
+}
+
+impl ScriptHook {
+    /// All hooks in lifecycle order
+    pub const ALL: [ScriptHook; 6] = [
+        ScriptHook::PreInstall,
+        ScriptHook::PostInstall,
+        ScriptHook::PreUninstall,
+        ScriptHook::PostUninstall,
+        ScriptHook::PreUpgrade,
+        ScriptHook::PostUpgrade,
+    ];
+
+    /// Get the manifest key for this hook
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptHook::PreInstall => "pre_install",
+            ScriptHook::PostInstall => "post_install",
+            ScriptHook::PreUninstall => "pre_uninstall",
+            ScriptHook::PostUninstall => "post_uninstall",
+            ScriptHook::PreUpgrade => "pre_upgrade",
+            ScriptHook::PostUpgrade => "post_upgrade",
+        }
+    }
+}
+
+/// Maintainer scripts map
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scripts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_install: Option<MaintainerScript>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<MaintainerScript>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_uninstall: Option<MaintainerScript>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_uninstall: Option<MaintainerScript>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_upgrade: Option<MaintainerScript>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_upgrade: Option<MaintainerScript>,
+}
+
+impl Scripts {
+    /// Get the script attached to a hook
+    pub fn get(&self, hook: ScriptHook) -> Option<&MaintainerScript> {
+        match hook {
+            ScriptHook::PreInstall => self.pre_install.as_ref(),
+            ScriptHook::PostInstall => self.post_install.as_ref(),
+            ScriptHook::PreUninstall => self.pre_uninstall.as_ref(),
+            ScriptHook::PostUninstall => self.post_uninstall.as_ref(),
+            ScriptHook::PreUpgrade => self.pre_upgrade.as_ref(),
+            ScriptHook::PostUpgrade => self.post_upgrade.as_ref(),
+        }
+    }
+
+    /// Check if no scripts are declared
+    pub fn is_empty(&self) -> bool {
+        ScriptHook::ALL.iter().all(|&hook| self.get(hook).is_none())
+    }
+}
+
+/// A maintainer script with execution settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintainerScript {
+    /// Script path (relative to package root)
+    pub path: PathBuf,
+
+    /// Interpreter to run the script with (e.g. "/bin/bash", "python3")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+
+    /// Maximum execution time in seconds (unlimited if not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl MaintainerScript {
+    /// Create a script entry with default settings
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            interpreter: None,
+            timeout: None,
+        }
+    }
+}
+
+/// Service health check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    /// Probe to run
+    #[serde(flatten)]
+    pub probe: HealthProbe,
+
+    /// Seconds to wait for the service to become healthy
+    #[serde(default = "default_health_timeout")]
+    pub timeout: u64,
+
+    /// Seconds between probe attempts
+    #[serde(default = "default_health_interval")]
+    pub interval: u64,
+}
+
+fn default_health_timeout() -> u64 {
+    30
+}
+
+fn default_health_interval() -> u64 {
+    1
+}
+
+/// Health probe kinds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HealthProbe {
+    /// HTTP GET that must answer with a 2xx or 3xx status (e.g. "http://127.0.0.1:8080/health")
+    Http { url: String },
+    /// TCP connect to an address (e.g. "127.0.0.1:8080")
+    Tcp { address: String },
+    /// Shell command that must exit successfully
+    Exec { command: String },
+}
+
+/// Scheduled command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    /// Task name (unique within the package)
+    pub name: String,
+
+    /// Cron schedule: five fields or a shortcut such as "@daily"
+    pub schedule: String,
+
+    /// Command to run (`{{NAME}}` placeholders are substituted)
+    pub command: String,
+
+    /// Timer unit that runs the task instead when systemd is available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timer: Option<String>,
+}
+
+/// Package dependency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dependency {
+    /// Dependency name
+    pub name: String,
+
+    /// Minimum version
+    #[serde(default)]
+    pub min_version: Option<String>,
+
+    /// Check command (e.g., "which docker")
+    #[serde(default)]
+    pub check_command: Option<String>,
+
+    /// Whether the package can be installed without this dependency
+    #[serde(default)]
+    pub optional: bool,
+
+    /// What the dependency is needed for (shown when it is missing)
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Manifest {
+    /// Parse manifest from JSON string
+    pub fn from_str(json: &str) -> IntResult<Self> {
+        serde_json::from_str(json).map_err(|e| IntError::ManifestParseError(e.to_string()))
+    }
+
+    /// Parse manifest from file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> IntResult<Self> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            IntError::ManifestParseError(format!("Failed to read manifest file: {}", e))
+        })?;
+        Self::from_str(&content)
+    }
+
+    /// Validate manifest
+    ///
+    /// Performs comprehensive validation to ensure the manifest is valid and safe.
+    pub fn validate(&self) -> IntResult<()> {
+        // Check version compatibility
+        if self.version != MANIFEST_VERSION {
+            return Err(IntError::UnsupportedVersion {
+                found: self.version.clone(),
+                expected: MANIFEST_VERSION.to_string(),
+            });
+        }
+
+        // Validate package name
+        if self.name.is_empty() {
+            return Err(IntError::MissingField("name".to_string()));
+        }
+
+        if !is_valid_package_name(&self.name) {
+            return Err(IntError::ValidationError(format!(
+                "Invalid package name: {}. Must contain only alphanumeric characters, hyphens, and underscores",
+                self.name
+            )));
+        }
+
+        // Validate version
+        if self.package_version.is_empty() {
+            return Err(IntError::MissingField("package_version".to_string()));
+        }
+
+        // Update feeds decide what gets offered as an upgrade
+        if let Some(ref url) = self.update_url {
+            if !url.starts_with("https://") {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid update_url '{}': must be an https:// URL",
+                    url
+                )));
+            }
+        }
+
+        // Validate install path
+        if !self.install_path.is_absolute() {
+            return Err(IntError::ValidationError(
+                "install_path must be absolute".to_string(),
+            ));
+        }
+
+        // Check for path traversal in install path
+        if has_path_traversal(&self.install_path) {
+            return Err(IntError::PathTraversalAttempt(self.install_path.clone()));
+        }
+
+        // Validate script paths
+        for hook in ScriptHook::ALL {
+            if let Some(script) = self.script(hook) {
+                validate_script(hook, &script)?;
+            }
+        }
+
+        // Validate documentation paths
+        for file in self.docs.files.iter().chain(&self.docs.licenses) {
+            if file.is_absolute() || file.file_name().is_none() {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid documentation path '{}': must be a relative file path",
+                    file.display()
+                )));
+            }
+            if has_path_traversal(file) {
+                return Err(IntError::PathTraversalAttempt(file.to_path_buf()));
+            }
+        }
+        // Files are installed by name, so names must not repeat in a directory
+        for files in [&self.docs.files, &self.docs.licenses] {
+            let mut names = HashSet::new();
+            for file in files {
+                if !names.insert(file.file_name()) {
+                    return Err(IntError::ValidationError(format!(
+                        "Duplicate documentation file name '{}'",
+                        file.display()
+                    )));
+                }
+            }
+        }
+
+        // Validate shell completions
+        for (_, file) in self.completions.files() {
+            if file.is_absolute() || file.file_name().is_none() {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid completion file '{}': must be a relative file path",
+                    file.display()
+                )));
+            }
+            if has_path_traversal(file) {
+                return Err(IntError::PathTraversalAttempt(file.to_path_buf()));
+            }
+        }
+
+        // Validate man pages
+        for page in &self.man_pages {
+            if page.is_absolute() || man_section(page).is_none() {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid man page '{}': must be a relative path ending in a section number (e.g. myapp.1 or myapp.1.gz)",
+                    page.display()
+                )));
+            }
+            if has_path_traversal(page) {
+                return Err(IntError::PathTraversalAttempt(page.to_path_buf()));
+            }
+        }
+
+        if let Some(ref file) = self.changelog_file {
+            if file.is_absolute() {
+                return Err(IntError::ValidationError(
+                    "changelog_file path must be relative".to_string(),
+                ));
+            }
+            if has_path_traversal(file) {
+                return Err(IntError::PathTraversalAttempt(file.to_path_buf()));
+            }
+        }
+
+        if let Some(ref file) = self.eula {
+            if file.is_absolute() || file.file_name().is_none() {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid eula path '{}': must be a relative file path",
+                    file.display()
+                )));
+            }
+            if has_path_traversal(file) {
+                return Err(IntError::PathTraversalAttempt(file.to_path_buf()));
+            }
+        }
+
+        self.validate_kind()?;
+
+        // Validate executable entries; each is linked into the PATH
+        // directory under its own name, so it must sit directly in bin/
+        for entry in self.executable_entries() {
+            let entry_path = Path::new(entry);
+            if has_path_traversal(entry_path) {
+                return Err(IntError::PathTraversalAttempt(entry_path.to_path_buf()));
+            }
+            if !is_subdir_path(entry_path) || entry_path.components().count() != 1 {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid entry '{}': must be a file name directly inside bin/",
+                    entry
+                )));
+            }
+        }
+
+        // Validate data/cache/log directories
+        for dir in self
+            .data_dirs
+            .iter()
+            .chain(&self.cache_dirs)
+            .chain(&self.log_dirs)
+        {
+            if has_path_traversal(dir) {
+                return Err(IntError::PathTraversalAttempt(dir.to_path_buf()));
+            }
+            if !is_subdir_path(dir) {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid directory '{}': data, cache and log directories must be relative paths below the scope's base directory",
+                    dir.display()
+                )));
+            }
+        }
+
+        // Validate service units
+        if !self.units.is_empty() && !self.service {
+            return Err(IntError::ValidationError(
+                "units requires service to be enabled".to_string(),
+            ));
+        }
+        if self.service && !is_valid_unit_name(self.service_name()) {
+            return Err(IntError::ValidationError(format!(
+                "Invalid service name '{}': only ASCII letters, digits, '@', '.', '_' and '-' are allowed",
+                self.service_name()
+            )));
+        }
+        for unit in &self.units {
+            if !is_valid_unit_name(unit) || UnitKind::parse(unit).is_none() {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid unit '{}': expected a .service, .timer or .socket file name",
+                    unit
+                )));
+            }
+        }
+
+        // Validate tmpfiles.d fragments
+        for fragment in &self.tmpfiles {
+            if fragment.contains('/')
+                || fragment.contains('\\')
+                || fragment.len() <= ".conf".len()
+                || !fragment.ends_with(".conf")
+            {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid tmpfiles fragment '{}': expected a .conf file name",
+                    fragment
+                )));
+            }
+        }
+
+        // Validate service environment
+        for (key, value) in &self.environment {
+            if !is_valid_env_name(key) {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid environment variable name: '{}'",
+                    key
+                )));
+            }
+            if value.contains('\n') || value.contains('\0') {
+                return Err(IntError::ValidationError(format!(
+                    "Environment variable '{}' must not contain newlines",
+                    key
+                )));
+            }
+        }
+
+        // Validate template variables
+        for (key, value) in &self.variables {
+            if !is_valid_env_name(key) {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid template variable name: '{}'",
+                    key
+                )));
+            }
+            if BUILTIN_VARIABLES.contains(&key.as_str()) {
+                return Err(IntError::ValidationError(format!(
+                    "Template variable '{}' is set by the installer and cannot be redefined",
+                    key
+                )));
+            }
+            if value.contains('\n') || value.contains('\0') {
+                return Err(IntError::ValidationError(format!(
+                    "Template variable '{}' must not contain newlines",
+                    key
+                )));
+            }
+        }
+
+        // Validate scheduled tasks
+        for (index, task) in self.schedules.iter().enumerate() {
+            if task.name.is_empty()
+                || !task
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid schedule name: '{}'",
+                    task.name
+                )));
+            }
+            if self.schedules[..index].iter().any(|t| t.name == task.name) {
+                return Err(IntError::ValidationError(format!(
+                    "Duplicate schedule name: '{}'",
+                    task.name
+                )));
+            }
+            if !is_valid_cron_schedule(&task.schedule) {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid schedule for '{}': {}",
+                    task.name, task.schedule
+                )));
+            }
+            if task.command.trim().is_empty()
+                || task.command.contains('\n')
+                || task.command.contains('\0')
+            {
+                return Err(IntError::ValidationError(format!(
+                    "Schedule '{}' must have a single-line command",
+                    task.name
+                )));
+            }
+            if let Some(ref timer) = task.timer {
+                if !timer.ends_with(".timer") || !self.units.contains(timer) {
+                    return Err(IntError::ValidationError(format!(
+                        "Schedule '{}' refers to '{}', which is not a timer in units",
+                        task.name, timer
+                    )));
+                }
+            }
+        }
+
+        // Validate health check
+        if let Some(ref health_check) = self.health_check {
+            if !self.service {
+                return Err(IntError::ValidationError(
+                    "health_check requires service to be enabled".to_string(),
+                ));
+            }
+            if health_check.timeout == 0 || health_check.interval == 0 {
+                return Err(IntError::ValidationError(
+                    "health_check timeout and interval must be greater than zero".to_string(),
+                ));
+            }
+            match &health_check.probe {
+                HealthProbe::Http { url } if !url.starts_with("http://") => {
+                    return Err(IntError::ValidationError(format!(
+                        "health_check url must start with http://: {}",
+                        url
+                    )));
+                }
+                HealthProbe::Tcp { address } if address.trim().is_empty() => {
+                    return Err(IntError::ValidationError(
+                        "health_check address must not be empty".to_string(),
+                    ));
+                }
+                HealthProbe::Exec { command } if command.trim().is_empty() => {
+                    return Err(IntError::ValidationError(
+                        "health_check command must not be empty".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        // Validate translations
+        for (locale, strings) in &self.localized {
+            if !is_valid_locale(locale) {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid locale '{}': expected e.g. \"de\", \"pt_BR\" or \"sr@latin\"",
+                    locale
+                )));
+            }
+            let values = strings.display_name.iter().chain(&strings.description);
+            if values.into_iter().any(|value| value.contains('\n')) {
+                return Err(IntError::ValidationError(format!(
+                    "Translations for '{}' must not contain newlines",
+                    locale
+                )));
+            }
+        }
+
+        // Validate D-Bus services
+        for service in &self.dbus_services {
+            if !is_valid_bus_name(&service.name) {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid D-Bus name '{}': expected a reverse-DNS name such as org.example.MyApp",
+                    service.name
+                )));
+            }
+            if service.exec.is_none() && self.launch_command().is_none() {
+                return Err(IntError::ValidationError(format!(
+                    "D-Bus service '{}' needs exec, launch_command or entry",
+                    service.name
+                )));
+            }
+        }
+
+        // Validate GSettings schemas
+        for file in &self.gschemas {
+            let name = file.to_string_lossy();
+            if file.is_absolute()
+                || !(name.ends_with(".gschema.xml") || name.ends_with(".gschema.override"))
+            {
+                return Err(IntError::ValidationError(format!(
+                    "Invalid GSettings schema '{}': must be a relative .gschema.xml or .gschema.override path",
+                    file.display()
+                )));
+            }
+            if has_path_traversal(file) {
+                return Err(IntError::PathTraversalAttempt(file.to_path_buf()));
+            }
+        }
+
+        // Validate desktop categories
+        if let Some(ref desktop) = self.desktop {
+            crate::desktop::validate_categories(&desktop.categories)?;
+
+            if desktop.set_default_for_mime_types && desktop.mime_types.is_empty() {
+                return Err(IntError::ValidationError(
+                    "set_default_for_mime_types requires mime_types".to_string(),
+                ));
+            }
+
+            for (index, action) in desktop.actions.iter().enumerate() {
+                if action.id.is_empty()
+                    || !action
+                        .id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+                {
+                    return Err(IntError::ValidationError(format!(
+                        "Invalid desktop action id: '{}'",
+                        action.id
+                    )));
+                }
+                if desktop.actions[..index].iter().any(|a| a.id == action.id) {
+                    return Err(IntError::ValidationError(format!(
+                        "Duplicate desktop action id: '{}'",
+                        action.id
+                    )));
+                }
+                if action.name.trim().is_empty() || action.exec.program().is_empty() {
+                    return Err(IntError::ValidationError(format!(
+                        "Desktop action '{}' needs a name and a command",
+                        action.id
+                    )));
+                }
+            }
+
+            if let Some(ref icons) = desktop.icons {
+                if let IconSet::Sizes(files) = icons {
+                    if let Some(size) = files.keys().find(|size| !is_valid_icon_size(size)) {
+                        return Err(IntError::ValidationError(format!(
+                            "Invalid icon size '{}': expected e.g. \"48x48\", \"48x48@2\" or \"scalable\"",
+                            size
+                        )));
+                    }
+                }
+                for path in icons.paths() {
+                    if path.is_absolute() || path.as_os_str().is_empty() {
+                        return Err(IntError::ValidationError(format!(
+                            "Invalid icon path '{}': must be relative to the install path",
+                            path.display()
+                        )));
+                    }
+                    if has_path_traversal(path) {
+                        return Err(IntError::PathTraversalAttempt(path.to_path_buf()));
+                    }
+                }
+            }
+
+            if let Some(ref thumbnailer) = desktop.thumbnailer {
+                let has_output = thumbnailer.exec.args().iter().any(|a| a.contains("%o"));
+                if thumbnailer.exec.program().is_empty() || !has_output {
+                    return Err(IntError::ValidationError(
+                        "thumbnailer exec needs a program and the %o output argument".to_string(),
+                    ));
+                }
+                if thumbnailer.mime_types.is_empty() && desktop.mime_types.is_empty() {
+                    return Err(IntError::ValidationError(
+                        "thumbnailer requires mime_types".to_string(),
+                    ));
+                }
+            }
+
+            for file in &desktop.mime_definitions {
+                if file.is_absolute() || file.extension().and_then(|e| e.to_str()) != Some("xml") {
+                    return Err(IntError::ValidationError(format!(
+                        "Invalid MIME definition '{}': must be a relative .xml file path",
+                        file.display()
+                    )));
+                }
+                if has_path_traversal(file) {
+                    return Err(IntError::PathTraversalAttempt(file.to_path_buf()));
+                }
+            }
+        }
+
+        // Validate launch command
+        if let Some(ref command) = self.launch_command {
+            if command.program().is_empty() {
+                return Err(IntError::ValidationError(
+                    "launch_command program must not be empty".to_string(),
+                ));
+            }
+            if let Some(dir) = command.working_dir() {
+                if has_path_traversal(dir) {
+                    return Err(IntError::PathTraversalAttempt(dir.to_path_buf()));
+                }
+            }
+            for (key, value) in command.env() {
+                if !is_valid_env_name(key) || value.contains(['\n', '\0']) {
+                    return Err(IntError::ValidationError(format!(
+                        "Invalid launch_command environment variable: '{}'",
+                        key
+                    )));
+                }
+            }
+        }
+
+        // Validate auto-launch
+        if self.auto_launch && self.launch_command.is_none() && self.entry.is_none() {
+            return Err(IntError::ValidationError(
+                "auto_launch is true but neither launch_command nor entry is specified".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Validate kind-specific requirements
+    fn validate_kind(&self) -> IntResult<()> {
+        match self.kind {
+            PackageKind::Application => {
+                if self.library.is_some() || self.plugin.is_some() {
+                    return Err(IntError::ValidationError(
+                        "library and plugin sections require a matching kind".to_string(),
+                    ));
+                }
+            }
+            PackageKind::Library => {
+                if self.desktop.is_some()
+                    || self.auto_launch
+                    || !self.executable_entries().is_empty()
+                {
+                    return Err(IntError::ValidationError(
+                        "library packages cannot declare desktop, entry, entries or auto_launch"
+                            .to_string(),
+                    ));
+                }
+                if self.plugin.is_some() {
+                    return Err(IntError::ValidationError(
+                        "library packages cannot declare a plugin section".to_string(),
+                    ));
+                }
+                if let Some(ref library) = self.library {
+                    for dir in &library.lib_dirs {
+                        if dir.is_absolute() {
+                            return Err(IntError::ValidationError(
+                                "library lib_dirs must be relative to install_path".to_string(),
+                            ));
+                        }
+                        if has_path_traversal(dir) {
+                            return Err(IntError::PathTraversalAttempt(dir.to_path_buf()));
+                        }
+                    }
+                }
+            }
+            PackageKind::Plugin => {
+                let plugin = self
+                    .plugin
+                    .as_ref()
+                    .ok_or_else(|| IntError::MissingField("plugin".to_string()))?;
+                if !is_valid_package_name(&plugin.host) {
+                    return Err(IntError::ValidationError(format!(
+                        "Invalid plugin host: {}",
+                        plugin.host
+                    )));
+                }
+                if plugin.plugin_dir.as_os_str().is_empty() {
+                    return Err(IntError::MissingField("plugin.plugin_dir".to_string()));
+                }
+                // Plugins may only link into the host application's directory
+                if plugin.plugin_dir.is_absolute() || has_path_traversal(&plugin.plugin_dir) {
+                    return Err(IntError::PathTraversalAttempt(plugin.plugin_dir.clone()));
+                }
+                if self.desktop.is_some() || self.library.is_some() {
+                    return Err(IntError::ValidationError(
+                        "plugin packages cannot declare desktop or library sections".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect non-fatal problems in the manifest
+    ///
+    /// Unlike `validate`, these do not block installation but should be
+    /// reported to the packager or the user.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if let Some(ref license) = self.license {
+            warnings.extend(license_warning(license));
+        }
+
+        if let Some(ref desktop) = self.desktop {
+            warnings.extend(crate::desktop::category_warnings(&desktop.categories));
+        }
+
+        warnings
+    }
+
+    /// Get display name or fallback to name
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Get service name or fallback to name
+    pub fn service_name(&self) -> &str {
+        self.service_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Get the unit files to register, defaulting to the main service unit
+    pub fn service_units(&self) -> Vec<String> {
+        if self.units.is_empty() {
+            vec![format!("{}.service", self.service_name())]
+        } else {
+            self.units.clone()
+        }
+    }
+
+    /// Get the units replaced by cron jobs when systemd is unavailable
+    ///
+    /// These are the timers named by scheduled tasks and the services they
+    /// activate.
+    pub fn cron_replaced_units(&self) -> Vec<String> {
+        let mut replaced = Vec::new();
+        for timer in self.schedules.iter().filter_map(|task| task.timer.as_ref()) {
+            replaced.push(timer.clone());
+            if let Some((stem, _)) = UnitKind::parse(timer) {
+                let service = format!("{}.service", stem);
+                if self.units.contains(&service) {
+                    replaced.push(service);
+                }
+            }
+        }
+        replaced
+    }
+
+    /// Get the scheduled tasks to install as cron jobs
+    ///
+    /// Tasks backed by a timer only need cron when systemd is not in use.
+    pub fn cron_tasks(&self, init_system: Option<InitSystem>) -> Vec<&ScheduledTask> {
+        let has_timers = self.service && init_system == Some(InitSystem::Systemd);
+        self.schedules
+            .iter()
+            .filter(|task| task.timer.is_none() || !has_timers)
+            .collect()
+    }
+
+    /// Get the init system the package's services need, if only one works
+    ///
+    /// Timers and sockets can only be registered with systemd, unless a
+    /// scheduled task replaces the timer.
+    pub fn required_init_system(&self) -> Option<InitSystem> {
+        let replaced = self.cron_replaced_units();
+        let needs_systemd = self.service
+            && self
+                .units
+                .iter()
+                .filter(|unit| !replaced.contains(unit))
+                .filter_map(|unit| UnitKind::parse(unit))
+                .any(|(_, kind)| kind != UnitKind::Service);
+
+        needs_systemd.then_some(InitSystem::Systemd)
+    }
+
+    /// Check that the package's services can be managed by the detected init system
+    ///
+    /// `detected` is the result of `platform::init_system()`.
+    pub fn check_init_system(&self, detected: Option<InitSystem>) -> IntResult<()> {
+        if !self.service {
+            return Ok(());
+        }
+
+        let required = self.required_init_system();
+        match (required, detected) {
+            (Some(required), Some(found)) if required == found => Ok(()),
+            (None, Some(_)) => Ok(()),
+            (required, found) => Err(IntError::InitSystemUnavailable {
+                required: required
+                    .map(|init| init.to_string())
+                    .unwrap_or_else(|| "a service manager".to_string()),
+                found: found.map(|init| init.to_string()),
+            }),
+        }
+    }
+
+    /// Get all executable entries, starting with the main entry
+    pub fn executable_entries(&self) -> Vec<&str> {
+        let mut entries: Vec<&str> = Vec::new();
+        for entry in self.entry.iter().chain(&self.entries) {
+            if !entries.contains(&entry.as_str()) {
+                entries.push(entry);
+            }
+        }
+        entries
+    }
+
+    /// Get documentation directory for this package
+    pub fn doc_dir(&self, scope: InstallScope) -> PathBuf {
+        scope.share_path().join("doc").join(&self.name)
+    }
+
+    /// Get the service environment file path for this package
+    pub fn env_file(&self, scope: InstallScope) -> PathBuf {
+        scope
+            .config_home()
+            .join(&self.name)
+            .join(format!("{}.env", self.service_name()))
+    }
+
+    /// Get license directory for this package
+    pub fn license_dir(&self, scope: InstallScope) -> PathBuf {
+        scope.share_path().join("licenses").join(&self.name)
+    }
+
+    /// Resolve declared data directories to absolute paths
+    pub fn resolved_data_dirs(&self, scope: InstallScope) -> Vec<PathBuf> {
+        resolve_dirs(&self.data_dirs, &scope.data_home())
+    }
+
+    /// Resolve declared cache directories to absolute paths
+    pub fn resolved_cache_dirs(&self, scope: InstallScope) -> Vec<PathBuf> {
+        resolve_dirs(&self.cache_dirs, &scope.cache_home())
+    }
+
+    /// Resolve declared log directories to absolute paths
+    pub fn resolved_log_dirs(&self, scope: InstallScope) -> Vec<PathBuf> {
+        resolve_dirs(&self.log_dirs, &scope.log_home())
+    }
+
+    /// Get the script for a lifecycle hook
+    ///
+    /// Falls back to the legacy `post_install`/`pre_uninstall` fields when the
+    /// `scripts` map does not declare the hook.
+    pub fn script(&self, hook: ScriptHook) -> Option<MaintainerScript> {
+        if let Some(script) = self.scripts.get(hook) {
+            return Some(script.clone());
+        }
+
+        let legacy = match hook {
+            ScriptHook::PostInstall => self.post_install.as_ref(),
+            ScriptHook::PreUninstall => self.pre_uninstall.as_ref(),
+            _ => None,
+        };
+        legacy.map(MaintainerScript::new)
+    }
+
+    /// Get launch command or fallback to the main entry
+    pub fn launch_command(&self) -> Option<LaunchCommand> {
+        self.launch_command
+            .clone()
+            .or_else(|| self.entry.clone().map(LaunchCommand::Program))
+    }
+
+    /// Check if package requires system-level installation
+    pub fn requires_system_install(&self) -> bool {
+        self.install_scope == InstallScope::System
+    }
+
+    /// Get installation metadata path for this package
+    pub fn metadata_path(&self, scope: InstallScope) -> PathBuf {
+        root::path(&match scope {
+            InstallScope::User => {
+                let home = std::env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
+                PathBuf::from(home)
+                    .join(".local/share/int-installer/installed")
+                    .join(format!("{}.json", self.name))
+            }
+            InstallScope::System => PathBuf::from("/var/lib/int-installer/installed")
+                .join(format!("{}.json", self.name)),
+        })
+    }
+
+    /// Serialize to JSON string (pretty)
+    pub fn to_string(&self) -> IntResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| IntError::Custom(format!("Failed to serialize manifest: {}", e)))
+    }
+
+    /// Serialize to compact canonical JSON string for signing/verification
+    pub fn to_canonical_string(&self) -> IntResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| IntError::Custom(format!("Failed to serialize manifest: {}", e)))
+    }
+}
+
+/// Validate package name format
+pub(crate) fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Validate unit and service name format
+///
+/// Names end up on init system command lines, so they start with a letter
+/// or digit and use the characters systemd allows in unit names.
+fn is_valid_unit_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '_' | '-'))
+}
+
+/// Validate environment variable name format
+fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Get the section of a man page from its file name
+///
+/// `myapp.1`, `myapp.1.gz` and `myapp.3p` are in sections 1, 1 and 3.
+pub fn man_section(page: &Path) -> Option<char> {
+    let name = page.file_name()?.to_str()?;
+    let name = [".gz", ".bz2", ".xz", ".zst"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+    let (stem, extension) = name.rsplit_once('.')?;
+
+    let section = extension.chars().next()?;
+    (!stem.is_empty() && matches!(section, '1'..='9' | 'n' | 'l')).then_some(section)
+}
+
+/// Check a well-known D-Bus name (at least two dot-separated elements)
+fn is_valid_bus_name(name: &str) -> bool {
+    let elements: Vec<&str> = name.split('.').collect();
+
+    name.len() <= 255
+        && elements.len() >= 2
+        && elements.iter().all(|element| {
+            matches!(element.chars().next(), Some(c) if !c.is_ascii_digit())
+                && element
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+/// Check a locale name of the form `lang[_COUNTRY][.ENCODING][@MODIFIER]`
+fn is_valid_locale(locale: &str) -> bool {
+    let lang_len = locale
+        .find(|c: char| !c.is_ascii_lowercase())
+        .unwrap_or(locale.len());
+    let rest = &locale[lang_len..];
+
+    (2..=3).contains(&lang_len)
+        && rest
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '@' | '-'))
+        && matches!(rest.chars().next(), None | Some('_' | '.' | '@'))
+}
+
+/// Check an icon theme size directory name ("48x48", "48x48@2" or "scalable")
+fn is_valid_icon_size(size: &str) -> bool {
+    if size == "scalable" {
+        return true;
+    }
+
+    let (dimensions, scale) = match size.split_once('@') {
+        Some((dimensions, scale)) => (dimensions, Some(scale)),
+        None => (size, None),
+    };
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+    matches!(dimensions.split_once('x'), Some((w, h)) if is_number(w) && w == h)
+        && scale.is_none_or(is_number)
+}
+
+/// Check that a cron schedule is a known shortcut or has five fields
+fn is_valid_cron_schedule(schedule: &str) -> bool {
+    const SHORTCUTS: &[&str] = &[
+        "@reboot",
+        "@yearly",
+        "@annually",
+        "@monthly",
+        "@weekly",
+        "@daily",
+        "@midnight",
+        "@hourly",
+    ];
+    if schedule.starts_with('@') {
+        return SHORTCUTS.contains(&schedule);
+    }
+
+    let fields: Vec<&str> = schedule.split_whitespace().collect();
+    fields.len() == 5
+        && fields.iter().all(|field| {
+            field
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '*' | ',' | '-' | '/'))
+        })
+}
+
+/// Check a license string against the SPDX expression syntax
+fn license_warning(license: &str) -> Option<String> {
+    let expression = match spdx::Expression::parse(license) {
+        Ok(expression) => expression,
+        Err(e) => {
+            return Some(format!(
+                "license '{}' is not a valid SPDX expression: {}",
+                license, e.reason
+            ))
+        }
+    };
+
+    let deprecated: Vec<&str> = expression
+        .requirements()
+        .filter_map(|req| req.req.license.id())
+        .filter(|id| id.is_deprecated())
+        .map(|id| id.name)
+        .collect();
+
+    if deprecated.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "license '{}' uses deprecated SPDX identifiers: {}",
+            license,
+            deprecated.join(", ")
+        ))
+    }
+}
+
+/// Validate a maintainer script declaration
+fn validate_script(hook: ScriptHook, script: &MaintainerScript) -> IntResult<()> {
+    if script.path.is_absolute() {
+        return Err(IntError::ValidationError(format!(
+            "{} script path must be relative",
+            hook.as_str()
+        )));
+    }
+    if has_path_traversal(&script.path) {
+        return Err(IntError::PathTraversalAttempt(script.path.clone()));
+    }
+    if script.interpreter.as_deref() == Some("") {
+        return Err(IntError::ValidationError(format!(
+            "{} script interpreter must not be empty",
+            hook.as_str()
+        )));
+    }
+    if script.timeout == Some(0) {
+        return Err(IntError::ValidationError(format!(
+            "{} script timeout must be greater than zero",
+            hook.as_str()
+        )));
+    }
+    Ok(())
+}
+
+/// Resolve relative directories against a base directory
+fn resolve_dirs(dirs: &[PathBuf], base: &Path) -> Vec<PathBuf> {
+    dirs.iter().map(|dir| base.join(dir)).collect()
+}
+
+/// Check that a path names a directory strictly below the one it is joined to
+///
+/// Only plain components are allowed: no root, no `.` and no `..`.
+pub(crate) fn is_subdir_path(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Check if path contains traversal attempts (..)
 fn has_path_traversal(path: &Path) -> bool {
     path.components()
         .any(|c| matches!(c, std::path::Component::ParentDir))
@@ -366,27 +1891,54 @@ mod tests {
         Manifest {
             version: MANIFEST_VERSION.to_string(),
             name: "test-app".to_string(),
+            kind: PackageKind::Application,
             display_name: Some("Test Application".to_string()),
             package_version: "1.0.0".to_string(),
             description: Some("A test application".to_string()),
+            localized: BTreeMap::new(),
             author: Some("Test Author".to_string()),
             install_scope: InstallScope::User,
             install_path: PathBuf::from("/home/user/.local/share/test-app"),
             entry: Some("test-app".to_string()),
+            entries: vec![],
             service: false,
             service_name: None,
+            hardening: false,
+            service_preset: None,
+            units: vec![],
+            tmpfiles: vec![],
+            environment: BTreeMap::new(),
+            variables: BTreeMap::new(),
+            schedules: vec![],
+            health_check: None,
             post_install: None,
             pre_uninstall: None,
+            scripts: Scripts::default(),
+            docs: DocFiles::default(),
+            completions: Completions::default(),
+            man_pages: vec![],
+            library: None,
+            plugin: None,
             desktop: None,
+            dbus_services: vec![],
+            gschemas: vec![],
             dependencies: vec![],
             required_space: Some(10_000_000),
             architecture: Some("x86_64".to_string()),
             license: Some("MIT".to_string()),
             homepage: Some("https://example.com".to_string()),
+            update_url: None,
             auto_launch: false,
             launch_command: None,
+            changelog: None,
+            changelog_file: None,
+            eula: None,
+            data_dirs: vec![],
+            cache_dirs: vec![],
+            log_dirs: vec![],
             signature: None,
             file_hashes: None,
+            compression: None,
         }
     }
 
@@ -428,6 +1980,541 @@ mod tests {
         assert_eq!(manifest.package_version, parsed.package_version);
     }
 
+    #[test]
+    fn test_executable_entries() {
+        let mut manifest = create_test_manifest();
+        manifest.entries = vec!["test-app".to_string(), "test-app-admin".to_string()];
+        assert_eq!(
+            manifest.executable_entries(),
+            vec!["test-app", "test-app-admin"]
+        );
+        assert!(manifest.validate().is_ok());
+
+        manifest.entries.push("../escape".to_string());
+        assert!(manifest.validate().is_err());
+
+        // Entries are linked by name, so subdirectories are rejected
+        for entry in ["tools/test-app-admin", "/usr/bin/test-app", "", "."] {
+            manifest.entries = vec![entry.to_string()];
+            assert!(manifest.validate().is_err(), "{} accepted", entry);
+        }
+    }
+
+    #[test]
+    fn test_license_warnings() {
+        let mut manifest = create_test_manifest();
+        assert!(manifest.warnings().is_empty());
+
+        manifest.license = Some("Apache-2.0 OR MIT".to_string());
+        assert!(manifest.warnings().is_empty());
+
+        manifest.license = Some("My Own License".to_string());
+        assert_eq!(manifest.warnings().len(), 1);
+        assert!(manifest.validate().is_ok());
+
+        manifest.license = Some("GPL-3.0".to_string());
+        assert!(manifest.warnings()[0].contains("deprecated"));
+    }
+
+    #[test]
+    fn test_category_warnings() {
+        let mut manifest = create_test_manifest();
+        manifest.desktop = Some(DesktopEntry {
+            categories: vec!["Utility".to_string(), "Utilty".to_string()],
+            mime_types: vec![],
+            mime_definitions: vec![],
+            icon: None,
+            icons: None,
+            show_in_menu: true,
+            keywords: vec![],
+            set_default_for_mime_types: false,
+            thumbnailer: None,
+            generic_name: None,
+            terminal: false,
+            startup_wm_class: None,
+            startup_notify: None,
+            actions: vec![],
+        });
+        assert!(manifest.validate().is_ok());
+
+        let warnings = manifest.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("did you mean 'Utility'"));
+    }
+
+    #[test]
+    fn test_scripts_map() {
+        let mut manifest = create_test_manifest();
+        manifest.post_install = Some(PathBuf::from("scripts/legacy.sh"));
+        manifest.scripts.pre_upgrade = Some(MaintainerScript {
+            path: PathBuf::from("scripts/migrate.py"),
+            interpreter: Some("python3".to_string()),
+            timeout: Some(60),
+        });
+        assert!(manifest.validate().is_ok());
+
+        // Legacy fields still work when the map does not declare the hook
+        assert_eq!(
+            manifest.script(ScriptHook::PostInstall).unwrap().path,
+            PathBuf::from("scripts/legacy.sh")
+        );
+        assert!(manifest.script(ScriptHook::PostUpgrade).is_none());
+
+        manifest.scripts.post_install = Some(MaintainerScript::new("scripts/setup.sh"));
+        assert_eq!(
+            manifest.script(ScriptHook::PostInstall).unwrap().path,
+            PathBuf::from("scripts/setup.sh")
+        );
+
+        manifest.scripts.post_uninstall = Some(MaintainerScript::new("../evil.sh"));
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_launch_command_forms() {
+        let legacy: LaunchCommand = serde_json::from_str(r#""test-app""#).unwrap();
+        assert_eq!(legacy, LaunchCommand::Program("test-app".to_string()));
+        assert!(legacy.args().is_empty());
+
+        let structured: LaunchCommand = serde_json::from_str(
+            r#"{"program": "test-app", "args": ["--config", "my file.conf"], "working_dir": "data",
+                "env": {"APP_MODE": "desktop"}}"#,
+        )
+        .unwrap();
+        assert_eq!(structured.args(), ["--config", "my file.conf"]);
+        assert_eq!(structured.env()["APP_MODE"], "desktop");
+        assert!(legacy.env().is_empty());
+
+        let install_path = Path::new("/opt/test-app");
+        assert_eq!(
+            structured.resolve_program(install_path),
+            PathBuf::from("/opt/test-app/bin/test-app")
+        );
+        assert_eq!(
+            structured.resolve_working_dir(install_path),
+            PathBuf::from("/opt/test-app/data")
+        );
+
+        // The original form must round-trip unchanged for signature checks
+        assert_eq!(serde_json::to_string(&legacy).unwrap(), r#""test-app""#);
+
+        let manifest = create_test_manifest();
+        assert_eq!(manifest.launch_command().unwrap().program(), "test-app");
+    }
+
+    #[test]
+    fn test_kind_validation() {
+        let mut manifest = create_test_manifest();
+        manifest.kind = PackageKind::Library;
+        manifest.entry = None;
+        manifest.library = Some(LibraryConfig {
+            lib_dirs: vec![PathBuf::from("lib")],
+        });
+        assert!(manifest.validate().is_ok());
+
+        manifest.entry = Some("test-app".to_string());
+        assert!(manifest.validate().is_err());
+
+        let mut manifest = create_test_manifest();
+        manifest.kind = PackageKind::Plugin;
+        assert!(manifest.validate().is_err());
+
+        manifest.plugin = Some(PluginConfig {
+            host: "host-app".to_string(),
+            plugin_dir: PathBuf::from("plugins"),
+        });
+        assert!(manifest.validate().is_ok());
+
+        for plugin_dir in ["/etc/profile.d", "../other-app/plugins"] {
+            manifest.plugin.as_mut().unwrap().plugin_dir = PathBuf::from(plugin_dir);
+            assert!(manifest.validate().is_err());
+        }
+        manifest.plugin.as_mut().unwrap().plugin_dir = PathBuf::from("plugins");
+
+        let json = manifest.to_string().unwrap();
+        assert_eq!(Manifest::from_str(&json).unwrap().kind, PackageKind::Plugin);
+    }
+
+    #[test]
+    fn test_health_check() {
+        let mut manifest = create_test_manifest();
+        manifest.service = true;
+        manifest.health_check = Some(HealthCheck {
+            probe: HealthProbe::Http {
+                url: "http://127.0.0.1:8080/health".to_string(),
+            },
+            timeout: 30,
+            interval: 1,
+        });
+        assert!(manifest.validate().is_ok());
+
+        let json = manifest.to_string().unwrap();
+        assert_eq!(
+            Manifest::from_str(&json).unwrap().health_check,
+            manifest.health_check
+        );
+
+        let check: HealthCheck =
+            serde_json::from_str(r#"{"type": "tcp", "address": "127.0.0.1:5432"}"#).unwrap();
+        assert_eq!(check.timeout, 30);
+        assert_eq!(check.interval, 1);
+
+        manifest.health_check = Some(HealthCheck {
+            probe: HealthProbe::Http {
+                url: "https://example.com".to_string(),
+            },
+            timeout: 30,
+            interval: 1,
+        });
+        assert!(manifest.validate().is_err());
+
+        manifest.service = false;
+        manifest.health_check = Some(check);
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_environment() {
+        let mut manifest = create_test_manifest();
+        manifest.service = true;
+        manifest
+            .environment
+            .insert("APP_PORT".to_string(), "8080".to_string());
+        assert!(manifest.validate().is_ok());
+        assert!(manifest
+            .env_file(InstallScope::System)
+            .ends_with("test-app/test-app.env"));
+
+        manifest
+            .environment
+            .insert("1INVALID".to_string(), "x".to_string());
+        assert!(manifest.validate().is_err());
+
+        let mut manifest = create_test_manifest();
+        manifest
+            .environment
+            .insert("MOTD".to_string(), "line\nbreak".to_string());
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_template_variables() {
+        let mut manifest = create_test_manifest();
+        manifest.service = true;
+        manifest
+            .variables
+            .insert("PORT".to_string(), "8080".to_string());
+        assert!(manifest.validate().is_ok());
+
+        manifest
+            .variables
+            .insert("USER".to_string(), "nobody".to_string());
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_service_units() {
+        let mut manifest = create_test_manifest();
+        manifest.service = true;
+        assert_eq!(manifest.service_units(), vec!["test-app.service"]);
+
+        manifest.units = vec!["test-app.service".to_string(), "test-app.timer".to_string()];
+        assert!(manifest.validate().is_ok());
+        assert_eq!(manifest.service_units().len(), 2);
+
+        manifest.units.push("test-app.path".to_string());
+        assert!(manifest.validate().is_err());
+
+        for unit in [
+            "../test-app.service",
+            "-now.service",
+            "a;reboot.service",
+            "a b.timer",
+        ] {
+            manifest.units = vec![unit.to_string()];
+            assert!(manifest.validate().is_err(), "{} accepted", unit);
+        }
+        manifest.units = vec!["test-app@main.service".to_string()];
+        assert!(manifest.validate().is_ok());
+
+        manifest.units = vec![];
+        manifest.service_name = Some("app$(reboot)".to_string());
+        assert!(manifest.validate().is_err());
+
+        manifest.units = vec!["test-app.timer".to_string()];
+        manifest.service = false;
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_check_init_system() {
+        let mut manifest = create_test_manifest();
+        assert!(manifest.check_init_system(None).is_ok());
+
+        manifest.service = true;
+        assert!(manifest.check_init_system(Some(InitSystem::OpenRc)).is_ok());
+        assert!(manifest.check_init_system(None).is_err());
+
+        manifest.units = vec!["test-app.service".to_string(), "test-app.timer".to_string()];
+        assert_eq!(manifest.required_init_system(), Some(InitSystem::Systemd));
+        assert!(manifest
+            .check_init_system(Some(InitSystem::Systemd))
+            .is_ok());
+        let err = manifest
+            .check_init_system(Some(InitSystem::OpenRc))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "This package requires systemd, but this system uses OpenRC"
+        );
+    }
+
+    #[test]
+    fn test_schedules() {
+        let mut manifest = create_test_manifest();
+        manifest.service = true;
+        manifest.units = vec!["test-app.service".to_string(), "test-app.timer".to_string()];
+        manifest.schedules = vec![ScheduledTask {
+            name: "backup".to_string(),
+            schedule: "30 3 * * 1-5".to_string(),
+            command: "{{ENTRY}} backup".to_string(),
+            timer: Some("test-app.timer".to_string()),
+        }];
+        assert!(manifest.validate().is_ok());
+
+        // The timer and its service are replaced by cron without systemd
+        assert_eq!(manifest.required_init_system(), None);
+        assert_eq!(
+            manifest.cron_replaced_units(),
+            vec!["test-app.timer", "test-app.service"]
+        );
+        assert!(manifest.cron_tasks(Some(InitSystem::Systemd)).is_empty());
+        assert_eq!(manifest.cron_tasks(Some(InitSystem::OpenRc)).len(), 1);
+
+        manifest.schedules[0].schedule = "every day".to_string();
+        assert!(manifest.validate().is_err());
+        manifest.schedules[0].schedule = "@daily".to_string();
+        manifest.schedules[0].timer = Some("test-app.service".to_string());
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_tmpfiles_fragments() {
+        let mut manifest = create_test_manifest();
+        manifest.tmpfiles = vec!["test-app.conf".to_string()];
+        assert!(manifest.validate().is_ok());
+        assert_eq!(
+            InstallScope::System.tmpfiles_path(),
+            PathBuf::from("/etc/tmpfiles.d")
+        );
+
+        manifest.tmpfiles = vec!["../test-app.conf".to_string()];
+        assert!(manifest.validate().is_err());
+
+        manifest.tmpfiles = vec!["test-app.txt".to_string()];
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_docs_declaration() {
+        let mut manifest = create_test_manifest();
+        manifest.docs.files = vec![PathBuf::from("README.md")];
+        manifest.docs.licenses = vec![PathBuf::from("LICENSE")];
+        assert!(manifest.validate().is_ok());
+
+        assert_eq!(
+            manifest.license_dir(InstallScope::System),
+            PathBuf::from("/usr/local/share/licenses/test-app")
+        );
+
+        manifest.docs.files.push(PathBuf::from("/etc/passwd"));
+        assert!(manifest.validate().is_err());
+
+        // Both would be installed as doc/test-app/README.md
+        manifest.docs.files = vec![PathBuf::from("README.md"), PathBuf::from("docs/README.md")];
+        assert!(manifest.validate().is_err());
+
+        // Docs and licenses go to different directories
+        manifest.docs.files = vec![PathBuf::from("LICENSE")];
+        assert!(manifest.validate().is_ok());
+    }
+
+    #[test]
+    fn test_mime_definitions() {
+        let mut manifest = create_test_manifest();
+        manifest.desktop = Some(DesktopEntry {
+            categories: vec![],
+            mime_types: vec!["application/x-test-app".to_string()],
+            mime_definitions: vec![PathBuf::from("share/mime/test-app.xml")],
+            icon: None,
+            icons: None,
+            show_in_menu: true,
+            keywords: vec![],
+            set_default_for_mime_types: false,
+            thumbnailer: None,
+            generic_name: None,
+            terminal: false,
+            startup_wm_class: None,
+            startup_notify: None,
+            actions: vec![],
+        });
+        assert!(manifest.validate().is_ok());
+        assert_eq!(
+            InstallScope::System.mime_path(),
+            PathBuf::from("/usr/local/share/mime")
+        );
+
+        if let Some(ref mut desktop) = manifest.desktop {
+            desktop.mime_definitions = vec![PathBuf::from("../test-app.xml")];
+        }
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_icon_declarations() {
+        let mut manifest = create_test_manifest();
+        let json = r#"{"48x48": "icons/48.png", "scalable": "icons/app.svg"}"#;
+        let icons: IconSet = serde_json::from_str(json).unwrap();
+        assert!(matches!(icons, IconSet::Sizes(ref files) if files.len() == 2));
+        let tree: IconSet = serde_json::from_str(r#""share/icons/hicolor""#).unwrap();
+        assert_eq!(tree, IconSet::Tree(PathBuf::from("share/icons/hicolor")));
+
+        let mut desktop: DesktopEntry = serde_json::from_str("{}").unwrap();
+        desktop.icons = Some(icons);
+        manifest.desktop = Some(desktop);
+        assert!(manifest.validate().is_ok());
+
+        assert!(is_valid_icon_size("256x256@2"));
+        assert!(!is_valid_icon_size("48x32"));
+        assert!(!is_valid_icon_size("big"));
+
+        manifest.desktop.as_mut().unwrap().icons = Some(IconSet::Tree(PathBuf::from("../icons")));
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_man_pages() {
+        assert_eq!(man_section(Path::new("man/myapp.1")), Some('1'));
+        assert_eq!(man_section(Path::new("myapp-config.5.gz")), Some('5'));
+        assert_eq!(man_section(Path::new("Myapp.3pm")), Some('3'));
+        assert_eq!(man_section(Path::new("README.md")), None);
+        assert_eq!(man_section(Path::new("myapp.gz")), None);
+
+        let mut manifest = create_test_manifest();
+        manifest.man_pages = vec![PathBuf::from("share/doc/myapp.1")];
+        assert!(manifest.validate().is_ok());
+        manifest.man_pages = vec![PathBuf::from("share/doc/myapp.txt")];
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_completions() {
+        let name = |shell: Shell, file: &str| shell.completion_file_name(Path::new(file));
+        assert_eq!(name(Shell::Bash, "completions/myapp.bash"), "myapp");
+        assert_eq!(name(Shell::Zsh, "completions/myapp.zsh"), "_myapp");
+        assert_eq!(name(Shell::Zsh, "completions/_myapp"), "_myapp");
+        assert_eq!(name(Shell::Fish, "completions/myapp"), "myapp.fish");
+
+        assert_eq!(
+            InstallScope::System.completion_path(Shell::Fish),
+            PathBuf::from("/usr/local/share/fish/vendor_completions.d")
+        );
+
+        let mut manifest = create_test_manifest();
+        manifest.completions = serde_json::from_str(r#"{"bash": ["c/myapp.bash"]}"#).unwrap();
+        assert_eq!(manifest.completions.files().len(), 1);
+        assert!(manifest.validate().is_ok());
+        manifest.completions.zsh = vec![PathBuf::from("../_myapp")];
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_localized_strings() {
+        assert!(is_valid_locale("de"));
+        assert!(is_valid_locale("pt_BR"));
+        assert!(is_valid_locale("sr@latin"));
+        assert!(is_valid_locale("de_DE.UTF-8"));
+        assert!(!is_valid_locale("German"));
+        assert!(!is_valid_locale("d"));
+        assert!(!is_valid_locale("de]"));
+
+        let mut manifest = create_test_manifest();
+        manifest.localized = serde_json::from_str(
+            r#"{"de": {"display_name": "Test-Anwendung", "description": "Eine Test-Anwendung"}}"#,
+        )
+        .unwrap();
+        assert!(manifest.validate().is_ok());
+        assert_eq!(
+            manifest.localized["de"].display_name.as_deref(),
+            Some("Test-Anwendung")
+        );
+    }
+
+    #[test]
+    fn test_dbus_services() {
+        assert!(is_valid_bus_name("org.example.MyApp"));
+        assert!(is_valid_bus_name("com.example.my_app-2"));
+        assert!(!is_valid_bus_name("myapp"));
+        assert!(!is_valid_bus_name("org.example.2app"));
+        assert!(!is_valid_bus_name("org..example"));
+
+        let mut manifest = create_test_manifest();
+        manifest.dbus_services = vec![DbusService {
+            name: "org.example.TestApp".to_string(),
+            exec: None,
+            systemd_service: None,
+        }];
+        assert!(manifest.validate().is_ok());
+
+        manifest.entry = None;
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_thumbnailer() {
+        let mut manifest = create_test_manifest();
+        let mut desktop: DesktopEntry = serde_json::from_str("{}").unwrap();
+        desktop.mime_types = vec!["application/x-test".to_string()];
+        desktop.thumbnailer = Some(Thumbnailer {
+            exec: LaunchCommand::Structured {
+                program: "test-app-thumbnailer".to_string(),
+                args: vec!["%i".to_string(), "%o".to_string()],
+                working_dir: None,
+                env: BTreeMap::new(),
+            },
+            mime_types: vec![],
+        });
+        manifest.desktop = Some(desktop);
+        assert!(manifest.validate().is_ok());
+
+        // Without %o the thumbnailer has nowhere to write
+        let desktop = manifest.desktop.as_mut().unwrap();
+        desktop.thumbnailer = Some(Thumbnailer {
+            exec: LaunchCommand::Program("test-app-thumbnailer".to_string()),
+            mime_types: vec![],
+        });
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_managed_dirs() {
+        let mut manifest = create_test_manifest();
+        manifest.data_dirs = vec![PathBuf::from("test-app")];
+        manifest.log_dirs = vec![PathBuf::from("test-app/server")];
+        assert!(manifest.validate().is_ok());
+
+        let system_data = manifest.resolved_data_dirs(InstallScope::System);
+        assert_eq!(system_data, vec![PathBuf::from("/var/lib/test-app")]);
+        let logs = manifest.resolved_log_dirs(InstallScope::System);
+        assert_eq!(logs, vec![PathBuf::from("/var/log/test-app/server")]);
+
+        // Purging removes these, so they must stay below the base directory
+        for dir in ["../escape", "/var/log", ".", "", "./test-app"] {
+            manifest.cache_dirs = vec![PathBuf::from(dir)];
+            assert!(manifest.validate().is_err(), "{} accepted", dir);
+        }
+    }
+
     #[test]
     fn test_install_scope_paths() {
         let user_scope = InstallScope::User;
@@ -0,0 +1,85 @@
+/// Platform detection
+///
+/// Probes the running system for the facilities packages integrate with,
+/// so the installer can pick the matching backend and refuse packages that
+/// cannot work here before anything is installed.
+use crate::root;
+use crate::service::InitSystem;
+use std::path::Path;
+
+/// Detect the init system managing services on this machine
+///
+/// Returns `None` when no supported init system is running, e.g. inside a
+/// container started without one. Inside an alternate root, the init system
+/// installed there is used instead.
+pub fn init_system() -> Option<InitSystem> {
+    if cfg!(windows) {
+        Some(InitSystem::Windows)
+    } else if cfg!(target_os = "macos") {
+        Some(InitSystem::Launchd)
+    } else if root::is_set() {
+        probe_installed_init_system(|path| root::path(Path::new(path)).exists())
+    } else {
+        probe_init_system(|path| Path::new(path).exists())
+    }
+}
+
+/// Identify a Linux init system from the paths it creates at runtime
+fn probe_init_system(exists: impl Fn(&str) -> bool) -> Option<InitSystem> {
+    if exists("/run/systemd/system") {
+        Some(InitSystem::Systemd)
+    } else if exists("/run/openrc") || exists("/sbin/openrc-run") {
+        Some(InitSystem::OpenRc)
+    } else if exists("/run/runit") || exists("/etc/runit/runsvdir") {
+        Some(InitSystem::Runit)
+    } else {
+        None
+    }
+}
+
+/// Identify the init system installed in a root that isn't running
+fn probe_installed_init_system(exists: impl Fn(&str) -> bool) -> Option<InitSystem> {
+    if exists("/usr/lib/systemd/systemd") || exists("/lib/systemd/systemd") {
+        Some(InitSystem::Systemd)
+    } else if exists("/sbin/openrc-run") || exists("/usr/sbin/openrc-run") {
+        Some(InitSystem::OpenRc)
+    } else if exists("/etc/runit/runsvdir") {
+        Some(InitSystem::Runit)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_init_system() {
+        let probe = |present: &'static [&'static str]| {
+            probe_init_system(move |path| present.contains(&path))
+        };
+
+        assert_eq!(probe(&["/run/systemd/system"]), Some(InitSystem::Systemd));
+        assert_eq!(probe(&["/run/openrc"]), Some(InitSystem::OpenRc));
+        assert_eq!(probe(&["/etc/runit/runsvdir"]), Some(InitSystem::Runit));
+        // systemd wins when several init systems are installed
+        assert_eq!(
+            probe(&["/sbin/openrc-run", "/run/systemd/system"]),
+            Some(InitSystem::Systemd)
+        );
+        assert_eq!(probe(&[]), None);
+    }
+
+    #[test]
+    fn test_probe_installed_init_system() {
+        let probe = |present: &'static [&'static str]| {
+            probe_installed_init_system(move |path| present.contains(&path))
+        };
+
+        assert_eq!(probe(&["/lib/systemd/systemd"]), Some(InitSystem::Systemd));
+        assert_eq!(probe(&["/usr/sbin/openrc-run"]), Some(InitSystem::OpenRc));
+        // Runtime paths say nothing about a root that isn't booted
+        assert_eq!(probe(&["/run/systemd/system"]), None);
+    }
+}
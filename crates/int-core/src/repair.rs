@@ -0,0 +1,251 @@
+/// Repair of installed files from the package cache
+///
+/// The package cache keeps a copy of each installed version (see `history`),
+/// and the metadata records the SHA256 of the archive that was installed, so
+/// the cached copy is known to be that very archive and not another build
+/// of the same version. `repair` checks the installed payload against it
+/// and restores files that went missing or were modified.
+use crate::compression::{open_archive, Decoder};
+use crate::error::{IntError, IntResult};
+use crate::history;
+use crate::installer::InstallMetadata;
+use crate::lock::OperationLock;
+use crate::manifest::InstallScope;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use tar::Entry;
+
+/// What was wrong with an installed file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileProblem {
+    Missing,
+    Modified,
+}
+
+/// File restored by `repair`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoredFile {
+    pub path: PathBuf,
+    pub problem: FileProblem,
+}
+
+/// Outcome of repairing a package
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairReport {
+    /// Installed payload files checked
+    pub checked: usize,
+    pub restored: Vec<RestoredFile>,
+}
+
+/// Restore the missing and modified payload files of an installed package
+///
+/// Only files the installation recorded are checked, and nothing else of
+/// the installation (scripts, services, desktop integration) is redone.
+pub fn repair(package_name: &str, scope: InstallScope, wait: bool) -> IntResult<RepairReport> {
+    let _lock = OperationLock::acquire(scope, wait)?;
+    let metadata = InstallMetadata::load(package_name, scope)?;
+    let version = &metadata.package_version;
+
+    let archive = history::cached_package(scope, package_name, version);
+    if !archive.is_file() {
+        return Err(IntError::Custom(format!(
+            "No cached copy of {} {} to repair from; reinstall the package",
+            package_name, version
+        )));
+    }
+    if let Some(ref expected) = metadata.package_sha256 {
+        if utils::sha256_file(&archive)? != *expected {
+            return Err(IntError::Custom(format!(
+                "Cached copy of {} {} is not the installed package; reinstall the package",
+                package_name, version
+            )));
+        }
+    }
+
+    let installed: HashSet<&Path> = metadata
+        .installed_files
+        .iter()
+        .map(PathBuf::as_path)
+        .collect();
+    let report = restore_files(&archive, &metadata.install_path, &installed)?;
+    for file in &report.restored {
+        tracing::info!("Restored {} ({:?})", file.path.display(), file.problem);
+    }
+    Ok(report)
+}
+
+/// Check installed files against an archive and restore the damaged ones
+fn restore_files(
+    archive: &Path,
+    install_path: &Path,
+    installed: &HashSet<&Path>,
+) -> IntResult<RepairReport> {
+    let mut report = RepairReport::default();
+    let mut damaged = HashMap::new();
+    for_each_payload_file(archive, install_path, installed, |entry, path| {
+        report.checked += 1;
+        let problem = if !path.exists() {
+            FileProblem::Missing
+        } else {
+            let mut hasher = Sha256::new();
+            io::copy(entry, &mut hasher).map_err(|e| {
+                IntError::CorruptedArchive(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+            if utils::sha256_file(path)? == format!("{:x}", hasher.finalize()) {
+                return Ok(());
+            }
+            FileProblem::Modified
+        };
+        damaged.insert(path.to_path_buf(), problem);
+        Ok(())
+    })?;
+
+    if damaged.is_empty() {
+        return Ok(report);
+    }
+    for_each_payload_file(archive, install_path, installed, |entry, path| {
+        let Some(problem) = damaged.remove(path) else {
+            return Ok(());
+        };
+        restore(entry, path)?;
+        report.restored.push(RestoredFile {
+            path: path.to_path_buf(),
+            problem,
+        });
+        Ok(())
+    })?;
+    report.restored.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+/// Call `f` with each regular payload file of an archive that was installed,
+/// and where it was installed to
+fn for_each_payload_file(
+    archive: &Path,
+    install_path: &Path,
+    installed: &HashSet<&Path>,
+    mut f: impl FnMut(&mut Entry<Decoder>, &Path) -> IntResult<()>,
+) -> IntResult<()> {
+    let mut archive = open_archive(archive)?;
+    for entry_result in archive
+        .entries()
+        .map_err(|e| IntError::CorruptedArchive(format!("Failed to read archive: {}", e)))?
+    {
+        let mut entry = entry_result
+            .map_err(|e| IntError::CorruptedArchive(format!("Failed to read entry: {}", e)))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry
+            .path()
+            .map_err(|e| IntError::CorruptedArchive(format!("Invalid entry path: {}", e)))?;
+        let Ok(relative) = entry_path.strip_prefix("payload") else {
+            continue;
+        };
+        let path = install_path.join(relative);
+        if installed.contains(path.as_path()) {
+            f(&mut entry, &path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write an archive entry over an installed file, with its permissions
+fn restore(entry: &mut Entry<Decoder>, path: &Path) -> IntResult<()> {
+    if let Some(parent) = path.parent() {
+        utils::ensure_dir(parent)?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".repair");
+    let temp = PathBuf::from(temp);
+
+    let result = File::create(&temp)
+        .and_then(|mut file| io::copy(entry, &mut file))
+        .and_then(|_| fs::rename(&temp, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(IntError::Custom(format!(
+            "Failed to restore {}: {}",
+            path.display(),
+            e
+        )));
+    }
+    if let Ok(mode) = entry.header().mode() {
+        utils::set_permissions(path, mode)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    #[test]
+    fn test_restore_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let archive = temp.path().join("myapp.int");
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        for (path, content, mode) in [
+            ("manifest.json", "{}", 0o644),
+            ("payload/bin/myapp", "#!/bin/sh\n", 0o755),
+            ("payload/share/data.txt", "data", 0o644),
+            ("payload/share/readme.txt", "readme", 0o644),
+            ("payload/extra.txt", "not installed", 0o644),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(mode);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let install_path = temp.path().join("install");
+        fs::create_dir_all(install_path.join("share")).unwrap();
+        fs::write(install_path.join("share/data.txt"), "data").unwrap();
+        fs::write(install_path.join("share/readme.txt"), "tampered").unwrap();
+        let files =
+            ["bin/myapp", "share/data.txt", "share/readme.txt"].map(|f| install_path.join(f));
+        let installed: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+
+        let report = restore_files(&archive, &install_path, &installed).unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(
+            report.restored,
+            vec![
+                RestoredFile {
+                    path: files[0].clone(),
+                    problem: FileProblem::Missing,
+                },
+                RestoredFile {
+                    path: files[2].clone(),
+                    problem: FileProblem::Modified,
+                },
+            ]
+        );
+        assert_eq!(fs::read_to_string(&files[2]).unwrap(), "readme");
+        assert!(!install_path.join("extra.txt").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&files[0]).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+
+        let report = restore_files(&archive, &install_path, &installed).unwrap();
+        assert!(report.restored.is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+/// Alternate root filesystem
+///
+/// Image builders install packages into a chroot or container rootfs rather
+/// than the live system. Once a root is set, every location of a scope
+/// (install directories, integration files, metadata) resolves below it,
+/// while symlink targets and generated files keep the paths the packages
+/// will have when the root is booted.
+///
+/// Commands that act on the running system (service managers, desktop and
+/// MIME database updates, ldconfig, ...) are not run against a root. They
+/// are appended to a shell script inside the root instead, to be run once
+/// the image boots (see `pending_actions_path`). Recorded paths in the
+/// installation metadata stay below the root, so packages installed this way
+/// are managed with the same root.
+use crate::error::{IntError, IntResult};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+const PENDING_ACTIONS: &str = "/var/lib/int-installer/pending-actions.sh";
+
+/// Install into a root filesystem instead of the live system (`None` to reset)
+pub fn set(root: Option<PathBuf>) {
+    // "/" is the live system
+    let root = root.filter(|root| root.components().any(|c| c != Component::RootDir));
+    *ROOT.write().unwrap() = root;
+}
+
+/// The alternate root, if one is set
+pub fn get() -> Option<PathBuf> {
+    ROOT.read().unwrap().clone()
+}
+
+/// Whether an alternate root is set
+pub fn is_set() -> bool {
+    ROOT.read().unwrap().is_some()
+}
+
+/// Location of an absolute system path inside the root
+///
+/// Paths already inside the root and relative paths are returned as is.
+pub fn path(path: &Path) -> PathBuf {
+    match get() {
+        Some(root) => reroot(&root, path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Path as seen from inside the root once it is booted
+pub fn target(path: &Path) -> PathBuf {
+    match get() {
+        Some(root) => unroot(&root, path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Rewrite the paths in generated file content to how they are seen from
+/// inside the root
+pub fn target_content(content: &str) -> String {
+    match get() {
+        Some(root) => unroot_content(&root, content),
+        None => content.to_string(),
+    }
+}
+
+/// Script collecting the deferred actions, inside the root
+pub fn pending_actions_path() -> PathBuf {
+    path(Path::new(PENDING_ACTIONS))
+}
+
+/// Record a command to run inside the root instead of running it now
+pub fn defer(command: &str) -> IntResult<()> {
+    tracing::debug!("Deferring to the root: {}", command);
+    append_action(&pending_actions_path(), command)
+}
+
+/// Quote an argument of a deferred command for `sh`
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+fn reroot(root: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() && !path.starts_with(root) {
+        root.join(path.strip_prefix("/").unwrap_or(path))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn unroot(root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(inside) => Path::new("/").join(inside),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn unroot_content(root: &Path, content: &str) -> String {
+    content.replace(&format!("{}/", root.display()), "/")
+}
+
+fn append_action(script: &Path, command: &str) -> IntResult<()> {
+    if let Some(parent) = script.parent() {
+        crate::utils::ensure_dir(parent)?;
+    }
+
+    let header = if script.exists() {
+        ""
+    } else {
+        "#!/bin/sh\n# Actions deferred while installing into this root; run once it is booted\n"
+    };
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(script)
+        .and_then(|mut file| writeln!(file, "{}{}", header, command))
+        .map_err(|e| {
+            IntError::Custom(format!(
+                "Failed to record deferred action in {}: {}",
+                script.display(),
+                e
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The process-wide root is left alone so other tests keep the live paths
+    #[test]
+    fn test_root_paths() {
+        let root = Path::new("/srv/image");
+
+        let desktop = reroot(root, Path::new("/usr/share/applications"));
+        assert_eq!(desktop, Path::new("/srv/image/usr/share/applications"));
+        assert_eq!(reroot(root, &desktop), desktop);
+        assert_eq!(reroot(root, Path::new("bin/app")), Path::new("bin/app"));
+        assert_eq!(unroot(root, &desktop), Path::new("/usr/share/applications"));
+        assert_eq!(unroot(root, Path::new("/opt/app")), Path::new("/opt/app"));
+        assert_eq!(
+            unroot_content(root, "Exec=/srv/image/opt/app/bin/app\n"),
+            "Exec=/opt/app/bin/app\n"
+        );
+    }
+
+    #[test]
+    fn test_defer() {
+        let temp = tempfile::tempdir().unwrap();
+        let script = temp.path().join("var/lib/int-installer/pending-actions.sh");
+
+        append_action(&script, "systemctl enable app").unwrap();
+        append_action(&script, "update-desktop-database /usr/share/applications").unwrap();
+        let content = std::fs::read_to_string(&script).unwrap();
+        assert!(content.starts_with("#!/bin/sh\n"));
+        assert!(content
+            .ends_with("systemctl enable app\nupdate-desktop-database /usr/share/applications\n"));
+        assert_eq!(content.matches("#!/bin/sh").count(), 1);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("app.service"), "'app.service'");
+        assert_eq!(shell_quote("it's; reboot"), r"'it'\''s; reboot'");
+    }
+}
@@ -0,0 +1,111 @@
+/// Service backend for an alternate root
+///
+/// Definitions are written into the root by the init system's own backend,
+/// but nothing is running there to enable or start services. Those actions
+/// are recorded in the root's pending actions instead, to be carried out
+/// once it boots. runit services are enabled by a link inside the root, so
+/// that backend still enables them itself.
+use super::{InitSystem, ServiceBackend, ServiceContext};
+use crate::error::IntResult;
+use crate::extractor::ExtractedPackage;
+use crate::manifest::InstallScope;
+use crate::root;
+use std::path::{Path, PathBuf};
+
+/// Backend recording service actions instead of running them
+pub struct DeferredBackend {
+    inner: Box<dyn ServiceBackend>,
+}
+
+impl DeferredBackend {
+    /// Wrap the backend of the root's init system
+    pub fn new(inner: Box<dyn ServiceBackend>) -> Self {
+        Self { inner }
+    }
+
+    /// Command carrying out an action inside the booted root
+    ///
+    /// Every argument is quoted, as the command is written to a shell script.
+    fn command(&self, action: &str, service_name: &str, scope: InstallScope) -> String {
+        let name = root::shell_quote(service_name);
+        let verb = root::shell_quote(action);
+        match (self.inner.init_system(), action) {
+            (InitSystem::Systemd, _) if scope == InstallScope::User => {
+                format!("systemctl --user {} {}", verb, name)
+            }
+            (InitSystem::Systemd, _) => format!("systemctl {} {}", verb, name),
+            (InitSystem::OpenRc, "enable") => format!("rc-update add {} default", name),
+            (InitSystem::OpenRc, "disable") => format!("rc-update del {} default", name),
+            (InitSystem::OpenRc, _) => format!("rc-service {} {}", name, verb),
+            (InitSystem::Runit, "start") => format!("sv up {}", name),
+            (init_system, _) => format!("# {} {} ({})", action, name, init_system),
+        }
+    }
+}
+
+impl ServiceBackend for DeferredBackend {
+    fn init_system(&self) -> InitSystem {
+        self.inner.init_system()
+    }
+
+    fn render(
+        &self,
+        extracted: &ExtractedPackage,
+        service_name: &str,
+        context: &ServiceContext,
+    ) -> IntResult<String> {
+        self.inner.render(extracted, service_name, context)
+    }
+
+    fn service_path(&self, service_name: &str, scope: InstallScope) -> IntResult<PathBuf> {
+        self.inner.service_path(service_name, scope)
+    }
+
+    fn file_mode(&self) -> u32 {
+        self.inner.file_mode()
+    }
+
+    fn install(&self, service_path: &Path, content: &str) -> IntResult<()> {
+        self.inner.install(service_path, content)
+    }
+
+    fn install_overrides(&self, service_path: &Path, context: &ServiceContext) -> IntResult<()> {
+        self.inner.install_overrides(service_path, context)
+    }
+
+    fn reload(&self, _scope: InstallScope) -> IntResult<()> {
+        // Definitions are read when the root boots
+        Ok(())
+    }
+
+    fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        match self.inner.init_system() {
+            InitSystem::Runit => self.inner.enable(service_name, scope),
+            _ => root::defer(&self.command("enable", service_name, scope)),
+        }
+    }
+
+    fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        match self.inner.init_system() {
+            InitSystem::Runit => self.inner.disable(service_name, scope),
+            _ => root::defer(&self.command("disable", service_name, scope)),
+        }
+    }
+
+    fn start(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        root::defer(&self.command("start", service_name, scope))
+    }
+
+    fn stop(&self, _service_name: &str, _scope: InstallScope) -> IntResult<()> {
+        // Nothing runs inside the root
+        Ok(())
+    }
+
+    fn is_active(&self, _service_name: &str, _scope: InstallScope) -> bool {
+        false
+    }
+
+    fn remove(&self, service_path: &Path, service_name: &str) -> IntResult<()> {
+        self.inner.remove(service_path, service_name)
+    }
+}
@@ -0,0 +1,167 @@
+/// Service health probes
+///
+/// Runs the health check declared in the manifest after a service is
+/// started. Each probe returns a short reason on failure so the installer
+/// can report why the service is considered unhealthy.
+use crate::manifest::HealthProbe;
+use crate::utils;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Run a health probe once
+pub(crate) fn run_probe(probe: &HealthProbe, timeout: Duration) -> Result<(), String> {
+    match probe {
+        HealthProbe::Http { url } => probe_http(url, timeout),
+        HealthProbe::Tcp { address } => connect(address, timeout).map(|_| ()),
+        HealthProbe::Exec { command } => probe_exec(command, timeout),
+    }
+}
+
+/// Open a TCP connection to the first reachable address
+fn connect(address: &str, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs = address
+        .to_socket_addrs()
+        .map_err(|e| format!("cannot resolve {}: {}", address, e))?;
+
+    let mut last_error = format!("no addresses found for {}", address);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = format!("cannot connect to {}: {}", addr, e),
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Split an `http://host[:port]/path` URL into address, host and path
+fn parse_http_url(url: &str) -> Result<(String, String, String), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("unsupported URL (only http:// is supported): {}", url))?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(format!("missing host in URL: {}", url));
+    }
+
+    let address = if authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()))
+    {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    Ok((address, authority.to_string(), path.to_string()))
+}
+
+/// Send a GET request and accept 2xx/3xx responses
+fn probe_http(url: &str, timeout: Duration) -> Result<(), String> {
+    let (address, host, path) = parse_http_url(url)?;
+    let mut stream = connect(&address, timeout)?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: int-installer\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("request to {} failed: {}", url, e))?;
+
+    let mut response = [0u8; 64];
+    let read = stream
+        .read(&mut response)
+        .map_err(|e| format!("no response from {}: {}", url, e))?;
+
+    let status_line = String::from_utf8_lossy(&response[..read]);
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| format!("invalid HTTP response from {}", url))?;
+
+    if (200..400).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("{} returned HTTP {}", url, status))
+    }
+}
+
+/// Run a shell command that must exit successfully
+fn probe_exec(command: &str, timeout: Duration) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot run '{}': {}", command, e))?;
+
+    match utils::wait_with_timeout(&mut child, timeout) {
+        Ok(Some(status)) if status.success() => Ok(()),
+        Ok(Some(status)) => Err(format!(
+            "'{}' exited with code {}",
+            command,
+            status.code().unwrap_or(-1)
+        )),
+        Ok(None) => Err(format!("'{}' timed out", command)),
+        Err(e) => Err(format!("cannot wait for '{}': {}", command, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse_http_url() {
+        assert_eq!(
+            parse_http_url("http://127.0.0.1:8080/health").unwrap(),
+            (
+                "127.0.0.1:8080".to_string(),
+                "127.0.0.1:8080".to_string(),
+                "/health".to_string()
+            )
+        );
+        assert_eq!(
+            parse_http_url("http://localhost").unwrap().0,
+            "localhost:80"
+        );
+        assert!(parse_http_url("https://localhost/").is_err());
+    }
+
+    #[test]
+    fn test_tcp_and_exec_probes() {
+        let timeout = Duration::from_secs(2);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let tcp = HealthProbe::Tcp {
+            address: address.clone(),
+        };
+        assert!(run_probe(&tcp, timeout).is_ok());
+
+        drop(listener);
+        assert!(run_probe(&tcp, timeout).is_err());
+
+        let ok = HealthProbe::Exec {
+            command: "true".to_string(),
+        };
+        let fail = HealthProbe::Exec {
+            command: "exit 3".to_string(),
+        };
+        assert!(run_probe(&ok, timeout).is_ok());
+        assert!(run_probe(&fail, timeout).unwrap_err().contains("code 3"));
+    }
+}
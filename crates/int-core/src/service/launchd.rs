@@ -0,0 +1,277 @@
+/// launchd service backend (macOS)
+///
+/// Installs property lists into `~/Library/LaunchAgents` (user scope) or
+/// `/Library/LaunchDaemons` (system scope) and controls them through
+/// `launchctl`. Packages may ship a ready-made `<name>.plist`; otherwise one
+/// is generated from the package's systemd unit.
+use super::{
+    read_service_source, render_template, run_command, InitSystem, ServiceBackend, ServiceContext,
+    UnitInfo,
+};
+use crate::error::{IntError, IntResult};
+use crate::extractor::ExtractedPackage;
+use crate::manifest::InstallScope;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// launchd service backend
+#[derive(Debug, Default)]
+pub struct LaunchdBackend;
+
+impl LaunchdBackend {
+    /// Create a new launchd backend
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Generate a launchd property list from a systemd unit
+    ///
+    /// The service name is used as the job label. `UserName` is only
+    /// emitted for daemons, since agents always run as the logged-in user.
+    /// launchd has no environment files, so the service environment is
+    /// embedded as `EnvironmentVariables`.
+    pub(crate) fn generate_plist(
+        &self,
+        service_name: &str,
+        unit: &str,
+        scope: InstallScope,
+        environment: &BTreeMap<String, String>,
+    ) -> IntResult<String> {
+        let info = UnitInfo::parse(unit);
+        let args = info
+            .exec_start
+            .as_deref()
+            .map(split_command)
+            .filter(|args| !args.is_empty())
+            .ok_or_else(|| {
+                IntError::ServiceRegistrationFailed(format!(
+                    "Service '{}' has no ExecStart to convert to a launchd plist",
+                    service_name
+                ))
+            })?;
+
+        let mut plist = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n<dict>\n",
+        );
+        push_string(&mut plist, "Label", service_name);
+
+        plist.push_str("\t<key>ProgramArguments</key>\n\t<array>\n");
+        for arg in &args {
+            plist.push_str(&format!("\t\t<string>{}</string>\n", escape_xml(arg)));
+        }
+        plist.push_str("\t</array>\n");
+
+        if let Some(dir) = &info.working_directory {
+            push_string(&mut plist, "WorkingDirectory", dir);
+        }
+        if let (Some(user), InstallScope::System) = (&info.user, scope) {
+            push_string(&mut plist, "UserName", user);
+        }
+
+        if !environment.is_empty() {
+            plist.push_str("\t<key>EnvironmentVariables</key>\n\t<dict>\n");
+            for (key, value) in environment {
+                plist.push_str(&format!(
+                    "\t\t<key>{}</key>\n\t\t<string>{}</string>\n",
+                    escape_xml(key),
+                    escape_xml(value)
+                ));
+            }
+            plist.push_str("\t</dict>\n");
+        }
+
+        plist.push_str("\t<key>RunAtLoad</key>\n\t<true/>\n");
+        plist.push_str("\t<key>KeepAlive</key>\n\t<true/>\n");
+        plist.push_str("</dict>\n</plist>\n");
+
+        Ok(plist)
+    }
+
+    fn launchctl(&self, action: &str, target: impl AsRef<std::ffi::OsStr>) -> Command {
+        let mut cmd = Command::new("launchctl");
+        cmd.arg(action).arg(target);
+        cmd
+    }
+}
+
+impl ServiceBackend for LaunchdBackend {
+    fn init_system(&self) -> InitSystem {
+        InitSystem::Launchd
+    }
+
+    fn render(
+        &self,
+        extracted: &ExtractedPackage,
+        service_name: &str,
+        context: &ServiceContext,
+    ) -> IntResult<String> {
+        if extracted.services_dir.is_none() {
+            return Err(IntError::ServiceRegistrationFailed(
+                "No services directory found".to_string(),
+            ));
+        }
+
+        // Prefer a native property list shipped with the package
+        if let Some(plist) = read_service_source(extracted, &format!("{}.plist", service_name))? {
+            return render_template(&plist, context);
+        }
+
+        let unit_file_name = format!("{}.service", service_name);
+        let unit = read_service_source(extracted, &unit_file_name)?.ok_or_else(|| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Service file not found: {}.plist or {}",
+                service_name, unit_file_name
+            ))
+        })?;
+
+        self.generate_plist(
+            service_name,
+            &render_template(&unit, context)?,
+            extracted.manifest.install_scope,
+            &context.environment,
+        )
+    }
+
+    fn service_path(&self, service_name: &str, scope: InstallScope) -> IntResult<PathBuf> {
+        Ok(scope.launchd_path().join(format!("{}.plist", service_name)))
+    }
+
+    fn reload(&self, _scope: InstallScope) -> IntResult<()> {
+        // launchd reads property lists when they are loaded
+        Ok(())
+    }
+
+    /// Load the job; `RunAtLoad` makes launchd start it right away
+    fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        let plist = self.service_path(service_name, scope)?;
+
+        let mut cmd = self.launchctl("load", &plist);
+        cmd.arg("-w");
+        run_command(&mut cmd, "load").map_err(|e| match e {
+            IntError::SystemdError(msg) => IntError::ServiceRegistrationFailed(msg),
+            other => other,
+        })
+    }
+
+    fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        let plist = self.service_path(service_name, scope)?;
+
+        let mut cmd = self.launchctl("unload", &plist);
+        cmd.arg("-w");
+        run_command(&mut cmd, "unload")
+    }
+
+    fn start(&self, service_name: &str, _scope: InstallScope) -> IntResult<()> {
+        run_command(&mut self.launchctl("start", service_name), "start")
+    }
+
+    fn stop(&self, service_name: &str, _scope: InstallScope) -> IntResult<()> {
+        // Ignore errors when stopping (service might not be running)
+        let _ = self.launchctl("stop", service_name).output();
+        Ok(())
+    }
+
+    fn is_active(&self, service_name: &str, _scope: InstallScope) -> bool {
+        self.launchctl("list", service_name)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+}
+
+/// Append a `<key>`/`<string>` pair to a property list
+fn push_string(plist: &mut String, key: &str, value: &str) {
+    plist.push_str(&format!(
+        "\t<key>{}</key>\n\t<string>{}</string>\n",
+        key,
+        escape_xml(value)
+    ));
+}
+
+/// Escape text for use inside XML elements
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Split a command line into arguments, honoring double quotes
+fn split_command(command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+
+    for c in command.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+
+    if has_arg {
+        args.push(current);
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command(r#"/opt/app/bin/app --name "My App" -v"#),
+            vec!["/opt/app/bin/app", "--name", "My App", "-v"]
+        );
+        assert!(split_command("   ").is_empty());
+    }
+
+    #[test]
+    fn test_generate_plist() {
+        let backend = LaunchdBackend::new();
+        let unit =
+            "[Service]\nExecStart=/opt/app/bin/app --serve\nUser=app\nWorkingDirectory=/opt/app\n";
+
+        let mut environment = BTreeMap::new();
+        environment.insert("INSTALL_PATH".to_string(), "/opt/app".to_string());
+
+        let plist = backend
+            .generate_plist("myapp", unit, InstallScope::System, &environment)
+            .unwrap();
+        assert!(plist.contains("<key>Label</key>\n\t<string>myapp</string>"));
+        assert!(
+            plist.contains("\t\t<string>/opt/app/bin/app</string>\n\t\t<string>--serve</string>\n")
+        );
+        assert!(plist.contains("<key>UserName</key>\n\t<string>app</string>"));
+        assert!(plist.contains("<key>WorkingDirectory</key>"));
+        assert!(plist.contains("\t\t<key>INSTALL_PATH</key>\n\t\t<string>/opt/app</string>\n"));
+
+        let agent = backend
+            .generate_plist("myapp", unit, InstallScope::User, &BTreeMap::new())
+            .unwrap();
+        assert!(!agent.contains("UserName"));
+
+        assert!(backend
+            .generate_plist("myapp", "[Service]\n", InstallScope::User, &environment)
+            .is_err());
+    }
+}
@@ -0,0 +1,1053 @@
+/// Service integration
+///
+/// This module handles service registration, management, and cleanup.
+/// Each init system is implemented as a `ServiceBackend`; `ServiceManager`
+/// selects the backend for the running system and drives it.
+mod deferred;
+mod health;
+mod launchd;
+mod openrc;
+mod runit;
+mod systemd;
+#[cfg(target_os = "linux")]
+mod systemd_dbus;
+mod windows;
+
+pub use deferred::DeferredBackend;
+pub use launchd::LaunchdBackend;
+pub use openrc::OpenRcBackend;
+pub use runit::RunitBackend;
+pub use systemd::SystemdBackend;
+pub use windows::WindowsBackend;
+
+use crate::error::{IntError, IntResult};
+use crate::extractor::ExtractedPackage;
+use crate::index;
+use crate::manifest::{HealthCheck, InstallScope, Manifest};
+use crate::platform;
+use crate::root;
+use crate::utils;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a service without a health check to come up
+const DEFAULT_START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Supported init systems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InitSystem {
+    Systemd,
+    OpenRc,
+    Runit,
+    Launchd,
+    Windows,
+}
+
+impl InitSystem {
+    /// Detect the init system of the running machine
+    ///
+    /// Falls back to systemd when nothing is recognized; use
+    /// `platform::init_system` to tell that case apart.
+    pub fn detect() -> Self {
+        platform::init_system().unwrap_or(InitSystem::Systemd)
+    }
+
+    /// Create the backend for this init system
+    ///
+    /// Inside an alternate root, service actions are recorded rather than run.
+    pub fn backend(&self) -> Box<dyn ServiceBackend> {
+        let backend: Box<dyn ServiceBackend> = match self {
+            InitSystem::Systemd => Box::new(SystemdBackend::new()),
+            InitSystem::OpenRc => Box::new(OpenRcBackend::new()),
+            InitSystem::Runit => Box::new(RunitBackend::new()),
+            InitSystem::Launchd => Box::new(LaunchdBackend::new()),
+            InitSystem::Windows => Box::new(WindowsBackend::new()),
+        };
+
+        if root::is_set() {
+            Box::new(DeferredBackend::new(backend))
+        } else {
+            backend
+        }
+    }
+}
+
+impl std::fmt::Display for InitSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InitSystem::Systemd => write!(f, "systemd"),
+            InitSystem::OpenRc => write!(f, "OpenRC"),
+            InitSystem::Runit => write!(f, "runit"),
+            InitSystem::Launchd => write!(f, "launchd"),
+            InitSystem::Windows => write!(f, "Windows Service Control Manager"),
+        }
+    }
+}
+
+/// Operations an init system must provide to manage package services
+pub trait ServiceBackend: Send + Sync {
+    /// Init system implemented by this backend
+    fn init_system(&self) -> InitSystem;
+
+    /// Render the service definition shipped in (or derived from) the package
+    fn render(
+        &self,
+        extracted: &ExtractedPackage,
+        service_name: &str,
+        context: &ServiceContext,
+    ) -> IntResult<String>;
+
+    /// Path where the service definition is installed
+    fn service_path(&self, service_name: &str, scope: InstallScope) -> IntResult<PathBuf>;
+
+    /// File mode for the installed service definition
+    fn file_mode(&self) -> u32 {
+        0o644
+    }
+
+    /// Write a rendered service definition to its installed location
+    fn install(&self, service_path: &Path, content: &str) -> IntResult<()> {
+        if let Some(parent) = service_path.parent() {
+            utils::ensure_dir(parent)?;
+        }
+
+        fs::write(service_path, root::target_content(content)).map_err(|e| {
+            IntError::ServiceRegistrationFailed(format!("Failed to write service file: {}", e))
+        })?;
+        utils::set_permissions(service_path, self.file_mode())
+    }
+
+    /// Write install-time settings as overrides of an installed definition
+    ///
+    /// Backends that support overrides keep the shipped definition untouched
+    /// and put values such as the environment file here, next to any
+    /// overrides added by an administrator. Others embed those values in the
+    /// rendered definition and do nothing.
+    fn install_overrides(&self, _service_path: &Path, _context: &ServiceContext) -> IntResult<()> {
+        Ok(())
+    }
+
+    /// Look up the administrator's preset for a unit
+    ///
+    /// Returns `Some(true)` for enable, `Some(false)` for disable and `None`
+    /// if no preset matches or the init system has no preset mechanism.
+    fn preset(&self, _service_name: &str, _scope: InstallScope) -> Option<bool> {
+        None
+    }
+
+    /// Make the init system pick up changed service definitions
+    fn reload(&self, scope: InstallScope) -> IntResult<()>;
+
+    /// Enable a service to start at boot (or login for user services)
+    fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()>;
+
+    /// Disable a service
+    fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()>;
+
+    /// Start a service
+    fn start(&self, service_name: &str, scope: InstallScope) -> IntResult<()>;
+
+    /// Stop a service
+    fn stop(&self, service_name: &str, scope: InstallScope) -> IntResult<()>;
+
+    /// Check if a service is running
+    fn is_active(&self, service_name: &str, scope: InstallScope) -> bool;
+
+    /// Check if a service has failed (crashed or exited with an error)
+    ///
+    /// Backends that cannot tell report `false`.
+    fn is_failed(&self, _service_name: &str, _scope: InstallScope) -> bool {
+        false
+    }
+
+    /// Get the runtime status of a service
+    fn status(&self, service_name: &str, scope: InstallScope) -> IntResult<ServiceStatus> {
+        let active_state = if self.is_failed(service_name, scope) {
+            "failed"
+        } else if self.is_active(service_name, scope) {
+            "active"
+        } else {
+            "inactive"
+        };
+
+        Ok(ServiceStatus {
+            name: service_name.to_string(),
+            init_system: self.init_system(),
+            active_state: active_state.to_string(),
+            sub_state: None,
+            pid: None,
+            active_since: None,
+        })
+    }
+
+    /// Get the most recent log lines of a service
+    fn logs(
+        &self,
+        _service_name: &str,
+        _scope: InstallScope,
+        _lines: usize,
+    ) -> IntResult<Vec<String>> {
+        Err(IntError::SystemdError(format!(
+            "Log retrieval is not supported for {} services",
+            self.init_system()
+        )))
+    }
+
+    /// Hint pointing the user at the service logs
+    fn log_hint(&self, _service_name: &str, _scope: InstallScope) -> Option<String> {
+        None
+    }
+
+    /// Remove an installed service definition
+    fn remove(&self, service_path: &Path, _service_name: &str) -> IntResult<()> {
+        if service_path.exists() {
+            fs::remove_file(service_path).map_err(|e| {
+                IntError::SystemdError(format!("Failed to remove service file: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Service manager
+pub struct ServiceManager {
+    backend: Box<dyn ServiceBackend>,
+}
+
+impl ServiceManager {
+    /// Create a service manager for the detected init system
+    pub fn new() -> Self {
+        Self::with_init_system(InitSystem::detect())
+    }
+
+    /// Create a service manager for a specific init system
+    pub fn with_init_system(init_system: InitSystem) -> Self {
+        Self {
+            backend: init_system.backend(),
+        }
+    }
+
+    /// Get the init system this manager drives
+    pub fn init_system(&self) -> InitSystem {
+        self.backend.init_system()
+    }
+
+    /// Register the services of a package
+    ///
+    /// Installs every unit declared by the manifest into the init system's
+    /// directory and enables those whose preset asks for it. `preset`
+    /// overrides both the manifest default and the system presets.
+    pub fn register(
+        &self,
+        extracted: &ExtractedPackage,
+        install_path: &Path,
+        preset: Option<ServicePreset>,
+    ) -> IntResult<Vec<ServiceUnit>> {
+        let scope = extracted.manifest.install_scope;
+        let default_preset = extracted.manifest.service_preset.unwrap_or_default();
+
+        // Write the environment file referenced by the service definitions
+        let context = ServiceContext::new(&extracted.manifest, install_path);
+        context.write_env_file()?;
+
+        // Timers replaced by cron jobs are skipped without systemd
+        let replaced = match self.init_system() {
+            InitSystem::Systemd => Vec::new(),
+            _ => extracted.manifest.cron_replaced_units(),
+        };
+
+        let mut units = Vec::new();
+        for file_name in extracted.manifest.service_units() {
+            if replaced.contains(&file_name) {
+                continue;
+            }
+            let (name, kind) = self.control_name(&file_name)?;
+            let content = self.backend.render(extracted, &name, &context)?;
+
+            // Write service file, never over a definition of someone else
+            let path = self.backend.service_path(&name, scope)?;
+            self.ensure_owned(&path, &extracted.manifest.name)?;
+            self.backend.install(&path, &content)?;
+            self.backend.install_overrides(&path, &context)?;
+
+            let preset =
+                preset.unwrap_or_else(|| self.resolve_preset(&name, scope, default_preset));
+            units.push(ServiceUnit {
+                name,
+                kind,
+                path,
+                preset,
+            });
+        }
+
+        // Reload init system
+        self.backend.reload(scope)?;
+
+        // Enable units (but don't start them yet)
+        for unit in units.iter().filter(|u| u.preset != ServicePreset::Disable) {
+            self.backend.enable(&unit.name, scope)?;
+        }
+
+        Ok(units)
+    }
+
+    /// Refuse to replace a service definition the package does not own
+    ///
+    /// A definition left by an earlier version of the package is recorded in
+    /// its metadata; anything else was written by another package or an
+    /// administrator, and uninstalling would later delete it.
+    fn ensure_owned(&self, path: &Path, package_name: &str) -> IntResult<()> {
+        if path.symlink_metadata().is_err() {
+            return Ok(());
+        }
+
+        match index::who_owns(path)? {
+            Some(owner) if owner.package == package_name => Ok(()),
+            Some(owner) => Err(IntError::ServiceRegistrationFailed(format!(
+                "{} belongs to package '{}'",
+                path.display(),
+                owner.package
+            ))),
+            None => Err(IntError::ServiceRegistrationFailed(format!(
+                "{} already exists and does not belong to any package",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Apply the system presets to the package's default for a unit
+    ///
+    /// As with distribution presets, a matching `disable` line keeps the
+    /// unit disabled and a matching `enable` line enables it.
+    fn resolve_preset(
+        &self,
+        name: &str,
+        scope: InstallScope,
+        default: ServicePreset,
+    ) -> ServicePreset {
+        match self.backend.preset(name, scope) {
+            Some(false) => ServicePreset::Disable,
+            Some(true) if default == ServicePreset::Disable => ServicePreset::Enable,
+            _ => default,
+        }
+    }
+
+    /// Name a unit file is controlled by
+    ///
+    /// Services are addressed by their bare name on every init system;
+    /// timers and sockets keep their suffix and are only supported by systemd.
+    fn control_name(&self, file_name: &str) -> IntResult<(String, UnitKind)> {
+        let (stem, kind) = UnitKind::parse(file_name).ok_or_else(|| {
+            IntError::ServiceRegistrationFailed(format!("Unsupported unit file: {}", file_name))
+        })?;
+
+        match kind {
+            UnitKind::Service => Ok((stem.to_string(), kind)),
+            _ if self.init_system() == InitSystem::Systemd => Ok((file_name.to_string(), kind)),
+            _ => Err(IntError::ServiceRegistrationFailed(format!(
+                "{} units are not supported by {}: {}",
+                kind.extension(),
+                self.init_system(),
+                file_name
+            ))),
+        }
+    }
+
+    /// Enable a service
+    pub fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.backend.enable(service_name, scope)
+    }
+
+    /// Disable a service
+    pub fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.backend.disable(service_name, scope)
+    }
+
+    /// Start a service
+    pub fn start(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.backend.start(service_name, scope)
+    }
+
+    /// Stop a service
+    pub fn stop(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.backend.stop(service_name, scope)
+    }
+
+    /// Restart a service, starting it if it isn't running
+    pub fn restart(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        if self.backend.is_active(service_name, scope) {
+            self.backend.stop(service_name, scope)?;
+        }
+        self.backend.start(service_name, scope)
+    }
+
+    /// Check if service is active
+    pub fn is_active(&self, service_name: &str, scope: InstallScope) -> bool {
+        self.backend.is_active(service_name, scope)
+    }
+
+    /// Get the runtime status of a service
+    pub fn status(&self, service_name: &str, scope: InstallScope) -> IntResult<ServiceStatus> {
+        self.backend.status(service_name, scope)
+    }
+
+    /// Get the last `lines` log lines of a service
+    pub fn logs(
+        &self,
+        service_name: &str,
+        scope: InstallScope,
+        lines: usize,
+    ) -> IntResult<Vec<String>> {
+        self.backend.logs(service_name, scope, lines)
+    }
+
+    /// Wait until a started service is healthy
+    ///
+    /// Polls the init system until the service is running and, if a health
+    /// check is given, its probe succeeds. Without a health check the
+    /// service must stay running for two consecutive polls, which catches
+    /// daemons that crash right after start. Fails early if the init system
+    /// reports the service as failed.
+    pub fn wait_healthy(
+        &self,
+        service_name: &str,
+        scope: InstallScope,
+        health_check: Option<&HealthCheck>,
+    ) -> IntResult<()> {
+        let (timeout, interval) = match health_check {
+            Some(check) => (
+                Duration::from_secs(check.timeout),
+                Duration::from_secs(check.interval),
+            ),
+            None => (DEFAULT_START_TIMEOUT, Duration::from_secs(1)),
+        };
+        let deadline = Instant::now() + timeout;
+        let mut was_active = false;
+        let mut reason = "service did not become active".to_string();
+
+        loop {
+            if self.backend.is_failed(service_name, scope) {
+                return Err(self.unhealthy(service_name, scope, "service failed after start"));
+            }
+
+            if self.backend.is_active(service_name, scope) {
+                match health_check {
+                    Some(check) => match health::run_probe(&check.probe, interval) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => reason = e,
+                    },
+                    None if was_active => return Ok(()),
+                    None => was_active = true,
+                }
+            } else {
+                was_active = false;
+                reason = "service is not running".to_string();
+            }
+
+            if Instant::now() >= deadline {
+                return Err(self.unhealthy(
+                    service_name,
+                    scope,
+                    &format!("{} after {}s", reason, timeout.as_secs()),
+                ));
+            }
+
+            std::thread::sleep(interval);
+        }
+    }
+
+    /// Build an unhealthy-service error, pointing at the logs when possible
+    fn unhealthy(&self, service_name: &str, scope: InstallScope, reason: &str) -> IntError {
+        let reason = match self.backend.log_hint(service_name, scope) {
+            Some(hint) => format!("{} (check the logs with `{}`)", reason, hint),
+            None => reason.to_string(),
+        };
+
+        IntError::ServiceUnhealthy {
+            service: service_name.to_string(),
+            reason,
+        }
+    }
+
+    /// Unregister all units of a package
+    ///
+    /// Timers and sockets are removed first so they cannot activate their
+    /// services again while those are being stopped.
+    pub fn unregister_units(&self, units: &[ServiceUnit], scope: InstallScope) -> IntResult<()> {
+        let (services, triggers): (Vec<_>, Vec<_>) = units
+            .iter()
+            .partition(|unit| unit.kind == UnitKind::Service);
+
+        for unit in triggers.into_iter().chain(services) {
+            let _ = self.backend.stop(&unit.name, scope);
+            let _ = self.backend.disable(&unit.name, scope);
+            self.backend.remove(&unit.path, &unit.name)?;
+        }
+
+        self.backend.reload(scope)
+    }
+
+    /// Unregister a service
+    pub fn unregister(
+        &self,
+        service_path: &Path,
+        service_name: &str,
+        scope: InstallScope,
+    ) -> IntResult<()> {
+        // Stop service if running
+        let _ = self.backend.stop(service_name, scope);
+
+        // Disable service
+        let _ = self.backend.disable(service_name, scope);
+
+        // Remove service file
+        self.backend.remove(service_path, service_name)?;
+
+        // Reload init system
+        self.backend.reload(scope)?;
+
+        Ok(())
+    }
+}
+
+impl Default for ServiceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Kinds of unit a package can ship in its `services/` directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitKind {
+    Service,
+    Timer,
+    Socket,
+}
+
+impl UnitKind {
+    /// Split a unit file name into its name and kind
+    ///
+    /// Returns `None` for names without a supported suffix.
+    pub fn parse(file_name: &str) -> Option<(&str, UnitKind)> {
+        let (stem, extension) = file_name.rsplit_once('.')?;
+        let kind = match extension {
+            "service" => UnitKind::Service,
+            "timer" => UnitKind::Timer,
+            "socket" => UnitKind::Socket,
+            _ => return None,
+        };
+
+        if stem.is_empty() {
+            return None;
+        }
+        Some((stem, kind))
+    }
+
+    /// File name suffix of this kind (without the dot)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            UnitKind::Service => "service",
+            UnitKind::Timer => "timer",
+            UnitKind::Socket => "socket",
+        }
+    }
+}
+
+/// What happens to a unit when it is registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServicePreset {
+    /// Enable the unit so it starts at boot (or login for user services)
+    #[default]
+    Enable,
+    /// Enable the unit and start it right away
+    EnableNow,
+    /// Install the unit without enabling it
+    Disable,
+}
+
+/// A unit registered with the init system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceUnit {
+    /// Name the unit is controlled by (`<name>` for services,
+    /// `<name>.timer` / `<name>.socket` otherwise)
+    pub name: String,
+    /// Kind of unit
+    pub kind: UnitKind,
+    /// Installed unit file
+    pub path: PathBuf,
+    /// Preset applied on registration
+    #[serde(default)]
+    pub preset: ServicePreset,
+}
+
+impl ServiceUnit {
+    /// Name of the service this unit activates (its own name for services)
+    pub fn service_name(&self) -> &str {
+        match self.kind {
+            UnitKind::Service => &self.name,
+            kind => self
+                .name
+                .strip_suffix(kind.extension())
+                .and_then(|name| name.strip_suffix('.'))
+                .unwrap_or(&self.name),
+        }
+    }
+
+    /// Units to start after registration
+    ///
+    /// Services activated by a timer or socket of the same name are left to
+    /// their trigger.
+    pub fn startable(units: &[ServiceUnit]) -> Vec<&ServiceUnit> {
+        units
+            .iter()
+            .filter(|unit| {
+                unit.kind != UnitKind::Service
+                    || !units.iter().any(|other| {
+                        other.kind != UnitKind::Service && other.service_name() == unit.name
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Full systemd unit name (`name` -> `name.service`, other unit names unchanged)
+pub(crate) fn unit_file_name(name: &str) -> String {
+    if UnitKind::parse(name).is_some() {
+        name.to_string()
+    } else {
+        format!("{}.service", name)
+    }
+}
+
+/// Runtime status of a service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    /// Service name
+    pub name: String,
+    /// Init system managing the service
+    pub init_system: InitSystem,
+    /// Active state (e.g. "active", "inactive", "failed")
+    pub active_state: String,
+    /// Backend-specific sub-state (e.g. "running", "exited")
+    pub sub_state: Option<String>,
+    /// Main process ID
+    pub pid: Option<u32>,
+    /// When the service last became active
+    pub active_since: Option<DateTime<Utc>>,
+}
+
+impl ServiceStatus {
+    /// Whether the service is currently running
+    pub fn is_active(&self) -> bool {
+        self.active_state == "active"
+    }
+
+    /// Time since the service became active
+    pub fn uptime(&self) -> Option<Duration> {
+        let since = self.active_since?;
+        if !self.is_active() {
+            return None;
+        }
+        (Utc::now() - since).to_std().ok()
+    }
+}
+
+/// Install-time values used when rendering service definitions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceContext {
+    /// Installation directory of the package
+    pub install_path: PathBuf,
+    /// Environment file the service reads its variables from
+    pub env_file: PathBuf,
+    /// Variables written to the environment file
+    pub environment: BTreeMap<String, String>,
+    /// Values substituted for `{{NAME}}` placeholders in service definitions
+    pub variables: BTreeMap<String, String>,
+    /// Whether to add sandboxing directives to the service
+    pub hardening: bool,
+    /// Paths the service may write to when sandboxed
+    pub writable_paths: Vec<PathBuf>,
+}
+
+impl ServiceContext {
+    /// Build the context for a package being installed
+    ///
+    /// The manifest `environment` is extended with install-time values
+    /// (`INSTALL_PATH` and the first declared data, cache and log
+    /// directories), which take precedence over declared values.
+    pub fn new(manifest: &Manifest, install_path: &Path) -> Self {
+        let scope = manifest.install_scope;
+        let mut environment = manifest.environment.clone();
+
+        environment.insert(
+            "INSTALL_PATH".to_string(),
+            install_path.display().to_string(),
+        );
+        let dirs = [
+            ("DATA_DIR", manifest.resolved_data_dirs(scope)),
+            ("CACHE_DIR", manifest.resolved_cache_dirs(scope)),
+            ("LOG_DIR", manifest.resolved_log_dirs(scope)),
+        ];
+        let mut writable_paths = Vec::new();
+        for (key, dirs) in dirs {
+            if let Some(dir) = dirs.first() {
+                environment.insert(key.to_string(), dir.display().to_string());
+            }
+            writable_paths.extend(dirs);
+        }
+
+        let env_file = manifest.env_file(scope);
+        let variables = template_variables(manifest, install_path, &env_file);
+
+        Self {
+            install_path: install_path.to_path_buf(),
+            env_file,
+            environment,
+            variables,
+            hardening: manifest.hardening,
+            writable_paths,
+        }
+    }
+
+    /// Render the environment file contents
+    ///
+    /// Values are double-quoted so the file can be read both by systemd's
+    /// `EnvironmentFile=` and by a POSIX shell.
+    pub fn env_file_content(&self) -> String {
+        self.environment
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"\n", key, escape_env_value(value)))
+            .collect()
+    }
+
+    /// Write the environment file
+    pub fn write_env_file(&self) -> IntResult<()> {
+        if let Some(parent) = self.env_file.parent() {
+            utils::ensure_dir(parent)?;
+        }
+
+        let content = root::target_content(&self.env_file_content());
+        fs::write(&self.env_file, content).map_err(|e| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Failed to write environment file {}: {}",
+                self.env_file.display(),
+                e
+            ))
+        })?;
+        utils::set_permissions(&self.env_file, 0o644)
+    }
+}
+
+/// Escape a value for use inside double quotes
+fn escape_env_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Read a service source file from the package's services directory
+///
+/// Returns `None` if the package does not ship the file.
+pub(crate) fn read_service_source(
+    extracted: &ExtractedPackage,
+    file_name: &str,
+) -> IntResult<Option<String>> {
+    let source = match extracted.service_path(file_name) {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+
+    fs::read_to_string(&source).map(Some).map_err(|e| {
+        IntError::ServiceRegistrationFailed(format!("Failed to read service file: {}", e))
+    })
+}
+
+/// Template variables computed at install time
+///
+/// Manifest `variables` may not redefine these.
+pub const BUILTIN_VARIABLES: &[&str] = &[
+    "INSTALL_PATH",
+    "USER",
+    "GROUP",
+    "DATA_DIR",
+    "ENV_FILE",
+    "ENTRY",
+];
+
+/// Build the placeholder values for a package's service definitions
+///
+/// `USER` and `GROUP` are the account the service runs under: root for
+/// system services, the installing user for user services. `DATA_DIR` and
+/// `ENTRY` are only defined when the manifest declares a data directory or
+/// main entry.
+fn template_variables(
+    manifest: &Manifest,
+    install_path: &Path,
+    env_file: &Path,
+) -> BTreeMap<String, String> {
+    let mut variables = manifest.variables.clone();
+
+    let (user, group) = match manifest.install_scope {
+        InstallScope::System => (Some("root".to_string()), Some("root".to_string())),
+        InstallScope::User => (
+            utils::get_current_username(),
+            utils::get_current_groupname(),
+        ),
+    };
+    let data_dir = manifest
+        .resolved_data_dirs(manifest.install_scope)
+        .into_iter()
+        .next();
+    let entry = manifest
+        .entry
+        .as_ref()
+        .map(|entry| install_path.join("bin").join(entry));
+
+    let builtins = [
+        ("INSTALL_PATH", Some(install_path.display().to_string())),
+        ("USER", user),
+        ("GROUP", group),
+        ("DATA_DIR", data_dir.map(|dir| dir.display().to_string())),
+        ("ENV_FILE", Some(env_file.display().to_string())),
+        ("ENTRY", entry.map(|entry| entry.display().to_string())),
+    ];
+    for (key, value) in builtins {
+        if let Some(value) = value {
+            variables.insert(key.to_string(), value);
+        }
+    }
+
+    variables
+}
+
+/// Replace `{{NAME}}` placeholders in a service definition
+///
+/// Fails if the definition references a variable without a value, so a
+/// unit is never installed with a literal placeholder in it.
+pub(crate) fn render_template(content: &str, context: &ServiceContext) -> IntResult<String> {
+    let mut rendered = String::with_capacity(content.len());
+    let mut unresolved: Vec<&str> = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let name = after.find("}}").map(|end| &after[..end]).filter(|name| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        match name {
+            Some(name) => {
+                match context.variables.get(name) {
+                    Some(value) => rendered.push_str(value),
+                    None if !unresolved.contains(&name) => unresolved.push(name),
+                    None => {}
+                }
+                rest = &after[name.len() + 2..];
+            }
+            // Not a placeholder; keep the braces as they are
+            None => {
+                rendered.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    if !unresolved.is_empty() {
+        let names: Vec<String> = unresolved
+            .iter()
+            .map(|name| format!("{{{{{}}}}}", name))
+            .collect();
+        return Err(IntError::ServiceRegistrationFailed(format!(
+            "Unresolved placeholders in service definition: {}",
+            names.join(", ")
+        )));
+    }
+
+    Ok(rendered)
+}
+
+/// Shell snippet exporting the variables of an environment file
+pub(crate) fn source_env_file(env_file: &Path) -> String {
+    format!(
+        "set -a\n[ -r \"{0}\" ] && . \"{0}\"\nset +a\n",
+        env_file.display()
+    )
+}
+
+/// Run an init system command, mapping failures to a service error
+pub(crate) fn run_command(cmd: &mut Command, action: &str) -> IntResult<()> {
+    let output = cmd
+        .output()
+        .map_err(|e| IntError::SystemdError(format!("Failed to execute {:?}: {}", cmd, e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(IntError::SystemdError(format!(
+            "Failed to {} service: {}",
+            action, stderr
+        )));
+    }
+
+    Ok(())
+}
+
+/// Settings extracted from a systemd unit, used to derive definitions for
+/// other init systems from the same package
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct UnitInfo {
+    pub description: Option<String>,
+    pub exec_start: Option<String>,
+    pub user: Option<String>,
+    pub working_directory: Option<String>,
+}
+
+impl UnitInfo {
+    /// Parse the relevant keys of a systemd unit file
+    pub fn parse(content: &str) -> Self {
+        let mut info = UnitInfo::default();
+
+        for line in content.lines() {
+            let line = line.trim();
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim().to_string()),
+                None => continue,
+            };
+
+            match key {
+                "Description" => info.description = Some(value),
+                // Strip systemd prefixes such as "-" (ignore failure) or "@"
+                "ExecStart" => {
+                    info.exec_start = Some(
+                        value
+                            .trim_start_matches(['-', '@', '+', '!', ':'])
+                            .to_string(),
+                    )
+                }
+                "User" => info.user = Some(value),
+                "WorkingDirectory" => info.working_directory = Some(value),
+                _ => {}
+            }
+        }
+
+        info
+    }
+
+    /// Split ExecStart into program and arguments
+    pub fn command(&self) -> Option<(&str, &str)> {
+        let exec = self.exec_start.as_deref()?.trim();
+        if exec.is_empty() {
+            return None;
+        }
+        Some(exec.split_once(char::is_whitespace).unwrap_or((exec, "")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_info_parse() {
+        let unit = "[Unit]\nDescription=My Service\n\n[Service]\nExecStart=-/opt/app/bin/app --port 80\nUser=app\n";
+        let info = UnitInfo::parse(unit);
+
+        assert_eq!(info.description.as_deref(), Some("My Service"));
+        assert_eq!(info.user.as_deref(), Some("app"));
+        assert_eq!(info.command(), Some(("/opt/app/bin/app", "--port 80")));
+    }
+
+    fn test_context() -> ServiceContext {
+        let mut variables = BTreeMap::new();
+        variables.insert("INSTALL_PATH".to_string(), "/opt/app".to_string());
+        variables.insert("USER".to_string(), "root".to_string());
+        variables.insert("PORT".to_string(), "8080".to_string());
+
+        ServiceContext {
+            install_path: PathBuf::from("/opt/app"),
+            env_file: PathBuf::from("/etc/app/app.env"),
+            environment: BTreeMap::new(),
+            variables,
+            hardening: false,
+            writable_paths: vec![],
+        }
+    }
+
+    #[test]
+    fn test_unit_kind_and_startable_units() {
+        assert_eq!(UnitKind::parse("app.timer"), Some(("app", UnitKind::Timer)));
+        assert_eq!(UnitKind::parse("app.path"), None);
+        assert_eq!(unit_file_name("app"), "app.service");
+        assert_eq!(unit_file_name("app-backup.timer"), "app-backup.timer");
+
+        let unit = |name: &str, kind| ServiceUnit {
+            name: name.to_string(),
+            kind,
+            path: PathBuf::from(name),
+            preset: ServicePreset::Enable,
+        };
+        let units = vec![
+            unit("app", UnitKind::Service),
+            unit("app-backup", UnitKind::Service),
+            unit("app-backup.timer", UnitKind::Timer),
+        ];
+
+        let startable: Vec<&str> = ServiceUnit::startable(&units)
+            .iter()
+            .map(|unit| unit.name.as_str())
+            .collect();
+        assert_eq!(startable, vec!["app", "app-backup.timer"]);
+    }
+
+    #[test]
+    fn test_render_template() {
+        let context = test_context();
+        let rendered = render_template(
+            "ExecStart={{INSTALL_PATH}}/bin/app --port {{PORT}}\nUser={{USER}}",
+            &context,
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "ExecStart=/opt/app/bin/app --port 8080\nUser=root"
+        );
+
+        // Text that is not a placeholder is left alone
+        let rendered = render_template("Args={{ .Name }} {{", &context).unwrap();
+        assert_eq!(rendered, "Args={{ .Name }} {{");
+
+        let err =
+            render_template("ExecStart={{ENTRY}} {{DATA_DIR}} {{ENTRY}}", &context).unwrap_err();
+        assert!(err.to_string().ends_with("{{ENTRY}}, {{DATA_DIR}}"));
+    }
+
+    #[test]
+    fn test_env_file_content() {
+        let mut context = test_context();
+        context
+            .environment
+            .insert("GREETING".to_string(), "say \"hi\" to $USER".to_string());
+        context
+            .environment
+            .insert("INSTALL_PATH".to_string(), "/opt/app".to_string());
+
+        assert_eq!(
+            context.env_file_content(),
+            "GREETING=\"say \\\"hi\\\" to \\$USER\"\nINSTALL_PATH=\"/opt/app\"\n"
+        );
+    }
+}
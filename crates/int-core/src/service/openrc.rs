@@ -0,0 +1,234 @@
+/// OpenRC service backend
+///
+/// Installs init scripts into `/etc/init.d`, adds them to the default
+/// runlevel with `rc-update` and controls them through `rc-service`.
+/// Packages may ship a ready-made `<name>.openrc` script; otherwise one is
+/// generated from the package's systemd unit.
+use super::{
+    read_service_source, render_template, run_command, source_env_file, InitSystem, ServiceBackend,
+    ServiceContext, UnitInfo,
+};
+use crate::error::{IntError, IntResult};
+use crate::extractor::ExtractedPackage;
+use crate::manifest::InstallScope;
+use crate::root;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory where OpenRC init scripts are installed
+const INIT_D_DIR: &str = "/etc/init.d";
+
+/// Runlevel services are added to
+const DEFAULT_RUNLEVEL: &str = "default";
+
+/// OpenRC service backend
+#[derive(Debug, Default)]
+pub struct OpenRcBackend;
+
+impl OpenRcBackend {
+    /// Create a new OpenRC backend
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// OpenRC only manages system-wide services
+    fn ensure_system_scope(&self, scope: InstallScope) -> IntResult<()> {
+        match scope {
+            InstallScope::System => Ok(()),
+            InstallScope::User => Err(IntError::ServiceRegistrationFailed(
+                "OpenRC does not support user services; install with system scope".to_string(),
+            )),
+        }
+    }
+
+    /// Generate an OpenRC init script from a systemd unit
+    ///
+    /// The script exports the variables of the package's environment file.
+    pub(crate) fn generate_script(
+        &self,
+        service_name: &str,
+        unit: &str,
+        env_file: &Path,
+    ) -> IntResult<String> {
+        let info = UnitInfo::parse(unit);
+        let (program, args) = info.command().ok_or_else(|| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Service '{}' has no ExecStart to convert to an OpenRC script",
+                service_name
+            ))
+        })?;
+
+        let mut script = String::from("#!/sbin/openrc-run\n\n");
+        script.push_str(&format!(
+            "description=\"{}\"\n",
+            info.description.as_deref().unwrap_or(service_name)
+        ));
+        script.push_str(&format!("command=\"{}\"\n", program));
+        if !args.is_empty() {
+            script.push_str(&format!("command_args=\"{}\"\n", args));
+        }
+        if let Some(user) = &info.user {
+            script.push_str(&format!("command_user=\"{}\"\n", user));
+        }
+        if let Some(dir) = &info.working_directory {
+            script.push_str(&format!("directory=\"{}\"\n", dir));
+        }
+        script.push_str("command_background=true\n");
+        script.push_str(&format!("pidfile=\"/run/{}.pid\"\n", service_name));
+        script.push('\n');
+        script.push_str(&source_env_file(env_file));
+        script.push_str("\ndepend() {\n\tuse net\n}\n");
+
+        Ok(script)
+    }
+
+    fn rc_service(&self, service_name: &str, action: &str) -> Command {
+        let mut cmd = Command::new("rc-service");
+        cmd.arg(service_name).arg(action);
+        cmd
+    }
+}
+
+impl ServiceBackend for OpenRcBackend {
+    fn init_system(&self) -> InitSystem {
+        InitSystem::OpenRc
+    }
+
+    fn render(
+        &self,
+        extracted: &ExtractedPackage,
+        service_name: &str,
+        context: &ServiceContext,
+    ) -> IntResult<String> {
+        if extracted.services_dir.is_none() {
+            return Err(IntError::ServiceRegistrationFailed(
+                "No services directory found".to_string(),
+            ));
+        }
+
+        // Prefer a native init script shipped with the package
+        if let Some(script) = read_service_source(extracted, &format!("{}.openrc", service_name))? {
+            return render_template(&script, context);
+        }
+
+        let unit_file_name = format!("{}.service", service_name);
+        let unit = read_service_source(extracted, &unit_file_name)?.ok_or_else(|| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Service file not found: {}.openrc or {}",
+                service_name, unit_file_name
+            ))
+        })?;
+
+        self.generate_script(
+            service_name,
+            &render_template(&unit, context)?,
+            &context.env_file,
+        )
+    }
+
+    fn service_path(&self, service_name: &str, scope: InstallScope) -> IntResult<PathBuf> {
+        self.ensure_system_scope(scope)?;
+        Ok(root::path(Path::new(INIT_D_DIR)).join(service_name))
+    }
+
+    fn file_mode(&self) -> u32 {
+        0o755
+    }
+
+    fn reload(&self, _scope: InstallScope) -> IntResult<()> {
+        // OpenRC reads init scripts on demand; nothing to reload
+        Ok(())
+    }
+
+    fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+
+        let mut cmd = Command::new("rc-update");
+        cmd.arg("add").arg(service_name).arg(DEFAULT_RUNLEVEL);
+        run_command(&mut cmd, "enable").map_err(|e| match e {
+            IntError::SystemdError(msg) => IntError::ServiceRegistrationFailed(msg),
+            other => other,
+        })
+    }
+
+    fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+
+        let mut cmd = Command::new("rc-update");
+        cmd.arg("del").arg(service_name).arg(DEFAULT_RUNLEVEL);
+        run_command(&mut cmd, "disable")
+    }
+
+    fn start(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+        run_command(&mut self.rc_service(service_name, "start"), "start")
+    }
+
+    fn stop(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+
+        // Ignore errors when stopping (service might not be running)
+        let _ = self.rc_service(service_name, "stop").output();
+        Ok(())
+    }
+
+    fn is_active(&self, service_name: &str, scope: InstallScope) -> bool {
+        if scope != InstallScope::System {
+            return false;
+        }
+
+        self.rc_service(service_name, "status")
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    /// `rc-service status` exits with 32 for crashed services
+    fn is_failed(&self, service_name: &str, scope: InstallScope) -> bool {
+        if scope != InstallScope::System {
+            return false;
+        }
+
+        self.rc_service(service_name, "status")
+            .output()
+            .map(|output| output.status.code() == Some(32))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_script() {
+        let backend = OpenRcBackend::new();
+        let unit = "[Unit]\nDescription=My App\n\n[Service]\nExecStart=/opt/myapp/bin/myapp --serve\nUser=myapp\nWorkingDirectory=/opt/myapp\n";
+
+        let script = backend
+            .generate_script("myapp", unit, Path::new("/etc/myapp/myapp.env"))
+            .unwrap();
+        assert!(script.starts_with("#!/sbin/openrc-run\n"));
+        assert!(script.contains("description=\"My App\""));
+        assert!(script.contains("command=\"/opt/myapp/bin/myapp\""));
+        assert!(script.contains("command_args=\"--serve\""));
+        assert!(script.contains("command_user=\"myapp\""));
+        assert!(script.contains("directory=\"/opt/myapp\""));
+        assert!(script.contains("pidfile=\"/run/myapp.pid\""));
+        assert!(script.contains(". \"/etc/myapp/myapp.env\""));
+
+        assert!(backend
+            .generate_script("myapp", "[Service]\n", Path::new("/etc/myapp/myapp.env"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_user_scope_rejected() {
+        let backend = OpenRcBackend::new();
+        assert!(backend.service_path("myapp", InstallScope::User).is_err());
+        assert_eq!(
+            backend.service_path("myapp", InstallScope::System).unwrap(),
+            PathBuf::from("/etc/init.d/myapp")
+        );
+    }
+}
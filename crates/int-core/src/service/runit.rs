@@ -0,0 +1,342 @@
+/// runit service backend
+///
+/// Creates service directories with a `run` script under `/etc/sv`, links
+/// them into the active runsvdir and controls them through `sv`.
+/// Packages may ship a ready-made `<name>.runit` run script; otherwise one is
+/// generated from the package's systemd unit.
+use super::{
+    read_service_source, render_template, run_command, source_env_file, InitSystem, ServiceBackend,
+    ServiceContext, UnitInfo,
+};
+use crate::error::{IntError, IntResult};
+use crate::extractor::ExtractedPackage;
+use crate::manifest::{self, InstallScope};
+use crate::root;
+use crate::utils;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory holding runit service definitions
+const SV_DIR: &str = "/etc/sv";
+
+/// Candidate directories for the active runlevel, in lookup order
+const RUNSVDIR_CANDIDATES: &[&str] = &["/var/service", "/etc/service", "/service"];
+
+/// Fallback runlevel when no active service directory exists
+const DEFAULT_RUNSVDIR: &str = "/etc/runit/runsvdir/default";
+
+/// runit service backend
+#[derive(Debug, Default)]
+pub struct RunitBackend;
+
+impl RunitBackend {
+    /// Create a new runit backend
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// runit services are only managed system-wide
+    fn ensure_system_scope(&self, scope: InstallScope) -> IntResult<()> {
+        match scope {
+            InstallScope::System => Ok(()),
+            InstallScope::User => Err(IntError::ServiceRegistrationFailed(
+                "runit does not support user services; install with system scope".to_string(),
+            )),
+        }
+    }
+
+    /// Directory of the active runlevel that runsvdir supervises
+    fn runsvdir(&self) -> PathBuf {
+        RUNSVDIR_CANDIDATES
+            .iter()
+            .map(|dir| root::path(Path::new(dir)))
+            .find(|dir| dir.is_dir())
+            .unwrap_or_else(|| root::path(Path::new(DEFAULT_RUNSVDIR)))
+    }
+
+    /// Path of the service link in the active runlevel
+    fn link_path(&self, service_name: &str) -> PathBuf {
+        self.runsvdir().join(service_name)
+    }
+
+    /// Generate a runit run script from a systemd unit
+    ///
+    /// The script exports the variables of the package's environment file.
+    pub(crate) fn generate_run_script(
+        &self,
+        service_name: &str,
+        unit: &str,
+        env_file: &Path,
+    ) -> IntResult<String> {
+        let info = UnitInfo::parse(unit);
+        let exec = info
+            .exec_start
+            .as_deref()
+            .filter(|exec| !exec.trim().is_empty())
+            .ok_or_else(|| {
+                IntError::ServiceRegistrationFailed(format!(
+                    "Service '{}' has no ExecStart to convert to a runit run script",
+                    service_name
+                ))
+            })?;
+
+        let mut script = String::from("#!/bin/sh\nexec 2>&1\n");
+        script.push_str(&source_env_file(env_file));
+        if let Some(dir) = &info.working_directory {
+            script.push_str(&format!("cd \"{}\" || exit 1\n", dir));
+        }
+        match &info.user {
+            Some(user) => script.push_str(&format!("exec chpst -u {} {}\n", user, exec.trim())),
+            None => script.push_str(&format!("exec {}\n", exec.trim())),
+        }
+
+        Ok(script)
+    }
+
+    fn sv(&self, service_name: &str, action: &str) -> Command {
+        let mut cmd = Command::new("sv");
+        cmd.arg(action).arg(self.link_path(service_name));
+        cmd
+    }
+}
+
+impl ServiceBackend for RunitBackend {
+    fn init_system(&self) -> InitSystem {
+        InitSystem::Runit
+    }
+
+    fn render(
+        &self,
+        extracted: &ExtractedPackage,
+        service_name: &str,
+        context: &ServiceContext,
+    ) -> IntResult<String> {
+        if extracted.services_dir.is_none() {
+            return Err(IntError::ServiceRegistrationFailed(
+                "No services directory found".to_string(),
+            ));
+        }
+
+        // Prefer a native run script shipped with the package
+        if let Some(script) = read_service_source(extracted, &format!("{}.runit", service_name))? {
+            return render_template(&script, context);
+        }
+
+        let unit_file_name = format!("{}.service", service_name);
+        let unit = read_service_source(extracted, &unit_file_name)?.ok_or_else(|| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Service file not found: {}.runit or {}",
+                service_name, unit_file_name
+            ))
+        })?;
+
+        self.generate_run_script(
+            service_name,
+            &render_template(&unit, context)?,
+            &context.env_file,
+        )
+    }
+
+    /// The service directory; the definition itself is its `run` script
+    ///
+    /// The name becomes a directory under `/etc/sv`, so it follows the
+    /// package name rules.
+    fn service_path(&self, service_name: &str, scope: InstallScope) -> IntResult<PathBuf> {
+        self.ensure_system_scope(scope)?;
+        if !manifest::is_valid_package_name(service_name) {
+            return Err(IntError::ServiceRegistrationFailed(format!(
+                "Invalid runit service name '{}': only letters, digits, '-' and '_' are allowed",
+                service_name
+            )));
+        }
+        Ok(root::path(Path::new(SV_DIR)).join(service_name))
+    }
+
+    fn file_mode(&self) -> u32 {
+        0o755
+    }
+
+    fn install(&self, service_path: &Path, content: &str) -> IntResult<()> {
+        utils::ensure_dir(service_path)?;
+
+        let run_script = service_path.join("run");
+        fs::write(&run_script, root::target_content(content)).map_err(|e| {
+            IntError::ServiceRegistrationFailed(format!("Failed to write run script: {}", e))
+        })?;
+        utils::set_permissions(&run_script, self.file_mode())
+    }
+
+    fn reload(&self, _scope: InstallScope) -> IntResult<()> {
+        // runsvdir rescans the active runlevel on its own
+        Ok(())
+    }
+
+    /// Link the service into the active runlevel; runsvdir starts it shortly after
+    fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        let service_dir = self.service_path(service_name, scope)?;
+        let link = self.link_path(service_name);
+
+        if link.symlink_metadata().is_ok() {
+            return Ok(());
+        }
+
+        if let Some(parent) = link.parent() {
+            utils::ensure_dir(parent)?;
+        }
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root::target(&service_dir), &link).map_err(|e| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Failed to link {} into {}: {}",
+                service_dir.display(),
+                link.display(),
+                e
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+
+        let link = self.link_path(service_name);
+        if link.symlink_metadata().is_ok() {
+            fs::remove_file(&link).map_err(|e| {
+                IntError::SystemdError(format!("Failed to remove {}: {}", link.display(), e))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn start(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+        run_command(&mut self.sv(service_name, "up"), "start")
+    }
+
+    fn stop(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+
+        // Ignore errors when stopping (service might not be running)
+        let _ = self.sv(service_name, "down").output();
+        Ok(())
+    }
+
+    fn is_active(&self, service_name: &str, scope: InstallScope) -> bool {
+        if scope != InstallScope::System {
+            return false;
+        }
+
+        self.sv(service_name, "status")
+            .output()
+            .map(|output| {
+                output.status.success()
+                    && String::from_utf8_lossy(&output.stdout).starts_with("run:")
+            })
+            .unwrap_or(false)
+    }
+
+    /// Remove the run scripts and runsv's state, then the emptied directories
+    ///
+    /// Files added next to the run script (`conf`, `finish`, ...) are not
+    /// ours, so a directory still holding them is left in place.
+    fn remove(&self, service_path: &Path, _service_name: &str) -> IntResult<()> {
+        for dir in [service_path.join("log"), service_path.to_path_buf()] {
+            let run_script = dir.join("run");
+            if run_script.symlink_metadata().is_ok() {
+                fs::remove_file(&run_script).map_err(|e| {
+                    IntError::SystemdError(format!("Failed to remove run script: {}", e))
+                })?;
+            }
+
+            // runsv keeps its state in `supervise`, which some distributions
+            // link to a directory under /run
+            let supervise = dir.join("supervise");
+            let removed = match supervise.symlink_metadata() {
+                Ok(meta) if meta.is_dir() => fs::remove_dir_all(&supervise),
+                Ok(_) => fs::remove_file(&supervise),
+                Err(_) => Ok(()),
+            };
+            removed.map_err(|e| {
+                IntError::SystemdError(format!("Failed to remove supervise state: {}", e))
+            })?;
+
+            let _ = fs::remove_dir(&dir);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_run_script() {
+        let backend = RunitBackend::new();
+        let env_file = Path::new("/etc/myapp/myapp.env");
+        let unit = "[Service]\nExecStart=/opt/myapp/bin/myapp --serve\nUser=myapp\nWorkingDirectory=/opt/myapp\n";
+
+        let script = backend
+            .generate_run_script("myapp", unit, env_file)
+            .unwrap();
+        assert_eq!(
+            script,
+            "#!/bin/sh\nexec 2>&1\nset -a\n[ -r \"/etc/myapp/myapp.env\" ] && . \"/etc/myapp/myapp.env\"\nset +a\ncd \"/opt/myapp\" || exit 1\nexec chpst -u myapp /opt/myapp/bin/myapp --serve\n"
+        );
+
+        let script = backend
+            .generate_run_script("myapp", "[Service]\nExecStart=/usr/bin/myapp\n", env_file)
+            .unwrap();
+        assert!(script.ends_with("exec /usr/bin/myapp\n"));
+
+        assert!(backend
+            .generate_run_script("myapp", "[Service]\n", env_file)
+            .is_err());
+    }
+
+    #[test]
+    fn test_install_creates_run_script() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let service_dir = temp.path().join("myapp");
+
+        let backend = RunitBackend::new();
+        backend
+            .install(&service_dir, "#!/bin/sh\nexec true\n")
+            .unwrap();
+        assert!(service_dir.join("run").is_file());
+
+        backend.remove(&service_dir, "myapp").unwrap();
+        assert!(!service_dir.exists());
+    }
+
+    #[test]
+    fn test_remove_keeps_foreign_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let service_dir = temp.path().join("myapp");
+
+        let backend = RunitBackend::new();
+        backend
+            .install(&service_dir, "#!/bin/sh\nexec true\n")
+            .unwrap();
+        fs::create_dir_all(service_dir.join("supervise")).unwrap();
+        fs::write(service_dir.join("supervise/pid"), "42\n").unwrap();
+        fs::write(service_dir.join("conf"), "OPTS=--verbose\n").unwrap();
+
+        backend.remove(&service_dir, "myapp").unwrap();
+        assert!(!service_dir.join("run").exists());
+        assert!(!service_dir.join("supervise").exists());
+        assert!(service_dir.join("conf").is_file());
+    }
+
+    #[test]
+    fn test_service_name_validation() {
+        let backend = RunitBackend::new();
+        assert!(backend.service_path("myapp", InstallScope::System).is_ok());
+        for name in ["", ".", "..", "../ssh", "my app", "a/b"] {
+            assert!(backend.service_path(name, InstallScope::System).is_err());
+        }
+    }
+}
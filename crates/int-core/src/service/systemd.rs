@@ -0,0 +1,625 @@
+/// systemd service backend
+///
+/// Installs unit files into the systemd unit directory and controls them
+/// over D-Bus. When the bus is not reachable, `systemctl` is used instead.
+#[cfg(target_os = "linux")]
+use super::systemd_dbus::SystemdBus;
+use super::{
+    read_service_source, render_template, run_command, unit_file_name, InitSystem, ServiceBackend,
+    ServiceContext, ServiceStatus,
+};
+use crate::error::{IntError, IntResult};
+use crate::extractor::ExtractedPackage;
+use crate::manifest::InstallScope;
+use crate::root;
+use crate::utils;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+#[cfg(target_os = "linux")]
+use std::sync::OnceLock;
+
+/// Sandboxing directives added to services that opt into hardening
+///
+/// The whole file system is read-only except for the package's data, cache
+/// and log directories, which are added as `ReadWritePaths=`.
+const HARDENING_DIRECTIVES: &str = "\
+    ProtectSystem=strict\n\
+    ProtectHome=read-only\n\
+    PrivateTmp=yes\n\
+    PrivateDevices=yes\n\
+    NoNewPrivileges=yes\n\
+    ProtectKernelTunables=yes\n\
+    ProtectKernelModules=yes\n\
+    ProtectControlGroups=yes\n\
+    RestrictSUIDSGID=yes\n";
+
+/// Directories searched for preset files, in order of precedence
+const PRESET_DIRS: &[&str] = &[
+    "/etc/systemd",
+    "/run/systemd",
+    "/usr/local/lib/systemd",
+    "/usr/lib/systemd",
+];
+
+/// File name of the drop-in holding install-time settings
+const DROP_IN_NAME: &str = "10-int-installer.conf";
+
+/// systemd service backend
+pub struct SystemdBackend {
+    use_dbus: bool,
+    #[cfg(target_os = "linux")]
+    system_bus: OnceLock<Option<SystemdBus>>,
+    #[cfg(target_os = "linux")]
+    session_bus: OnceLock<Option<SystemdBus>>,
+}
+
+impl SystemdBackend {
+    /// Create a new systemd backend that prefers D-Bus
+    pub fn new() -> Self {
+        Self::with_dbus(true)
+    }
+
+    /// Create a systemd backend that only uses `systemctl`
+    pub fn cli() -> Self {
+        Self::with_dbus(false)
+    }
+
+    fn with_dbus(use_dbus: bool) -> Self {
+        Self {
+            use_dbus,
+            #[cfg(target_os = "linux")]
+            system_bus: OnceLock::new(),
+            #[cfg(target_os = "linux")]
+            session_bus: OnceLock::new(),
+        }
+    }
+
+    /// Get the D-Bus connection for a scope, connecting on first use
+    #[cfg(target_os = "linux")]
+    fn bus(&self, scope: InstallScope) -> Option<&SystemdBus> {
+        if !self.use_dbus {
+            return None;
+        }
+
+        let cell = match scope {
+            InstallScope::System => &self.system_bus,
+            InstallScope::User => &self.session_bus,
+        };
+        cell.get_or_init(|| SystemdBus::connect(scope)).as_ref()
+    }
+
+    /// Build a systemctl command for the given scope
+    fn systemctl(&self, scope: InstallScope) -> Command {
+        let (systemctl_cmd, user_flag) = self.get_systemctl_command(scope);
+
+        let mut cmd = Command::new(systemctl_cmd);
+        if let Some(flag) = user_flag {
+            cmd.arg(flag);
+        }
+        cmd
+    }
+
+    /// Get systemctl command and user flag based on scope
+    fn get_systemctl_command(&self, scope: InstallScope) -> (&str, Option<&str>) {
+        match scope {
+            InstallScope::User => ("systemctl", Some("--user")),
+            InstallScope::System => ("systemctl", None),
+        }
+    }
+}
+
+impl Default for SystemdBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServiceBackend for SystemdBackend {
+    fn init_system(&self) -> InitSystem {
+        InitSystem::Systemd
+    }
+
+    fn render(
+        &self,
+        extracted: &ExtractedPackage,
+        service_name: &str,
+        context: &ServiceContext,
+    ) -> IntResult<String> {
+        let service_file_name = unit_file_name(service_name);
+
+        if extracted.services_dir.is_none() {
+            return Err(IntError::ServiceRegistrationFailed(
+                "No services directory found".to_string(),
+            ));
+        }
+
+        let content = read_service_source(extracted, &service_file_name)?.ok_or_else(|| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Service file not found: {}",
+                service_file_name
+            ))
+        })?;
+
+        render_template(&content, context)
+    }
+
+    fn service_path(&self, service_name: &str, scope: InstallScope) -> IntResult<PathBuf> {
+        Ok(scope
+            .systemd_service_path()
+            .join(unit_file_name(service_name)))
+    }
+
+    /// Write the installer's drop-in into `<unit>.d/`
+    ///
+    /// Only the installer's own drop-in is rewritten, so drop-ins added by an
+    /// administrator (e.g. with `systemctl edit`) survive upgrades.
+    fn install_overrides(&self, service_path: &Path, context: &ServiceContext) -> IntResult<()> {
+        if service_path.extension().and_then(|ext| ext.to_str()) != Some("service") {
+            return Ok(());
+        }
+
+        let drop_in = drop_in_path(service_path);
+        if let Some(parent) = drop_in.parent() {
+            utils::ensure_dir(parent)?;
+        }
+        fs::write(&drop_in, root::target_content(&drop_in_content(context))).map_err(|e| {
+            IntError::ServiceRegistrationFailed(format!(
+                "Failed to write drop-in {}: {}",
+                drop_in.display(),
+                e
+            ))
+        })?;
+        utils::set_permissions(&drop_in, 0o644)
+    }
+
+    fn reload(&self, scope: InstallScope) -> IntResult<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            return bus.reload();
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("daemon-reload");
+        run_command(&mut cmd, "reload daemon for")
+    }
+
+    fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            return bus.enable(service_name);
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("enable").arg(service_name);
+        run_command(&mut cmd, "enable").map_err(|e| match e {
+            IntError::SystemdError(msg) => IntError::ServiceRegistrationFailed(msg),
+            other => other,
+        })
+    }
+
+    fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            return bus.disable(service_name);
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("disable").arg(service_name);
+        run_command(&mut cmd, "disable")
+    }
+
+    fn start(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            return bus.start(service_name);
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("start").arg(service_name);
+        run_command(&mut cmd, "start")
+    }
+
+    fn stop(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            return bus.stop(service_name);
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("stop").arg(service_name);
+
+        // Ignore errors when stopping (service might not be running)
+        cmd.output()
+            .map_err(|e| IntError::SystemdError(format!("Failed to execute systemctl: {}", e)))?;
+
+        Ok(())
+    }
+
+    fn is_active(&self, service_name: &str, scope: InstallScope) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            return bus
+                .active_state(service_name)
+                .map(|state| state == "active")
+                .unwrap_or(false);
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("is-active").arg(service_name);
+
+        cmd.output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    fn is_failed(&self, service_name: &str, scope: InstallScope) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            return bus
+                .active_state(service_name)
+                .map(|state| state == "failed")
+                .unwrap_or(false);
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("is-failed").arg(service_name);
+
+        cmd.output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    fn status(&self, service_name: &str, scope: InstallScope) -> IntResult<ServiceStatus> {
+        #[cfg(target_os = "linux")]
+        if let Some(bus) = self.bus(scope) {
+            let unit = bus.status(service_name)?;
+            return Ok(ServiceStatus {
+                name: service_name.to_string(),
+                init_system: InitSystem::Systemd,
+                active_state: unit.active_state,
+                sub_state: Some(unit.sub_state),
+                pid: (unit.main_pid != 0).then_some(unit.main_pid),
+                active_since: timestamp_from_usec(unit.active_enter_usec),
+            });
+        }
+
+        let mut cmd = self.systemctl(scope);
+        cmd.arg("show")
+            .arg(service_name)
+            .arg("--timestamp=unix")
+            .arg("--property=ActiveState,SubState,MainPID,ActiveEnterTimestamp");
+
+        let output = cmd
+            .output()
+            .map_err(|e| IntError::SystemdError(format!("Failed to execute systemctl: {}", e)))?;
+        if !output.status.success() {
+            return Err(IntError::SystemdError(format!(
+                "Failed to query service status: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        Ok(parse_show_output(
+            service_name,
+            &String::from_utf8_lossy(&output.stdout),
+        ))
+    }
+
+    fn logs(
+        &self,
+        service_name: &str,
+        scope: InstallScope,
+        lines: usize,
+    ) -> IntResult<Vec<String>> {
+        let mut cmd = Command::new("journalctl");
+        if scope == InstallScope::User {
+            cmd.arg("--user");
+        }
+        cmd.arg("-u")
+            .arg(service_name)
+            .arg("-n")
+            .arg(lines.to_string())
+            .arg("--no-pager")
+            .arg("--output=short-iso");
+
+        let output = cmd
+            .output()
+            .map_err(|e| IntError::SystemdError(format!("Failed to execute journalctl: {}", e)))?;
+        if !output.status.success() {
+            return Err(IntError::SystemdError(format!(
+                "Failed to read service logs: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.starts_with("-- "))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Remove the unit and the installer's drop-in
+    ///
+    /// Administrator drop-ins are kept so they apply again on reinstall.
+    fn remove(&self, service_path: &Path, _service_name: &str) -> IntResult<()> {
+        let drop_in = drop_in_path(service_path);
+        for path in [service_path, drop_in.as_path()] {
+            if path.exists() {
+                fs::remove_file(path).map_err(|e| {
+                    IntError::SystemdError(format!("Failed to remove {}: {}", path.display(), e))
+                })?;
+            }
+        }
+
+        // Only succeeds if no administrator drop-ins are left
+        if let Some(drop_in_dir) = drop_in.parent() {
+            let _ = fs::remove_dir(drop_in_dir);
+        }
+        Ok(())
+    }
+
+    /// Evaluate the `*.preset` files of the scope like `systemctl preset`
+    fn preset(&self, service_name: &str, scope: InstallScope) -> Option<bool> {
+        let kind = match scope {
+            InstallScope::System => "system-preset",
+            InstallScope::User => "user-preset",
+        };
+
+        // Files are ordered by name; a name in an earlier directory masks
+        // the same name in later ones
+        let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+        for dir in PRESET_DIRS {
+            let entries = match fs::read_dir(Path::new(dir).join(kind)) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.ends_with(".preset") {
+                    files.entry(name).or_insert_with(|| entry.path());
+                }
+            }
+        }
+
+        let unit = unit_file_name(service_name);
+        files
+            .values()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .find_map(|content| match_preset(&content, &unit))
+    }
+
+    fn log_hint(&self, service_name: &str, scope: InstallScope) -> Option<String> {
+        Some(match scope {
+            InstallScope::User => format!("journalctl --user -u {}", service_name),
+            InstallScope::System => format!("journalctl -u {}", service_name),
+        })
+    }
+}
+
+/// Find the first `enable`/`disable` line of a preset file matching a unit
+fn match_preset(content: &str, unit: &str) -> Option<bool> {
+    content.lines().find_map(|line| {
+        let line = line.trim();
+        let (action, rest) = line.split_once(char::is_whitespace)?;
+        let pattern = rest.split_whitespace().next()?;
+        if !glob_match(pattern, unit) {
+            return None;
+        }
+
+        match action {
+            "enable" => Some(true),
+            "disable" => Some(false),
+            _ => None,
+        }
+    })
+}
+
+/// Match a unit name against a shell-style pattern (`*` and `?`)
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Convert a systemd realtime timestamp (microseconds) to a date
+fn timestamp_from_usec(usec: u64) -> Option<DateTime<Utc>> {
+    if usec == 0 {
+        return None;
+    }
+    DateTime::from_timestamp(
+        (usec / 1_000_000) as i64,
+        ((usec % 1_000_000) * 1000) as u32,
+    )
+}
+
+/// Parse `systemctl show --timestamp=unix` output into a status
+fn parse_show_output(service_name: &str, output: &str) -> ServiceStatus {
+    let mut status = ServiceStatus {
+        name: service_name.to_string(),
+        init_system: InitSystem::Systemd,
+        active_state: "unknown".to_string(),
+        sub_state: None,
+        pid: None,
+        active_since: None,
+    };
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key {
+            "ActiveState" => status.active_state = value.to_string(),
+            "SubState" if !value.is_empty() => status.sub_state = Some(value.to_string()),
+            "MainPID" => status.pid = value.parse().ok().filter(|pid| *pid != 0),
+            "ActiveEnterTimestamp" => {
+                status.active_since = value
+                    .strip_prefix('@')
+                    .and_then(|secs| secs.parse::<i64>().ok())
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0));
+            }
+            _ => {}
+        }
+    }
+
+    status
+}
+
+/// Path of the installer's drop-in for a unit
+///
+/// The `10-` prefix sorts it before drop-ins created by administrators
+/// (such as `override.conf`), so their settings take precedence.
+fn drop_in_path(service_path: &Path) -> PathBuf {
+    let mut dir = service_path.as_os_str().to_owned();
+    dir.push(".d");
+    PathBuf::from(dir).join(DROP_IN_NAME)
+}
+
+/// Render the installer's drop-in
+///
+/// The `-` prefix keeps the unit working if the environment file is removed
+/// by an admin.
+fn drop_in_content(context: &ServiceContext) -> String {
+    let mut content = format!(
+        "# Generated by int-installer; rewritten on upgrade.\n\
+         # Put local changes in another drop-in (e.g. with `systemctl edit`).\n\
+         [Service]\n\
+         EnvironmentFile=-{}\n",
+        context.env_file.display()
+    );
+
+    if context.hardening {
+        content.push_str(HARDENING_DIRECTIVES);
+        for path in &context.writable_paths {
+            content.push_str(&format!("ReadWritePaths=-{}\n", path.display()));
+        }
+    }
+
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_systemctl_command() {
+        let backend = SystemdBackend::cli();
+
+        let (cmd, flag) = backend.get_systemctl_command(InstallScope::User);
+        assert_eq!(cmd, "systemctl");
+        assert_eq!(flag, Some("--user"));
+
+        let (cmd, flag) = backend.get_systemctl_command(InstallScope::System);
+        assert_eq!(cmd, "systemctl");
+        assert_eq!(flag, None);
+    }
+
+    #[test]
+    fn test_drop_in() {
+        let unit = Path::new("/etc/systemd/system/myapp.service");
+        assert_eq!(
+            drop_in_path(unit),
+            PathBuf::from("/etc/systemd/system/myapp.service.d/10-int-installer.conf")
+        );
+
+        let mut context = test_context();
+        let content = drop_in_content(&context);
+        assert!(content.contains("\n[Service]\nEnvironmentFile=-/etc/myapp/myapp.env\n"));
+        assert!(!content.contains("ProtectSystem"));
+
+        context.hardening = true;
+        context.writable_paths = vec![PathBuf::from("/var/lib/myapp")];
+        let content = drop_in_content(&context);
+        assert!(content.contains("ProtectSystem=strict\n"));
+        assert!(content.contains("NoNewPrivileges=yes\n"));
+        assert!(content.ends_with("ReadWritePaths=-/var/lib/myapp\n"));
+    }
+
+    fn test_context() -> ServiceContext {
+        ServiceContext {
+            install_path: PathBuf::from("/opt/myapp"),
+            env_file: PathBuf::from("/etc/myapp/myapp.env"),
+            environment: Default::default(),
+            variables: Default::default(),
+            hardening: false,
+            writable_paths: vec![],
+        }
+    }
+
+    #[test]
+    fn test_drop_ins_survive_reinstall() {
+        let temp = tempfile::tempdir().unwrap();
+        let unit = temp.path().join("myapp.service");
+        let context = test_context();
+        let backend = SystemdBackend::cli();
+
+        backend
+            .install(&unit, "[Service]\nExecStart=/opt/myapp/bin/myapp\n")
+            .unwrap();
+        backend.install_overrides(&unit, &context).unwrap();
+        let admin = temp.path().join("myapp.service.d/override.conf");
+        fs::write(&admin, "[Service]\nNice=5\n").unwrap();
+
+        backend.remove(&unit, "myapp").unwrap();
+        assert!(!unit.exists());
+        assert!(!drop_in_path(&unit).exists());
+        assert!(admin.exists());
+    }
+
+    #[test]
+    fn test_match_preset() {
+        let preset = "# Local policy\nenable myapp-*.timer\ndisable myapp.service\nenable *\n";
+
+        assert_eq!(match_preset(preset, "myapp.service"), Some(false));
+        assert_eq!(match_preset(preset, "myapp-backup.timer"), Some(true));
+        assert_eq!(match_preset(preset, "other.service"), Some(true));
+        assert_eq!(match_preset("disable *.socket\n", "myapp.service"), None);
+
+        assert!(glob_match("my?pp*.service", "myapp-web.service"));
+        assert!(!glob_match("*.timer", "myapp.service"));
+    }
+
+    #[test]
+    fn test_parse_show_output() {
+        let output = "ActiveState=active\nSubState=running\nMainPID=4242\nActiveEnterTimestamp=@1700000000\n";
+        let status = parse_show_output("myapp", output);
+
+        assert!(status.is_active());
+        assert_eq!(status.sub_state.as_deref(), Some("running"));
+        assert_eq!(status.pid, Some(4242));
+        assert_eq!(status.active_since.unwrap().timestamp(), 1_700_000_000);
+
+        let status = parse_show_output(
+            "myapp",
+            "ActiveState=inactive\nMainPID=0\nActiveEnterTimestamp=\n",
+        );
+        assert!(!status.is_active());
+        assert_eq!(status.pid, None);
+        assert_eq!(status.active_since, None);
+        assert_eq!(status.uptime(), None);
+    }
+}
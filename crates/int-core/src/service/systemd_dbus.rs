@@ -0,0 +1,251 @@
+/// systemd D-Bus client
+///
+/// Talks to the systemd manager (`org.freedesktop.systemd1`) directly over
+/// D-Bus instead of spawning `systemctl` for every operation. The system bus
+/// is used for system services and the session bus for user services.
+use super::unit_file_name;
+use crate::error::{IntError, IntResult};
+use crate::manifest::InstallScope;
+use std::time::{Duration, Instant};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+const DESTINATION: &str = "org.freedesktop.systemd1";
+const MANAGER_PATH: &str = "/org/freedesktop/systemd1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.systemd1.Manager";
+const UNIT_INTERFACE: &str = "org.freedesktop.systemd1.Unit";
+const SERVICE_INTERFACE: &str = "org.freedesktop.systemd1.Service";
+const JOB_INTERFACE: &str = "org.freedesktop.systemd1.Job";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// How long to wait for a start or stop job to finish
+///
+/// systemd enforces the unit's own timeouts (`TimeoutStartSec`,
+/// `TimeoutStopSec`); this only keeps a stuck job from hanging the installer.
+const JOB_TIMEOUT: Duration = Duration::from_secs(300);
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Connection to the systemd manager of one scope
+#[derive(Clone)]
+pub(crate) struct SystemdBus {
+    connection: Connection,
+}
+
+impl SystemdBus {
+    /// Connect to the bus for the given scope
+    ///
+    /// Returns `None` when the bus is not reachable (no D-Bus daemon, no
+    /// user session, ...), in which case callers fall back to `systemctl`.
+    pub fn connect(scope: InstallScope) -> Option<Self> {
+        let connection = match scope {
+            InstallScope::System => Connection::system(),
+            InstallScope::User => Connection::session(),
+        }
+        .ok()?;
+
+        Some(Self { connection })
+    }
+
+    fn manager(&self) -> IntResult<Proxy<'_>> {
+        Proxy::new(
+            &self.connection,
+            DESTINATION,
+            MANAGER_PATH,
+            MANAGER_INTERFACE,
+        )
+        .map_err(map_error)
+    }
+
+    /// Reload unit files (`daemon-reload`)
+    pub fn reload(&self) -> IntResult<()> {
+        self.manager()?
+            .call::<_, _, ()>("Reload", &())
+            .map_err(map_error)
+    }
+
+    /// Enable a unit file
+    pub fn enable(&self, service_name: &str) -> IntResult<()> {
+        let units = vec![unit_file_name(service_name)];
+        self.manager()?
+            .call::<_, _, (bool, Vec<(String, String, String)>)>(
+                "EnableUnitFiles",
+                &(units, false, true),
+            )
+            .map_err(map_error)?;
+        Ok(())
+    }
+
+    /// Disable a unit file
+    pub fn disable(&self, service_name: &str) -> IntResult<()> {
+        let units = vec![unit_file_name(service_name)];
+        self.manager()?
+            .call::<_, _, Vec<(String, String, String)>>("DisableUnitFiles", &(units, false))
+            .map_err(map_error)?;
+        Ok(())
+    }
+
+    /// Start a unit, waiting for the start job to finish like `systemctl start`
+    pub fn start(&self, service_name: &str) -> IntResult<()> {
+        let job = self
+            .manager()?
+            .call::<_, _, OwnedObjectPath>("StartUnit", &(unit_file_name(service_name), "replace"))
+            .map_err(map_error)?;
+        self.wait_for_job(&job)?;
+
+        if self.active_state(service_name)? == "failed" {
+            return Err(IntError::SystemdError(format!(
+                "Failed to start service: {} entered the failed state",
+                unit_file_name(service_name)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Stop a unit, waiting for the stop job to finish like `systemctl stop`
+    ///
+    /// A unit that is not loaded is already stopped.
+    pub fn stop(&self, service_name: &str) -> IntResult<()> {
+        let job = match self
+            .manager()?
+            .call::<_, _, OwnedObjectPath>("StopUnit", &(unit_file_name(service_name), "replace"))
+        {
+            Ok(job) => job,
+            Err(zbus::Error::MethodError(name, _, _)) if name.as_str() == NO_SUCH_UNIT => {
+                return Ok(())
+            }
+            Err(e) => return Err(map_error(e)),
+        };
+        self.wait_for_job(&job)
+    }
+
+    /// Wait until a job has finished
+    ///
+    /// systemd removes the job object once the job is done, so the job is
+    /// polled until it no longer exists.
+    fn wait_for_job(&self, job: &OwnedObjectPath) -> IntResult<()> {
+        let properties = Proxy::new(
+            &self.connection,
+            DESTINATION,
+            job.as_ref(),
+            PROPERTIES_INTERFACE,
+        )
+        .map_err(map_error)?;
+
+        let deadline = Instant::now() + JOB_TIMEOUT;
+        loop {
+            match properties.call::<_, _, OwnedValue>("Get", &(JOB_INTERFACE, "State")) {
+                Ok(_) => {}
+                Err(e) if is_unknown_object(&e) => return Ok(()),
+                Err(e) => return Err(map_error(e)),
+            }
+            if Instant::now() >= deadline {
+                return Err(IntError::SystemdError(format!(
+                    "Timed out waiting for systemd job {}",
+                    job.as_str()
+                )));
+            }
+            std::thread::sleep(JOB_POLL_INTERVAL);
+        }
+    }
+
+    /// Get the `ActiveState` of a unit (e.g. `active`, `inactive`, `failed`)
+    pub fn active_state(&self, service_name: &str) -> IntResult<String> {
+        let unit = self.unit_properties(service_name)?;
+        get_property(&unit, UNIT_INTERFACE, "ActiveState")
+    }
+
+    /// Get the runtime status of a unit
+    pub fn status(&self, service_name: &str) -> IntResult<UnitStatus> {
+        let unit = self.unit_properties(service_name)?;
+
+        let pid: u32 = get_property(&unit, SERVICE_INTERFACE, "MainPID").unwrap_or(0);
+        let active_enter: u64 =
+            get_property(&unit, UNIT_INTERFACE, "ActiveEnterTimestamp").unwrap_or(0);
+
+        Ok(UnitStatus {
+            active_state: get_property(&unit, UNIT_INTERFACE, "ActiveState")?,
+            sub_state: get_property(&unit, UNIT_INTERFACE, "SubState")?,
+            main_pid: pid,
+            active_enter_usec: active_enter,
+        })
+    }
+
+    /// Properties proxy for a unit, loading it if necessary
+    fn unit_properties(&self, service_name: &str) -> IntResult<Proxy<'_>> {
+        let unit_path = self
+            .manager()?
+            .call::<_, _, OwnedObjectPath>("LoadUnit", &(unit_file_name(service_name),))
+            .map_err(map_error)?;
+
+        Proxy::new(
+            &self.connection,
+            DESTINATION,
+            unit_path,
+            PROPERTIES_INTERFACE,
+        )
+        .map_err(map_error)
+    }
+}
+
+/// Raw unit status as reported by systemd
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UnitStatus {
+    pub active_state: String,
+    pub sub_state: String,
+    pub main_pid: u32,
+    /// Realtime timestamp of the last activation in microseconds (0 if never)
+    pub active_enter_usec: u64,
+}
+
+/// Read a property through an `org.freedesktop.DBus.Properties` proxy
+fn get_property<T>(properties: &Proxy<'_>, interface: &str, name: &str) -> IntResult<T>
+where
+    T: TryFrom<OwnedValue>,
+    T::Error: std::fmt::Display,
+{
+    let value = properties
+        .call::<_, _, OwnedValue>("Get", &(interface, name))
+        .map_err(map_error)?;
+
+    T::try_from(value).map_err(|e| IntError::SystemdError(format!("{}: {}", name, e)))
+}
+
+/// Error systemd returns for units that are not loaded
+const NO_SUCH_UNIT: &str = "org.freedesktop.systemd1.NoSuchUnit";
+
+/// Whether a call failed because the object it was made on does not exist
+fn is_unknown_object(error: &zbus::Error) -> bool {
+    match error {
+        zbus::Error::MethodError(name, _, _) => matches!(
+            name.as_str(),
+            "org.freedesktop.DBus.Error.UnknownObject"
+                | "org.freedesktop.DBus.Error.UnknownInterface"
+                | "org.freedesktop.DBus.Error.UnknownMethod"
+        ),
+        zbus::Error::FDO(e) => matches!(
+            **e,
+            zbus::fdo::Error::UnknownObject(_)
+                | zbus::fdo::Error::UnknownInterface(_)
+                | zbus::fdo::Error::UnknownMethod(_)
+        ),
+        _ => false,
+    }
+}
+
+/// Convert a zbus error into a structured service error
+fn map_error(error: zbus::Error) -> IntError {
+    match error {
+        zbus::Error::MethodError(name, message, _) => IntError::ServiceBusError {
+            name: name.to_string(),
+            message: message.unwrap_or_default(),
+        },
+        zbus::Error::FDO(e) => match *e {
+            zbus::fdo::Error::AccessDenied(message) => IntError::ServiceBusError {
+                name: "org.freedesktop.DBus.Error.AccessDenied".to_string(),
+                message,
+            },
+            other => IntError::SystemdError(other.to_string()),
+        },
+        other => IntError::SystemdError(other.to_string()),
+    }
+}
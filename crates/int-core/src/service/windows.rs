@@ -0,0 +1,231 @@
+/// Windows service backend
+///
+/// Registers services with the Service Control Manager through `sc.exe`.
+/// The service command line comes from `<name>.winsvc` (a single line) or,
+/// failing that, from the `ExecStart` of the package's systemd unit. The
+/// rendered command line is kept in a record file under `%ProgramData%` so
+/// the service can be located again on uninstall.
+use super::{
+    read_service_source, render_template, run_command, InitSystem, ServiceBackend, ServiceContext,
+    UnitInfo,
+};
+use crate::error::{IntError, IntResult};
+use crate::extractor::ExtractedPackage;
+use crate::manifest::InstallScope;
+use crate::utils;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Windows service backend
+#[derive(Debug, Default)]
+pub struct WindowsBackend;
+
+impl WindowsBackend {
+    /// Create a new Windows service backend
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Windows services are always machine-wide
+    fn ensure_system_scope(&self, scope: InstallScope) -> IntResult<()> {
+        match scope {
+            InstallScope::System => Ok(()),
+            InstallScope::User => Err(IntError::ServiceRegistrationFailed(
+                "Windows services require system scope".to_string(),
+            )),
+        }
+    }
+
+    /// Directory holding service record files
+    fn records_dir(&self) -> PathBuf {
+        let program_data =
+            std::env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+        PathBuf::from(program_data)
+            .join("int-installer")
+            .join("services")
+    }
+
+    /// Service name stored in a record file path
+    fn name_from_record(&self, service_path: &Path) -> IntResult<String> {
+        service_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                IntError::ServiceRegistrationFailed(format!(
+                    "Invalid service record: {}",
+                    service_path.display()
+                ))
+            })
+    }
+
+    /// Set the start type of a service (`auto` or `demand`)
+    fn set_start_type(&self, service_name: &str, start_type: &str) -> Command {
+        let mut cmd = self.sc("config", service_name);
+        cmd.arg("start=").arg(start_type);
+        cmd
+    }
+
+    fn sc(&self, action: &str, service_name: &str) -> Command {
+        let mut cmd = Command::new("sc.exe");
+        cmd.arg(action).arg(service_name);
+        cmd
+    }
+}
+
+impl ServiceBackend for WindowsBackend {
+    fn init_system(&self) -> InitSystem {
+        InitSystem::Windows
+    }
+
+    /// Render the service command line
+    fn render(
+        &self,
+        extracted: &ExtractedPackage,
+        service_name: &str,
+        context: &ServiceContext,
+    ) -> IntResult<String> {
+        if extracted.services_dir.is_none() {
+            return Err(IntError::ServiceRegistrationFailed(
+                "No services directory found".to_string(),
+            ));
+        }
+
+        let command_line =
+            match read_service_source(extracted, &format!("{}.winsvc", service_name))? {
+                Some(content) => content.trim().to_string(),
+                None => {
+                    let unit_file_name = format!("{}.service", service_name);
+                    let unit =
+                        read_service_source(extracted, &unit_file_name)?.ok_or_else(|| {
+                            IntError::ServiceRegistrationFailed(format!(
+                                "Service file not found: {}.winsvc or {}",
+                                service_name, unit_file_name
+                            ))
+                        })?;
+                    UnitInfo::parse(&unit).exec_start.unwrap_or_default()
+                }
+            };
+
+        if command_line.is_empty() {
+            return Err(IntError::ServiceRegistrationFailed(format!(
+                "Service '{}' has no command line",
+                service_name
+            )));
+        }
+
+        render_template(&command_line, context)
+    }
+
+    fn service_path(&self, service_name: &str, scope: InstallScope) -> IntResult<PathBuf> {
+        self.ensure_system_scope(scope)?;
+        Ok(self.records_dir().join(format!("{}.winsvc", service_name)))
+    }
+
+    /// Write the record file and create the service with the Service Control Manager
+    fn install(&self, service_path: &Path, content: &str) -> IntResult<()> {
+        let service_name = self.name_from_record(service_path)?;
+
+        if let Some(parent) = service_path.parent() {
+            utils::ensure_dir(parent)?;
+        }
+        fs::write(service_path, content).map_err(|e| {
+            IntError::ServiceRegistrationFailed(format!("Failed to write service record: {}", e))
+        })?;
+
+        // Replace a service left behind by a previous installation
+        let _ = self.sc("delete", &service_name).output();
+
+        let mut cmd = self.sc("create", &service_name);
+        cmd.arg("binPath=")
+            .arg(content)
+            .arg("start=")
+            .arg("demand")
+            .arg("DisplayName=")
+            .arg(&service_name);
+        run_command(&mut cmd, "create").map_err(|e| match e {
+            IntError::SystemdError(msg) => IntError::ServiceRegistrationFailed(msg),
+            other => other,
+        })
+    }
+
+    fn reload(&self, _scope: InstallScope) -> IntResult<()> {
+        // The Service Control Manager applies changes immediately
+        Ok(())
+    }
+
+    fn enable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+        run_command(&mut self.set_start_type(service_name, "auto"), "enable").map_err(|e| match e {
+            IntError::SystemdError(msg) => IntError::ServiceRegistrationFailed(msg),
+            other => other,
+        })
+    }
+
+    fn disable(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+        run_command(&mut self.set_start_type(service_name, "demand"), "disable")
+    }
+
+    fn start(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+        run_command(&mut self.sc("start", service_name), "start")
+    }
+
+    fn stop(&self, service_name: &str, scope: InstallScope) -> IntResult<()> {
+        self.ensure_system_scope(scope)?;
+
+        // Ignore errors when stopping (service might not be running)
+        let _ = self.sc("stop", service_name).output();
+        Ok(())
+    }
+
+    fn is_active(&self, service_name: &str, scope: InstallScope) -> bool {
+        if scope != InstallScope::System {
+            return false;
+        }
+
+        self.sc("query", service_name)
+            .output()
+            .map(|output| {
+                output.status.success()
+                    && String::from_utf8_lossy(&output.stdout).contains("RUNNING")
+            })
+            .unwrap_or(false)
+    }
+
+    /// Delete the service from the Service Control Manager and drop its record
+    fn remove(&self, service_path: &Path, service_name: &str) -> IntResult<()> {
+        let registered = self
+            .sc("query", service_name)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        if registered {
+            run_command(&mut self.sc("delete", service_name), "delete")?;
+        }
+
+        if service_path.exists() {
+            fs::remove_file(service_path).map_err(|e| {
+                IntError::SystemdError(format!("Failed to remove service record: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_record_path() {
+        let backend = WindowsBackend::new();
+        assert!(backend.service_path("myapp", InstallScope::User).is_err());
+
+        let record = backend.service_path("myapp", InstallScope::System).unwrap();
+        assert!(record.ends_with("int-installer/services/myapp.winsvc"));
+        assert_eq!(backend.name_from_record(&record).unwrap(), "myapp");
+    }
+}
@@ -0,0 +1,335 @@
+/// Signature inspection of packages
+///
+/// Installing a package verifies its signature and fails when it doesn't
+/// check out. Before asking for confirmation, front ends want to say more
+/// than that: who signed the package, with which key, whether the key is
+/// trusted, and how much of the package the signature vouches for. `inspect`
+/// answers this from the archive alone, without extracting or installing it.
+///
+/// An embedded signature covers the manifest, and through its `file_hashes`
+/// the files they list. A detached `.int.sig` signature covers the whole
+/// archive.
+use crate::compression::open_archive;
+use crate::error::{IntError, IntResult};
+use crate::manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+/// Where the signature of a package is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureKind {
+    /// `signature` field of the manifest
+    Embedded,
+    /// `.int.sig` file next to the package
+    Detached,
+}
+
+/// Outcome of verifying a signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureTrust {
+    /// The package is not signed
+    Unsigned,
+    /// Good signature by a key trusted in the keyring
+    Trusted,
+    /// Good signature by a key nobody vouched for, or that has expired
+    Untrusted,
+    /// The signing key is not in the keyring
+    UnknownKey,
+    /// Bad signature, or signed by a revoked key
+    Invalid,
+}
+
+/// How the files of a package match the hashes in its manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashCoverage {
+    /// Files in the package, besides the manifest
+    pub total_files: usize,
+    /// Files whose hash matches the manifest
+    pub verified_files: usize,
+    /// Files the manifest has no hash for
+    pub unhashed: Vec<String>,
+    /// Files whose hash differs from the manifest
+    pub mismatched: Vec<String>,
+    /// Hashed files missing from the package
+    pub missing: Vec<String>,
+}
+
+impl HashCoverage {
+    /// Whether every file is hashed and matches
+    pub fn is_complete(&self) -> bool {
+        self.verified_files == self.total_files && self.missing.is_empty()
+    }
+}
+
+/// Signer, key and trust of a package's signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureInfo {
+    /// Where the signature is, if the package is signed
+    pub kind: Option<SignatureKind>,
+    pub trust: SignatureTrust,
+    /// User ID of the signing key, e.g. "Vendor <release@vendor.com>"
+    pub signer: Option<String>,
+    /// Long ID of the signing key, known even when the key isn't
+    pub key_id: Option<String>,
+    /// Fingerprint of the signing key, for good signatures
+    pub fingerprint: Option<String>,
+    /// Why the signature isn't trusted
+    pub detail: Option<String>,
+    pub hashes: HashCoverage,
+}
+
+/// Verify the signature of a package without installing it
+pub fn inspect<P: AsRef<Path>>(package_path: P) -> IntResult<SignatureInfo> {
+    let package_path = package_path.as_ref();
+    let (manifest, file_hashes) = read_package(package_path)?;
+    let hashes = hash_coverage(
+        manifest.file_hashes.as_ref().unwrap_or(&BTreeMap::new()),
+        &file_hashes,
+    );
+
+    let detached = package_path.with_extension("int.sig");
+    let (kind, status) = if let Some(ref signature) = manifest.signature {
+        let mut unsigned = manifest.clone();
+        unsigned.signature = None;
+        let data = unsigned.to_canonical_string()?;
+        let status = verify_embedded(signature, &data)?;
+        (Some(SignatureKind::Embedded), status)
+    } else if detached.exists() {
+        let status = gpg_verify(&detached, package_path)?;
+        (Some(SignatureKind::Detached), status)
+    } else {
+        (None, String::new())
+    };
+
+    let mut info = parse_status(&status);
+    info.kind = kind;
+    info.hashes = hashes;
+    if kind.is_none() {
+        info.trust = SignatureTrust::Unsigned;
+    }
+    Ok(info)
+}
+
+/// Manifest of a package and the SHA256 hash of each of its other files
+fn read_package(package_path: &Path) -> IntResult<(Manifest, BTreeMap<String, String>)> {
+    let mut archive = open_archive(package_path)?;
+
+    let mut manifest = None;
+    let mut hashes = BTreeMap::new();
+    for entry_result in archive
+        .entries()
+        .map_err(|e| IntError::CorruptedArchive(format!("Failed to read archive: {}", e)))?
+    {
+        let mut entry = entry_result
+            .map_err(|e| IntError::CorruptedArchive(format!("Failed to read entry: {}", e)))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .map_err(|e| IntError::CorruptedArchive(format!("Invalid entry path: {}", e)))?
+            .to_string_lossy()
+            .into_owned();
+
+        if path == "manifest.json" {
+            let mut content = String::new();
+            io::Read::read_to_string(&mut entry, &mut content)
+                .map_err(|e| IntError::ManifestParseError(e.to_string()))?;
+            manifest = Some(Manifest::from_str(&content)?);
+        } else {
+            let mut hasher = Sha256::new();
+            io::copy(&mut entry, &mut hasher).map_err(|e| {
+                IntError::CorruptedArchive(format!("Failed to read {}: {}", path, e))
+            })?;
+            hashes.insert(path, format!("{:x}", hasher.finalize()));
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| {
+        IntError::InvalidPackage("manifest.json not found in package".to_string())
+    })?;
+    Ok((manifest, hashes))
+}
+
+/// Compare the hashes listed in a manifest with those of the packaged files
+fn hash_coverage(
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> HashCoverage {
+    let mut coverage = HashCoverage {
+        total_files: actual.len(),
+        ..Default::default()
+    };
+    for (path, hash) in actual {
+        match expected.get(path) {
+            Some(expected) if expected == hash => coverage.verified_files += 1,
+            Some(_) => coverage.mismatched.push(path.clone()),
+            None => coverage.unhashed.push(path.clone()),
+        }
+    }
+    coverage.missing = expected
+        .keys()
+        .filter(|path| !actual.contains_key(*path))
+        .cloned()
+        .collect();
+    coverage
+}
+
+fn verify_embedded(signature: &str, data: &str) -> IntResult<String> {
+    let temp_file = |content: &str| {
+        let mut file = tempfile::NamedTempFile::new()
+            .map_err(|e| IntError::Custom(format!("Failed to create temp file: {}", e)))?;
+        file.write_all(content.as_bytes())
+            .map_err(IntError::IoError)?;
+        Ok::<_, IntError>(file)
+    };
+    let sig_file = temp_file(signature)?;
+    let data_file = temp_file(data)?;
+    gpg_verify(sig_file.path(), data_file.path())
+}
+
+/// Machine-readable status lines of `gpg --verify`
+///
+/// A failed verification is not an error here, the status says why.
+fn gpg_verify(signature: &Path, data: &Path) -> IntResult<String> {
+    let output = Command::new("gpg")
+        .args(["--batch", "--status-fd", "1", "--verify"])
+        .arg(signature)
+        .arg(data)
+        .output()
+        .map_err(|e| IntError::Custom(format!("Failed to execute gpg: {}", e)))?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Signature details from gpg status lines
+fn parse_status(status: &str) -> SignatureInfo {
+    let mut info = SignatureInfo {
+        kind: None,
+        trust: SignatureTrust::Invalid,
+        signer: None,
+        key_id: None,
+        fingerprint: None,
+        detail: None,
+        hashes: HashCoverage::default(),
+    };
+    let mut good = false;
+    let mut expired = false;
+
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let (keyword, args) = line.split_once(' ').unwrap_or((line, ""));
+        let mut fields = args.splitn(2, ' ');
+        let first = fields.next().filter(|f| !f.is_empty()).map(str::to_string);
+        let rest = fields.next().map(str::to_string);
+
+        match keyword {
+            "GOODSIG" | "EXPKEYSIG" | "REVKEYSIG" | "BADSIG" => {
+                info.key_id = first;
+                info.signer = rest;
+                match keyword {
+                    "GOODSIG" => good = true,
+                    "EXPKEYSIG" => {
+                        good = true;
+                        expired = true;
+                    }
+                    "REVKEYSIG" => info.detail = Some("Signing key has been revoked".to_string()),
+                    _ => info.detail = Some("Signature doesn't match the package".to_string()),
+                }
+            }
+            "ERRSIG" => {
+                info.key_id = first;
+                info.detail = Some("Signature could not be checked".to_string());
+            }
+            "NO_PUBKEY" => {
+                info.trust = SignatureTrust::UnknownKey;
+                info.detail = Some("Signing key is not in the keyring".to_string());
+            }
+            "VALIDSIG" => info.fingerprint = first,
+            "TRUST_FULLY" | "TRUST_ULTIMATE" if good && !expired => {
+                info.trust = SignatureTrust::Trusted;
+            }
+            "TRUST_UNDEFINED" | "TRUST_NEVER" | "TRUST_MARGINAL" if good => {
+                info.trust = SignatureTrust::Untrusted;
+                info.detail = Some("Signing key is not certified as trusted".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    if expired {
+        info.trust = SignatureTrust::Untrusted;
+        info.detail = Some("Signing key has expired".to_string());
+    } else if good && info.trust == SignatureTrust::Invalid {
+        // gpg prints no trust line for keys without a trust model
+        info.trust = SignatureTrust::Untrusted;
+    }
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let good = "\
+[GNUPG:] NEWSIG
+[GNUPG:] GOODSIG 0123456789ABCDEF Vendor <release@vendor.com>
+[GNUPG:] VALIDSIG 89ABCDEF0123456789ABCDEF0123456789ABCDEF 2024-01-01 1704067200 0 4 0 1 10 00 89ABCDEF0123456789ABCDEF0123456789ABCDEF
+[GNUPG:] TRUST_ULTIMATE 0 pgp
+";
+        let info = parse_status(good);
+        assert_eq!(info.trust, SignatureTrust::Trusted);
+        assert_eq!(info.signer.as_deref(), Some("Vendor <release@vendor.com>"));
+        assert_eq!(info.key_id.as_deref(), Some("0123456789ABCDEF"));
+        assert_eq!(
+            info.fingerprint.as_deref(),
+            Some("89ABCDEF0123456789ABCDEF0123456789ABCDEF")
+        );
+
+        let untrusted = good.replace("TRUST_ULTIMATE", "TRUST_UNDEFINED");
+        assert_eq!(parse_status(&untrusted).trust, SignatureTrust::Untrusted);
+
+        let unknown = "\
+[GNUPG:] ERRSIG 0123456789ABCDEF 1 10 00 1704067200 9 -
+[GNUPG:] NO_PUBKEY 0123456789ABCDEF
+";
+        let info = parse_status(unknown);
+        assert_eq!(info.trust, SignatureTrust::UnknownKey);
+        assert_eq!(info.key_id.as_deref(), Some("0123456789ABCDEF"));
+
+        let bad = "[GNUPG:] BADSIG 0123456789ABCDEF Vendor <release@vendor.com>\n";
+        assert_eq!(parse_status(bad).trust, SignatureTrust::Invalid);
+    }
+
+    #[test]
+    fn test_hash_coverage() {
+        let entry = |path: &str, hash: &str| (path.to_string(), hash.to_string());
+        let expected = BTreeMap::from([
+            entry("payload/app", "aa"),
+            entry("payload/lib.so", "bb"),
+            entry("payload/gone", "cc"),
+        ]);
+        let actual = BTreeMap::from([
+            entry("payload/app", "aa"),
+            entry("payload/lib.so", "ff"),
+            entry("scripts/post_install.sh", "dd"),
+        ]);
+
+        let coverage = hash_coverage(&expected, &actual);
+        assert_eq!(coverage.total_files, 3);
+        assert_eq!(coverage.verified_files, 1);
+        assert_eq!(coverage.mismatched, vec!["payload/lib.so"]);
+        assert_eq!(coverage.unhashed, vec!["scripts/post_install.sh"]);
+        assert_eq!(coverage.missing, vec!["payload/gone"]);
+        assert!(!coverage.is_complete());
+    }
+}
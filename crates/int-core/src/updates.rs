@@ -0,0 +1,197 @@
+/// Update checks for installed packages
+///
+/// Packages can declare an `update_url` in their manifest, pointing to a
+/// JSON feed published by the vendor. The feed describes the latest release
+/// of the package, or lists the latest releases of several packages like a
+/// repository index, so one file can serve a whole product line. `check`
+/// fetches the feeds of a scope's packages and reports those with a newer
+/// version.
+use crate::error::{IntError, IntResult};
+use crate::installer::InstallMetadata;
+use crate::manifest::InstallScope;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Seconds to wait for a feed before giving up
+const FETCH_TIMEOUT: u32 = 20;
+
+/// Release described by an update feed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    /// Package name, required in repository indexes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub version: String,
+    /// Where to download the package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Release notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+}
+
+/// Update feed document
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Feed {
+    Index { packages: Vec<Release> },
+    Single(Release),
+}
+
+impl Feed {
+    /// Latest release of a package listed in the feed
+    fn release(self, package_name: &str) -> Option<Release> {
+        match self {
+            Feed::Single(release) => Some(release),
+            Feed::Index { packages } => packages
+                .into_iter()
+                .filter(|release| release.name.as_deref() == Some(package_name))
+                .max_by(|a, b| utils::compare_versions(&a.version, &b.version)),
+        }
+    }
+}
+
+/// Newer version available for an installed package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvailableUpdate {
+    pub package: String,
+    pub scope: InstallScope,
+    pub installed_version: String,
+    pub version: String,
+    /// Where to download the new version, if the feed says
+    pub url: Option<String>,
+    pub changelog: Option<String>,
+    /// The package is held at its version (see `Installer::set_held`)
+    pub held: bool,
+}
+
+/// Outcome of checking a scope for updates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateReport {
+    pub updates: Vec<AvailableUpdate>,
+    /// Packages whose feed could not be read, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Check the installed packages of a scope for newer versions
+///
+/// Each feed is fetched once, however many packages share it. Packages
+/// without an update feed are skipped, and a feed that fails doesn't stop
+/// the others.
+pub fn check(scope: InstallScope) -> IntResult<UpdateReport> {
+    let mut report = UpdateReport::default();
+    let mut feeds: HashMap<String, Result<String, String>> = HashMap::new();
+
+    for metadata in InstallMetadata::list(scope)? {
+        let Some(url) = metadata
+            .manifest
+            .as_ref()
+            .and_then(|m| m.update_url.clone())
+        else {
+            continue;
+        };
+        let body = feeds
+            .entry(url.clone())
+            .or_insert_with(|| fetch(&url).map_err(|e| e.to_string()));
+
+        let feed = match body {
+            Ok(body) => serde_json::from_str::<Feed>(body)
+                .map_err(|e| format!("Invalid update feed {}: {}", url, e)),
+            Err(e) => Err(e.clone()),
+        };
+        match feed {
+            Ok(feed) => report.updates.extend(available_update(&metadata, feed)),
+            Err(e) => report.failed.push((metadata.package_name.clone(), e)),
+        }
+    }
+
+    Ok(report)
+}
+
+/// The feed's release of a package, if newer than the installed version
+fn available_update(metadata: &InstallMetadata, feed: Feed) -> Option<AvailableUpdate> {
+    let release = feed.release(&metadata.package_name)?;
+    if utils::compare_versions(&release.version, &metadata.package_version) != Ordering::Greater {
+        return None;
+    }
+
+    Some(AvailableUpdate {
+        package: metadata.package_name.clone(),
+        scope: metadata.install_scope,
+        installed_version: metadata.package_version.clone(),
+        version: release.version,
+        url: release.url,
+        changelog: release.changelog,
+        held: metadata.held,
+    })
+}
+
+/// Download a feed with curl
+fn fetch(url: &str) -> IntResult<String> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--proto", "=https", "--max-time"])
+        .arg(FETCH_TIMEOUT.to_string())
+        .arg(url)
+        .output()
+        .map_err(|e| IntError::Custom(format!("Failed to run curl to fetch {}: {}", url, e)))?;
+
+    if !output.status.success() {
+        return Err(IntError::Custom(format!(
+            "Failed to fetch {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| IntError::Custom(format!("Update feed {} is not valid UTF-8", url)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(name: &str, version: &str) -> InstallMetadata {
+        serde_json::from_value(serde_json::json!({
+            "install_id": "test",
+            "package_name": name,
+            "package_version": version,
+            "install_date": "2024-01-01T00:00:00Z",
+            "install_path": "/opt/test",
+            "install_scope": "user",
+            "installed_files": [],
+            "desktop_entry": null,
+            "service_file": null,
+            "service_name": null,
+            "bin_symlink": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_available_update() {
+        let single: Feed = serde_json::from_str(
+            r#"{"version": "1.10.0", "url": "https://example.com/myapp-1.10.0.int"}"#,
+        )
+        .unwrap();
+        let update = available_update(&metadata("myapp", "1.9.2"), single.clone()).unwrap();
+        assert_eq!(update.version, "1.10.0");
+        assert_eq!(update.installed_version, "1.9.2");
+        assert!(available_update(&metadata("myapp", "1.10.0"), single).is_none());
+
+        let index: Feed = serde_json::from_str(
+            r#"{"packages": [
+                {"name": "myapp", "version": "2.0.0"},
+                {"name": "myapp", "version": "2.1.0-rc1"},
+                {"name": "other", "version": "9.0.0"}
+            ]}"#,
+        )
+        .unwrap();
+        let update = available_update(&metadata("myapp", "1.0.0"), index.clone()).unwrap();
+        assert_eq!(update.version, "2.1.0-rc1");
+        assert!(available_update(&metadata("missing", "1.0.0"), index).is_none());
+    }
+}
@@ -1,10 +1,16 @@
 /// Utility functions for INT Installer
 
 use crate::error::{IntError, IntResult};
+use std::cell::RefCell;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+thread_local! {
+    /// Directories created by `ensure_dir` while a `DirTracker` is active
+    static CREATED_DIRS: RefCell<Option<Vec<PathBuf>>> = const { RefCell::new(None) };
+}
+
 /// Copy directory recursively
 ///
 /// Copies all files and subdirectories from source to destination.
@@ -227,6 +233,75 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
+/// Compare two package versions
+///
+/// Versions are compared part by part, numerically where both parts are
+/// numbers (so 1.10 is newer than 1.9). A leading `v` and build metadata
+/// after `+` are ignored, missing parts count as 0, and a pre-release
+/// (`1.0.0-rc1`) is older than its release.
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    fn split(version: &str) -> (Vec<&str>, Option<&str>) {
+        let version = version.trim();
+        let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+        let version = version.split('+').next().unwrap_or(version);
+        let (release, pre) = match version.split_once('-') {
+            Some((release, pre)) => (release, Some(pre)),
+            None => (version, None),
+        };
+        (release.split('.').collect(), pre)
+    }
+
+    fn compare_parts(a: &[&str], b: &[&str]) -> Ordering {
+        for i in 0..a.len().max(b.len()) {
+            let x = a.get(i).copied().unwrap_or("0");
+            let y = b.get(i).copied().unwrap_or("0");
+            let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                _ => x.cmp(y),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    let (a_release, a_pre) = split(a);
+    let (b_release, b_pre) = split(b);
+    compare_parts(&a_release, &b_release).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => {
+            let a: Vec<&str> = a.split('.').collect();
+            let b: Vec<&str> = b.split('.').collect();
+            compare_parts(&a, &b)
+        }
+    })
+}
+
+/// Calculate the SHA256 hash of a file as lowercase hex
+pub fn sha256_file(path: &Path) -> IntResult<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let mut file = fs::File::open(path).map_err(IntError::IoError)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+
+    loop {
+        let count = file.read(&mut buffer).map_err(IntError::IoError)?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 /// Get current username
 pub fn get_current_username() -> Option<String> {
     #[cfg(unix)]
@@ -247,6 +322,162 @@ pub fn get_current_username() -> Option<String> {
     }
 }
 
+/// Get the name of the current user's primary group
+pub fn get_current_groupname() -> Option<String> {
+    #[cfg(unix)]
+    {
+        use nix::unistd::{getgid, Group};
+
+        Group::from_gid(getgid())
+            .ok()
+            .flatten()
+            .map(|group| group.name)
+    }
+
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Get the UID of the user who requested elevation (pkexec or sudo)
+pub fn invoking_uid() -> Option<u32> {
+    std::env::var("PKEXEC_UID")
+        .or_else(|_| std::env::var("SUDO_UID"))
+        .ok()
+        .and_then(|uid| uid.parse().ok())
+}
+
+/// Hand ownership of a path back to the user who requested elevation
+///
+/// This is a no-op unless running as root on behalf of another user.
+#[cfg(unix)]
+pub fn chown_to_invoking_user(path: &Path) -> IntResult<()> {
+    use nix::unistd::{chown, Uid, User};
+
+    if !crate::security::has_root_privileges() {
+        return Ok(());
+    }
+
+    let uid = match invoking_uid() {
+        Some(uid) => Uid::from_raw(uid),
+        None => return Ok(()),
+    };
+
+    let gid = User::from_uid(uid)
+        .map_err(|e| IntError::UserLookupError(e.to_string()))?
+        .map(|user| user.gid);
+
+    chown(path, Some(uid), gid).map_err(|e| {
+        IntError::PermissionError(format!(
+            "Failed to change owner of {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+#[cfg(not(unix))]
+pub fn chown_to_invoking_user(_path: &Path) -> IntResult<()> {
+    Ok(()) // No-op on non-Unix platforms
+}
+
+/// Refresh the dynamic linker cache
+///
+/// Errors are ignored since ldconfig may be unavailable (e.g. in containers).
+pub fn run_ldconfig() {
+    if crate::root::is_set() {
+        let _ = crate::root::defer("ldconfig");
+        return;
+    }
+    let _ = std::process::Command::new("ldconfig").output();
+}
+
+/// Refresh the man page index
+///
+/// Errors are ignored since mandb is optional; man finds new pages without it.
+pub fn run_mandb() {
+    if crate::root::is_set() {
+        let _ = crate::root::defer("mandb --quiet");
+        return;
+    }
+    let _ = std::process::Command::new("mandb").arg("--quiet").output();
+}
+
+/// Wait for a child process, killing it once the timeout expires
+///
+/// Returns `None` if the process had to be killed.
+pub fn wait_with_timeout(
+    child: &mut std::process::Child,
+    timeout: std::time::Duration,
+) -> std::io::Result<Option<std::process::ExitStatus>> {
+    let deadline = std::time::Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
+/// Make a command start its process in a session of its own
+///
+/// The process is then not tied to our terminal or process group, and keeps
+/// running when we exit.
+#[cfg(unix)]
+pub fn detach(command: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+
+    // Safety: setsid is async-signal-safe and nothing is allocated
+    unsafe {
+        command.pre_exec(|| {
+            nix::unistd::setsid()
+                .map(|_| ())
+                .map_err(std::io::Error::from)
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn detach(_command: &mut std::process::Command) {}
+
+/// Make a command start its process in a new process group
+///
+/// [`kill_process_group`] can then stop it together with everything it
+/// started.
+#[cfg(unix)]
+pub fn new_process_group(command: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+}
+
+#[cfg(not(unix))]
+pub fn new_process_group(_command: &mut std::process::Command) {}
+
+/// Kill a child started with [`new_process_group`] and the rest of its group
+#[cfg(unix)]
+pub fn kill_process_group(child: &mut std::process::Child) {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+    let _ = child.kill();
+}
+
+#[cfg(not(unix))]
+pub fn kill_process_group(child: &mut std::process::Child) {
+    let _ = child.kill();
+}
+
 /// Ensure directory exists with proper permissions
 pub fn ensure_dir(path: &Path) -> IntResult<()> {
     if path.exists() {
@@ -259,13 +490,112 @@ pub fn ensure_dir(path: &Path) -> IntResult<()> {
         return Ok(());
     }
 
+    let missing: Vec<PathBuf> = path
+        .ancestors()
+        .take_while(|p| !p.as_os_str().is_empty() && !p.exists())
+        .map(Path::to_path_buf)
+        .collect();
+
     fs::create_dir_all(path).map_err(|e| {
         IntError::DirectoryCreationFailed(format!(
             "Failed to create directory {}: {}",
             path.display(),
             e
         ))
-    })
+    })?;
+
+    CREATED_DIRS.with(|created| {
+        if let Some(ref mut created) = *created.borrow_mut() {
+            created.extend(missing);
+        }
+    });
+    Ok(())
+}
+
+/// Records the directories `ensure_dir` creates on this thread
+///
+/// Tracking stops when the tracker is finished or dropped.
+pub struct DirTracker(());
+
+impl DirTracker {
+    /// Start recording created directories
+    pub fn start() -> Self {
+        CREATED_DIRS.with(|created| *created.borrow_mut() = Some(Vec::new()));
+        DirTracker(())
+    }
+
+    /// Stop recording and return the created directories, parents first
+    pub fn finish(self) -> Vec<PathBuf> {
+        let mut dirs = CREATED_DIRS
+            .with(|created| created.borrow_mut().take())
+            .unwrap_or_default();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+}
+
+impl Drop for DirTracker {
+    fn drop(&mut self) {
+        CREATED_DIRS.with(|created| *created.borrow_mut() = None);
+    }
+}
+
+/// Path of the backup `write_atomic` keeps of a file's previous contents
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Replace a file's contents without ever leaving it half written
+///
+/// The contents go to a temporary file in the same directory, which is
+/// synced and then renamed over the target. The previous version is kept at
+/// `backup_path` for recovery.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> IntResult<()> {
+    use std::io::Write;
+
+    let write_error =
+        |e: std::io::Error| IntError::Custom(format!("Failed to write {}: {}", path.display(), e));
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut file = fs::File::create(&temp).map_err(write_error)?;
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            write_error(e)
+        })?;
+
+    if path.exists() {
+        fs::copy(path, backup_path(path)).map_err(write_error)?;
+    }
+    fs::rename(&temp, path).map_err(write_error)?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove directories that are empty, deepest first
+///
+/// Directories that still contain anything are left alone.
+pub fn remove_empty_dirs(dirs: &[PathBuf]) {
+    let mut dirs: Vec<&PathBuf> = dirs.iter().collect();
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    for dir in dirs {
+        let _ = fs::remove_dir(dir);
+    }
 }
 
 #[cfg(test)]
@@ -275,6 +605,23 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_write_atomic() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("app.json");
+
+        write_atomic(&path, b"first").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert!(!backup_path(&path).exists());
+
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "first");
+
+        // Only the target and its backup are left behind
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 2);
+    }
+
     #[test]
     fn test_copy_dir_recursive() {
         let temp = TempDir::new().unwrap();
@@ -312,6 +659,19 @@ mod tests {
         assert_eq!(format_bytes(1_073_741_824), "1.00 GB");
     }
 
+    #[test]
+    fn test_compare_versions() {
+        use std::cmp::Ordering::*;
+
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Equal);
+        assert_eq!(compare_versions("v2.0.0", "2.0.0+build.5"), Equal);
+        assert_eq!(compare_versions("1.0.0-rc1", "1.0.0"), Less);
+        assert_eq!(compare_versions("1.0.0-rc.2", "1.0.0-rc.10"), Less);
+        assert_eq!(compare_versions("0.9.9", "1.0.0-beta"), Less);
+        assert_eq!(compare_versions("2024.1b", "2024.1a"), Greater);
+    }
+
     #[test]
     fn test_ensure_dir() {
         let temp = TempDir::new().unwrap();
@@ -325,6 +685,47 @@ mod tests {
         ensure_dir(&dir).unwrap();
     }
 
+    #[test]
+    fn test_dir_tracker() {
+        let temp = TempDir::new().unwrap();
+        let tracker = DirTracker::start();
+        ensure_dir(&temp.path().join("a/b")).unwrap();
+        ensure_dir(&temp.path().join("a/c")).unwrap();
+        let created = tracker.finish();
+        assert_eq!(
+            created,
+            vec![
+                temp.path().join("a"),
+                temp.path().join("a/b"),
+                temp.path().join("a/c")
+            ]
+        );
+
+        // Untracked directories are not recorded
+        ensure_dir(&temp.path().join("d")).unwrap();
+        assert!(DirTracker::start().finish().is_empty());
+
+        fs::write(temp.path().join("a/c/file"), "").unwrap();
+        remove_empty_dirs(&created);
+        assert!(!temp.path().join("a/b").exists());
+        assert!(temp.path().join("a/c").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_wait_with_timeout() {
+        use std::process::Command;
+        use std::time::Duration;
+
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let status = wait_with_timeout(&mut child, Duration::from_millis(100)).unwrap();
+        assert!(status.is_none());
+
+        let mut child = Command::new("true").spawn().unwrap();
+        let status = wait_with_timeout(&mut child, Duration::from_secs(5)).unwrap();
+        assert!(status.unwrap().success());
+    }
+
     #[test]
     #[cfg(unix)]
     fn test_make_executable() {
@@ -1,10 +1,24 @@
+use crate::operations::{OperationKind, OperationStatus};
+use crate::progress::{install_event, log_event};
 use crate::state::AppState;
+use int_core::repair::{self, RepairReport};
+use int_core::updates::{self, UpdateReport};
 use int_core::{
-    InstallConfig, InstallProgress, InstallScope, Installer, PackageExtractor, Uninstaller,
+    history, utils, EntryKind, InitSystem, InstallConfig, InstallMetadata, InstallPlan,
+    InstallScope, Installer, LaunchCommand, Manifest, PackageExtractor, RestartPolicy,
+    ServiceManager, ServiceStatus, SignatureInfo, Transaction, UninstallProgress, UninstallReport,
+    Uninstaller,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use tauri::{Emitter, State, WebviewWindow};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+/// How long a launched application is watched for exiting with an error
+const LAUNCH_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Lines of error output reported when a launch fails
+const LAUNCH_ERROR_LINES: usize = 20;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PackageInfo {
@@ -17,7 +31,19 @@ pub struct PackageInfo {
     pub install_scope: String,
     pub install_path: String,
     pub auto_launch: bool,
-    pub launch_command: Option<String>,
+    pub launch_command: Option<LaunchCommand>,
+    pub changelog: Option<String>,
+    pub installed_version: Option<String>,
+    pub has_service: bool,
+    pub installed_size: Option<u64>,
+    pub installed_size_display: Option<String>,
+    /// When the package was installed (RFC 3339), for installed packages
+    pub install_date: Option<String>,
+    pub service_name: Option<String>,
+    /// Held at its version (see `int-engine hold`)
+    pub held: bool,
+    /// Manifest stored with the installed version
+    pub manifest: Option<Manifest>,
 }
 
 #[tauri::command]
@@ -32,6 +58,11 @@ pub async fn validate_package(
         .validate_package(&path)
         .map_err(|e| format!("Validation error: {}", e))?;
 
+    // Refuse packages whose services cannot run here before showing the wizard
+    manifest
+        .check_init_system(int_core::platform::init_system())
+        .map_err(|e| e.to_string())?;
+
     let info = PackageInfo {
         name: manifest.name.clone(),
         display_name: manifest.display_name().to_string(),
@@ -42,7 +73,18 @@ pub async fn validate_package(
         install_scope: format!("{:?}", manifest.install_scope),
         install_path: manifest.install_path.to_string_lossy().to_string(),
         auto_launch: manifest.auto_launch,
-        launch_command: manifest.launch_command.clone(),
+        launch_command: manifest.launch_command(),
+        changelog: extractor.read_changelog(&path).unwrap_or_default(),
+        installed_version: InstallMetadata::load(&manifest.name, manifest.install_scope)
+            .ok()
+            .map(|m| m.package_version),
+        has_service: manifest.service,
+        installed_size: None,
+        installed_size_display: None,
+        install_date: None,
+        service_name: None,
+        held: false,
+        manifest: None,
     };
 
     let mut current = state.current_manifest.lock().unwrap();
@@ -51,230 +93,893 @@ pub async fn validate_package(
     Ok(info)
 }
 
+/// Entry of a package as previewed before installing
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EntryPreview {
+    /// Path inside the package
+    pub path: String,
+    /// Where the entry is installed, for payload entries
+    pub destination: Option<String>,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub size_display: String,
+    pub mode: u32,
+    pub link_target: Option<String>,
+}
+
+/// List what a package contains and where it will be installed
+///
+/// Destinations are below `install_path` if given, the manifest's install
+/// path otherwise.
+#[tauri::command]
+pub async fn list_entries(
+    path: String,
+    install_path: Option<String>,
+) -> Result<Vec<EntryPreview>, String> {
+    let extractor = PackageExtractor::new();
+    let install_path = match install_path {
+        Some(install_path) => PathBuf::from(install_path),
+        None => {
+            extractor
+                .validate_package(&path)
+                .map_err(|e| format!("Validation error: {}", e))?
+                .install_path
+        }
+    };
+
+    let entries = extractor
+        .list_entries(&path)
+        .map_err(|e| format!("Failed to list package contents: {}", e))?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| EntryPreview {
+            path: entry.path.to_string_lossy().to_string(),
+            destination: entry
+                .destination(&install_path)
+                .map(|destination| destination.to_string_lossy().to_string()),
+            kind: entry.kind,
+            size: entry.size,
+            size_display: utils::format_bytes(entry.size),
+            mode: entry.mode,
+            link_target: entry
+                .link_target
+                .map(|target| target.to_string_lossy().to_string()),
+        })
+        .collect())
+}
+
+/// Verify the signature of a package for the confirmation screen
+#[tauri::command]
+pub async fn get_signature_info(path: String) -> Result<SignatureInfo, String> {
+    int_core::signature::inspect(&path).map_err(|e| format!("Failed to check signature: {}", e))
+}
+
+/// Installation options chosen in the GUI, as far as they change the plan
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PlanOptions {
+    pub install_path: Option<String>,
+    pub create_desktop_entry: bool,
+    pub allow_downgrade: bool,
+    pub force: bool,
+    pub eula_accepted: bool,
+}
+
+impl Default for PlanOptions {
+    fn default() -> Self {
+        Self {
+            install_path: None,
+            create_desktop_entry: true,
+            allow_downgrade: false,
+            force: false,
+            eula_accepted: false,
+        }
+    }
+}
+
+/// Report what installing a package would do, for the review step
+#[tauri::command]
+pub async fn plan_install(
+    path: String,
+    config: Option<PlanOptions>,
+) -> Result<InstallPlan, String> {
+    let options = config.unwrap_or_default();
+    let config = InstallConfig {
+        install_path: options.install_path.map(PathBuf::from),
+        create_desktop_entry: options.create_desktop_entry,
+        allow_downgrade: options.allow_downgrade,
+        force: options.force,
+        eula_accepted: options.eula_accepted,
+        ..InstallConfig::default()
+    };
+
+    Installer::new()
+        .plan(&path, &config)
+        .map_err(|e| format!("Failed to plan installation: {}", e))
+}
+
+/// License agreement the user has to accept before installing, if any
+#[tauri::command]
+pub async fn get_eula(path: String) -> Result<Option<String>, String> {
+    PackageExtractor::new()
+        .read_eula(&path)
+        .map_err(|e| format!("Failed to read license agreement: {}", e))
+}
+
+/// Start installing a package in the background and return the operation ID
+///
+/// Progress is emitted to all windows and recorded for
+/// `get_operation_status`; an `operation-finished` event reports the outcome.
+/// `eula_accepted` tells that the user accepted the package's license
+/// agreement, without which packages declaring one are refused.
 #[tauri::command]
 pub async fn install_package(
-    window: WebviewWindow,
+    app: AppHandle,
     path: String,
     install_path: Option<String>,
     start_service: bool,
     scope: String,
-) -> Result<(), String> {
+    eula_accepted: Option<bool>,
+) -> Result<u64, String> {
+    let eula_accepted = eula_accepted.unwrap_or(false);
     let install_scope = match scope.as_str() {
         "system" => InstallScope::System,
         _ => InstallScope::User,
     };
 
-    // Check if we need elevation
-    if install_scope == InstallScope::System && !int_core::security::has_root_privileges() {
-        let _ = window.emit("install-log", serde_json::json!({ "message": "Elevation required for system installation. Requesting via pkexec..." }));
+    let package = path.clone();
+    Ok(spawn_operation(
+        app,
+        OperationKind::Install,
+        &package,
+        move |app, id| {
+            // System installs are carried out by an elevated copy of the CLI
+            if install_scope == InstallScope::System && !int_core::security::has_root_privileges() {
+                return install_elevated(
+                    app,
+                    id,
+                    &path,
+                    install_path.as_deref(),
+                    start_service,
+                    eula_accepted,
+                );
+            }
+            install(app, id, &path, install_path, start_service, eula_accepted)
+        },
+    ))
+}
+
+/// Install a package in this process
+fn install(
+    app: &AppHandle,
+    id: u64,
+    path: &str,
+    install_path: Option<String>,
+    start_service: bool,
+    eula_accepted: bool,
+) -> Result<(), String> {
+    let path_buf = PathBuf::from(path);
+    let config = InstallConfig {
+        install_path: install_path.map(PathBuf::from),
+        start_service,
+        create_desktop_entry: true,
+        dry_run: false,
+        restart_policy: RestartPolicy::Restart,
+        service_preset: None,
+        as_dependency: false,
+        allow_downgrade: false,
+        force: false,
+        eula_accepted,
+    };
+
+    let app = app.clone();
+    let installer = Installer::new().with_progress(move |progress| {
+        let (event_name, payload) = install_event(progress);
+        emit_progress(&app, id, event_name, payload);
+    });
 
-        let current_exe = std::env::current_exe()
-            .map_err(|e| format!("Failed to get current executable: {}", e))?;
+    installer
+        .install(&path_buf, config)
+        .map_err(|e| format!("Installation failed: {}", e))?;
 
-        let mut cmd = std::process::Command::new("pkexec");
-        cmd.arg(current_exe).arg(&path).arg("--scope").arg("system");
+    Ok(())
+}
 
-        if let Some(ref p) = install_path {
-            cmd.arg("--install-path").arg(p);
+/// Install a system-scope package through pkexec
+///
+/// The CLI runs as root with `--json`, so its progress events are passed on
+/// as if the install ran here. The installation was already confirmed in the
+/// GUI, hence `--yes`.
+fn install_elevated(
+    app: &AppHandle,
+    id: u64,
+    path: &str,
+    install_path: Option<&str>,
+    start_service: bool,
+    eula_accepted: bool,
+) -> Result<(), String> {
+    use std::io::{BufRead, BufReader};
+
+    emit_progress(
+        app,
+        id,
+        "install-log",
+        log_event(
+            "installer",
+            "Elevation required for system installation. Requesting via pkexec...",
+        ),
+    );
+
+    let current_exe =
+        std::env::current_exe().map_err(|e| format!("Failed to get current executable: {}", e))?;
+
+    let mut cmd = std::process::Command::new("pkexec");
+    cmd.arg(current_exe)
+        .arg(path)
+        .args(["--scope", "system", "--yes", "--json"]);
+    if let Some(install_path) = install_path {
+        cmd.arg("--install-path").arg(install_path);
+    }
+    if start_service {
+        cmd.arg("--start-service");
+    }
+    if eula_accepted {
+        cmd.arg("--accept-eula");
+    }
+    cmd.stdout(std::process::Stdio::piped());
+    cmd.stderr(std::process::Stdio::piped());
+
+    let mut child = cmd.spawn().map_err(|e| {
+        format!(
+            "Failed to execute pkexec: {}. Make sure PolicyKit is installed.",
+            e
+        )
+    })?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    // Progress events, one JSON object per line
+    let events_app = app.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let event: Option<(String, serde_json::Value)> =
+                serde_json::from_str::<serde_json::Value>(&line)
+                    .ok()
+                    .and_then(|mut value| {
+                        let name = value.get("event")?.as_str()?.to_string();
+                        Some((name, value.get_mut("payload")?.take()))
+                    });
+            match event {
+                Some((name, payload)) => emit_progress(&events_app, id, &name, payload),
+                None => emit_progress(&events_app, id, "install-log", log_event("installer", line)),
+            }
         }
+    });
 
-        if start_service {
-            cmd.arg("--start-service");
+    // Warnings, and the error the install failed with
+    let log_app = app.clone();
+    let errors = std::thread::spawn(move || {
+        let mut error = None;
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            let reported = serde_json::from_str::<serde_json::Value>(&line)
+                .ok()
+                .and_then(|value| Some(value.get("error")?.get("message")?.as_str()?.to_string()));
+            let message = reported.clone().unwrap_or_else(|| line.clone());
+            emit_progress(
+                &log_app,
+                id,
+                "install-log",
+                log_event("installer", format!("Error: {}", message)),
+            );
+            error = reported.or(error);
         }
+        error
+    });
 
-        // Set pipe for stdout/stderr to capture logs
-        cmd.stdout(std::process::Stdio::piped());
-        cmd.stderr(std::process::Stdio::piped());
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for pkexec: {}", e))?;
+    let error = errors.join().ok().flatten();
+
+    // pkexec exits with 126 when the authentication dialog is dismissed and
+    // 127 when authorization fails; other codes are the installer's
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(126) => Err("Authentication was cancelled".to_string()),
+        Some(127) => Err(
+            "Not authorized to install system-wide. Check that PolicyKit is set up.".to_string(),
+        ),
+        _ => Err(match error {
+            Some(error) => format!("Installation failed: {}", error),
+            None => {
+                "Installation with elevated privileges failed. Check logs for details.".to_string()
+            }
+        }),
+    }
+}
 
-        let mut child = cmd.spawn().map_err(|e| {
-            format!(
-                "Failed to execute pkexec: {}. Make sure PolicyKit is installed.",
-                e
-            )
-        })?;
+/// List installed packages of a scope, or of both scopes with "all"
+///
+/// Sorted by name, or largest first with `sort` set to "size".
+#[tauri::command]
+pub async fn list_installed(
+    scope: String,
+    sort: Option<String>,
+) -> Result<Vec<PackageInfo>, String> {
+    let uninstaller = Uninstaller::new();
+    let packages: Vec<(InstallMetadata, InstallScope)> = match scope.as_str() {
+        "all" => uninstaller.list_all_installed().map(|packages| {
+            packages
+                .into_iter()
+                .map(|p| (p.metadata, p.scope))
+                .collect()
+        }),
+        _ => {
+            let scope = match scope.as_str() {
+                "system" => InstallScope::System,
+                _ => InstallScope::User,
+            };
+            uninstaller
+                .list_installed(scope)
+                .map(|packages| packages.into_iter().map(|p| (p, scope)).collect())
+        }
+    }
+    .map_err(|e| format!("Failed to list packages: {}", e))?;
 
-        // Handle stdout/stderr in separate threads to emit logs
-        let stdout = child.stdout.take().unwrap();
-        let stderr = child.stderr.take().unwrap();
+    let mut packages: Vec<_> = packages
+        .into_iter()
+        .map(|(p, scope)| (p.size(), p, scope))
+        .collect();
+    match sort.as_deref() {
+        Some("size") => packages.sort_by_key(|(size, _, _)| std::cmp::Reverse(*size)),
+        _ => packages.sort_by(|(_, a, _), (_, b, _)| a.package_name.cmp(&b.package_name)),
+    }
 
-        let window_clone = window.clone();
-        std::thread::spawn(move || {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stdout);
-            for line in reader.lines() {
-                if let Ok(l) = line {
-                    let _ = window_clone.emit("install-log", serde_json::json!({ "message": l }));
-                }
+    Ok(packages
+        .into_iter()
+        .map(|(size, p, scope)| {
+            let manifest = p.manifest.as_ref();
+            PackageInfo {
+                name: p.package_name.clone(),
+                display_name: p.display_name().to_string(),
+                version: p.package_version.clone(),
+                description: manifest
+                    .and_then(|m| m.description.clone())
+                    .unwrap_or_default(),
+                author: manifest.and_then(|m| m.author.clone()).unwrap_or_default(),
+                license: manifest.and_then(|m| m.license.clone()).unwrap_or_default(),
+                install_scope: format!("{:?}", scope),
+                install_path: p.install_path.to_string_lossy().to_string(),
+                auto_launch: false,
+                launch_command: manifest.and_then(|m| m.launch_command()),
+                changelog: None,
+                installed_version: Some(p.package_version.clone()),
+                has_service: p.service_name.is_some(),
+                installed_size: Some(size),
+                installed_size_display: Some(utils::format_bytes(size)),
+                install_date: Some(p.install_date.clone()),
+                service_name: p.service_name.clone(),
+                held: p.held,
+                manifest: p.manifest.clone(),
             }
-        });
+        })
+        .collect())
+}
 
-        let window_clone2 = window.clone();
-        std::thread::spawn(move || {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                if let Ok(l) = line {
-                    let _ = window_clone2.emit(
-                        "install-log",
-                        serde_json::json!({ "message": format!("Error: {}", l) }),
-                    );
-                }
-            }
-        });
+/// Search installed packages, best matches first
+///
+/// Every word of the query must match the name, display name, description
+/// or a desktop category of a package. Matches on the name rank above
+/// matches on the display name, categories and description, in that order.
+#[tauri::command]
+pub async fn search_installed(query: String, scope: String) -> Result<Vec<PackageInfo>, String> {
+    let packages = list_installed(scope, None).await?;
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
 
-        let status = child
-            .wait()
-            .map_err(|e| format!("Failed to wait for pkexec: {}", e))?;
+    let mut ranked: Vec<(u32, PackageInfo)> = packages
+        .into_iter()
+        .filter_map(|package| Some((search_rank(&package, &terms)?, package)))
+        .collect();
+    // Ties keep the alphabetical order of list_installed
+    ranked.sort_by_key(|(rank, _)| std::cmp::Reverse(*rank));
+    Ok(ranked.into_iter().map(|(_, package)| package).collect())
+}
 
-        if !status.success() {
-            return Err(
-                "Installation with elevated privileges failed. Check logs for details.".to_string(),
-            );
-        }
+/// Rank of a package for search terms, `None` if a term doesn't match
+fn search_rank(package: &PackageInfo, terms: &[String]) -> Option<u32> {
+    let name = package.name.to_lowercase();
+    let display_name = package.display_name.to_lowercase();
+    let description = package.description.to_lowercase();
+    let categories: Vec<String> = package
+        .manifest
+        .as_ref()
+        .and_then(|manifest| manifest.desktop.as_ref())
+        .map(|desktop| {
+            desktop
+                .categories
+                .iter()
+                .map(|c| c.to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    terms.iter().try_fold(0, |rank, term| {
+        let term_rank = if name == *term {
+            100
+        } else if name.starts_with(term.as_str()) {
+            60
+        } else if name.contains(term.as_str()) {
+            40
+        } else if display_name.starts_with(term.as_str()) {
+            50
+        } else if display_name.contains(term.as_str()) {
+            30
+        } else if categories.iter().any(|category| category == term) {
+            20
+        } else if description.contains(term.as_str()) {
+            10
+        } else {
+            return None;
+        };
+        Some(rank + term_rank)
+    })
+}
 
-        let _ = window.emit("install-progress-completed", serde_json::json!({}));
-        return Ok(());
+/// Restore missing or modified files of an installed package from the
+/// cached copy of its package
+#[tauri::command]
+pub async fn repair_package(name: String, scope: String) -> Result<RepairReport, String> {
+    let scope = match scope.as_str() {
+        "system" => InstallScope::System,
+        _ => InstallScope::User,
+    };
+
+    repair::repair(&name, scope, false).map_err(|e| format!("Failed to repair {}: {}", name, e))
+}
+
+/// Check installed packages for newer versions in their update feeds
+#[tauri::command]
+pub async fn check_updates(scope: String) -> Result<UpdateReport, String> {
+    let scopes = match scope.as_str() {
+        "all" => vec![InstallScope::User, InstallScope::System],
+        "system" => vec![InstallScope::System],
+        _ => vec![InstallScope::User],
+    };
+
+    let mut report = UpdateReport::default();
+    for scope in scopes {
+        let checked =
+            updates::check(scope).map_err(|e| format!("Failed to check for updates: {}", e))?;
+        report.updates.extend(checked.updates);
+        report.failed.extend(checked.failed);
     }
+    for (package, error) in &report.failed {
+        tracing::warn!("Update check failed for {}: {}", package, error);
+    }
+    Ok(report)
+}
 
-    let path_buf = PathBuf::from(path);
-    let config = InstallConfig {
-        install_path: install_path.map(PathBuf::from),
-        start_service,
-        create_desktop_entry: true,
-        dry_run: false,
+/// Start uninstalling a package in the background and return the operation ID
+#[tauri::command]
+pub async fn uninstall_package(app: AppHandle, name: String, scope: String) -> Result<u64, String> {
+    let scope = match scope.as_str() {
+        "system" => InstallScope::System,
+        _ => InstallScope::User,
     };
 
-    let installer = Installer::new().with_progress(move |progress| {
+    let package = name.clone();
+    Ok(spawn_operation(
+        app,
+        OperationKind::Uninstall,
+        &package,
+        move |app, id| uninstall(app, id, &name, scope),
+    ))
+}
+
+/// Uninstall a package in this process
+fn uninstall(app: &AppHandle, id: u64, name: &str, scope: InstallScope) -> Result<(), String> {
+    let app = app.clone();
+    let uninstaller = Uninstaller::new().with_progress(move |progress| {
         let event_name = match progress {
-            InstallProgress::Extracting { .. } => "install-progress-extracting",
-            InstallProgress::CopyingFiles { .. } => "install-progress-copying",
-            InstallProgress::SettingPermissions => "install-progress-permissions",
-            InstallProgress::ExecutingScript { .. } => "install-progress-script",
-            InstallProgress::RegisteringService => "install-progress-service",
-            InstallProgress::CreatingDesktopEntry => "install-progress-desktop",
-            InstallProgress::Finalizing => "install-progress-finalizing",
-            InstallProgress::Log { .. } => "install-log",
-            InstallProgress::Completed => "install-progress-completed",
+            UninstallProgress::ExecutingScript { .. } => "uninstall-progress-script",
+            UninstallProgress::StoppingService => "uninstall-progress-service",
+            UninstallProgress::RemovingIntegration => "uninstall-progress-integration",
+            UninstallProgress::RemovingFiles { .. } => "uninstall-progress-files",
+            UninstallProgress::Completed => "uninstall-progress-completed",
         };
 
         let payload = match progress {
-            InstallProgress::Extracting { current, total } => {
-                serde_json::json!({ "current": current, "total": total })
+            UninstallProgress::ExecutingScript { script } => {
+                serde_json::json!({ "script": script })
             }
-            InstallProgress::CopyingFiles { current, total } => {
+            UninstallProgress::RemovingFiles { current, total } => {
                 serde_json::json!({ "current": current as u64, "total": total as u64 })
             }
-            InstallProgress::Log { message } => {
-                serde_json::json!({ "message": message })
-            }
             _ => serde_json::json!({}),
         };
 
-        let _ = window.emit(event_name, payload);
+        emit_progress(&app, id, event_name, payload);
     });
-
-    installer
-        .install(&path_buf, config)
-        .map_err(|e| format!("Installation failed: {}", e))?;
+    uninstaller
+        .uninstall(name, scope)
+        .map_err(|e| format!("Uninstallation failed: {}", e))?;
 
     Ok(())
 }
 
+/// Run an install or uninstall on its own thread and return its ID
+///
+/// The final status is emitted as `operation-finished`.
+fn spawn_operation<F>(app: AppHandle, kind: OperationKind, package: &str, run: F) -> u64
+where
+    F: FnOnce(&AppHandle, u64) -> Result<(), String> + Send + 'static,
+{
+    let id = app.state::<AppState>().operations.start(kind, package);
+    std::thread::spawn(move || {
+        let result = run(&app, id);
+        if let Some(status) = app.state::<AppState>().operations.finish(id, &result) {
+            let _ = app.emit("operation-finished", status);
+        }
+    });
+    id
+}
+
+/// Emit a progress event to all windows, recording it for the operation
+fn emit_progress(app: &AppHandle, id: u64, event: &str, payload: serde_json::Value) {
+    app.state::<AppState>()
+        .operations
+        .record(id, event, &payload);
+    let _ = app.emit(event, payload);
+}
+
+/// Status of an install or uninstall started in this session
 #[tauri::command]
-pub async fn list_installed(scope: String) -> Result<Vec<PackageInfo>, String> {
+pub async fn get_operation_status(
+    state: State<'_, AppState>,
+    id: u64,
+) -> Result<OperationStatus, String> {
+    state
+        .operations
+        .get(id)
+        .ok_or_else(|| format!("Unknown operation: {}", id))
+}
+
+/// Installs and uninstalls started in this session, oldest first
+///
+/// Only the most recent finished operations are remembered.
+#[tauri::command]
+pub async fn list_operations(state: State<'_, AppState>) -> Result<Vec<OperationStatus>, String> {
+    Ok(state.operations.list())
+}
+
+/// Report what uninstalling a package would remove, for confirmation
+#[tauri::command]
+pub async fn uninstall_preview(
+    name: String,
+    scope: String,
+    purge: bool,
+) -> Result<UninstallReport, String> {
     let scope = match scope.as_str() {
         "system" => InstallScope::System,
         _ => InstallScope::User,
     };
 
-    let uninstaller = Uninstaller::new();
-    let packages = uninstaller
-        .list_installed(scope)
-        .map_err(|e| format!("Failed to list packages: {}", e))?;
+    Uninstaller::new()
+        .dry_run(&name, scope, purge)
+        .map_err(|e| format!("Failed to preview uninstallation: {}", e))
+}
 
-    Ok(packages
-        .into_iter()
-        .map(|p| PackageInfo {
-            name: p.package_name.clone(),
-            display_name: p.package_name,
-            version: p.package_version,
-            description: String::new(),
-            author: String::new(),
-            license: String::new(),
-            install_scope: format!("{:?}", scope),
-            install_path: String::new(),
-            auto_launch: false,
-            launch_command: None,
-        })
-        .collect())
+/// Past installs, upgrades and uninstalls, most recent first
+#[tauri::command]
+pub async fn transaction_history(scope: String) -> Result<Vec<Transaction>, String> {
+    let scope = match scope.as_str() {
+        "system" => InstallScope::System,
+        _ => InstallScope::User,
+    };
+
+    let mut transactions =
+        history::list(scope).map_err(|e| format!("Failed to read history: {}", e))?;
+    transactions.reverse();
+    Ok(transactions)
 }
 
+/// Undo a transaction from the history
 #[tauri::command]
-pub async fn uninstall_package(name: String, scope: String) -> Result<(), String> {
+pub async fn rollback_transaction(id: String, scope: String) -> Result<(), String> {
     let scope = match scope.as_str() {
         "system" => InstallScope::System,
         _ => InstallScope::User,
     };
 
-    let uninstaller = Uninstaller::new();
-    uninstaller
-        .uninstall(&name, scope)
-        .map_err(|e| format!("Uninstallation failed: {}", e))?;
+    history::rollback(scope, &id).map_err(|e| format!("Rollback failed: {}", e))
+}
 
-    Ok(())
+/// Look up the service of an installed package
+fn installed_service(name: &str, scope: InstallScope) -> Result<(ServiceManager, String), String> {
+    let metadata = InstallMetadata::load(name, scope)
+        .map_err(|e| format!("Failed to load package metadata: {}", e))?;
+    let service_name = metadata
+        .service_name
+        .ok_or_else(|| format!("Package {} has no service", name))?;
+    let init_system = metadata.init_system.unwrap_or_else(InitSystem::detect);
+
+    Ok((ServiceManager::with_init_system(init_system), service_name))
+}
+
+#[tauri::command]
+pub async fn service_status(name: String, scope: String) -> Result<ServiceStatus, String> {
+    let scope = match scope.as_str() {
+        "system" => InstallScope::System,
+        _ => InstallScope::User,
+    };
+
+    let (manager, service_name) = installed_service(&name, scope)?;
+    manager
+        .status(&service_name, scope)
+        .map_err(|e| format!("Failed to get service status: {}", e))
+}
+
+/// Start the service of an installed package, returning its new status
+#[tauri::command]
+pub async fn service_start(name: String, scope: String) -> Result<ServiceStatus, String> {
+    service_action(&name, &scope, "start", ServiceManager::start)
+}
+
+/// Stop the service of an installed package, returning its new status
+#[tauri::command]
+pub async fn service_stop(name: String, scope: String) -> Result<ServiceStatus, String> {
+    service_action(&name, &scope, "stop", ServiceManager::stop)
 }
 
+/// Restart the service of an installed package, returning its new status
 #[tauri::command]
-pub async fn launch_app(command: String, install_path: String) -> Result<(), String> {
-    let install_path = std::path::PathBuf::from(install_path);
-
-    // Command can be absolute or relative to install_path/bin
-    let full_command = if std::path::Path::new(&command).is_absolute() {
-        std::path::PathBuf::from(&command)
-    } else {
-        install_path.join("bin").join(&command)
+pub async fn service_restart(name: String, scope: String) -> Result<ServiceStatus, String> {
+    service_action(&name, &scope, "restart", ServiceManager::restart)
+}
+
+fn service_action(
+    name: &str,
+    scope: &str,
+    action: &str,
+    run: fn(&ServiceManager, &str, InstallScope) -> int_core::IntResult<()>,
+) -> Result<ServiceStatus, String> {
+    let scope = match scope {
+        "system" => InstallScope::System,
+        _ => InstallScope::User,
+    };
+
+    let (manager, service_name) = installed_service(name, scope)?;
+    run(&manager, &service_name, scope)
+        .map_err(|e| format!("Failed to {} service: {}", action, e))?;
+    manager
+        .status(&service_name, scope)
+        .map_err(|e| format!("Failed to get service status: {}", e))
+}
+
+#[tauri::command]
+pub async fn service_logs(
+    name: String,
+    scope: String,
+    lines: usize,
+) -> Result<Vec<String>, String> {
+    let scope = match scope.as_str() {
+        "system" => InstallScope::System,
+        _ => InstallScope::User,
     };
 
-    if !full_command.exists() {
-        return Err(format!(
-            "Launch command not found: {}",
-            full_command.display()
-        ));
+    let (manager, service_name) = installed_service(&name, scope)?;
+    manager
+        .logs(&service_name, scope, lines)
+        .map_err(|e| format!("Failed to read service logs: {}", e))
+}
+
+/// Launch an installed application in a session of its own
+///
+/// `args` are appended to the arguments of the launch command and `env` is
+/// set on top of its environment. If the application exits with an error
+/// right away, a `launch-failed` event reports the exit code and the end of
+/// its error output.
+#[tauri::command]
+pub async fn launch_app(
+    window: WebviewWindow,
+    command: LaunchCommand,
+    install_path: String,
+    args: Option<Vec<String>>,
+    env: Option<BTreeMap<String, String>>,
+) -> Result<(), String> {
+    use std::collections::VecDeque;
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::Instant;
+
+    let install_path = PathBuf::from(install_path);
+
+    // Program can be absolute or relative to install_path/bin
+    let program = command.resolve_program(&install_path);
+    if !program.exists() {
+        return Err(format!("Launch command not found: {}", program.display()));
     }
 
-    std::process::Command::new(full_command)
-        .current_dir(install_path)
+    let mut cmd = command.to_command(&install_path);
+    cmd.args(args.unwrap_or_default())
+        .envs(env.unwrap_or_default())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    utils::detach(&mut cmd);
+    let mut child = cmd
         .spawn()
         .map_err(|e| format!("Failed to launch application: {}", e))?;
+    let deadline = Instant::now() + LAUNCH_GRACE_PERIOD;
+
+    // The last lines of error output are kept during the grace period; after
+    // that the output is read and dropped while the installer runs, so the
+    // application never blocks on a full pipe and nothing piles up on disk
+    let tail = Arc::new(Mutex::new(VecDeque::new()));
+    let (eof_tx, eof_rx) = mpsc::channel::<()>();
+    if let Some(stderr) = child.stderr.take() {
+        let tail = Arc::clone(&tail);
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stderr);
+            let mut line = Vec::new();
+            while Instant::now() < deadline
+                && reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0)
+            {
+                let mut tail = tail.lock().unwrap();
+                tail.push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+                if tail.len() > LAUNCH_ERROR_LINES {
+                    tail.pop_front();
+                }
+                line.clear();
+            }
+            drop(eof_tx);
+            let _ = std::io::copy(&mut reader, &mut std::io::sink());
+        });
+    }
+
+    std::thread::spawn(move || {
+        let status = loop {
+            match child.try_wait() {
+                Ok(None) if Instant::now() < deadline => {
+                    std::thread::sleep(std::time::Duration::from_millis(100))
+                }
+                Ok(status) => break status,
+                Err(_) => return,
+            }
+        };
+        let Some(status) = status.filter(|status| !status.success()) else {
+            // Still running (or exited cleanly); reap it whenever it exits
+            let _ = child.wait();
+            return;
+        };
+
+        // Wait for the rest of the output, unless something the application
+        // started keeps the pipe open
+        let _ = eof_rx.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+        let tail = Vec::from_iter(tail.lock().unwrap().iter().cloned()).join("\n");
+        tracing::warn!("{} exited with {}: {}", program.display(), status, tail);
+
+        let _ = window.emit(
+            "launch-failed",
+            serde_json::json!({
+                "program": program,
+                "code": status.code(),
+                "stderr": tail,
+            }),
+        );
+    });
 
     Ok(())
 }
 
+/// Open the install directory of a package in the file manager
+///
+/// With `reveal`, a file below the install directory is selected in the file
+/// manager instead. File managers that can't do this show its folder.
+#[tauri::command]
+pub async fn open_install_dir(
+    name: String,
+    scope: String,
+    reveal: Option<String>,
+) -> Result<(), String> {
+    let scope = match scope.as_str() {
+        "system" => InstallScope::System,
+        _ => InstallScope::User,
+    };
+
+    let metadata = InstallMetadata::load(&name, scope)
+        .map_err(|e| format!("Failed to load package metadata: {}", e))?;
+    let install_dir = metadata
+        .install_path
+        .canonicalize()
+        .ok()
+        .filter(|dir| dir.is_dir())
+        .ok_or_else(|| {
+            format!(
+                "Install directory not found: {}",
+                metadata.install_path.display()
+            )
+        })?;
+
+    let Some(reveal) = reveal else {
+        return xdg_open(&install_dir);
+    };
+    // Only ever point the file manager at the package's own files
+    let file = install_dir
+        .join(reveal)
+        .canonicalize()
+        .ok()
+        .filter(|file| file.starts_with(&install_dir))
+        .ok_or_else(|| format!("Not a file of {}", name))?;
+
+    let shown = std::process::Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(&file)))
+        .arg("string:")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if shown {
+        return Ok(());
+    }
+    xdg_open(file.parent().unwrap_or(&install_dir))
+}
+
+fn xdg_open(path: &Path) -> Result<(), String> {
+    let status = std::process::Command::new("xdg-open")
+        .arg(path)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to run xdg-open: {}", e))?;
+    if !status.success() {
+        return Err(format!("No file manager could open {}", path.display()));
+    }
+    Ok(())
+}
+
+/// `file://` URI of an absolute path
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(*byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
 #[tauri::command]
 pub async fn exit_app() {
     std::process::exit(0);
 }
 
+/// Package opened from the file manager or the command line
 #[tauri::command]
 pub fn get_launch_args() -> Option<String> {
-    let args: Vec<String> = std::env::args().collect();
-    // In production, the file path is usually the second argument (index 1)
-    // In dev, it might be different, but we focus on production behavior for now.
-    if args.len() > 1 {
-        // Simple check: return the last argument if it looks like a file path
-        // This handles cases where there might be other flags
-        // For simple association, the OS passes the file as an argument.
-        for arg in args.iter().skip(1) {
-            if arg.ends_with(".int") {
-                return Some(arg.clone());
-            }
-        }
-        // If no .int file found, but there is an arg, maybe it's the file (drag & drop often passes just the path)
-        // Let's safe guard it to only return if it looks like a path or specific extension if strictly enforcing
-        // For now, let's try to return the first non-flag argument if no .int specific found?
-        // Actually, let's stick to .int for safety.
-        None
-    } else {
-        None
-    }
+    let cwd = std::env::current_dir().ok()?;
+    package_arg(std::env::args().skip(1), &cwd).map(|path| path.to_string_lossy().to_string())
+}
+
+/// First existing `.int` file among command-line arguments, canonicalized
+///
+/// Relative paths are resolved against `cwd`, the working directory of the
+/// process the arguments were given to.
+pub fn package_arg<I: IntoIterator<Item = String>>(args: I, cwd: &Path) -> Option<PathBuf> {
+    args.into_iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .filter_map(|arg| cwd.join(arg).canonicalize().ok())
+        .find(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "int"))
 }
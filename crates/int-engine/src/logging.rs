@@ -0,0 +1,57 @@
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::prelude::*;
+
+/// Target of the event recording the error a CLI command failed with
+///
+/// The error is already printed by the CLI, so it only goes to the log file.
+pub const FAILURE_TARGET: &str = "int_engine::failure";
+
+/// Set up logging to stderr and, optionally, a log file
+///
+/// Stderr shows warnings by default, more with each `-v` and only errors
+/// with `--quiet`. The log file always records debug events and above (or
+/// everything with `-vvv`), so an operation can be looked into afterwards.
+pub fn init(verbosity: u8, quiet: bool, log_file: Option<&Path>) -> anyhow::Result<()> {
+    let level = match (quiet, verbosity) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .with_filter(filter_fn(move |metadata| {
+            *metadata.level() <= level && metadata.target() != FAILURE_TARGET
+        }));
+
+    let file = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| {
+                    anyhow::anyhow!("Failed to open log file {}: {}", path.display(), e)
+                })?;
+            let level = level.max(LevelFilter::DEBUG);
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .with_filter(level),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("Failed to set up logging: {}", e))
+}
@@ -1,30 +1,69 @@
 mod commands;
+mod logging;
+mod operations;
+mod progress;
 mod state;
 
-use clap::Parser;
-use int_core::{InstallConfig, InstallProgress, InstallScope, Installer, Uninstaller};
+use clap::{CommandFactory, Parser, Subcommand};
+use int_core::{
+    InitSystem, InstallConfig, InstallMetadata, InstallScope, Installer, PackageSet, RestartPolicy,
+    ServiceManager, ServicePreset, Uninstaller,
+};
+use progress::CliProgress;
 use state::AppState;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Emitter, Manager};
+
+/// Set by `--quiet`
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Print a progress or banner line, unless output is quiet
+///
+/// Lines that are the output asked for (listings, reports, JSON) use
+/// `println!` so they are printed either way.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !quiet() {
+            println!($($arg)*);
+        }
+    };
+}
 
 #[derive(Parser)]
 #[command(name = "int-engine")]
 #[command(version, about = "INT Package Installer", long_about = None)]
 struct Cli {
-    /// Package file to install (.int)
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Package file to install (.int), or `-` to read it from stdin
     package: Option<PathBuf>,
 
     /// Uninstall a package
     #[arg(short, long)]
     uninstall: Option<String>,
 
+    /// Also remove data, cache and log directories when uninstalling
+    #[arg(long, requires = "uninstall")]
+    purge: bool,
+
     /// List installed packages
     #[arg(short, long)]
     list: bool,
 
-    /// Installation scope (user or system)
-    #[arg(long, default_value = "user")]
+    /// Order of the package list (name, or size for the largest first)
+    #[arg(long, requires = "list")]
+    sort: Option<String>,
+
+    /// Installation scope (user or system; `all` for --list)
+    #[arg(long, default_value = "user", global = true)]
     scope: String,
 
+    /// Install into an alternate root filesystem (chroot or image rootfs)
+    #[arg(long, value_name = "DIR", global = true)]
+    root: Option<PathBuf>,
+
     /// Custom installation path
     #[arg(long)]
     install_path: Option<PathBuf>,
@@ -33,39 +72,287 @@ struct Cli {
     #[arg(long)]
     start_service: bool,
 
-    /// Dry run (don't actually install)
+    /// Dry run (don't actually install or uninstall)
     #[arg(long)]
     dry_run: bool,
 
+    /// Override whether services are enabled (enable, enable-now or disable)
+    #[arg(long)]
+    service_preset: Option<String>,
+
+    /// What to do with running services when upgrading (restart, stop or ask)
+    #[arg(long, default_value = "restart")]
+    on_upgrade: String,
+
+    /// Wait for another install or uninstall in the same scope to finish
+    #[arg(long)]
+    wait: bool,
+
+    /// Install the package only as a dependency (see `autoremove`)
+    #[arg(long)]
+    as_dependency: bool,
+
+    /// Install even if the package is older than the installed version
+    #[arg(long)]
+    allow_downgrade: bool,
+
+    /// Override safety checks (downgrades, missing required dependencies)
+    #[arg(long)]
+    force: bool,
+
+    /// Accept the package's license agreement without showing it
+    #[arg(long)]
+    accept_eula: bool,
+
+    /// Approve all confirmation prompts
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Fail instead of prompting for confirmation (for scripts and CI)
+    #[arg(long, conflicts_with = "yes")]
+    non_interactive: bool,
+
+    /// Print machine-readable JSON output (errors are written as JSON to stderr)
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Show more log output on stderr (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only print errors and requested output, no progress or banners
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
+
+    /// Append a debug log of the operation to a file
+    #[arg(long, value_name = "FILE", global = true)]
+    log_file: Option<PathBuf>,
+
     /// Run in GUI mode
     #[arg(short, long)]
     gui: bool,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect the service of an installed package
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    /// List the files recorded for an installed package, with size and hash
+    Files {
+        /// Installed package name
+        package: String,
+    },
+    /// Show the state of an installed package: version, size, service,
+    /// desktop integration and integrity
+    Status {
+        /// Installed package name
+        package: String,
+    },
+    /// Find the installed package that owns a file
+    Which {
+        /// Path of the file
+        path: PathBuf,
+    },
+    /// Show past installs, upgrades and uninstalls
+    History {
+        /// Transaction ID (or prefix) to show in detail
+        id: Option<String>,
+    },
+    /// Undo an install, upgrade or uninstall from the history
+    Rollback {
+        /// Transaction ID (or prefix)
+        id: String,
+    },
+    /// Keep an installed package at its version: installs of other
+    /// versions are refused (unless forced) and imports skip it
+    Hold {
+        /// Installed package name
+        package: String,
+    },
+    /// Release a held package so other versions can be installed again
+    Unhold {
+        /// Installed package name
+        package: String,
+    },
+    /// Write the installed packages of the scope to a .json or .toml file
+    Export {
+        /// Output file
+        file: PathBuf,
+    },
+    /// Install the packages listed in an exported .json or .toml file
+    Import {
+        /// Package set file
+        file: PathBuf,
+
+        /// Directory with the package files (default: next to the set file)
+        #[arg(long)]
+        packages_dir: Option<PathBuf>,
+
+        /// Accept the license agreements of the packages without showing them
+        #[arg(long)]
+        accept_eula: bool,
+    },
+    /// Uninstall packages installed as dependencies that are no longer needed
+    Autoremove {
+        /// Only list the packages that would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Find and clean up orphaned installation state
+    Repair {
+        /// Only report problems without fixing them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Check that this machine can install packages, with fixes for problems
+    Doctor,
+    /// Remove old cached packages and leftovers of interrupted installs
+    Clean {
+        /// Days to keep cached packages for (the installed version is always kept)
+        #[arg(long, default_value_t = 30)]
+        keep_days: u64,
+        /// Only list what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to complete for (bash, zsh, fish, elvish or powershell)
+        shell: clap_complete::Shell,
+    },
+    /// Write man pages for int-engine and its subcommands
+    #[command(hide = true)]
+    GenMan {
+        /// Output directory
+        #[arg(default_value = ".")]
+        out_dir: PathBuf,
+    },
+    /// Manage the installed-package database
+    #[cfg(feature = "sqlite")]
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Subcommand)]
+enum DbAction {
+    /// Move the per-package JSON metadata of the scope into the database
+    Migrate,
+}
+
+#[derive(Subcommand)]
+enum ServiceAction {
+    /// Show the service status
+    Status {
+        /// Installed package name
+        package: String,
+    },
+    /// Show the most recent service log lines
+    Logs {
+        /// Installed package name
+        package: String,
+
+        /// Number of lines to show
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+    },
+}
+
 fn main() {
     let cli = Cli::parse();
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+
+    if let Err(e) = logging::init(cli.verbose, cli.quiet, cli.log_file.as_deref()) {
+        report_error(&e, cli.json);
+    }
 
-    if cli.gui || (cli.package.is_none() && !cli.list && cli.uninstall.is_none()) {
+    if cli.gui
+        || (cli.command.is_none() && cli.package.is_none() && !cli.list && cli.uninstall.is_none())
+    {
         run_gui();
     } else {
+        let json = cli.json;
         if let Err(e) = run_cli(cli) {
-            eprintln!("❌ Error: {}", e);
-            std::process::exit(1);
+            report_error(&e, json);
         }
     }
 }
 
+/// Print a CLI error and exit with its error code
+///
+/// Errors from int-core exit with `IntError::code()`; anything else exits
+/// with 1.
+fn report_error(error: &anyhow::Error, json: bool) -> ! {
+    let int_error = error.downcast_ref::<int_core::IntError>();
+    let code = int_error.map_or(1, |e| e.code());
+    tracing::error!(target: logging::FAILURE_TARGET, "{:#}", error);
+
+    if json {
+        let report = serde_json::json!({
+            "error": {
+                "code": code,
+                "class": int_error.map_or("error", |e| e.class()),
+                "message": error.to_string(),
+            }
+        });
+        eprintln!("{}", report);
+    } else if quiet() {
+        eprintln!("Error: {}", error);
+    } else {
+        eprintln!("❌ Error: {}", error);
+    }
+    std::process::exit(code);
+}
+
+/// Whether `--quiet` was given
+fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
 fn run_gui() {
     tauri::Builder::default()
+        // Packages opened while the GUI runs go to the running instance
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+            if let Some(path) = commands::package_arg(args.into_iter().skip(1), Path::new(&cwd)) {
+                let _ = app.emit("open-package", path.to_string_lossy());
+            }
+        }))
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(AppState::new())
         .invoke_handler(tauri::generate_handler![
             commands::validate_package,
+            commands::get_signature_info,
+            commands::list_entries,
+            commands::plan_install,
+            commands::get_eula,
             commands::install_package,
             commands::list_installed,
+            commands::search_installed,
+            commands::repair_package,
+            commands::check_updates,
             commands::uninstall_package,
+            commands::uninstall_preview,
+            commands::get_operation_status,
+            commands::list_operations,
+            commands::transaction_history,
+            commands::rollback_transaction,
             commands::launch_app,
+            commands::open_install_dir,
+            commands::service_status,
+            commands::service_start,
+            commands::service_stop,
+            commands::service_restart,
+            commands::service_logs,
             commands::exit_app,
             commands::get_launch_args
         ])
@@ -74,6 +361,23 @@ fn run_gui() {
 }
 
 fn run_cli(cli: Cli) -> anyhow::Result<()> {
+    if let Some(ref root) = cli.root {
+        if !root.is_dir() {
+            anyhow::bail!("Root directory not found: {}", root.display());
+        }
+        int_core::root::set(Some(std::path::absolute(root)?));
+    }
+
+    let by_size = match cli.sort.as_deref() {
+        None | Some("name") => false,
+        Some("size") => true,
+        Some(other) => anyhow::bail!("Invalid sort order: {}. Use 'name' or 'size'", other),
+    };
+
+    if cli.list && cli.command.is_none() && cli.scope == "all" {
+        return cmd_list_all(by_size);
+    }
+
     // Parse scope
     let scope = match cli.scope.as_str() {
         "user" => InstallScope::User,
@@ -81,116 +385,408 @@ fn run_cli(cli: Cli) -> anyhow::Result<()> {
         _ => anyhow::bail!("Invalid scope: {}. Use 'user' or 'system'", cli.scope),
     };
 
+    let restart_policy = match cli.on_upgrade.as_str() {
+        "restart" => RestartPolicy::Restart,
+        "stop" => RestartPolicy::LeaveStopped,
+        "ask" => RestartPolicy::Ask,
+        _ => anyhow::bail!(
+            "Invalid upgrade policy: {}. Use 'restart', 'stop' or 'ask'",
+            cli.on_upgrade
+        ),
+    };
+
+    let service_preset = match cli.service_preset.as_deref() {
+        None => None,
+        Some("enable") => Some(ServicePreset::Enable),
+        Some("enable-now") => Some(ServicePreset::EnableNow),
+        Some("disable") => Some(ServicePreset::Disable),
+        Some(other) => anyhow::bail!(
+            "Invalid service preset: {}. Use 'enable', 'enable-now' or 'disable'",
+            other
+        ),
+    };
+
+    let from_stdin = cli.package.as_deref() == Some(Path::new("-"));
+    let prompt = if cli.yes {
+        Prompt::Approve
+    } else if cli.non_interactive {
+        Prompt::Fail
+    } else if from_stdin {
+        // stdin carries the package, so answers have to come from the terminal
+        match std::fs::File::open("/dev/tty") {
+            Ok(_) => Prompt::AskTty,
+            Err(_) => Prompt::Fail,
+        }
+    } else {
+        Prompt::Ask
+    };
+    if prompt == Prompt::Fail && restart_policy == RestartPolicy::Ask {
+        anyhow::bail!("--on-upgrade ask cannot be used with --non-interactive");
+    }
+
     // Handle commands
-    if cli.list {
-        cmd_list(scope)?;
+    if let Some(command) = cli.command {
+        match command {
+            Command::Service { action } => cmd_service(action, scope)?,
+            Command::Files { package } => cmd_files(&package, scope, cli.json)?,
+            Command::Status { package } => cmd_status(&package, scope, cli.json)?,
+            Command::Which { path } => cmd_which(&path, cli.json)?,
+            Command::History { id } => cmd_history(scope, id.as_deref())?,
+            Command::Rollback { id } => cmd_rollback(scope, &id)?,
+            Command::Hold { package } => cmd_hold(&package, scope, true, cli.wait)?,
+            Command::Unhold { package } => cmd_hold(&package, scope, false, cli.wait)?,
+            Command::Export { file } => cmd_export(scope, &file)?,
+            Command::Import {
+                file,
+                packages_dir,
+                accept_eula,
+            } => cmd_import(
+                &file,
+                packages_dir.as_deref(),
+                accept_eula || cli.accept_eula,
+                cli.wait,
+            )?,
+            Command::Autoremove { dry_run } => cmd_autoremove(scope, dry_run)?,
+            Command::Repair { check } => cmd_repair(scope, check)?,
+            Command::Doctor => cmd_doctor(scope, cli.json)?,
+            Command::Clean { keep_days, dry_run } => {
+                cmd_clean(scope, keep_days, dry_run, cli.wait, cli.json)?
+            }
+            Command::Completions { shell } => cmd_completions(shell),
+            Command::GenMan { out_dir } => cmd_gen_man(&out_dir)?,
+            #[cfg(feature = "sqlite")]
+            Command::Db { action } => cmd_db(action, scope)?,
+        }
+    } else if cli.list {
+        cmd_list(scope, by_size)?;
     } else if let Some(package_name) = cli.uninstall {
-        cmd_uninstall(&package_name, scope)?;
+        cmd_uninstall(&package_name, scope, cli.purge, cli.dry_run, cli.wait)?;
     } else if let Some(package_path) = cli.package {
         let config = InstallConfig {
             install_path: cli.install_path,
             start_service: cli.start_service,
             create_desktop_entry: true,
             dry_run: cli.dry_run,
+            restart_policy,
+            service_preset,
+            as_dependency: cli.as_dependency,
+            allow_downgrade: cli.allow_downgrade,
+            force: cli.force,
+            eula_accepted: cli.accept_eula,
+            temporary_package: false,
         };
-        cmd_install(&package_path, config)?;
+        // Progress is reported as JSON lines instead of banners
+        if cli.json {
+            QUIET.store(true, Ordering::Relaxed);
+        }
+        cmd_install(&package_path, config, cli.wait, prompt, cli.json)?;
     }
 
     Ok(())
 }
 
+/// How confirmation prompts are answered
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    /// Ask on the terminal
+    Ask,
+    /// Ask on the controlling terminal, when stdin is taken by the package
+    AskTty,
+    /// Answer yes without asking (`--yes`)
+    Approve,
+    /// Fail instead of asking (`--non-interactive`)
+    Fail,
+}
+
+impl Prompt {
+    /// Get approval to continue, failing if it is refused
+    fn confirm(self, question: &str) -> anyhow::Result<()> {
+        match self {
+            Prompt::Approve => Ok(()),
+            Prompt::Fail => {
+                anyhow::bail!("Confirmation required: {} (use --yes to approve)", question)
+            }
+            Prompt::Ask | Prompt::AskTty if ask(question, self == Prompt::AskTty) => Ok(()),
+            Prompt::Ask | Prompt::AskTty => anyhow::bail!("Cancelled"),
+        }
+    }
+}
+
+/// Ask a yes/no question on the terminal, defaulting to yes
+///
+/// The answer is read from stdin, or from `/dev/tty` if `tty` is set.
+fn ask(question: &str, tty: bool) -> bool {
+    print!("❓ {} [Y/n] ", question);
+    match read_answer(tty) {
+        Some(answer) => !matches!(answer.as_str(), "n" | "no"),
+        None => true,
+    }
+}
+
+/// Ask a yes/no question on the terminal that has to be answered with yes
+fn accept(question: &str, tty: bool) -> bool {
+    print!("❓ {} [y/N] ", question);
+    matches!(read_answer(tty).as_deref(), Some("y" | "yes"))
+}
+
+/// Read a lowercased answer from stdin, or from `/dev/tty` if `tty` is set
+fn read_answer(tty: bool) -> Option<String> {
+    use std::io::BufRead;
+
+    std::io::Write::flush(&mut std::io::stdout()).unwrap();
+    let mut answer = String::new();
+    let read = if tty {
+        std::fs::File::open("/dev/tty")
+            .and_then(|tty| std::io::BufReader::new(tty).read_line(&mut answer))
+    } else {
+        std::io::stdin().read_line(&mut answer)
+    };
+    read.ok().map(|_| answer.trim().to_lowercase())
+}
+
+/// Copy a package piped on stdin to a temp file only the current user can
+/// read, removed again when dropped
+fn spool_stdin() -> anyhow::Result<tempfile::NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("int-stdin-")
+        .suffix(".int")
+        .tempfile()?;
+    std::io::copy(&mut std::io::stdin().lock(), &mut file)?;
+    Ok(file)
+}
+
 /// Install a package (CLI version)
-fn cmd_install(package_path: &PathBuf, config: InstallConfig) -> anyhow::Result<()> {
+fn cmd_install(
+    package_path: &Path,
+    mut config: InstallConfig,
+    wait: bool,
+    prompt: Prompt,
+    json: bool,
+) -> anyhow::Result<()> {
     use int_core::PackageExtractor;
 
-    println!("📦 Installing package: {}", package_path.display());
-    println!();
+    let spooled;
+    let package_path = if package_path == Path::new("-") {
+        say!("📦 Installing package from stdin");
+        spooled = spool_stdin()?;
+        // The spooled file is deleted after installing
+        config.temporary_package = true;
+        spooled.path()
+    } else {
+        say!("📦 Installing package: {}", package_path.display());
+        package_path
+    };
+    say!();
 
     // Validate package first
     let extractor = PackageExtractor::new();
     let manifest = extractor.validate_package(package_path)?;
 
-    println!("Package Information:");
-    println!("  Name: {}", manifest.display_name());
-    println!("  Version: {}", manifest.package_version);
+    say!("Package Information:");
+    say!("  Name: {}", manifest.display_name());
+    say!("  Version: {}", manifest.package_version);
     if let Some(ref desc) = manifest.description {
-        println!("  Description: {}", desc);
+        say!("  Description: {}", desc);
     }
-    println!("  Scope: {:?}", manifest.install_scope);
-    println!();
+    say!("  Scope: {:?}", manifest.install_scope);
+    say!();
 
-    // Create installer with progress callback
-    let installer = Installer::new().with_progress(|progress| match progress {
-        InstallProgress::Extracting { current, total } => {
-            print!("\r🔄 Extracting... {}/{} bytes", current, total);
-            std::io::Write::flush(&mut std::io::stdout()).unwrap();
+    // Show what changed when upgrading an existing installation
+    let installed = InstallMetadata::load(&manifest.name, manifest.install_scope).ok();
+    if let Some(ref installed) = installed {
+        if installed.held && installed.package_version != manifest.package_version && !config.force
+        {
+            return Err(int_core::IntError::PackageHeld {
+                package: manifest.name.clone(),
+                version: installed.package_version.clone(),
+            }
+            .into());
         }
-        InstallProgress::CopyingFiles { current, total } => {
-            print!("\r📁 Copying files... {}/{}", current, total);
-            std::io::Write::flush(&mut std::io::stdout()).unwrap();
+        let downgrade = int_core::utils::compare_versions(
+            &manifest.package_version,
+            &installed.package_version,
+        ) == std::cmp::Ordering::Less;
+        if downgrade && !(config.allow_downgrade || config.force) {
+            return Err(int_core::IntError::DowngradeRefused {
+                package: manifest.name.clone(),
+                installed: installed.package_version.clone(),
+                version: manifest.package_version.clone(),
+            }
+            .into());
         }
-        InstallProgress::SettingPermissions => {
-            print!("\r🔒 Setting permissions...");
-            std::io::Write::flush(&mut std::io::stdout()).unwrap();
-        }
-        InstallProgress::ExecutingScript { script } => {
-            println!("\n🔧 Running script: {}", script);
-        }
-        InstallProgress::RegisteringService => {
-            println!("\n⚙️  Registering service...");
+        let action = if downgrade {
+            "Downgrading"
+        } else {
+            "Upgrading"
+        };
+        say!(
+            "{} from version {} to {}",
+            action,
+            installed.package_version,
+            manifest.package_version
+        );
+        if let Some(changelog) = extractor.read_changelog(package_path)? {
+            say!();
+            say!("Changelog:");
+            for line in changelog.trim_end().lines() {
+                say!("  {}", line);
+            }
         }
-        InstallProgress::CreatingDesktopEntry => {
-            println!("\n🖥️  Creating desktop entry...");
+        say!();
+    }
+
+    // The license agreement is only accepted when asked, not by --yes
+    let interactive = matches!(prompt, Prompt::Ask | Prompt::AskTty);
+    if !config.eula_accepted && !config.dry_run && interactive {
+        if let Some(eula) = extractor.read_eula(package_path)? {
+            println!("License Agreement:");
+            for line in eula.trim_end().lines() {
+                println!("  {}", line);
+            }
+            println!();
+            config.eula_accepted = accept(
+                "Do you accept the license agreement?",
+                prompt == Prompt::AskTty,
+            );
         }
-        InstallProgress::Finalizing => {
-            println!("\n✨ Finalizing...");
+    }
+
+    // Confirm the steps that change more than the package's own files
+    if !config.dry_run {
+        if let Some(ref installed) = installed {
+            prompt.confirm(&format!(
+                "Replace installed version {} of {}?",
+                installed.package_version, manifest.name
+            ))?;
         }
-        InstallProgress::Log { message } => {
-            println!("📝 {}", message);
+        let check_commands = manifest
+            .dependencies
+            .iter()
+            .any(|dependency| dependency.check_command.is_some());
+        if !manifest.scripts.is_empty() || manifest.post_install.is_some() || check_commands {
+            prompt.confirm("The package runs installation scripts. Continue?")?;
         }
-        InstallProgress::Completed => {
-            println!("\n✅ Installation completed!");
+        if manifest.service && manifest.install_scope == InstallScope::System {
+            prompt.confirm(&format!(
+                "Register system service {}?",
+                manifest.service_name()
+            ))?;
         }
-    });
+    }
+
+    // Create installer with progress display, or progress events for --json
+    let installer = if json {
+        Installer::new().with_progress(|progress| {
+            let (event, payload) = progress::install_event(progress);
+            let line = serde_json::json!({ "event": event, "payload": payload });
+            println!("{}", line);
+        })
+    } else {
+        let display = CliProgress::new(quiet());
+        Installer::new().with_progress(move |progress| display.install(progress))
+    };
+
+    // Answer installer questions (e.g. restarting services) on the terminal;
+    // without a callback the installer answers yes itself
+    let installer = match prompt {
+        Prompt::Ask => installer.with_confirm(|question| ask(question, false)),
+        Prompt::AskTty => installer.with_confirm(|question| ask(question, true)),
+        _ => installer,
+    };
+    let installer = installer.wait_for_lock(wait);
 
     // Install
     let metadata = installer.install(package_path, config)?;
 
-    println!();
-    println!("Installation Details:");
-    println!("  Installed to: {}", metadata.install_path.display());
-    println!("  Files installed: {}", metadata.installed_files.len());
+    say!();
+    say!("Installation Details:");
+    say!("  Installed to: {}", metadata.install_path.display());
+    say!("  Files installed: {}", metadata.installed_files.len());
 
     if let Some(ref desktop) = metadata.desktop_entry {
-        println!("  Desktop entry: {}", desktop.display());
+        say!("  Desktop entry: {}", desktop.display());
     }
 
     if let Some(ref service) = metadata.service_name {
-        println!("  Service: {}", service);
+        say!("  Service: {}", service);
     }
 
-    println!();
-    println!("🎉 Package installed successfully!");
+    let pending = int_core::root::pending_actions_path();
+    if int_core::root::is_set() && pending.exists() {
+        say!("  Deferred actions: {}", pending.display());
+    }
+
+    say!();
+    say!("🎉 Package installed successfully!");
 
     Ok(())
 }
 
 /// Uninstall a package (CLI version)
-fn cmd_uninstall(package_name: &str, scope: InstallScope) -> anyhow::Result<()> {
-    println!("🗑️  Uninstalling package: {}", package_name);
+fn cmd_uninstall(
+    package_name: &str,
+    scope: InstallScope,
+    purge: bool,
+    dry_run: bool,
+    wait: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        let uninstaller = Uninstaller::new();
+        let report = uninstaller.dry_run(package_name, scope, purge)?;
+        println!(
+            "🔍 Dry run: uninstalling {} v{} would remove:",
+            report.package_name, report.package_version
+        );
+        print_paths("Directories", &report.directories);
+        print_paths("Files", &report.files);
+        print_paths("Symlinks", &report.symlinks);
+        print_paths("Desktop entries", &report.desktop_entries);
+        print_paths("Integration files", &report.integration_files);
+        if !report.services.is_empty() {
+            println!("   Services: {}", report.services.join(", "));
+        }
+        if !report.scripts.is_empty() {
+            println!("   Scripts run: {}", report.scripts.join(", "));
+        }
+        return Ok(());
+    }
 
-    let uninstaller = Uninstaller::new();
-    uninstaller.uninstall(package_name, scope)?;
+    say!("🗑️  Uninstalling package: {}", package_name);
+
+    let display = CliProgress::new(quiet());
+    let uninstaller = Uninstaller::new().with_progress(move |progress| display.uninstall(progress));
+    let uninstaller = uninstaller.wait_for_lock(wait);
+    if purge {
+        uninstaller.purge(package_name, scope)?;
+    } else {
+        uninstaller.uninstall(package_name, scope)?;
+    }
 
-    println!("✅ Package uninstalled successfully!");
+    say!("✅ Package uninstalled successfully!");
 
     Ok(())
 }
 
+/// Print a labelled list of paths, if any
+fn print_paths(label: &str, paths: &[PathBuf]) {
+    if paths.is_empty() {
+        return;
+    }
+    println!("   {} ({}):", label, paths.len());
+    for path in paths {
+        println!("     {}", path.display());
+    }
+}
+
 /// List installed packages (CLI version)
-fn cmd_list(scope: InstallScope) -> anyhow::Result<()> {
+fn cmd_list(scope: InstallScope, by_size: bool) -> anyhow::Result<()> {
     let uninstaller = Uninstaller::new();
-    let packages = uninstaller.list_installed(scope)?;
+    let mut packages = uninstaller.list_installed(scope)?;
+    if by_size {
+        packages.sort_by_key(|pkg| std::cmp::Reverse(pkg.size()));
+    } else {
+        packages.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+    }
 
     if packages.is_empty() {
         println!("No packages installed ({:?} scope)", scope);
@@ -201,14 +797,592 @@ fn cmd_list(scope: InstallScope) -> anyhow::Result<()> {
     println!();
 
     for pkg in packages {
-        println!("📦 {} v{}", pkg.package_name, pkg.package_version);
-        println!("   Path: {}", pkg.install_path.display());
-        println!("   Installed: {}", pkg.install_date);
-        if let Some(ref service) = pkg.service_name {
-            println!("   Service: {}", service);
+        print_package(&pkg);
+        println!();
+    }
+
+    Ok(())
+}
+
+/// List installed packages of both scopes (CLI version)
+fn cmd_list_all(by_size: bool) -> anyhow::Result<()> {
+    let mut packages = Uninstaller::new().list_all_installed()?;
+    if by_size {
+        packages.sort_by_key(|pkg| std::cmp::Reverse(pkg.metadata.size()));
+    }
+
+    if packages.is_empty() {
+        println!("No packages installed");
+        return Ok(());
+    }
+
+    println!("Installed Packages:");
+    println!();
+
+    for pkg in packages {
+        print_package(&pkg.metadata);
+        println!("   Scope: {:?}", pkg.scope);
+        if let Some(other) = pkg.also_installed_in {
+            println!("   Also installed in {:?} scope", other);
         }
         println!();
     }
 
     Ok(())
 }
+
+/// Print the details of an installed package
+fn print_package(pkg: &InstallMetadata) {
+    let held = if pkg.held { " [held]" } else { "" };
+    println!("📦 {} v{}{}", pkg.display_name(), pkg.package_version, held);
+    if let Some(ref manifest) = pkg.manifest {
+        if let Some(ref description) = manifest.description {
+            println!("   {}", description);
+        }
+        if let Some(ref author) = manifest.author {
+            println!("   Author: {}", author);
+        }
+    }
+    println!("   Path: {}", pkg.install_path.display());
+    println!("   Size: {}", int_core::utils::format_bytes(pkg.size()));
+    println!("   Installed: {}", pkg.install_date);
+    if let Some(ref service) = pkg.service_name {
+        println!("   Service: {}", service);
+    }
+}
+
+/// List the files of an installed package (CLI version)
+fn cmd_files(package_name: &str, scope: InstallScope, json: bool) -> anyhow::Result<()> {
+    let metadata = InstallMetadata::load(package_name, scope)?;
+    let files = metadata.files();
+
+    if json {
+        let report = serde_json::json!({
+            "package": metadata.package_name,
+            "version": metadata.package_version,
+            "scope": metadata.install_scope,
+            "files": files,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "📦 {} v{} ({} files)",
+        metadata.display_name(),
+        metadata.package_version,
+        files.len()
+    );
+    for file in &files {
+        let size = match file.size {
+            Some(size) => int_core::utils::format_bytes(size),
+            None => "missing".to_string(),
+        };
+        println!(
+            "{:<13} {:>10}  {:<64}  {}",
+            file.kind.as_str(),
+            size,
+            file.sha256.as_deref().unwrap_or("-"),
+            file.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Show the state of an installed package (CLI version)
+fn cmd_status(package_name: &str, scope: InstallScope, json: bool) -> anyhow::Result<()> {
+    let metadata = InstallMetadata::load(package_name, scope)?;
+    let service = metadata.service_name.as_ref().map(|name| {
+        let init_system = metadata.init_system.unwrap_or_else(InitSystem::detect);
+        ServiceManager::with_init_system(init_system).status(name, scope)
+    });
+    let desktop_entry = metadata
+        .desktop_entry
+        .as_ref()
+        .map(|path| (path, path.exists()));
+    let issues = int_core::doctor::check(&metadata);
+
+    if json {
+        let report = serde_json::json!({
+            "package": metadata.package_name,
+            "version": metadata.package_version,
+            "scope": metadata.install_scope,
+            "install_path": metadata.install_path,
+            "installed_size": metadata.size(),
+            "held": metadata.held,
+            "service": service.as_ref().map(|status| match status {
+                Ok(status) => serde_json::json!({
+                    "name": status.name,
+                    "active_state": status.active_state,
+                    "sub_state": status.sub_state,
+                }),
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            }),
+            "desktop_entry": desktop_entry.map(|(path, exists)| serde_json::json!({
+                "path": path,
+                "exists": exists,
+            })),
+            "issues": issues,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "📦 {} v{} ({:?} scope)",
+        metadata.display_name(),
+        metadata.package_version,
+        metadata.install_scope
+    );
+    println!("   Path: {}", metadata.install_path.display());
+    println!(
+        "   Size: {}",
+        int_core::utils::format_bytes(metadata.size())
+    );
+    println!("   Installed: {}", metadata.install_date);
+    if metadata.held {
+        println!("   Held: yes (other versions are not installed)");
+    }
+    match service {
+        Some(Ok(status)) => match status.sub_state {
+            Some(ref sub_state) => {
+                println!(
+                    "   Service: {} {} ({})",
+                    status.name, status.active_state, sub_state
+                )
+            }
+            None => println!("   Service: {} {}", status.name, status.active_state),
+        },
+        Some(Err(e)) => println!("   Service: unknown ({})", e),
+        None => println!("   Service: none"),
+    }
+    match desktop_entry {
+        Some((path, true)) => println!("   Desktop entry: {}", path.display()),
+        Some((path, false)) => println!("   Desktop entry: {} (missing)", path.display()),
+        None => println!("   Desktop entry: none"),
+    }
+    if issues.is_empty() {
+        println!("   Integrity: ✅ OK");
+    } else {
+        println!("   Integrity: ⚠️  {} problem(s)", issues.len());
+        for issue in &issues {
+            println!("      {}", issue);
+        }
+    }
+    Ok(())
+}
+
+/// Find the package owning a file (CLI version)
+///
+/// Both scopes are searched, so `--scope` doesn't apply.
+fn cmd_which(path: &Path, json: bool) -> anyhow::Result<()> {
+    let path = std::path::absolute(path)?;
+    let owner = int_core::who_owns(&path)?;
+    let version = owner
+        .as_ref()
+        .and_then(|owner| InstallMetadata::load(&owner.package, owner.scope).ok())
+        .map(|metadata| metadata.package_version);
+
+    if json {
+        let report = serde_json::json!({
+            "path": path,
+            "package": owner.as_ref().map(|owner| &owner.package),
+            "version": version,
+            "scope": owner.as_ref().map(|owner| owner.scope),
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    match owner {
+        Some(owner) => println!(
+            "{} is owned by {} v{} ({:?} scope)",
+            path.display(),
+            owner.package,
+            version.as_deref().unwrap_or("?"),
+            owner.scope
+        ),
+        None => println!("{} is not owned by any package", path.display()),
+    }
+    Ok(())
+}
+
+/// Show the transaction history (CLI version)
+fn cmd_history(scope: InstallScope, id: Option<&str>) -> anyhow::Result<()> {
+    use int_core::{history, TransactionResult};
+
+    if let Some(id) = id {
+        let transaction = history::get(scope, id)?
+            .ok_or_else(|| anyhow::anyhow!("No transaction with ID {}", id))?;
+        println!("Transaction {}", transaction.id);
+        println!("   Date: {}", transaction.timestamp);
+        println!("   Operation: {:?}", transaction.operation);
+        match transaction.result {
+            TransactionResult::Success => println!("   Result: success"),
+            TransactionResult::Failed { ref error } => println!("   Result: failed ({})", error),
+        }
+        for change in &transaction.packages {
+            println!(
+                "   {}: {} -> {}",
+                change.name,
+                change.old_version.as_deref().unwrap_or("-"),
+                change.new_version.as_deref().unwrap_or("-")
+            );
+        }
+        return Ok(());
+    }
+
+    let transactions = history::list(scope)?;
+    if transactions.is_empty() {
+        println!("No transactions recorded ({:?} scope)", scope);
+        return Ok(());
+    }
+
+    for transaction in transactions.iter().rev() {
+        let packages: Vec<_> = transaction
+            .packages
+            .iter()
+            .map(|change| match change.new_version {
+                Some(ref version) => format!("{} {}", change.name, version),
+                None => change.name.clone(),
+            })
+            .collect();
+        let status = match transaction.result {
+            TransactionResult::Success => "✅",
+            TransactionResult::Failed { .. } => "❌",
+        };
+        println!(
+            "{} {}  {}  {:?} {}",
+            status,
+            &transaction.id[..8.min(transaction.id.len())],
+            transaction.timestamp,
+            transaction.operation,
+            packages.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Export the installed-package set (CLI version)
+fn cmd_export(scope: InstallScope, file: &Path) -> anyhow::Result<()> {
+    let set = PackageSet::from_installed(&[scope])?;
+    set.save(file)?;
+    println!(
+        "✅ Exported {} package(s) to {}",
+        set.packages.len(),
+        file.display()
+    );
+
+    Ok(())
+}
+
+/// Install an exported package set (CLI version)
+fn cmd_import(
+    file: &Path,
+    packages_dir: Option<&Path>,
+    accept_eula: bool,
+    wait: bool,
+) -> anyhow::Result<()> {
+    let set = PackageSet::load(file)?;
+    let packages_dir = packages_dir
+        .or_else(|| file.parent())
+        .unwrap_or_else(|| Path::new("."));
+
+    say!("📦 Installing {} package(s)...", set.packages.len());
+    let installer = Installer::new().wait_for_lock(wait);
+    let report = set.install(&installer, packages_dir, accept_eula);
+
+    for name in &report.installed {
+        say!("  ✅ {}", name);
+    }
+    for name in &report.skipped {
+        say!("  ⏭️  {} (already installed)", name);
+    }
+    for name in &report.held {
+        say!("  📌 {} (held)", name);
+    }
+    for (name, error) in &report.failed {
+        println!("  ❌ {}: {}", name, error);
+    }
+    if !report.failed.is_empty() {
+        anyhow::bail!("{} package(s) could not be installed", report.failed.len());
+    }
+
+    Ok(())
+}
+
+/// Remove unneeded dependency packages (CLI version)
+fn cmd_autoremove(scope: InstallScope, dry_run: bool) -> anyhow::Result<()> {
+    let uninstaller = Uninstaller::new();
+    let candidates = uninstaller.autoremove_candidates(scope)?;
+    if candidates.is_empty() {
+        say!("✅ No unneeded packages");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("🔍 Dry run: autoremove would uninstall:");
+        for package in &candidates {
+            println!("   {} v{}", package.package_name, package.package_version);
+        }
+        return Ok(());
+    }
+
+    for name in uninstaller.autoremove(scope)? {
+        say!("🗑️  Removed {}", name);
+    }
+    Ok(())
+}
+
+/// Hold or release an installed package (CLI version)
+fn cmd_hold(package_name: &str, scope: InstallScope, held: bool, wait: bool) -> anyhow::Result<()> {
+    let metadata = Installer::new()
+        .wait_for_lock(wait)
+        .set_held(package_name, scope, held)?;
+    if held {
+        say!(
+            "📌 {} held at version {}",
+            metadata.package_name,
+            metadata.package_version
+        );
+    } else {
+        say!("✅ {} is no longer held", metadata.package_name);
+    }
+    Ok(())
+}
+
+/// Roll back a transaction (CLI version)
+fn cmd_rollback(scope: InstallScope, id: &str) -> anyhow::Result<()> {
+    use int_core::history;
+
+    say!("⏪ Rolling back transaction {}", id);
+    history::rollback(scope, id)?;
+    say!("✅ Rollback completed!");
+
+    Ok(())
+}
+
+/// Look up the service of an installed package
+fn installed_service(
+    package_name: &str,
+    scope: InstallScope,
+) -> anyhow::Result<(ServiceManager, String)> {
+    let metadata = InstallMetadata::load(package_name, scope)?;
+    let service_name = metadata
+        .service_name
+        .ok_or_else(|| anyhow::anyhow!("Package {} has no service", package_name))?;
+    let init_system = metadata.init_system.unwrap_or_else(InitSystem::detect);
+
+    Ok((ServiceManager::with_init_system(init_system), service_name))
+}
+
+/// Show service status or logs (CLI version)
+fn cmd_service(action: ServiceAction, scope: InstallScope) -> anyhow::Result<()> {
+    match action {
+        ServiceAction::Status { package } => {
+            let (manager, service_name) = installed_service(&package, scope)?;
+            let status = manager.status(&service_name, scope)?;
+
+            println!("⚙️  {} ({})", status.name, status.init_system);
+            match status.sub_state {
+                Some(ref sub_state) => {
+                    println!("   State: {} ({})", status.active_state, sub_state)
+                }
+                None => println!("   State: {}", status.active_state),
+            }
+            if let Some(pid) = status.pid {
+                println!("   PID: {}", pid);
+            }
+            if let Some(since) = status.active_since {
+                println!("   Since: {}", since.to_rfc3339());
+            }
+            if let Some(uptime) = status.uptime() {
+                println!("   Uptime: {}s", uptime.as_secs());
+            }
+        }
+        ServiceAction::Logs { package, lines } => {
+            let (manager, service_name) = installed_service(&package, scope)?;
+            for line in manager.logs(&service_name, scope, lines)? {
+                println!("{}", line);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Find and clean up orphaned installation state (CLI version)
+fn cmd_repair(scope: InstallScope, check: bool) -> anyhow::Result<()> {
+    use int_core::doctor;
+
+    let issues = doctor::scan(scope)?;
+    if issues.is_empty() {
+        say!("✅ No problems found");
+        return Ok(());
+    }
+
+    for issue in &issues {
+        println!("  ⚠️  {}", issue);
+    }
+    if check {
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for issue in issues.iter().filter(|issue| issue.is_repairable()) {
+        if let Err(e) = doctor::repair(issue, scope) {
+            eprintln!("  ❌ {}: {}", issue, e);
+            failed += 1;
+        }
+    }
+
+    let unrepairable = issues.iter().filter(|issue| !issue.is_repairable()).count();
+    if unrepairable > 0 {
+        println!(
+            "ℹ️  {} problem(s) need the package to be reinstalled",
+            unrepairable
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!("{} problem(s) could not be repaired", failed);
+    }
+    say!("✅ Repaired installation state");
+    Ok(())
+}
+
+/// Diagnose the host environment (CLI version)
+fn cmd_doctor(scope: InstallScope, json: bool) -> anyhow::Result<()> {
+    use int_core::doctor::{self, CheckStatus};
+
+    let checks = doctor::diagnose(scope)?;
+    let errors = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Error)
+        .count();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        say!("🩺 Checking {:?} scope...", scope);
+        for check in &checks {
+            let icon = match check.status {
+                CheckStatus::Ok => "✅",
+                CheckStatus::Warning => "⚠️ ",
+                CheckStatus::Error => "❌",
+            };
+            println!("  {} {}: {}", icon, check.name, check.detail);
+            if let Some(ref fix) = check.fix {
+                println!("     Fix: {}", fix);
+            }
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!("{} check(s) failed", errors);
+    }
+    Ok(())
+}
+
+/// Remove old cached packages and leftovers (CLI version)
+fn cmd_clean(
+    scope: InstallScope,
+    keep_days: u64,
+    dry_run: bool,
+    wait: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    use int_core::clean;
+    use int_core::utils::format_bytes;
+
+    // Keep installs from writing the files being cleaned up
+    let _lock = int_core::OperationLock::acquire(scope, wait)?;
+    let retention = std::time::Duration::from_secs(keep_days * 24 * 60 * 60);
+    let leftovers = clean::scan(scope, retention)?;
+
+    let mut removed = Vec::new();
+    let mut failed = 0;
+    if dry_run {
+        removed.extend(&leftovers);
+    } else {
+        for leftover in &leftovers {
+            match clean::remove(leftover) {
+                Ok(()) => removed.push(leftover),
+                Err(e) => {
+                    eprintln!("  ❌ {}", e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    let reclaimed: u64 = removed.iter().map(|leftover| leftover.size).sum();
+
+    if json {
+        let report = serde_json::json!({
+            "dry_run": dry_run,
+            "removed": removed,
+            "reclaimed": reclaimed,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if leftovers.is_empty() {
+        say!("✅ Nothing to clean");
+    } else {
+        if dry_run {
+            say!("🔍 Dry run: clean would remove:");
+        }
+        for leftover in &removed {
+            say!("   {}", leftover);
+        }
+        if dry_run {
+            println!("{} would be reclaimed", format_bytes(reclaimed));
+        } else {
+            println!("Reclaimed {}", format_bytes(reclaimed));
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} item(s) could not be removed", failed);
+    }
+    Ok(())
+}
+
+/// Write a completion script for a shell to stdout
+///
+/// The scripts are static, so package names are not completed: that needs
+/// clap_complete's dynamic completion, which is still behind its
+/// `unstable-dynamic` feature.
+fn cmd_completions(shell: clap_complete::Shell) {
+    clap_complete::generate(
+        shell,
+        &mut Cli::command(),
+        "int-engine",
+        &mut std::io::stdout(),
+    );
+}
+
+/// Write the man pages to a directory
+fn cmd_gen_man(out_dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(out_dir)?;
+    clap_mangen::generate_to(Cli::command(), out_dir)?;
+    say!("✅ Man pages written to {}", out_dir.display());
+    Ok(())
+}
+
+/// Manage the installed-package database (CLI version)
+#[cfg(feature = "sqlite")]
+fn cmd_db(action: DbAction, scope: InstallScope) -> anyhow::Result<()> {
+    use int_core::{db, PackageDatabase};
+
+    match action {
+        DbAction::Migrate => {
+            let count = db::migrate_json(scope)?;
+            println!(
+                "✅ Migrated {} package(s) to {}",
+                count,
+                PackageDatabase::path(scope).display()
+            );
+        }
+    }
+
+    Ok(())
+}
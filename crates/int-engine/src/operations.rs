@@ -0,0 +1,140 @@
+use crate::progress::timestamp;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Finished operations kept for windows that look for them later
+const FINISHED_KEPT: usize = 20;
+
+/// Log events kept per operation
+const LOG_EVENTS_KEPT: usize = 500;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Install,
+    Uninstall,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationState {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Install or uninstall running in the background
+///
+/// Besides being emitted to all windows, progress events are recorded here
+/// so a window opened later can pick up where the operation is.
+#[derive(Serialize, Clone)]
+pub struct OperationStatus {
+    pub id: u64,
+    pub kind: OperationKind,
+    /// Package path for installs, package name for uninstalls
+    pub package: String,
+    pub state: OperationState,
+    /// Name of the last progress event, and its payload
+    pub phase: Option<String>,
+    pub progress: serde_json::Value,
+    /// Payloads of the log events, oldest first
+    pub logs: Vec<serde_json::Value>,
+    pub error: Option<String>,
+    /// Milliseconds since the Unix epoch
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+/// Operations of this process, by ID
+#[derive(Default)]
+pub struct OperationRegistry {
+    inner: Mutex<Registry>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    operations: BTreeMap<u64, OperationStatus>,
+}
+
+impl OperationRegistry {
+    /// Register a new running operation and return its ID
+    pub fn start(&self, kind: OperationKind, package: &str) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.operations.insert(
+            id,
+            OperationStatus {
+                id,
+                kind,
+                package: package.to_string(),
+                state: OperationState::Running,
+                phase: None,
+                progress: serde_json::json!({}),
+                logs: Vec::new(),
+                error: None,
+                started_at: timestamp(),
+                finished_at: None,
+            },
+        );
+        id
+    }
+
+    /// Record a progress event of an operation
+    pub fn record(&self, id: u64, event: &str, payload: &serde_json::Value) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(operation) = inner.operations.get_mut(&id) else {
+            return;
+        };
+        if event.ends_with("-log") {
+            if operation.logs.len() == LOG_EVENTS_KEPT {
+                operation.logs.remove(0);
+            }
+            operation.logs.push(payload.clone());
+        } else {
+            operation.phase = Some(event.to_string());
+            operation.progress = payload.clone();
+        }
+    }
+
+    /// Mark an operation as done and return its final status
+    ///
+    /// Only the most recent finished operations are kept.
+    pub fn finish(&self, id: u64, result: &Result<(), String>) -> Option<OperationStatus> {
+        let mut inner = self.inner.lock().unwrap();
+        let operation = inner.operations.get_mut(&id)?;
+        operation.state = match result {
+            Ok(()) => OperationState::Completed,
+            Err(_) => OperationState::Failed,
+        };
+        operation.error = result.clone().err();
+        operation.finished_at = Some(timestamp());
+        let status = operation.clone();
+
+        let finished: Vec<u64> = inner
+            .operations
+            .values()
+            .filter(|operation| operation.state != OperationState::Running)
+            .map(|operation| operation.id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(FINISHED_KEPT))
+        {
+            inner.operations.remove(id);
+        }
+        Some(status)
+    }
+
+    pub fn get(&self, id: u64) -> Option<OperationStatus> {
+        self.inner.lock().unwrap().operations.get(&id).cloned()
+    }
+
+    /// All known operations, oldest first
+    pub fn list(&self) -> Vec<OperationStatus> {
+        let inner = self.inner.lock().unwrap();
+        inner.operations.values().cloned().collect()
+    }
+}
@@ -0,0 +1,200 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use int_core::{InstallProgress, UninstallProgress};
+use std::io::IsTerminal;
+use std::sync::Mutex;
+
+/// Progress display for CLI installs and uninstalls
+///
+/// Phases with a known size (extraction, verification, copying, removal)
+/// get a progress bar; other steps and log lines are printed above the
+/// bars. When stdout is not a terminal, each phase is announced on a plain
+/// line instead. Quiet progress shows nothing.
+pub struct CliProgress {
+    /// Show nothing (`--quiet`)
+    quiet: bool,
+    /// Bar container, only when drawing to a terminal
+    multi: Option<MultiProgress>,
+    /// Phases started so far, with their bar
+    phases: Mutex<Vec<(&'static str, Option<ProgressBar>)>>,
+}
+
+/// Name and payload of the event reporting an install progress update
+///
+/// The GUI emits these to its window; `--json` installs print them as
+/// `{"event": ..., "payload": ...}` lines so an elevated install can be
+/// followed the same way.
+pub fn install_event(progress: InstallProgress) -> (&'static str, serde_json::Value) {
+    let event_name = match progress {
+        InstallProgress::Extracting { .. } => "install-progress-extracting",
+        InstallProgress::Verifying { .. } => "install-progress-verifying",
+        InstallProgress::CopyingFiles { .. } => "install-progress-copying",
+        InstallProgress::SettingPermissions => "install-progress-permissions",
+        InstallProgress::ExecutingScript { .. } => "install-progress-script",
+        InstallProgress::ScriptOutput { .. } => "install-log",
+        InstallProgress::RegisteringService => "install-progress-service",
+        InstallProgress::CreatingDesktopEntry => "install-progress-desktop",
+        InstallProgress::Finalizing => "install-progress-finalizing",
+        InstallProgress::Log { .. } => "install-log",
+        InstallProgress::Completed => "install-progress-completed",
+    };
+
+    let payload = match progress {
+        InstallProgress::Extracting { current, total } => {
+            serde_json::json!({ "current": current, "total": total })
+        }
+        InstallProgress::Verifying { current, total }
+        | InstallProgress::CopyingFiles { current, total } => {
+            serde_json::json!({ "current": current as u64, "total": total as u64 })
+        }
+        InstallProgress::Log { message } => log_event("installer", message),
+        InstallProgress::ScriptOutput { line } => log_event("script", line),
+        _ => serde_json::json!({}),
+    };
+
+    (event_name, payload)
+}
+
+/// Payload of an `install-log` event
+///
+/// `source` tells installer messages ("installer") from the output of
+/// maintainer scripts ("script"); `timestamp` is in milliseconds since the
+/// Unix epoch.
+pub fn log_event(source: &str, message: impl Into<String>) -> serde_json::Value {
+    serde_json::json!({
+        "timestamp": timestamp(),
+        "source": source,
+        "message": message.into(),
+    })
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub fn timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Unit of a phase's progress
+#[derive(Clone, Copy)]
+enum Unit {
+    Bytes,
+    Files,
+}
+
+impl CliProgress {
+    pub fn new(quiet: bool) -> Self {
+        let multi = (!quiet && std::io::stdout().is_terminal())
+            .then(|| MultiProgress::with_draw_target(ProgressDrawTarget::stdout()));
+        Self {
+            quiet,
+            multi,
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Show an install progress update
+    pub fn install(&self, progress: InstallProgress) {
+        if self.quiet {
+            return;
+        }
+        match progress {
+            InstallProgress::Extracting { current, total } => {
+                self.advance("Extracting", Unit::Bytes, current, total)
+            }
+            InstallProgress::Verifying { current, total } => {
+                self.advance("Verifying", Unit::Files, current as u64, total as u64)
+            }
+            InstallProgress::CopyingFiles { current, total } => {
+                self.advance("Copying", Unit::Files, current as u64, total as u64)
+            }
+            InstallProgress::SettingPermissions => self.println("🔒 Setting permissions..."),
+            InstallProgress::ExecutingScript { script } => {
+                self.println(&format!("🔧 Running script: {}", script))
+            }
+            InstallProgress::ScriptOutput { line } => self.println(&format!("   │ {}", line)),
+            InstallProgress::RegisteringService => self.println("⚙️  Registering service..."),
+            InstallProgress::CreatingDesktopEntry => self.println("🖥️  Creating desktop entry..."),
+            InstallProgress::Finalizing => self.println("✨ Finalizing..."),
+            InstallProgress::Log { message } => self.println(&format!("📝 {}", message)),
+            InstallProgress::Completed => {
+                self.finish();
+                self.println("✅ Installation completed!");
+            }
+        }
+    }
+
+    /// Show an uninstall progress update
+    pub fn uninstall(&self, progress: UninstallProgress) {
+        if self.quiet {
+            return;
+        }
+        match progress {
+            UninstallProgress::ExecutingScript { script } => {
+                self.println(&format!("🔧 Running script: {}", script))
+            }
+            UninstallProgress::StoppingService => self.println("⚙️  Stopping service..."),
+            UninstallProgress::RemovingIntegration => {
+                self.println("🖥️  Removing system integration...")
+            }
+            UninstallProgress::RemovingFiles { current, total } => {
+                self.advance("Removing", Unit::Files, current as u64, total as u64)
+            }
+            UninstallProgress::Completed => self.finish(),
+        }
+    }
+
+    /// Move the bar of a phase, starting the phase if needed
+    fn advance(&self, phase: &'static str, unit: Unit, current: u64, total: u64) {
+        let mut phases = self.phases.lock().unwrap();
+        if !phases.iter().any(|(name, _)| *name == phase) {
+            // Earlier phases are over once a later one starts
+            for bar in phases.iter().filter_map(|(_, bar)| bar.as_ref()) {
+                bar.finish();
+            }
+            let bar = match self.multi {
+                Some(ref multi) => Some(multi.add(Self::bar(phase, unit))),
+                None => {
+                    println!("🔄 {}...", phase);
+                    None
+                }
+            };
+            phases.push((phase, bar));
+        }
+
+        if let Some((_, Some(bar))) = phases.iter().find(|(name, _)| *name == phase) {
+            bar.set_length(total);
+            bar.set_position(current);
+        }
+    }
+
+    fn bar(phase: &str, unit: Unit) -> ProgressBar {
+        let counter = match unit {
+            Unit::Bytes => "{bytes}/{total_bytes}",
+            Unit::Files => "{pos}/{len} files",
+        };
+        let template = format!("{:<10} [{{bar:30}}] {}", phase, counter);
+        let style = ProgressStyle::with_template(&template)
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> ");
+        ProgressBar::new(0).with_style(style)
+    }
+
+    /// Print a line above the bars
+    fn println(&self, line: &str) {
+        match self.multi {
+            Some(ref multi) => {
+                let _ = multi.println(line);
+            }
+            None => println!("{}", line),
+        }
+    }
+
+    /// Finish all bars, leaving them on screen
+    fn finish(&self) {
+        let phases = self.phases.lock().unwrap();
+        for bar in phases.iter().filter_map(|(_, bar)| bar.as_ref()) {
+            bar.finish();
+        }
+    }
+}
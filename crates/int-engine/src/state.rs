@@ -1,14 +1,18 @@
 use std::sync::Mutex;
 use int_core::Manifest;
+use crate::operations::OperationRegistry;
 
 pub struct AppState {
     pub current_manifest: Mutex<Option<Manifest>>,
+    /// Installs and uninstalls started from the GUI
+    pub operations: OperationRegistry,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
             current_manifest: Mutex::new(None),
+            operations: OperationRegistry::default(),
         }
     }
 }
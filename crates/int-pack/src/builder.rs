@@ -1,44 +1,122 @@
-use anyhow::{anyhow, Result};
-use flate2::write::GzEncoder;
-use flate2::Compression;
+use crate::changelog::{self, DEFAULT_CHANGELOG};
+use crate::config::{BuildConfig, BuildOptions, CONFIG_FILE};
+use crate::manifest_file;
+use crate::sbom::SbomFormat;
+use anyhow::{anyhow, bail, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use int_core::compression::Decoder;
+use int_core::extractor::{EntryKind, PackageExtractor};
 use int_core::manifest::Manifest;
+use int_core::signature::{self, SignatureTrust};
+use int_core::utils::format_bytes;
+use rayon::prelude::*;
 use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use tar::Builder;
-use tracing::info;
+use tracing::{info, warn};
 use walkdir::WalkDir;
 
 pub struct PackageBuilder {
     source_dir: PathBuf,
 }
 
+/// File or directory of the package directory to package
+pub(crate) struct SourceEntry {
+    pub(crate) source: PathBuf,
+    /// Path in the package
+    pub(crate) path: PathBuf,
+    pub(crate) is_dir: bool,
+    /// Size in bytes (0 for directories)
+    pub(crate) size: u64,
+}
+
+/// Packages with at least this many bytes of files show hashing progress
+const HASH_PROGRESS_THRESHOLD: u64 = 64 * 1024 * 1024;
+
 impl PackageBuilder {
     pub fn new(source_dir: PathBuf) -> Self {
         Self { source_dir }
     }
 
     /// Build a .int package from directory
-    pub async fn build(
-        &self,
-        output: Option<PathBuf>,
-        _compress: bool,
-        sign: bool,
-        key: Option<String>,
-    ) -> Result<PathBuf> {
-        // Force compression for .int packages to be compatible with int-core
+    ///
+    /// `intpack.toml` in the directory selects the files to package and
+    /// provides defaults for the options not given.
+    pub async fn build(&self, options: BuildOptions) -> Result<PathBuf> {
         info!("Starting package build from: {}", self.source_dir.display());
 
-        // Use int-core to parse and validate manifest
-        let manifest_path = self.source_dir.join("manifest.json");
-        let mut manifest = Manifest::from_file(&manifest_path)
+        // The manifest may be TOML or YAML; it is packaged as canonical JSON
+        let mut manifest = manifest_file::load(&self.source_dir)
             .map_err(|e| anyhow!("Failed to read manifest for build: {}", e))?;
 
+        let config = BuildConfig::load_profile(&self.source_dir, options.profile.as_deref())?;
+        if let Some(ref profile) = config.build.profile {
+            info!("Using build profile {}", profile);
+        }
+        let sign = options.sign || config.build.sign;
+        let key = options.key.or_else(|| config.build.key.clone());
+        let (compression, level) = config
+            .build
+            .compression(options.compression, options.level)?;
+        manifest.compression = Some(compression);
+
+        let changelog = options.changelog.or_else(|| {
+            config
+                .build
+                .changelog
+                .as_ref()
+                .map(|path| self.source_dir.join(path))
+        });
+        let require_changelog = options.require_changelog || config.build.require_changelog;
+        self.embed_changelog(&mut manifest, changelog, require_changelog)?;
+
+        // Determine output path based on name and version
+        let ext = ".int";
+        let default_name = format!("{}-{}{}", manifest.name, manifest.package_version, ext);
+        let output_path = options
+            .output
+            .or_else(|| {
+                config
+                    .build
+                    .output
+                    .as_ref()
+                    .map(|output| self.source_dir.join(output))
+            })
+            .unwrap_or_else(|| PathBuf::from(default_name));
+
+        let mut entries = self.collect_entries(&config, Some(&output_path))?;
+
+        // Stripped copies are packaged in place of the originals
+        let strip_dir = tempfile::tempdir()?;
+        if options.strip || config.build.strip {
+            let (stripped, saved) = self.strip_binaries(&mut entries, &config, strip_dir.path())?;
+            info!(
+                "Stripped {} binaries, saving {}",
+                stripped,
+                format_bytes(saved)
+            );
+        }
+
         // Calculate file hashes for all files that will be included
         info!("Calculating file hashes...");
-        let hashes = self.collect_file_hashes(&self.source_dir)?;
+        let mut hashes = self.collect_file_hashes(&entries)?;
+
+        // The SBOM lists the hashed files, and is hashed itself if packaged
+        let sbom_dir = tempfile::tempdir()?;
+        let mut sbom_file = None;
+        if let Some(format) = options.sbom.or(config.build.sbom) {
+            let target = options.sbom_to.or(config.build.sbom_to).unwrap_or_default();
+            let sbom = serde_json::to_string_pretty(&format.generate(&manifest, &hashes))?;
+            if target.embeds() {
+                self.embed_sbom(&mut entries, &mut hashes, format, &sbom, sbom_dir.path())?;
+            }
+            if target.writes_file() {
+                sbom_file = Some((format.path_for(&output_path), sbom));
+            }
+        }
         manifest.file_hashes = Some(hashes);
 
         // Sign manifest if requested
@@ -52,12 +130,20 @@ impl PackageBuilder {
             .validate()
             .map_err(|e| anyhow!("Manifest validation failed: {}", e))?;
 
-        // Determine output path based on name and version
-        let ext = ".int";
-        let default_name = format!("{}-{}{}", manifest.name, manifest.package_version, ext);
-        let output_path = output
-            .clone()
-            .unwrap_or_else(|| PathBuf::from(default_name));
+        for warning in manifest.warnings() {
+            warn!("{}", warning);
+        }
+
+        // Installs are refused without the agreement, so it has to be packaged
+        if let Some(ref eula) = manifest.eula {
+            if !self.source_dir.join(eula).is_file() {
+                return Err(anyhow!(
+                    "License agreement {} not found in {}",
+                    eula.display(),
+                    self.source_dir.display()
+                ));
+            }
+        }
 
         // We need to write the UPDATED manifest to a temporary location or
         // handle it specially during tar creation.
@@ -69,21 +155,122 @@ impl PackageBuilder {
         std::fs::write(&temp_manifest_path, manifest.to_canonical_string()?)?;
 
         // Create tar archive
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         let tar_file = File::create(&output_path)?;
-        let encoder = GzEncoder::new(tar_file, Compression::default());
+        let encoder = compression
+            .encoder(tar_file, level)
+            .map_err(|e| anyhow!("{}", e))?;
         let mut tar_builder = Builder::new(encoder);
 
         // Add updated manifest first
         tar_builder.append_path_with_name(&temp_manifest_path, "manifest.json")?;
 
         // Add rest of the files (skipping original manifest)
-        self.add_directory_to_tar(&mut tar_builder, &self.source_dir, true)?;
-        tar_builder.finish()?;
+        self.add_entries_to_tar(&mut tar_builder, &entries)?;
+        tar_builder.into_inner()?.finish()?;
 
-        info!("Package built: {}", output_path.display());
+        if let Some((path, sbom)) = sbom_file {
+            std::fs::write(&path, sbom)?;
+            info!("SBOM written: {}", path.display());
+        }
+
+        info!(
+            "Package built: {} ({} level {})",
+            output_path.display(),
+            compression,
+            level
+        );
         Ok(output_path)
     }
 
+    /// Embed the changelog entry for the version being built
+    ///
+    /// The entry becomes the manifest's `changelog`, shown by the installer.
+    /// Unless a changelog path is given, the manifest's `changelog_file` or
+    /// `CHANGELOG.md` is used, and an inline `changelog` is left as it is.
+    fn embed_changelog(
+        &self,
+        manifest: &mut Manifest,
+        path: Option<PathBuf>,
+        required: bool,
+    ) -> Result<()> {
+        let version = &manifest.package_version;
+        let path = match path {
+            Some(path) => path,
+            None if manifest.changelog.is_some() => return Ok(()),
+            None => {
+                let file = manifest
+                    .changelog_file
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(DEFAULT_CHANGELOG));
+                let path = self.source_dir.join(file);
+                if !path.is_file() {
+                    if required {
+                        bail!(
+                            "No changelog found for version {} ({} does not exist)",
+                            version,
+                            path.display()
+                        );
+                    }
+                    return Ok(());
+                }
+                path
+            }
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read changelog {}: {}", path.display(), e))?;
+        match changelog::find_entry(&content, version) {
+            Some(entry) => {
+                info!(
+                    "Embedding changelog entry for {} from {}",
+                    version,
+                    path.display()
+                );
+                manifest.changelog = Some(entry);
+            }
+            None if required => bail!("{} has no entry for version {}", path.display(), version),
+            None => warn!("{} has no entry for version {}", path.display(), version),
+        }
+        Ok(())
+    }
+
+    /// Add an SBOM to the files to package
+    fn embed_sbom(
+        &self,
+        entries: &mut Vec<SourceEntry>,
+        hashes: &mut BTreeMap<String, String>,
+        format: SbomFormat,
+        sbom: &str,
+        work_dir: &Path,
+    ) -> Result<()> {
+        let path = PathBuf::from(format.file_name());
+        if entries.iter().any(|entry| entry.path == path) {
+            bail!(
+                "{} would be packaged in place of the SBOM (exclude it in {})",
+                path.display(),
+                CONFIG_FILE
+            );
+        }
+
+        let source = work_dir.join(&path);
+        std::fs::write(&source, sbom)?;
+        hashes.insert(
+            format.file_name().to_string(),
+            format!("{:x}", Sha256::digest(sbom.as_bytes())),
+        );
+        entries.push(SourceEntry {
+            source,
+            path,
+            is_dir: false,
+            size: sbom.len() as u64,
+        });
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(())
+    }
+
     /// Sign manifest content using GPG
     fn sign_manifest(&self, manifest: &Manifest, key: Option<String>) -> Result<String> {
         // We sign a copy without the signature field (which should be None anyway)
@@ -126,39 +313,190 @@ impl PackageBuilder {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
-    /// Collect SHA256 hashes of all files in a directory
-    fn collect_file_hashes(&self, dir: &Path) -> Result<BTreeMap<String, String>> {
-        let mut hashes = BTreeMap::new();
+    /// Files and directories to package, ordered by their path in the package
+    ///
+    /// The manifest, the build configuration and the output itself are left
+    /// out.
+    pub(crate) fn collect_entries(
+        &self,
+        config: &BuildConfig,
+        output: Option<&Path>,
+    ) -> Result<Vec<SourceEntry>> {
+        let selector = config.selector()?;
+        let output = output.map(std::path::absolute).transpose()?;
+        let relative = |path: &Path| path.strip_prefix(&self.source_dir).map(Path::to_path_buf);
+
+        let walker = WalkDir::new(&self.source_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| match relative(entry.path()) {
+                Ok(path) => path.as_os_str().is_empty() || selector.enters(&path),
+                Err(_) => false,
+            });
+
+        let mut entries = Vec::new();
+        let mut sources = BTreeMap::new();
+        for entry in walker.filter_map(|e| e.ok()) {
+            let source = entry.path();
+            let relative = relative(source)?;
+            if relative.as_os_str().is_empty()
+                || manifest_file::is_manifest(&relative)
+                || relative == Path::new(CONFIG_FILE)
+                || Some(std::path::absolute(source)?) == output
+            {
+                continue;
+            }
 
-        for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path.is_dir() || path.file_name().and_then(|s| s.to_str()) == Some("manifest.json") {
+            let Some(path) = selector.package_path(&relative) else {
                 continue;
+            };
+            if path.to_str().is_none() {
+                bail!("Invalid path encoding: {}", relative.display());
             }
 
-            let relative = path
-                .strip_prefix(dir)?
-                .to_str()
-                .ok_or_else(|| anyhow!("Invalid path encoding"))?
-                .to_string();
+            let is_dir = source.is_dir();
+            match sources.insert(path.clone(), relative.clone()) {
+                Some(_) if is_dir => continue,
+                Some(previous) => bail!(
+                    "{} and {} are both packaged as {}",
+                    previous.display(),
+                    relative.display(),
+                    path.display()
+                ),
+                None => {}
+            }
+            let size = if is_dir {
+                0
+            } else {
+                std::fs::metadata(source)?.len()
+            };
+            entries.push(SourceEntry {
+                source: source.to_path_buf(),
+                path,
+                is_dir,
+                size,
+            });
+        }
 
-            // Skip common temporary/vcs files
-            if relative.starts_with(".git") || relative.starts_with("target") {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// Strip ELF executables and libraries in the payload
+    ///
+    /// Each is copied into `work_dir` and stripped there, leaving the
+    /// package directory untouched. Returns how many binaries were stripped
+    /// and the bytes saved.
+    pub(crate) fn strip_binaries(
+        &self,
+        entries: &mut [SourceEntry],
+        config: &BuildConfig,
+        work_dir: &Path,
+    ) -> Result<(usize, u64)> {
+        use std::process::Command;
+
+        let options = &config.strip;
+        let matcher = options.matcher()?;
+        let (mut stripped, mut saved) = (0, 0);
+
+        for entry in entries.iter_mut() {
+            if entry.is_dir
+                || !entry.path.starts_with("payload")
+                || matcher.excludes(&entry.path)
+                || !is_elf(&entry.source)?
+            {
                 continue;
             }
 
-            let hash = self.calculate_sha256(path)?;
-            hashes.insert(relative, hash);
+            let copy = work_dir.join(&entry.path);
+            if let Some(parent) = copy.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&entry.source, &copy)?;
+
+            // Read-only binaries have to be writable to strip them
+            let permissions = std::fs::metadata(&copy)?.permissions();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let writable = std::fs::Permissions::from_mode(permissions.mode() | 0o200);
+                std::fs::set_permissions(&copy, writable)?;
+            }
+
+            let output = Command::new(&options.tool)
+                .args(&options.args)
+                .arg(&copy)
+                .output()
+                .map_err(|e| anyhow!("Failed to run {}: {}", options.tool, e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = match stderr.trim() {
+                    "" => output.status.to_string(),
+                    stderr => stderr.to_string(),
+                };
+                bail!(
+                    "{} failed on {}: {} (add it to [strip] exclude in {} to skip it)",
+                    options.tool,
+                    entry.path.display(),
+                    reason,
+                    CONFIG_FILE
+                );
+            }
+            std::fs::set_permissions(&copy, permissions)?;
+
+            let size = std::fs::metadata(&copy)?.len();
+            saved += entry.size.saturating_sub(size);
+            stripped += 1;
+            entry.source = copy;
+            entry.size = size;
         }
 
-        Ok(hashes)
+        Ok((stripped, saved))
+    }
+
+    /// Collect SHA256 hashes of the files to package
+    ///
+    /// Files are hashed in parallel; large packages show a progress bar on
+    /// terminals.
+    pub(crate) fn collect_file_hashes(
+        &self,
+        entries: &[SourceEntry],
+    ) -> Result<BTreeMap<String, String>> {
+        let files: Vec<&SourceEntry> = entries.iter().filter(|entry| !entry.is_dir).collect();
+        let total: u64 = files.iter().map(|entry| entry.size).sum();
+
+        let progress = if total >= HASH_PROGRESS_THRESHOLD {
+            let style = ProgressStyle::with_template("Hashing    [{bar:30}] {bytes}/{total_bytes}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> ");
+            ProgressBar::new(total).with_style(style)
+        } else {
+            ProgressBar::hidden()
+        };
+
+        let hashes = files
+            .par_iter()
+            .map(|entry| {
+                let relative = entry
+                    .path
+                    .to_str()
+                    .ok_or_else(|| anyhow!("Invalid path encoding"))?
+                    .to_string();
+
+                let hash = self.calculate_sha256(&entry.source, &progress)?;
+                Ok((relative, hash))
+            })
+            .collect::<Result<BTreeMap<_, _>>>();
+
+        progress.finish_and_clear();
+        hashes
     }
 
     /// Calculate SHA256 hash of a file
-    fn calculate_sha256(&self, path: &Path) -> Result<String> {
+    fn calculate_sha256(&self, path: &Path, progress: &ProgressBar) -> Result<String> {
         let mut file = File::open(path)?;
         let mut hasher = Sha256::new();
-        let mut buffer = [0u8; 8192];
+        let mut buffer = [0u8; 65536];
 
         loop {
             let count = file.read(&mut buffer)?;
@@ -166,42 +504,24 @@ impl PackageBuilder {
                 break;
             }
             hasher.update(&buffer[..count]);
+            progress.inc(count as u64);
         }
 
         Ok(format!("{:x}", hasher.finalize()))
     }
 
-    /// Add directory contents to tar archive
-    fn add_directory_to_tar<W: std::io::Write>(
+    /// Add the files to package to tar archive
+    pub(crate) fn add_entries_to_tar<W: std::io::Write>(
         &self,
         tar: &mut Builder<W>,
-        dir: &Path,
-        skip_manifest: bool,
+        entries: &[SourceEntry],
     ) -> Result<()> {
-        for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path == dir {
-                continue;
-            }
-
-            let relative = path.strip_prefix(dir)?;
-            let rel_str = relative.to_str().unwrap_or("");
-
-            // Skip manifest.json if requested (because we already added the updated one)
-            if skip_manifest && rel_str == "manifest.json" {
-                continue;
-            }
-
-            // Skip common temporary/vcs files if they accidentally exist
-            if rel_str.starts_with(".git") || rel_str.starts_with("target") {
-                continue;
-            }
-
-            if path.is_dir() {
-                tar.append_dir(relative, path)?;
+        for entry in entries {
+            if entry.is_dir {
+                tar.append_dir(&entry.path, &entry.source)?;
             } else {
-                let mut file = File::open(path)?;
-                tar.append_file(relative, &mut file)?;
+                let mut file = File::open(&entry.source)?;
+                tar.append_file(&entry.path, &mut file)?;
             }
         }
         Ok(())
@@ -209,22 +529,23 @@ impl PackageBuilder {
 
     /// Show package information
     pub async fn show_info(&self) -> Result<()> {
-        let manifest_path = if self.source_dir.is_file() {
-            // If it's a file, it might be a .int package, but for now int-pack info
-            // seems designed for source directories.
-            // TODO: Support reading from .int archive directly
-            return Err(anyhow!("Currently 'info' command only supports package source directories. Reading from .int files coming soon."));
-        } else {
-            self.source_dir.join("manifest.json")
+        // A file is a built package, read without extracting it
+        let package = Some(self.source_dir.as_path()).filter(|path| path.is_file());
+        let extractor = PackageExtractor::new();
+
+        let manifest = match package {
+            Some(path) => extractor
+                .validate_package(path)
+                .map_err(|e| anyhow!("Failed to read manifest: {}", e))?,
+            None => manifest_file::load(&self.source_dir)
+                .map_err(|e| anyhow!("Failed to read manifest: {}", e))?,
         };
 
-        let manifest = Manifest::from_file(manifest_path)
-            .map_err(|e| anyhow!("Failed to read manifest: {}", e))?;
-
         println!("\n📦 Package Information:\n");
         println!("Name:         {}", manifest.name);
         println!("Display Name: {}", manifest.display_name());
         println!("Version:      {}", manifest.package_version);
+        println!("Kind:         {:?}", manifest.kind);
         println!(
             "Description:  {}",
             manifest.description.as_deref().unwrap_or("N/A")
@@ -243,16 +564,95 @@ impl PackageBuilder {
             "Auto-Launch:  {} (Command: {})",
             manifest.auto_launch,
             manifest
-                .launch_command
-                .as_deref()
-                .or(manifest.entry.as_deref())
-                .unwrap_or("none")
+                .launch_command()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "none".to_string())
         );
 
+        if !manifest.entries.is_empty() {
+            println!("Executables:  {}", manifest.executable_entries().join(", "));
+        }
+
         if let Some(ref desktop) = manifest.desktop {
             println!("UI Categories: {:?}", desktop.categories);
         }
 
+        if let Some(path) = package {
+            self.show_archive_info(path)?;
+        }
+
+        let changelog = match package {
+            Some(path) => extractor.read_changelog(path).ok().flatten(),
+            None => manifest.changelog.clone().or_else(|| {
+                manifest
+                    .changelog_file
+                    .as_ref()
+                    .and_then(|file| std::fs::read_to_string(self.source_dir.join(file)).ok())
+            }),
+        };
+        if let Some(changelog) = changelog {
+            println!("\n📝 Changelog:\n");
+            println!("{}", changelog.trim_end());
+        }
+
+        Ok(())
+    }
+
+    /// Print size and signature of a built package
+    fn show_archive_info(&self, package_path: &Path) -> Result<()> {
+        let entries = PackageExtractor::new()
+            .list_entries(package_path)
+            .map_err(|e| anyhow!("Failed to read package: {}", e))?;
+        let files = entries
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::File)
+            .count();
+        let uncompressed: u64 = entries.iter().map(|entry| entry.size).sum();
+        let compressed = std::fs::metadata(package_path)?.len();
+        let compression = Decoder::open(package_path)
+            .map_err(|e| anyhow!("Failed to read package: {}", e))?
+            .compression();
+
+        let info = signature::inspect(package_path)
+            .map_err(|e| anyhow!("Failed to check signature: {}", e))?;
+        let signer = info.signer.as_deref().unwrap_or("unknown signer");
+        let trust = match info.trust {
+            SignatureTrust::Unsigned => "not signed".to_string(),
+            SignatureTrust::Trusted => format!("trusted ({})", signer),
+            SignatureTrust::Untrusted => format!("good but untrusted ({})", signer),
+            SignatureTrust::UnknownKey => {
+                format!("unknown key {}", info.key_id.as_deref().unwrap_or(""))
+            }
+            SignatureTrust::Invalid => format!(
+                "INVALID ({})",
+                info.detail.as_deref().unwrap_or("bad signature")
+            ),
+        };
+
+        println!("\n📁 Archive:\n");
+        println!("Entries:      {} ({} files)", entries.len(), files);
+        println!(
+            "Size:         {} compressed, {} uncompressed",
+            format_bytes(compressed),
+            format_bytes(uncompressed)
+        );
+        println!("Compression:  {}", compression);
+        println!("Signature:    {}", trust);
+        println!(
+            "File Hashes:  {}/{} files verified",
+            info.hashes.verified_files, info.hashes.total_files
+        );
         Ok(())
     }
 }
+
+/// Whether a file is an ELF executable or shared object
+fn is_elf(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; 4];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == b"\x7fELF"),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,422 @@
+use crate::changelog::{self, DEFAULT_CHANGELOG};
+use crate::manifest_file::{self, ManifestFormat};
+use anyhow::{anyhow, bail, Result};
+use int_core::utils::compare_versions;
+use semver::{BuildMetadata, Prerelease, Version};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// New version asked of `int-pack bump`
+#[derive(Debug, Clone)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+    Version(Version),
+}
+
+impl FromStr for Bump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "major" => Ok(Bump::Major),
+            "minor" => Ok(Bump::Minor),
+            "patch" => Ok(Bump::Patch),
+            _ => Version::parse(s.strip_prefix('v').unwrap_or(s))
+                .map(Bump::Version)
+                .map_err(|e| {
+                    format!(
+                        "expected major, minor, patch or a version like 1.2.0 ({})",
+                        e
+                    )
+                }),
+        }
+    }
+}
+
+impl Bump {
+    /// Version following `current`
+    ///
+    /// As with npm and cargo, bumping a pre-release releases it when it is
+    /// a pre-release of that level (`1.3.0-rc.1` bumps to `1.3.0` for minor)
+    /// and build metadata is dropped.
+    pub fn apply(&self, current: &str) -> Result<Version> {
+        if let Bump::Version(version) = self {
+            if compare_versions(&version.to_string(), current) != Ordering::Greater {
+                bail!(
+                    "{} is not newer than the current version {}",
+                    version,
+                    current
+                );
+            }
+            return Ok(version.clone());
+        }
+
+        let mut version = parse_lenient(current).ok_or_else(|| {
+            anyhow!(
+                "package_version {} is not a semantic version; give the new version instead",
+                current
+            )
+        })?;
+        let pre_release = !version.pre.is_empty();
+        match self {
+            Bump::Major if pre_release && version.minor == 0 && version.patch == 0 => {}
+            Bump::Major => {
+                version.major += 1;
+                version.minor = 0;
+                version.patch = 0;
+            }
+            Bump::Minor if pre_release && version.patch == 0 => {}
+            Bump::Minor => {
+                version.minor += 1;
+                version.patch = 0;
+            }
+            Bump::Patch if pre_release => {}
+            Bump::Patch => version.patch += 1,
+            Bump::Version(_) => unreachable!(),
+        }
+        version.pre = Prerelease::EMPTY;
+        version.build = BuildMetadata::EMPTY;
+        Ok(version)
+    }
+}
+
+/// Parse a version, completing versions like `1.2` with zeros
+fn parse_lenient(version: &str) -> Option<Version> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let (release, rest) = match version.find(['-', '+']) {
+        Some(i) => version.split_at(i),
+        None => (version, ""),
+    };
+    let parts = release.split('.').count();
+    if parts > 3 {
+        return None;
+    }
+    let padded = format!("{}{}{}", release, ".0".repeat(3 - parts), rest);
+    Version::parse(&padded).ok()
+}
+
+/// Updates the version of a package directory
+pub struct VersionBumper {
+    source_dir: PathBuf,
+}
+
+impl VersionBumper {
+    pub fn new(source_dir: PathBuf) -> Self {
+        Self { source_dir }
+    }
+
+    /// Set the manifest's `package_version`, returning the old and new
+    /// versions
+    ///
+    /// With `changelog`, the changelog gets a heading for the new version.
+    /// With `tag`, the changed files are committed to git and the commit is
+    /// tagged `v<version>`.
+    pub fn bump(&self, bump: &Bump, changelog: bool, tag: bool) -> Result<(String, String)> {
+        let manifest_path = manifest_file::find(&self.source_dir)?;
+        let format = ManifestFormat::from_path(&manifest_path)?;
+        let content = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", manifest_path.display(), e))?;
+        let manifest = format
+            .parse(&content)
+            .map_err(|e| anyhow!("Failed to read manifest: {}", e))?;
+
+        let old = manifest.package_version.clone();
+        let new = bump.apply(&old)?.to_string();
+        if tag {
+            self.check_tag(&new)?;
+        }
+
+        // The manifest is edited in place to keep its formatting
+        let updated = set_package_version(&content, format, &old, &new)?;
+        std::fs::write(&manifest_path, updated)?;
+        // Relative to the package directory, where git runs
+        let mut changed = vec![PathBuf::from(format.file_name())];
+
+        if changelog {
+            let file = manifest
+                .changelog_file
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_CHANGELOG));
+            if self.update_changelog(&self.source_dir.join(&file), &new)? {
+                changed.push(file);
+            }
+        }
+
+        if tag {
+            self.commit_and_tag(&manifest.name, &new, &changed)?;
+        }
+        Ok((old, new))
+    }
+
+    /// Add a heading for the version to the changelog, creating it if needed
+    fn update_changelog(&self, path: &Path, version: &str) -> Result<bool> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => "# Changelog\n".to_string(),
+            Err(e) => bail!("Failed to read {}: {}", path.display(), e),
+        };
+        let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+        match changelog::release(&content, version, &date) {
+            Some(updated) => {
+                std::fs::write(path, updated)?;
+                info!("Added {} to {}", version, path.display());
+                Ok(true)
+            }
+            None => {
+                warn!("{} already has an entry for {}", path.display(), version);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Make sure the version can be tagged before changing anything
+    fn check_tag(&self, version: &str) -> Result<()> {
+        self.git(&["rev-parse", "--git-dir"])?;
+        let tag = format!("v{}", version);
+        if self.git(&["tag", "--list", &tag])?.trim() == tag {
+            bail!("Git tag {} already exists", tag);
+        }
+        Ok(())
+    }
+
+    fn commit_and_tag(&self, name: &str, version: &str, files: &[PathBuf]) -> Result<()> {
+        let message = format!("Release {} {}", name, version);
+        let tag = format!("v{}", version);
+
+        let files: Vec<String> = files
+            .iter()
+            .map(|file| file.to_string_lossy().into_owned())
+            .collect();
+        let files = files.iter().map(String::as_str);
+
+        // A new changelog has to be added before it can be committed
+        self.git(
+            &["add", "--"]
+                .into_iter()
+                .chain(files.clone())
+                .collect::<Vec<_>>(),
+        )?;
+        self.git(
+            &["commit", "-m", &message, "--"]
+                .into_iter()
+                .chain(files)
+                .collect::<Vec<_>>(),
+        )?;
+        self.git(&["tag", "-a", &tag, "-m", &message])?;
+
+        info!("Committed and tagged {}", tag);
+        Ok(())
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.source_dir)
+            .args(args)
+            .output()
+            .map_err(|e| anyhow!("Failed to run git: {}", e))?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Replace the `package_version` value in manifest text
+fn set_package_version(
+    content: &str,
+    format: ManifestFormat,
+    old: &str,
+    new: &str,
+) -> Result<String> {
+    let (key, separator) = match format {
+        ManifestFormat::Json => ("\"package_version\"", ':'),
+        ManifestFormat::Toml => ("package_version", '='),
+        ManifestFormat::Yaml => ("package_version", ':'),
+    };
+    // TOML and YAML strings may also be in single quotes, YAML ones in none
+    let quotes: &[&str] = match format {
+        ManifestFormat::Yaml => &["\"", "'", ""],
+        _ => &["\"", "'"],
+    };
+    let mut search = 0;
+    while let Some(found) = content[search..].find(key) {
+        let after_key = search + found + key.len();
+        let value = content[after_key..]
+            .trim_start()
+            .strip_prefix(separator)
+            .map(str::trim_start);
+        let old_value = value.and_then(|value| {
+            quotes
+                .iter()
+                .map(|quote| (*quote, format!("{0}{1}{0}", quote, old)))
+                .find(|(quote, old_value)| {
+                    // An unquoted value ends at whitespace or the line end
+                    value.strip_prefix(old_value.as_str()).is_some_and(|rest| {
+                        !quote.is_empty() || rest.chars().next().is_none_or(char::is_whitespace)
+                    })
+                })
+                .map(|(quote, old_value)| (content.len() - value.len(), quote, old_value))
+        });
+        if let Some((start, quote, old_value)) = old_value {
+            let updated = format!(
+                "{}{}{}{}{}",
+                &content[..start],
+                quote,
+                new,
+                quote,
+                &content[start + old_value.len()..]
+            );
+            // Make sure the right key was changed
+            let manifest = format
+                .parse(&updated)
+                .map_err(|e| anyhow!("Failed to update manifest: {}", e))?;
+            if manifest.package_version == new {
+                return Ok(updated);
+            }
+        }
+        search = after_key;
+    }
+    bail!(
+        "Could not find package_version \"{}\" in {}",
+        old,
+        format.file_name()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bumped(bump: Bump, current: &str) -> String {
+        bump.apply(current).unwrap().to_string()
+    }
+
+    fn toml_manifest(fields: &str) -> String {
+        format!(
+            "version = \"1.0\"\nname = \"myapp\"\n{}\ninstall_scope = \"user\"\n\
+             install_path = \"/home/user/.local/share/myapp\"\n",
+            fields
+        )
+    }
+
+    #[test]
+    fn test_bump_apply() {
+        assert_eq!(bumped(Bump::Patch, "1.2.3"), "1.2.4");
+        assert_eq!(bumped(Bump::Minor, "1.2.3"), "1.3.0");
+        assert_eq!(bumped(Bump::Major, "1.2.3"), "2.0.0");
+        assert_eq!(bumped(Bump::Patch, "1.2.3+build.5"), "1.2.4");
+
+        // A pre-release of the bumped level is released
+        assert_eq!(bumped(Bump::Patch, "1.3.0-rc.1"), "1.3.0");
+        assert_eq!(bumped(Bump::Minor, "1.3.0-rc.1"), "1.3.0");
+        assert_eq!(bumped(Bump::Major, "1.3.0-rc.1"), "2.0.0");
+        assert_eq!(bumped(Bump::Major, "2.0.0-beta"), "2.0.0");
+        assert_eq!(bumped(Bump::Minor, "1.2.1-rc.1"), "1.3.0");
+
+        // Short versions are completed before bumping
+        assert_eq!(bumped(Bump::Patch, "1.2"), "1.2.1");
+        assert_eq!(bumped(Bump::Minor, "v1"), "1.1.0");
+        assert!(Bump::Patch.apply("2024.01.02.1").is_err());
+
+        let explicit: Bump = "v2.0.0".parse().unwrap();
+        assert_eq!(bumped(explicit.clone(), "1.2.3"), "2.0.0");
+        assert!(explicit.apply("2.0.0").is_err());
+        assert!(explicit.apply("2.1").is_err());
+        assert!("next".parse::<Bump>().is_err());
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        assert_eq!(parse_lenient("1.2"), Some(Version::new(1, 2, 0)));
+        assert_eq!(parse_lenient("v1"), Some(Version::new(1, 0, 0)));
+        assert_eq!(
+            parse_lenient("1.2-rc.1"),
+            Some(Version::parse("1.2.0-rc.1").unwrap())
+        );
+        assert_eq!(
+            parse_lenient("1.2+build"),
+            Some(Version::parse("1.2.0+build").unwrap())
+        );
+        assert_eq!(parse_lenient("1.2.3.4"), None);
+        assert_eq!(parse_lenient("latest"), None);
+    }
+
+    #[test]
+    fn test_set_package_version_keeps_quotes() {
+        let double = toml_manifest("package_version = \"1.0.0\"");
+        let updated = set_package_version(&double, ManifestFormat::Toml, "1.0.0", "1.1.0").unwrap();
+        assert_eq!(updated, toml_manifest("package_version = \"1.1.0\""));
+
+        let single = toml_manifest("package_version='1.0.0'");
+        let updated = set_package_version(&single, ManifestFormat::Toml, "1.0.0", "1.1.0").unwrap();
+        assert_eq!(updated, toml_manifest("package_version='1.1.0'"));
+
+        let json = r#"{
+  "version": "1.0",
+  "name": "myapp",
+  "package_version" : "1.0.0",
+  "install_scope": "user",
+  "install_path": "/home/user/.local/share/myapp"
+}
+"#;
+        let updated = set_package_version(json, ManifestFormat::Json, "1.0.0", "1.1.0").unwrap();
+        assert_eq!(
+            updated,
+            json.replace(
+                r#""package_version" : "1.0.0""#,
+                r#""package_version" : "1.1.0""#
+            )
+        );
+
+        assert!(set_package_version(&double, ManifestFormat::Toml, "0.9.0", "1.1.0").is_err());
+    }
+
+    #[test]
+    fn test_set_package_version_yaml() {
+        let yaml = |version: &str| {
+            format!(
+                "version: \"1.0\"\nname: myapp\npackage_version: {}  # released\n\
+                 install_scope: user\ninstall_path: /home/user/.local/share/myapp\n",
+                version
+            )
+        };
+        for (old, new) in [
+            ("1.0.0", "1.1.0"),
+            ("'1.0.0'", "'1.1.0'"),
+            ("\"1.0.0\"", "\"1.1.0\""),
+        ] {
+            let updated =
+                set_package_version(&yaml(old), ManifestFormat::Yaml, "1.0.0", "1.1.0").unwrap();
+            assert_eq!(updated, yaml(new));
+        }
+
+        // 1.0 is only the start of the unquoted value
+        assert!(set_package_version(&yaml("1.0.0"), ManifestFormat::Yaml, "1.0", "1.1").is_err());
+    }
+
+    #[test]
+    fn test_set_package_version_picks_key() {
+        // The first occurrence is inside the description
+        let content = toml_manifest(
+            "description = 'Set package_version = \"1.0.0\" to release'\npackage_version = \"1.0.0\"",
+        );
+        let updated =
+            set_package_version(&content, ManifestFormat::Toml, "1.0.0", "2.0.0").unwrap();
+        assert_eq!(
+            updated,
+            toml_manifest(
+                "description = 'Set package_version = \"1.0.0\" to release'\npackage_version = \"2.0.0\"",
+            )
+        );
+    }
+}
@@ -0,0 +1,127 @@
+/// Changelog file looked for in the package directory
+pub const DEFAULT_CHANGELOG: &str = "CHANGELOG.md";
+
+/// Entry of a Markdown changelog for one version
+///
+/// Entries start with a heading naming the version, in any of the usual
+/// forms (`## 1.2.0`, `## [1.2.0] - 2024-05-01`, `### v1.2.0`), and run up
+/// to the next heading of the same or a higher level. The entry is returned
+/// with its heading.
+pub fn find_entry(changelog: &str, version: &str) -> Option<String> {
+    let lines: Vec<&str> = changelog.lines().collect();
+    let (start, level) = lines.iter().enumerate().find_map(|(i, line)| {
+        let (level, title) = heading(line)?;
+        names_version(title, version).then_some((i, level))
+    })?;
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| matches!(heading(line), Some((l, _)) if l <= level))
+        .map_or(lines.len(), |i| start + 1 + i);
+
+    let entry = lines[start..end].join("\n");
+    Some(format!("{}\n", entry.trim_end()))
+}
+
+/// Level and title of a Markdown heading line
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = line[level..].strip_prefix(' ')?;
+    (level > 0).then_some((level, title.trim()))
+}
+
+/// Whether a heading names the version, e.g. "[1.2.0] - 2024-05-01"
+fn names_version(title: &str, version: &str) -> bool {
+    title
+        .split(|c: char| c.is_whitespace() || "[]():,".contains(c))
+        .any(|word| word.strip_prefix(['v', 'V']).unwrap_or(word) == version)
+}
+
+/// Add a heading for a new version to a Markdown changelog
+///
+/// An "Unreleased" heading becomes the version's; otherwise a heading is
+/// added above the newest entry, following its style. Returns `None` when
+/// the changelog already has an entry for the version.
+pub fn release(changelog: &str, version: &str, date: &str) -> Option<String> {
+    if find_entry(changelog, version).is_some() {
+        return None;
+    }
+
+    let mut lines: Vec<String> = changelog.lines().map(str::to_string).collect();
+    let unreleased = lines.iter().position(|line| {
+        heading(line).is_some_and(|(_, title)| {
+            title
+                .trim_matches(['[', ']'])
+                .eq_ignore_ascii_case("unreleased")
+        })
+    });
+
+    match unreleased {
+        Some(i) => {
+            let (level, title) = heading(&lines[i]).unwrap();
+            lines[i] = entry_heading(level, title.starts_with('['), version, date);
+        }
+        None => {
+            // Entries are the headings below the title, if there is one
+            let newest = lines
+                .iter()
+                .enumerate()
+                .filter_map(|(i, line)| heading(line).map(|(level, title)| (i, level, title)))
+                .find(|&(_, level, _)| level > 1);
+            let (i, level, bracketed) = match newest {
+                Some((i, level, title)) => (i, level, title.starts_with('[')),
+                None => {
+                    if lines.last().is_some_and(|line| !line.is_empty()) {
+                        lines.push(String::new());
+                    }
+                    (lines.len(), 2, false)
+                }
+            };
+            lines.insert(i, entry_heading(level, bracketed, version, date));
+            lines.insert(i + 1, String::new());
+        }
+    }
+    Some(format!("{}\n", lines.join("\n").trim_end()))
+}
+
+fn entry_heading(level: usize, bracketed: bool, version: &str, date: &str) -> String {
+    let version = if bracketed {
+        format!("[{}]", version)
+    } else {
+        version.to_string()
+    };
+    format!("{} {} - {}", "#".repeat(level), version, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_unreleased() {
+        let changelog =
+            "# Changelog\n\n## [Unreleased]\n\n- Fix crash\n\n## [1.0.0] - 2024-01-01\n\n- First\n";
+        assert_eq!(
+            release(changelog, "1.1.0", "2024-05-01").unwrap(),
+            "# Changelog\n\n## [1.1.0] - 2024-05-01\n\n- Fix crash\n\n## [1.0.0] - 2024-01-01\n\n- First\n"
+        );
+    }
+
+    #[test]
+    fn test_release_follows_newest_entry() {
+        let changelog = "# Changelog\n\n### v1.0.0 - 2024-01-01\n\n- First\n";
+        assert_eq!(
+            release(changelog, "1.1.0", "2024-05-01").unwrap(),
+            "# Changelog\n\n### 1.1.0 - 2024-05-01\n\n### v1.0.0 - 2024-01-01\n\n- First\n"
+        );
+
+        // Without entries, the heading goes at the end
+        assert_eq!(
+            release("# Changelog\n", "1.0.0", "2024-05-01").unwrap(),
+            "# Changelog\n\n## 1.0.0 - 2024-05-01\n"
+        );
+
+        // Versions with an entry are left alone
+        assert_eq!(release(changelog, "1.0.0", "2024-05-01"), None);
+    }
+}
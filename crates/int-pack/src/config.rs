@@ -0,0 +1,573 @@
+use crate::sbom::{SbomFormat, SbomTarget};
+use anyhow::{anyhow, bail, Result};
+use glob::{MatchOptions, Pattern};
+use int_core::compression::Compression;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Build configuration file, next to the manifest
+pub const CONFIG_FILE: &str = "intpack.toml";
+
+/// Left out of packages unless `exclude` is set
+const DEFAULT_EXCLUDE: &[&str] = &[".git*", "/target"];
+
+/// Settings read from `intpack.toml`
+///
+/// Every section is optional; without the file, the whole package
+/// directory is packaged as-is.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+    pub build: BuildOptions,
+    pub files: FileRules,
+    pub strip: StripOptions,
+    /// Rules moving source paths to other paths in the package
+    pub remap: Vec<Remap>,
+    /// Named profiles, selected with `--profile`
+    pub profile: BTreeMap<String, Profile>,
+}
+
+/// Options of `int-pack build`
+///
+/// Read from the command line and from `[build]`, which provides defaults
+/// for the options not given on the command line. Paths in `[build]` are
+/// relative to the package directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildOptions {
+    /// Output path
+    pub output: Option<PathBuf>,
+    pub sign: bool,
+    /// GPG key ID to sign with
+    pub key: Option<String>,
+    /// Compression of the archive (gzip when not set)
+    pub compression: Option<Compression>,
+    /// Compression level, used with `compression` only
+    pub level: Option<u32>,
+    /// Strip ELF binaries in the payload
+    pub strip: bool,
+    /// Changelog to embed the current version's entry from
+    pub changelog: Option<PathBuf>,
+    /// Fail the build when the changelog has no entry for the version
+    pub require_changelog: bool,
+    /// Generate an SBOM in this format
+    pub sbom: Option<SbomFormat>,
+    /// Where to put the SBOM (both when not set)
+    pub sbom_to: Option<SbomTarget>,
+    /// Profile to build with
+    pub profile: Option<String>,
+}
+
+impl BuildOptions {
+    /// Compression and level to build with, given those chosen on the
+    /// command line
+    pub fn compression(
+        &self,
+        compression: Option<Compression>,
+        level: Option<u32>,
+    ) -> Result<(Compression, u32)> {
+        let compression = compression.or(self.compression).unwrap_or_default();
+        // A level for one algorithm means nothing for another
+        let level = match level {
+            Some(level) => level,
+            None if self.compression == Some(compression) => {
+                self.level.unwrap_or_else(|| compression.default_level())
+            }
+            None => compression.default_level(),
+        };
+        compression
+            .check_level(level)
+            .map_err(|e| anyhow!("{}", e))?;
+        Ok((compression, level))
+    }
+}
+
+/// Build options for one kind of build, e.g. `dev` or `release`
+///
+/// Options set in a profile replace those in `[build]`; `exclude` and
+/// `remap` add to `[files]` and `[[remap]]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub output: Option<PathBuf>,
+    pub sign: Option<bool>,
+    pub key: Option<String>,
+    pub compression: Option<Compression>,
+    pub level: Option<u32>,
+    pub strip: Option<bool>,
+    pub changelog: Option<PathBuf>,
+    pub require_changelog: Option<bool>,
+    pub sbom: Option<SbomFormat>,
+    pub sbom_to: Option<SbomTarget>,
+    /// More paths to leave out
+    pub exclude: Vec<String>,
+    /// More files to package, applied before `[[remap]]`
+    pub remap: Vec<Remap>,
+}
+
+/// Glob patterns selecting the files to package
+///
+/// Patterns containing a `/` match whole paths (a leading `/` only anchors
+/// them); other patterns match file and directory names at any depth.
+/// Including or excluding a directory does the same to everything in it.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileRules {
+    /// Paths in the package to keep, after remapping; everything when empty
+    pub include: Vec<String>,
+    /// Paths in the package directory to leave out
+    pub exclude: Vec<String>,
+}
+
+impl Default for FileRules {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: DEFAULT_EXCLUDE.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// How binaries are stripped (`int-pack build --strip`)
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StripOptions {
+    /// Program to run, e.g. `aarch64-linux-gnu-strip` for cross builds
+    pub tool: String,
+    /// Arguments given before the file
+    pub args: Vec<String>,
+    /// Paths in the package not to strip, in the `[files]` pattern syntax
+    pub exclude: Vec<String>,
+}
+
+impl Default for StripOptions {
+    fn default() -> Self {
+        Self {
+            tool: "strip".to_string(),
+            args: vec!["--strip-unneeded".to_string()],
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl StripOptions {
+    /// Compile the `exclude` patterns
+    pub fn matcher(&self) -> Result<FileMatcher> {
+        FileMatcher::new(&[], &self.exclude)
+    }
+}
+
+/// Package a source file or directory under another path
+///
+/// E.g. `from = "build/release/myapp"`, `to = "payload/bin/myapp"`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Remap {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl Remap {
+    /// Path in the package of a source path under `from`
+    fn apply(&self, path: &Path) -> Option<PathBuf> {
+        let rest = path.strip_prefix(&self.from).ok()?;
+        if rest.as_os_str().is_empty() {
+            Some(self.to.clone())
+        } else {
+            Some(self.to.join(rest))
+        }
+    }
+}
+
+impl BuildConfig {
+    /// Read `intpack.toml` from a package directory, if there is one
+    pub fn load(source_dir: &Path) -> Result<Self> {
+        let path = source_dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let config: Self =
+            toml::from_str(&content).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read `intpack.toml` and apply a profile to it
+    ///
+    /// Without `profile`, the `profile` set in `[build]` is applied, if any.
+    pub fn load_profile(source_dir: &Path, profile: Option<&str>) -> Result<Self> {
+        let config = Self::load(source_dir)?;
+        match profile.map(str::to_string).or(config.build.profile.clone()) {
+            Some(name) => config.with_profile(&name),
+            None => Ok(config),
+        }
+    }
+
+    /// Apply `[profile.<name>]` over the other settings
+    pub fn with_profile(mut self, name: &str) -> Result<Self> {
+        let Some(profile) = self.profile.remove(name) else {
+            let names: Vec<&str> = self.profile.keys().map(String::as_str).collect();
+            bail!(
+                "Unknown build profile '{}' (profiles in {}: {})",
+                name,
+                CONFIG_FILE,
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            );
+        };
+
+        let build = &mut self.build;
+        build.output = profile.output.or(build.output.take());
+        build.sign = profile.sign.unwrap_or(build.sign);
+        build.key = profile.key.or(build.key.take());
+        // A level set in [build] is for the compression set there
+        if profile.compression.is_some() {
+            build.compression = profile.compression;
+            build.level = profile.level;
+        } else {
+            build.level = profile.level.or(build.level);
+        }
+        build.strip = profile.strip.unwrap_or(build.strip);
+        build.changelog = profile.changelog.or(build.changelog.take());
+        build.require_changelog = profile.require_changelog.unwrap_or(build.require_changelog);
+        build.sbom = profile.sbom.or(build.sbom);
+        build.sbom_to = profile.sbom_to.or(build.sbom_to);
+        build.profile = Some(name.to_string());
+
+        self.files.exclude.extend(profile.exclude);
+        self.remap.splice(0..0, profile.remap);
+        Ok(self)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let profile_remaps = self.profile.values().flat_map(|profile| &profile.remap);
+        for remap in self.remap.iter().chain(profile_remaps) {
+            for path in [&remap.from, &remap.to] {
+                let relative = !path.as_os_str().is_empty()
+                    && path
+                        .components()
+                        .all(|component| matches!(component, Component::Normal(_)));
+                if !relative {
+                    bail!(
+                        "Remap path '{}' must be relative to the package directory",
+                        path.display()
+                    );
+                }
+            }
+            if remap.to == Path::new("manifest.json") {
+                bail!("Remap target cannot be manifest.json");
+            }
+        }
+        self.strip.matcher()?;
+        for profile in self.profile.values() {
+            FileMatcher::new(&[], &profile.exclude)?;
+        }
+        self.selector().map(|_| ())
+    }
+
+    /// Compile the file rules
+    pub fn selector(&self) -> Result<FileSelector<'_>> {
+        Ok(FileSelector {
+            remap: &self.remap,
+            matcher: self.files.matcher()?,
+        })
+    }
+}
+
+/// Decides which files of the package directory are packaged, and where
+pub struct FileSelector<'a> {
+    remap: &'a [Remap],
+    matcher: FileMatcher,
+}
+
+impl FileSelector<'_> {
+    /// Path in the package of a source path, or `None` to leave it out
+    ///
+    /// The first remap rule whose `from` contains the path applies. Remapped
+    /// files are packaged even if a directory above `from` is excluded, so
+    /// build directories can be excluded while their output is remapped.
+    pub fn package_path(&self, source: &Path) -> Option<PathBuf> {
+        let remap = self
+            .remap
+            .iter()
+            .find(|remap| source.starts_with(&remap.from));
+        let excluded = source
+            .ancestors()
+            .take_while(|path| match remap {
+                Some(remap) => path.starts_with(&remap.from),
+                None => !path.as_os_str().is_empty(),
+            })
+            .any(|path| self.matcher.is_excluded(path));
+        if excluded {
+            return None;
+        }
+
+        let path = remap
+            .and_then(|remap| remap.apply(source))
+            .unwrap_or_else(|| source.to_path_buf());
+        Some(path).filter(|path| self.matcher.is_included(path))
+    }
+
+    /// Whether a directory can hold files to package
+    pub fn enters(&self, dir: &Path) -> bool {
+        !self.matcher.is_excluded(dir) || self.remap.iter().any(|remap| remap.from.starts_with(dir))
+    }
+}
+
+impl FileRules {
+    fn matcher(&self) -> Result<FileMatcher> {
+        FileMatcher::new(&self.include, &self.exclude)
+    }
+}
+
+/// Compiled include and exclude patterns
+pub struct FileMatcher {
+    include: Vec<Rule>,
+    exclude: Vec<Rule>,
+}
+
+impl FileMatcher {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |patterns: &[String]| -> Result<Vec<Rule>> {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    // Paths are relative, so the anchor is dropped
+                    let anchored = pattern.strip_prefix('/');
+                    let glob = Pattern::new(anchored.unwrap_or(pattern))
+                        .map_err(|e| anyhow!("Invalid pattern '{}': {}", pattern, e))?;
+                    Ok(Rule {
+                        glob,
+                        whole_path: anchored.is_some() || pattern.contains('/'),
+                    })
+                })
+                .collect()
+        };
+        Ok(FileMatcher {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Whether a path itself, not a directory it is in, is excluded
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude.iter().any(|rule| rule.matches(path))
+    }
+
+    /// Whether a path, or a directory it is in, is excluded
+    pub fn excludes(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|path| !path.as_os_str().is_empty())
+            .any(|path| self.is_excluded(path))
+    }
+
+    /// Whether a path, or a directory it is in, is included
+    fn is_included(&self, path: &Path) -> bool {
+        self.include.is_empty()
+            || path
+                .ancestors()
+                .any(|path| self.include.iter().any(|rule| rule.matches(path)))
+    }
+}
+
+struct Rule {
+    glob: Pattern,
+    /// Match the whole path rather than the name
+    whole_path: bool,
+}
+
+impl Rule {
+    fn matches(&self, path: &Path) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        if self.whole_path {
+            return self.glob.matches_path_with(path, options);
+        }
+        path.file_name()
+            .map(|name| self.glob.matches_with(&name.to_string_lossy(), options))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(content: &str) -> BuildConfig {
+        toml::from_str(content).unwrap()
+    }
+
+    fn matcher(include: &[&str], exclude: &[&str]) -> FileMatcher {
+        let rules = FileRules {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+        };
+        rules.matcher().unwrap()
+    }
+
+    fn package_path(selector: &FileSelector, source: &str) -> Option<String> {
+        selector
+            .package_path(Path::new(source))
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn test_file_rules() {
+        let matcher = matcher(&[], &["*.log", "/target", "docs/*.tmp"]);
+
+        // Name-only patterns match at any depth
+        assert!(matcher.is_excluded(Path::new("app.log")));
+        assert!(matcher.is_excluded(Path::new("var/logs/app.log")));
+
+        // Patterns with a slash match whole paths
+        assert!(matcher.is_excluded(Path::new("target")));
+        assert!(!matcher.is_excluded(Path::new("src/target")));
+        assert!(matcher.is_excluded(Path::new("docs/notes.tmp")));
+        assert!(!matcher.is_excluded(Path::new("docs/drafts/notes.tmp")));
+        assert!(!matcher.is_excluded(Path::new("src/docs/notes.tmp")));
+
+        let matcher = self::matcher(&["payload", "*.md"], &[]);
+        assert!(matcher.is_included(Path::new("payload/bin/myapp")));
+        assert!(matcher.is_included(Path::new("docs/README.md")));
+        assert!(!matcher.is_included(Path::new("scripts/build.sh")));
+
+        let defaults = FileRules::default().matcher().unwrap();
+        assert!(defaults.is_excluded(Path::new("payload/.gitignore")));
+        assert!(!defaults.is_excluded(Path::new("payload/target")));
+    }
+
+    #[test]
+    fn test_package_path() {
+        let config = config(
+            r#"
+            [files]
+            exclude = ["/build", "*.debug", "*.log"]
+
+            [[remap]]
+            from = "build/release/myapp"
+            to = "payload/bin/myapp"
+
+            [[remap]]
+            from = "build/release"
+            to = "payload/lib"
+            "#,
+        );
+        let selector = config.selector().unwrap();
+
+        assert_eq!(
+            package_path(&selector, "payload/bin/run.sh").as_deref(),
+            Some("payload/bin/run.sh")
+        );
+        // Remapped output is packaged although build/ is excluded
+        assert_eq!(
+            package_path(&selector, "build/release/myapp").as_deref(),
+            Some("payload/bin/myapp")
+        );
+        assert_eq!(
+            package_path(&selector, "build/release/libmyapp.so").as_deref(),
+            Some("payload/lib/libmyapp.so")
+        );
+        assert_eq!(package_path(&selector, "build/debug/myapp"), None);
+        // Exclusions below `from` still apply
+        assert_eq!(package_path(&selector, "build/release/myapp.debug"), None);
+
+        assert!(selector.enters(Path::new("build")));
+        assert!(selector.enters(Path::new("build/release")));
+        assert!(!selector.enters(Path::new("app.log")));
+    }
+
+    #[test]
+    fn test_package_path_first_remap_wins() {
+        let config = config(
+            r#"
+            [[remap]]
+            from = "build/release"
+            to = "payload/lib"
+
+            [[remap]]
+            from = "build/release/myapp"
+            to = "payload/bin/myapp"
+            "#,
+        );
+        let selector = config.selector().unwrap();
+        assert_eq!(
+            package_path(&selector, "build/release/myapp").as_deref(),
+            Some("payload/lib/myapp")
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = config("[[remap]]\nfrom = \"build\"\nto = \"payload\"\n");
+        assert!(valid.validate().is_ok());
+
+        for invalid in [
+            "[[remap]]\nfrom = \"/opt/build\"\nto = \"payload\"\n",
+            "[[remap]]\nfrom = \"build\"\nto = \"../payload\"\n",
+            "[[remap]]\nfrom = \"build\"\nto = \"\"\n",
+            "[[remap]]\nfrom = \"build/manifest.json\"\nto = \"manifest.json\"\n",
+            "[files]\nexclude = [\"[\"]\n",
+            "[files]\ninclude = [\"payload/[\"]\n",
+            "[profile.dev]\nexclude = [\"[\"]\n",
+            "[[profile.dev.remap]]\nfrom = \"/build\"\nto = \"payload\"\n",
+        ] {
+            assert!(config(invalid).validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_with_profile() {
+        let content = r#"
+            [build]
+            output = "dist/myapp.int"
+            compression = "zstd"
+            level = 19
+            strip = true
+
+            [files]
+            exclude = ["*.log"]
+
+            [[remap]]
+            from = "build"
+            to = "payload"
+
+            [profile.dev]
+            compression = "gzip"
+            strip = false
+            exclude = ["*.md"]
+
+            [[profile.dev.remap]]
+            from = "build/debug"
+            to = "payload/bin"
+
+            [profile.small]
+            level = 22
+            "#;
+
+        let dev = config(content).with_profile("dev").unwrap();
+        assert_eq!(dev.build.compression, Some(Compression::Gzip));
+        // The zstd level of [build] doesn't carry over to gzip
+        assert_eq!(dev.build.level, None);
+        assert!(!dev.build.strip);
+        assert_eq!(dev.build.output, Some(PathBuf::from("dist/myapp.int")));
+        assert_eq!(dev.build.profile.as_deref(), Some("dev"));
+        assert_eq!(dev.files.exclude, ["*.log", "*.md"]);
+        assert_eq!(dev.remap[0].from, Path::new("build/debug"));
+        assert_eq!(dev.remap[1].from, Path::new("build"));
+
+        let small = config(content).with_profile("small").unwrap();
+        assert_eq!(small.build.compression, Some(Compression::Zstd));
+        assert_eq!(small.build.level, Some(22));
+        assert!(small.build.strip);
+
+        assert!(config(content).with_profile("release").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::manifest_file;
+use anyhow::{anyhow, Result};
+use int_core::desktop::{ADDITIONAL_CATEGORIES, MAIN_CATEGORIES};
+use int_core::manifest::{Manifest, ScriptHook};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Payload files above this size (in MiB) are reported by default
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Problem found by the linter
+#[derive(Debug, Clone)]
+pub struct Finding {
+    /// Name of the lint rule (e.g. "missing-icon")
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// File the finding is about, relative to the package directory
+    pub path: Option<PathBuf>,
+}
+
+impl Finding {
+    fn warning(rule: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            severity: Severity::Warning,
+            message: message.into(),
+            path: None,
+        }
+    }
+
+    fn at(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "rule": self.rule,
+            "severity": self.severity.as_str(),
+            "message": self.message,
+            "path": self.path,
+        })
+    }
+}
+
+/// Checks a package source directory for likely mistakes
+///
+/// Unlike validation, which rejects manifests that cannot be installed,
+/// linting reports packages that install but are likely to misbehave.
+/// Validation errors are reported as findings of the "invalid-manifest"
+/// rule, so all problems are shown at once.
+pub struct PackageLinter {
+    source_dir: PathBuf,
+    max_file_size: u64,
+}
+
+impl PackageLinter {
+    /// Create a linter for a package directory or its manifest path
+    pub fn new(path: PathBuf) -> Self {
+        let source_dir = if path.is_file() {
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        } else {
+            path
+        };
+        Self {
+            source_dir,
+            max_file_size: DEFAULT_MAX_FILE_SIZE * 1024 * 1024,
+        }
+    }
+
+    /// Report payload files larger than `bytes`
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Lint the package, returning its manifest and the findings
+    pub fn lint(&self) -> Result<(Manifest, Vec<Finding>)> {
+        let manifest_path = manifest_file::find(&self.source_dir)?;
+        let manifest = manifest_file::read(&manifest_path)
+            .map_err(|e| anyhow!("Manifest parse error: {}", e))?;
+
+        let mut findings = Vec::new();
+        if let Err(e) = manifest.validate() {
+            findings.push(Finding {
+                rule: "invalid-manifest",
+                severity: Severity::Error,
+                message: e.to_string(),
+                path: manifest_path.file_name().map(PathBuf::from),
+            });
+        }
+
+        self.lint_desktop(&manifest, &mut findings);
+        self.lint_entries(&manifest, &mut findings);
+        self.lint_scripts(&manifest, &mut findings);
+        self.lint_payload(&manifest, &mut findings)?;
+        Ok((manifest, findings))
+    }
+
+    /// Desktop data of GUI applications
+    fn lint_desktop(&self, manifest: &Manifest, findings: &mut Vec<Finding>) {
+        let Some(ref desktop) = manifest.desktop else {
+            return;
+        };
+
+        match desktop.icon {
+            None if desktop.icons.is_none() => findings.push(Finding::warning(
+                "missing-icon",
+                "Desktop entry has no icon; menus show a generic one",
+            )),
+            // Icon paths are relative to the install path
+            Some(ref icon) if icon.contains('/') && !Path::new(icon).is_absolute() => {
+                let path = Path::new("payload").join(icon);
+                if !self.source_dir.join(&path).is_file() {
+                    findings.push(
+                        Finding::warning("missing-icon", format!("Icon {} not found", icon))
+                            .at(path),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        if desktop.categories.is_empty() {
+            findings.push(Finding::warning(
+                "missing-categories",
+                "Desktop entry has no categories; menus may file it under Other",
+            ));
+        } else if !desktop
+            .categories
+            .iter()
+            .any(|category| MAIN_CATEGORIES.contains(&category.trim()))
+        {
+            findings.push(Finding::warning(
+                "missing-main-category",
+                "Desktop entry has no main category (e.g. Utility, Development); menus may file it under Other",
+            ));
+        }
+        for category in &desktop.categories {
+            let category = category.trim();
+            let registered = MAIN_CATEGORIES
+                .iter()
+                .chain(ADDITIONAL_CATEGORIES)
+                .any(|&known| known == category);
+            if !registered {
+                findings.push(Finding::warning(
+                    "unknown-category",
+                    format!(
+                        "Category '{}' is not a freedesktop.org category and is ignored by most menus",
+                        category
+                    ),
+                ));
+            }
+        }
+
+        if manifest.description.is_none() && desktop.generic_name.is_none() {
+            findings.push(Finding::warning(
+                "missing-description",
+                "GUI application has no description or generic_name for the menu tooltip",
+            ));
+        }
+    }
+
+    /// Executable entries in `payload/bin`
+    fn lint_entries(&self, manifest: &Manifest, findings: &mut Vec<Finding>) {
+        for entry in manifest.executable_entries() {
+            let path = Path::new("payload").join("bin").join(entry);
+            let Ok(metadata) = fs::metadata(self.source_dir.join(&path)) else {
+                findings.push(
+                    Finding::warning(
+                        "missing-entry",
+                        format!("Entry '{}' not found in payload/bin", entry),
+                    )
+                    .at(path),
+                );
+                continue;
+            };
+            if !is_executable(&metadata) {
+                findings.push(
+                    Finding::warning(
+                        "entry-not-executable",
+                        format!("Entry '{}' is not executable", entry),
+                    )
+                    .at(path),
+                );
+            }
+        }
+    }
+
+    /// Maintainer scripts hardcoding paths the installer provides
+    fn lint_scripts(&self, manifest: &Manifest, findings: &mut Vec<Finding>) {
+        let install_path = manifest.install_path.to_string_lossy();
+        for hook in ScriptHook::ALL {
+            let Some(script) = manifest.script(hook) else {
+                continue;
+            };
+            let Ok(content) = fs::read_to_string(self.source_dir.join(&script.path)) else {
+                continue;
+            };
+            for (number, line) in content.lines().enumerate() {
+                let absolute = if line.contains(install_path.as_ref()) {
+                    Some(install_path.as_ref())
+                } else if line.contains("/home/") {
+                    Some("/home/")
+                } else {
+                    None
+                };
+                if let Some(absolute) = absolute {
+                    findings.push(
+                        Finding::warning(
+                            "absolute-script-path",
+                            format!(
+                                "{} script uses the absolute path {} on line {}; use $INSTALL_PATH or $HOME, as the install path can be changed",
+                                hook.as_str(),
+                                absolute,
+                                number + 1
+                            ),
+                        )
+                        .at(&script.path),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Payload permissions and sizes
+    fn lint_payload(&self, manifest: &Manifest, findings: &mut Vec<Finding>) -> Result<()> {
+        let mut total = 0;
+        for entry in WalkDir::new(self.source_dir.join("payload")).follow_links(false) {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            let path = entry.path().strip_prefix(&self.source_dir)?.to_path_buf();
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = metadata.permissions().mode();
+                if !metadata.is_symlink() && mode & 0o002 != 0 {
+                    findings.push(
+                        Finding::warning("broad-permissions", "Payload path is world-writable")
+                            .at(&path),
+                    );
+                }
+                if metadata.is_file() && mode & 0o6000 != 0 {
+                    findings.push(
+                        Finding::warning("broad-permissions", "Payload file is setuid or setgid")
+                            .at(&path),
+                    );
+                }
+            }
+
+            if !metadata.is_file() {
+                continue;
+            }
+            total += metadata.len();
+            if metadata.len() > self.max_file_size {
+                findings.push(
+                    Finding::warning(
+                        "oversized-file",
+                        format!(
+                            "Payload file is {} MiB; consider downloading large data on first run",
+                            metadata.len() / (1024 * 1024)
+                        ),
+                    )
+                    .at(&path),
+                );
+            }
+        }
+
+        match manifest.required_space {
+            None => findings.push(Finding::warning(
+                "missing-required-space",
+                format!(
+                    "required_space is not set; the payload needs at least {} bytes",
+                    total
+                ),
+            )),
+            Some(required) if required < total => findings.push(Finding::warning(
+                "missing-required-space",
+                format!(
+                    "required_space ({} bytes) is less than the payload size ({} bytes)",
+                    required, total
+                ),
+            )),
+            Some(_) => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
@@ -1,12 +1,26 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use int_core::compression::Compression;
 use std::path::PathBuf;
 use tracing_subscriber;
 
 mod builder;
+mod bump;
+mod changelog;
+mod config;
+mod linter;
+mod manifest_file;
+mod sbom;
+mod size;
 mod template;
 mod validator;
 
 use builder::PackageBuilder;
+use bump::{Bump, VersionBumper};
+use config::BuildOptions;
+use linter::{PackageLinter, Severity};
+use manifest_file::ManifestFormat;
+use sbom::{SbomFormat, SbomTarget};
+use size::SizeReport;
 use template::TemplateGenerator;
 use validator::PackageValidator;
 
@@ -44,10 +58,22 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Compress with gzip
-        #[arg(short, long)]
+        /// Deprecated: packages are compressed unless --compression is none
+        #[arg(short, long, hide = true)]
         compress: bool,
 
+        /// Compression: gzip, zstd, xz or none [default: gzip]
+        #[arg(long)]
+        compression: Option<Compression>,
+
+        /// Compression level (gzip and xz: 0-9, zstd: 1-22)
+        #[arg(long)]
+        level: Option<u32>,
+
+        /// Strip ELF binaries in the payload (tool and exclusions in intpack.toml)
+        #[arg(long)]
+        strip: bool,
+
         /// Sign the package with GPG
         #[arg(short, long)]
         sign: bool,
@@ -55,18 +81,136 @@ enum Commands {
         /// GPG key ID to use for signing
         #[arg(short, long)]
         key: Option<String>,
+
+        /// Changelog to embed the entry for this version from [default: CHANGELOG.md]
+        #[arg(long)]
+        changelog: Option<PathBuf>,
+
+        /// Fail if the changelog has no entry for this version
+        #[arg(long)]
+        require_changelog: bool,
+
+        /// Generate an SBOM: cyclonedx or spdx
+        #[arg(long)]
+        sbom: Option<SbomFormat>,
+
+        /// Where to put the SBOM: package, file (next to the package) or both [default: both]
+        #[arg(long)]
+        sbom_to: Option<SbomTarget>,
+
+        /// Build profile from intpack.toml, e.g. dev or release
+        #[arg(short, long)]
+        profile: Option<String>,
     },
 
-    /// Validate manifest
+    /// Validate a manifest or a built package
     Validate {
-        /// Manifest file path
-        manifest: PathBuf,
+        /// Manifest file or .int package path
+        path: PathBuf,
+    },
+
+    /// Check a package for likely mistakes beyond validation
+    Lint {
+        /// Package directory or manifest path
+        path: PathBuf,
+
+        /// Report payload files larger than this many MiB
+        #[arg(long, default_value_t = linter::DEFAULT_MAX_FILE_SIZE)]
+        max_file_size: u64,
+
+        /// Fail on warnings too, not only on errors
+        #[arg(long)]
+        strict: bool,
+
+        /// Print findings as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show package information
     Info {
+        /// Package directory or .int package path
+        path: PathBuf,
+    },
+
+    /// Bump the package version in the manifest
+    Bump {
+        /// major, minor, patch or the new version
+        version: Bump,
+
         /// Package directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Add a heading for the new version to the changelog
+        #[arg(long)]
+        changelog: bool,
+
+        /// Commit the changes and create a git tag v<version>
+        #[arg(long)]
+        tag: bool,
+    },
+
+    /// Translate a manifest between JSON, TOML and YAML
+    ConvertManifest {
+        /// Manifest file or package directory
+        path: PathBuf,
+
+        /// Format to convert to: json, toml or yaml
+        #[arg(long)]
+        to: ManifestFormat,
+
+        /// Output file (standard output when not given)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write a software bill of materials for a package
+    Sbom {
+        /// Package directory or .int package path
+        path: PathBuf,
+
+        /// SBOM format: cyclonedx or spdx
+        #[arg(short, long, default_value_t = SbomFormat::CycloneDx)]
+        format: SbomFormat,
+
+        /// Output file (standard output when not given)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Build profile a package directory would be built with
+        #[arg(short, long)]
+        profile: Option<String>,
+    },
+
+    /// Show what takes up space in a package
+    Size {
+        /// Package directory or .int package path
         path: PathBuf,
+
+        /// Directory depth of the breakdown
+        #[arg(long, default_value_t = 2)]
+        depth: usize,
+
+        /// Number of largest files to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Build profile a package directory would be built with
+        #[arg(short, long)]
+        profile: Option<String>,
+    },
+
+    /// Write man pages for int-pack and its subcommands
+    #[command(hide = true)]
+    GenMan {
+        /// Output directory
+        #[arg(default_value = ".")]
+        out_dir: PathBuf,
     },
 }
 
@@ -88,25 +232,173 @@ async fn main() -> anyhow::Result<()> {
         Commands::Build {
             path,
             output,
-            compress,
+            compress: _,
+            compression,
+            level,
+            strip,
             sign,
             key,
+            changelog,
+            require_changelog,
+            sbom,
+            sbom_to,
+            profile,
         } => {
             let builder = PackageBuilder::new(path);
-            let output_path = builder.build(output, compress, sign, key).await?;
+            let output_path = builder
+                .build(BuildOptions {
+                    output,
+                    sign,
+                    key,
+                    compression,
+                    level,
+                    strip,
+                    changelog,
+                    require_changelog,
+                    sbom,
+                    sbom_to,
+                    profile,
+                })
+                .await?;
             println!("✓ Package built successfully: {}", output_path.display());
         }
 
-        Commands::Validate { manifest } => {
+        Commands::Validate { path } => {
             let validator = PackageValidator::new();
-            validator.validate(&manifest)?;
-            println!("✓ Manifest is valid and compatible with int-core");
+            if PackageValidator::is_package(&path) {
+                validator.validate_package(&path)?;
+                println!("✓ Package is intact, verified and complete");
+            } else {
+                validator.validate(&path)?;
+                println!("✓ Manifest is valid and compatible with int-core");
+            }
+        }
+
+        Commands::Lint {
+            path,
+            max_file_size,
+            strict,
+            json,
+        } => {
+            let linter = PackageLinter::new(path).with_max_file_size(max_file_size * 1024 * 1024);
+            let (manifest, findings) = linter.lint()?;
+            let errors = findings
+                .iter()
+                .filter(|f| f.severity == Severity::Error)
+                .count();
+            let warnings = findings.len() - errors;
+
+            if json {
+                let report = serde_json::json!({
+                    "package": manifest.name,
+                    "version": manifest.package_version,
+                    "errors": errors,
+                    "warnings": warnings,
+                    "findings": findings.iter().map(|f| f.to_json()).collect::<Vec<_>>(),
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                for finding in &findings {
+                    match finding.path {
+                        Some(ref path) => println!(
+                            "{}[{}]: {} ({})",
+                            finding.severity.as_str(),
+                            finding.rule,
+                            finding.message,
+                            path.display()
+                        ),
+                        None => println!(
+                            "{}[{}]: {}",
+                            finding.severity.as_str(),
+                            finding.rule,
+                            finding.message
+                        ),
+                    }
+                }
+                if findings.is_empty() {
+                    println!("✓ No problems found in {}", manifest.name);
+                } else {
+                    println!("{} error(s), {} warning(s)", errors, warnings);
+                }
+            }
+
+            if errors > 0 || (strict && warnings > 0) {
+                std::process::exit(1);
+            }
         }
 
         Commands::Info { path } => {
             let builder = PackageBuilder::new(path);
             builder.show_info().await?;
         }
+
+        Commands::Bump {
+            version,
+            path,
+            changelog,
+            tag,
+        } => {
+            let bumper = VersionBumper::new(path);
+            let (old, new) = bumper.bump(&version, changelog, tag)?;
+            println!("✓ Version bumped: {} → {}", old, new);
+        }
+
+        Commands::ConvertManifest { path, to, output } => {
+            let path = if path.is_dir() {
+                manifest_file::find(&path)?
+            } else {
+                path
+            };
+            let converted = manifest_file::convert(&path, to)?;
+            match output {
+                Some(output) => {
+                    std::fs::write(&output, converted)?;
+                    println!("✓ Manifest converted: {}", output.display());
+                }
+                None => print!("{}", converted),
+            }
+        }
+
+        Commands::Sbom {
+            path,
+            format,
+            output,
+            profile,
+        } => {
+            let (manifest, hashes) = sbom::read_package(&path, profile.as_deref())?;
+            let document = serde_json::to_string_pretty(&format.generate(&manifest, &hashes))?;
+            match output {
+                Some(output) => {
+                    std::fs::write(&output, document)?;
+                    println!("✓ SBOM written: {}", output.display());
+                }
+                None => println!("{}", document),
+            }
+        }
+
+        Commands::Size {
+            path,
+            depth,
+            top,
+            json,
+            profile,
+        } => {
+            let report = SizeReport::new(&path, profile.as_deref())?;
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&report.to_json(depth, top))?
+                );
+            } else {
+                report.print(depth, top);
+            }
+        }
+
+        Commands::GenMan { out_dir } => {
+            std::fs::create_dir_all(&out_dir)?;
+            clap_mangen::generate_to(Cli::command(), &out_dir)?;
+            println!("✓ Man pages written to {}", out_dir.display());
+        }
     }
 
     Ok(())
@@ -0,0 +1,190 @@
+use anyhow::{anyhow, bail, Result};
+use int_core::manifest::Manifest;
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Format a manifest is written in
+///
+/// Packages always hold `manifest.json`; a `manifest.toml` or
+/// `manifest.yaml` in the package directory is converted to it by
+/// `int-pack build`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ManifestFormat {
+    pub const ALL: [ManifestFormat; 3] = [
+        ManifestFormat::Json,
+        ManifestFormat::Toml,
+        ManifestFormat::Yaml,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManifestFormat::Json => "json",
+            ManifestFormat::Toml => "toml",
+            ManifestFormat::Yaml => "yaml",
+        }
+    }
+
+    /// Name of the manifest in a package directory
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ManifestFormat::Json => "manifest.json",
+            ManifestFormat::Toml => "manifest.toml",
+            ManifestFormat::Yaml => "manifest.yaml",
+        }
+    }
+
+    /// Format of a manifest file, from its extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        extension.parse().map_err(|_| {
+            anyhow!(
+                "Unknown manifest format of {} (expected a .json, .toml or .yaml file)",
+                path.display()
+            )
+        })
+    }
+
+    pub fn parse(&self, content: &str) -> Result<Manifest> {
+        match self {
+            ManifestFormat::Json => Manifest::from_str(content).map_err(|e| anyhow!("{}", e)),
+            ManifestFormat::Toml => {
+                toml::from_str(content).map_err(|e| anyhow!("Invalid manifest.toml: {}", e))
+            }
+            ManifestFormat::Yaml => {
+                serde_norway::from_str(content).map_err(|e| anyhow!("Invalid manifest.yaml: {}", e))
+            }
+        }
+    }
+
+    /// Read a manifest as a plain document, keeping only what it sets
+    fn read_document(&self, content: &str) -> Result<Value> {
+        match self {
+            ManifestFormat::Json => serde_json::from_str(content).map_err(|e| anyhow!("{}", e)),
+            ManifestFormat::Toml => toml::from_str(content).map_err(|e| anyhow!("{}", e)),
+            ManifestFormat::Yaml => serde_norway::from_str(content).map_err(|e| anyhow!("{}", e)),
+        }
+    }
+
+    fn write_document(&self, document: &Value) -> Result<String> {
+        match self {
+            ManifestFormat::Json => Ok(format!("{}\n", serde_json::to_string_pretty(document)?)),
+            // TOML has no null; unset fields are left out instead
+            ManifestFormat::Toml => toml::to_string_pretty(&without_nulls(document))
+                .map_err(|e| anyhow!("Failed to write TOML: {}", e)),
+            ManifestFormat::Yaml => serde_norway::to_string(document)
+                .map_err(|e| anyhow!("Failed to write YAML: {}", e)),
+        }
+    }
+}
+
+impl fmt::Display for ManifestFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ManifestFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ManifestFormat::ALL
+            .into_iter()
+            .find(|format| format.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown manifest format '{}' (expected json, toml or yaml)",
+                    s
+                )
+            })
+    }
+}
+
+/// Manifest file of a package directory
+///
+/// A directory holds exactly one of `manifest.json`, `manifest.toml` and
+/// `manifest.yaml`.
+pub fn find(source_dir: &Path) -> Result<PathBuf> {
+    let found: Vec<PathBuf> = ManifestFormat::ALL
+        .iter()
+        .map(|format| source_dir.join(format.file_name()))
+        .filter(|path| path.is_file())
+        .collect();
+    match found.as_slice() {
+        [path] => Ok(path.clone()),
+        [] => bail!(
+            "No manifest.json, manifest.toml or manifest.yaml found in {}",
+            source_dir.display()
+        ),
+        _ => {
+            let names: Vec<String> = found
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            bail!(
+                "{} found in {}; keep only one",
+                names.join(" and "),
+                source_dir.display()
+            )
+        }
+    }
+}
+
+/// Read a manifest file in any format
+pub fn read(path: &Path) -> Result<Manifest> {
+    let format = ManifestFormat::from_path(path)?;
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    format.parse(&content)
+}
+
+/// Read the manifest of a package directory
+pub fn load(source_dir: &Path) -> Result<Manifest> {
+    read(&find(source_dir)?)
+}
+
+/// Whether a path in a package directory is its manifest
+pub fn is_manifest(relative: &Path) -> bool {
+    ManifestFormat::ALL
+        .iter()
+        .any(|format| relative == Path::new(format.file_name()))
+}
+
+/// Translate a manifest file to another format
+///
+/// Only the fields the manifest sets are written, so defaults are not
+/// spelled out. The result is checked to be a valid manifest.
+pub fn convert(path: &Path, to: ManifestFormat) -> Result<String> {
+    let from = ManifestFormat::from_path(path)?;
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    from.parse(&content)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+
+    let document = from.read_document(&content)?;
+    let converted = to.write_document(&document)?;
+    to.parse(&converted)
+        .map_err(|e| anyhow!("Converted manifest does not read back: {}", e))?;
+    Ok(converted)
+}
+
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.clone(), without_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_nulls).collect()),
+        value => value.clone(),
+    }
+}
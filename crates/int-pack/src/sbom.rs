@@ -0,0 +1,332 @@
+use crate::builder::PackageBuilder;
+use crate::config::BuildConfig;
+use crate::manifest_file;
+use anyhow::{anyhow, Result};
+use int_core::extractor::PackageExtractor;
+use int_core::manifest::Manifest;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Software bill of materials format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    #[default]
+    CycloneDx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+impl SbomFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SbomFormat::CycloneDx => "cyclonedx",
+            SbomFormat::Spdx => "spdx",
+        }
+    }
+
+    /// Name of the SBOM inside packages
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            SbomFormat::CycloneDx => "sbom.cdx.json",
+            SbomFormat::Spdx => "sbom.spdx.json",
+        }
+    }
+
+    /// Path of the SBOM written next to a package, e.g. `myapp-1.0.0.cdx.json`
+    pub fn path_for(&self, package_path: &Path) -> PathBuf {
+        let extension = match self {
+            SbomFormat::CycloneDx => "cdx.json",
+            SbomFormat::Spdx => "spdx.json",
+        };
+        package_path.with_extension(extension)
+    }
+
+    /// Describe a package from its manifest and the hashes of its files
+    pub fn generate(&self, manifest: &Manifest, hashes: &BTreeMap<String, String>) -> Value {
+        // An SBOM embedded by an earlier build does not describe itself
+        let files: BTreeMap<&str, &str> = hashes
+            .iter()
+            .filter(|(path, _)| !is_sbom(path))
+            .map(|(path, hash)| (path.as_str(), hash.as_str()))
+            .collect();
+        let created = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+        match self {
+            SbomFormat::CycloneDx => cyclonedx(manifest, &files, &created),
+            SbomFormat::Spdx => spdx(manifest, &files, &created),
+        }
+    }
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SbomFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cyclonedx" => Ok(SbomFormat::CycloneDx),
+            "spdx" => Ok(SbomFormat::Spdx),
+            _ => Err(format!(
+                "unknown SBOM format '{}' (expected cyclonedx or spdx)",
+                s
+            )),
+        }
+    }
+}
+
+/// Where `int-pack build --sbom` puts the SBOM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomTarget {
+    /// Inside the package
+    Package,
+    /// Next to the package
+    File,
+    #[default]
+    Both,
+}
+
+impl SbomTarget {
+    pub fn embeds(&self) -> bool {
+        matches!(self, SbomTarget::Package | SbomTarget::Both)
+    }
+
+    pub fn writes_file(&self) -> bool {
+        matches!(self, SbomTarget::File | SbomTarget::Both)
+    }
+}
+
+impl FromStr for SbomTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "package" => Ok(SbomTarget::Package),
+            "file" => Ok(SbomTarget::File),
+            "both" => Ok(SbomTarget::Both),
+            _ => Err(format!(
+                "unknown SBOM target '{}' (expected package, file or both)",
+                s
+            )),
+        }
+    }
+}
+
+/// Manifest and file hashes of a built package or a package directory
+///
+/// For a directory, the files are selected, remapped and stripped as
+/// `int-pack build` would with the profile, and hashed.
+pub fn read_package(
+    path: &Path,
+    profile: Option<&str>,
+) -> Result<(Manifest, BTreeMap<String, String>)> {
+    if path.is_file() {
+        let mut manifest = PackageExtractor::new()
+            .validate_package(path)
+            .map_err(|e| anyhow!("Failed to read package: {}", e))?;
+        let hashes = manifest
+            .file_hashes
+            .take()
+            .ok_or_else(|| anyhow!("Package has no file_hashes to list"))?;
+        return Ok((manifest, hashes));
+    }
+
+    let manifest =
+        manifest_file::load(path).map_err(|e| anyhow!("Failed to read manifest: {}", e))?;
+    let config = BuildConfig::load_profile(path, profile)?;
+    let builder = PackageBuilder::new(path.to_path_buf());
+    let output = config.build.output.as_ref().map(|output| path.join(output));
+    let mut entries = builder.collect_entries(&config, output.as_deref())?;
+    let strip_dir = tempfile::tempdir()?;
+    if config.build.strip {
+        builder.strip_binaries(&mut entries, &config, strip_dir.path())?;
+    }
+    let hashes = builder.collect_file_hashes(&entries)?;
+    Ok((manifest, hashes))
+}
+
+/// Whether a path in a package is an embedded SBOM
+pub fn is_sbom(path: &str) -> bool {
+    [SbomFormat::CycloneDx, SbomFormat::Spdx]
+        .iter()
+        .any(|format| format.file_name() == path)
+}
+
+fn cyclonedx(manifest: &Manifest, files: &BTreeMap<&str, &str>, created: &str) -> Value {
+    let package_ref = format!("{}@{}", manifest.name, manifest.package_version);
+
+    let mut package = json!({
+        "type": "application",
+        "bom-ref": package_ref,
+        "name": manifest.name,
+        "version": manifest.package_version,
+    });
+    if let Some(ref description) = manifest.description {
+        package["description"] = json!(description);
+    }
+    if let Some(ref author) = manifest.author {
+        package["author"] = json!(author);
+    }
+    if let Some(ref license) = manifest.license {
+        package["licenses"] = json!([{ "expression": license }]);
+    }
+    if let Some(ref homepage) = manifest.homepage {
+        package["externalReferences"] = json!([{ "type": "website", "url": homepage }]);
+    }
+
+    let mut components: Vec<Value> = files
+        .iter()
+        .map(|(path, hash)| {
+            json!({
+                "type": "file",
+                "bom-ref": format!("file:{}", path),
+                "name": path,
+                "hashes": [{ "alg": "SHA-256", "content": hash }],
+            })
+        })
+        .collect();
+    let mut depends_on = Vec::new();
+    for dependency in &manifest.dependencies {
+        let dependency_ref = format!("dependency:{}", dependency.name);
+        let mut component = json!({
+            "type": "application",
+            "bom-ref": dependency_ref,
+            "name": dependency.name,
+            "scope": if dependency.optional { "optional" } else { "required" },
+        });
+        if let Some(ref reason) = dependency.reason {
+            component["description"] = json!(reason);
+        }
+        if let Some(ref min_version) = dependency.min_version {
+            component["properties"] = json!([{ "name": "int:min_version", "value": min_version }]);
+        }
+        components.push(component);
+        depends_on.push(dependency_ref);
+    }
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": created,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "int-pack",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": package,
+        },
+        "components": components,
+        "dependencies": [{ "ref": package_ref, "dependsOn": depends_on }],
+    })
+}
+
+/// SPDX document for a package
+///
+/// SPDX expects SHA-1 checksums for files; only the SHA-256 hashes from the
+/// manifest are given, so the package is marked as not analyzed.
+fn spdx(manifest: &Manifest, files: &BTreeMap<&str, &str>, created: &str) -> Value {
+    let mut package = json!({
+        "SPDXID": "SPDXRef-Package",
+        "name": manifest.name,
+        "versionInfo": manifest.package_version,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": manifest.license.as_deref().unwrap_or("NOASSERTION"),
+        "copyrightText": "NOASSERTION",
+    });
+    if let Some(ref description) = manifest.description {
+        package["description"] = json!(description);
+    }
+    if let Some(ref author) = manifest.author {
+        package["supplier"] = json!(format!("Person: {}", author));
+    }
+    if let Some(ref homepage) = manifest.homepage {
+        package["homepage"] = json!(homepage);
+    }
+
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": "SPDXRef-Package",
+    })];
+
+    let mut spdx_files = Vec::new();
+    for (i, (path, hash)) in files.iter().enumerate() {
+        let id = format!("SPDXRef-File-{}", i + 1);
+        spdx_files.push(json!({
+            "SPDXID": id,
+            "fileName": format!("./{}", path),
+            "checksums": [{ "algorithm": "SHA256", "checksumValue": hash }],
+        }));
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-Package",
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": id,
+        }));
+    }
+
+    let mut packages = vec![package];
+    for (i, dependency) in manifest.dependencies.iter().enumerate() {
+        let id = format!("SPDXRef-Dependency-{}", i + 1);
+        let mut package = json!({
+            "SPDXID": id,
+            "name": dependency.name,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+        });
+        if let Some(ref min_version) = dependency.min_version {
+            package["comment"] = json!(format!("Minimum version {}", min_version));
+        }
+        packages.push(package);
+        relationships.push(if dependency.optional {
+            json!({
+                "spdxElementId": id,
+                "relationshipType": "OPTIONAL_DEPENDENCY_OF",
+                "relatedSpdxElement": "SPDXRef-Package",
+            })
+        } else {
+            json!({
+                "spdxElementId": "SPDXRef-Package",
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": id,
+            })
+        });
+    }
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": format!("{}-{}", manifest.name, manifest.package_version),
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}-{}",
+            manifest.name,
+            manifest.package_version,
+            uuid::Uuid::new_v4()
+        ),
+        "creationInfo": {
+            "created": created,
+            "creators": [format!("Tool: int-pack-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "files": spdx_files,
+        "relationships": relationships,
+    })
+}
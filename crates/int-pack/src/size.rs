@@ -0,0 +1,233 @@
+use crate::builder::PackageBuilder;
+use crate::config::BuildConfig;
+use crate::manifest_file;
+use anyhow::{anyhow, Result};
+use int_core::compression::{Compression, Decoder};
+use int_core::extractor::{EntryKind, PackageExtractor};
+use int_core::utils::format_bytes;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Sizes of the files in a package or package directory
+pub struct SizeReport {
+    pub name: String,
+    pub version: String,
+    pub compression: Compression,
+    /// Size of the .int file (estimated for directories)
+    pub compressed: u64,
+    pub uncompressed: u64,
+    /// Files by their path in the package, with their size
+    pub files: Vec<(PathBuf, u64)>,
+    /// Whether `compressed` was estimated by compressing a directory
+    pub estimated: bool,
+    /// Binaries stripped and bytes saved, when the build strips them
+    pub stripped: Option<(usize, u64)>,
+}
+
+impl SizeReport {
+    /// Report on a built package or a package directory
+    ///
+    /// `profile` is the build profile a directory would be built with.
+    pub fn new(path: &Path, profile: Option<&str>) -> Result<Self> {
+        if path.is_file() {
+            Self::for_package(path)
+        } else {
+            Self::for_directory(path, profile)
+        }
+    }
+
+    fn for_package(package_path: &Path) -> Result<Self> {
+        let extractor = PackageExtractor::new();
+        let manifest = extractor
+            .validate_package(package_path)
+            .map_err(|e| anyhow!("Failed to read package: {}", e))?;
+        let files: Vec<(PathBuf, u64)> = extractor
+            .list_entries(package_path)
+            .map_err(|e| anyhow!("Failed to read package: {}", e))?
+            .into_iter()
+            .filter(|entry| entry.kind == EntryKind::File)
+            .map(|entry| (entry.path, entry.size))
+            .collect();
+        let compression = Decoder::open(package_path)
+            .map_err(|e| anyhow!("Failed to read package: {}", e))?
+            .compression();
+
+        Ok(Self {
+            name: manifest.name,
+            version: manifest.package_version,
+            compression,
+            compressed: std::fs::metadata(package_path)?.len(),
+            uncompressed: files.iter().map(|(_, size)| size).sum(),
+            files,
+            estimated: false,
+            stripped: None,
+        })
+    }
+
+    /// Report on what `int-pack build` would package from a directory
+    ///
+    /// The files are selected, remapped and stripped as `intpack.toml`
+    /// says, then compressed (without being written) to measure the archive.
+    fn for_directory(source_dir: &Path, profile: Option<&str>) -> Result<Self> {
+        let manifest = manifest_file::load(source_dir)
+            .map_err(|e| anyhow!("Failed to read manifest: {}", e))?;
+        let config = BuildConfig::load_profile(source_dir, profile)?;
+        let (compression, level) = config.build.compression(None, None)?;
+
+        let builder = PackageBuilder::new(source_dir.to_path_buf());
+        let output = config
+            .build
+            .output
+            .as_ref()
+            .map(|output| source_dir.join(output));
+        let mut entries = builder.collect_entries(&config, output.as_deref())?;
+        let strip_dir = tempfile::tempdir()?;
+        let stripped = if config.build.strip {
+            Some(builder.strip_binaries(&mut entries, &config, strip_dir.path())?)
+        } else {
+            None
+        };
+
+        let encoder = compression
+            .encoder(ByteCounter::default(), level)
+            .map_err(|e| anyhow!("{}", e))?;
+        let mut tar = tar::Builder::new(encoder);
+        builder.add_entries_to_tar(&mut tar, &entries)?;
+        let compressed = tar.into_inner()?.finish()?.0;
+
+        let files: Vec<(PathBuf, u64)> = entries
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| (entry.path, entry.size))
+            .collect();
+
+        Ok(Self {
+            name: manifest.name,
+            version: manifest.package_version,
+            compression,
+            compressed,
+            uncompressed: files.iter().map(|(_, size)| size).sum(),
+            files,
+            estimated: true,
+            stripped,
+        })
+    }
+
+    /// Total size of each directory up to `depth` levels deep, by path
+    ///
+    /// Like `du`, a directory's total includes its subdirectories.
+    pub fn directories(&self, depth: usize) -> Vec<(PathBuf, u64)> {
+        let mut totals: BTreeMap<PathBuf, u64> = BTreeMap::new();
+        for (path, size) in &self.files {
+            let Some(parent) = path.parent() else {
+                continue;
+            };
+            let mut dir = PathBuf::new();
+            for component in parent.components().take(depth) {
+                dir.push(component);
+                *totals.entry(dir.clone()).or_default() += size;
+            }
+        }
+        totals.into_iter().collect()
+    }
+
+    /// The `count` largest files, largest first
+    pub fn largest(&self, count: usize) -> Vec<&(PathBuf, u64)> {
+        let mut files: Vec<&(PathBuf, u64)> = self.files.iter().collect();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        files.truncate(count);
+        files
+    }
+
+    pub fn print(&self, depth: usize, top: usize) {
+        println!("\n📦 {} {}\n", self.name, self.version);
+        println!(
+            "Files:        {} ({} uncompressed)",
+            self.files.len(),
+            format_bytes(self.uncompressed)
+        );
+        println!(
+            "Compressed:   {} ({}{})",
+            format_bytes(self.compressed),
+            self.compression,
+            if self.estimated { ", estimated" } else { "" }
+        );
+        if let Some((count, saved)) = self.stripped {
+            println!(
+                "Stripped:     {} binaries ({} saved)",
+                count,
+                format_bytes(saved)
+            );
+        }
+
+        println!("\n📁 By directory:\n");
+        for (dir, size) in self.directories(depth) {
+            println!(
+                "{:>12}  {:>5.1}%  {}",
+                format_bytes(size),
+                self.percent(size),
+                dir.display()
+            );
+        }
+
+        println!("\n📄 Largest files:\n");
+        for (path, size) in self.largest(top) {
+            println!(
+                "{:>12}  {:>5.1}%  {}",
+                format_bytes(*size),
+                self.percent(*size),
+                path.display()
+            );
+        }
+    }
+
+    pub fn to_json(&self, depth: usize, top: usize) -> serde_json::Value {
+        let sizes = |entries: Vec<&(PathBuf, u64)>| -> Vec<serde_json::Value> {
+            entries
+                .into_iter()
+                .map(|(path, size)| serde_json::json!({ "path": path, "size": size }))
+                .collect()
+        };
+        let directories = self.directories(depth);
+
+        serde_json::json!({
+            "name": self.name,
+            "version": self.version,
+            "compression": self.compression,
+            "compressed": self.compressed,
+            "uncompressed": self.uncompressed,
+            "estimated": self.estimated,
+            "stripped": self.stripped.map(|(count, saved)| {
+                serde_json::json!({ "binaries": count, "saved": saved })
+            }),
+            "files": self.files.len(),
+            "directories": sizes(directories.iter().collect()),
+            "largest": sizes(self.largest(top)),
+        })
+    }
+
+    /// Share of the uncompressed size
+    fn percent(&self, size: u64) -> f64 {
+        if self.uncompressed == 0 {
+            0.0
+        } else {
+            size as f64 * 100.0 / self.uncompressed as f64
+        }
+    }
+}
+
+/// Writer counting and discarding what is written to it
+#[derive(Default)]
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
-use anyhow::Result;
-use int_core::manifest::Manifest;
+use crate::manifest_file;
+use anyhow::{anyhow, bail, Result};
+use int_core::extractor::{EntryKind, PackageExtractor};
+use int_core::manifest::{Manifest, ScriptHook};
+use int_core::signature::{self, SignatureTrust};
+use std::collections::BTreeSet;
 use std::path::Path;
-use tracing::info;
+use tracing::{info, warn};
 
 pub struct PackageValidator;
 
@@ -10,16 +14,131 @@ impl PackageValidator {
         Self
     }
 
+    /// Whether a path is a built package rather than a manifest
+    pub fn is_package(path: &Path) -> bool {
+        path.extension().and_then(|ext| ext.to_str()) == Some("int")
+    }
+
     pub fn validate(&self, manifest_path: &Path) -> Result<()> {
         info!("Validating manifest: {}", manifest_path.display());
 
-        let manifest = Manifest::from_file(manifest_path)
+        let manifest = manifest_file::read(manifest_path)
             .map_err(|e| anyhow::anyhow!("Manifest parse error: {}", e))?;
 
         manifest.validate()
             .map_err(|e| anyhow::anyhow!("Manifest validation error: {}", e))?;
 
+        for warning in manifest.warnings() {
+            warn!("{}", warning);
+        }
+
         info!("✓ Manifest validation passed: {} ({})", manifest.name, manifest.package_version);
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Validate a built .int package
+    ///
+    /// Besides the manifest, checks that the archive reads to the end, that
+    /// every file matches its hash in `file_hashes`, that the signature (if
+    /// any) is good, and that the files the manifest declares are packaged.
+    pub fn validate_package(&self, package_path: &Path) -> Result<()> {
+        info!("Validating package: {}", package_path.display());
+
+        let extractor = PackageExtractor::new();
+        let entries = extractor
+            .list_entries(package_path)
+            .map_err(|e| anyhow!("Archive error: {}", e))?;
+        let manifest = extractor
+            .validate_package(package_path)
+            .map_err(|e| anyhow!("Manifest validation error: {}", e))?;
+        for warning in manifest.warnings() {
+            warn!("{}", warning);
+        }
+
+        // Hashing reads every file, so this also checks the compressed data
+        let info = signature::inspect(package_path)
+            .map_err(|e| anyhow!("Signature check failed: {}", e))?;
+
+        let mut problems = Vec::new();
+        if manifest.file_hashes.is_none() {
+            problems.push("Manifest has no file_hashes".to_string());
+        } else {
+            let hashes = &info.hashes;
+            for path in &hashes.unhashed {
+                problems.push(format!("{} has no hash in file_hashes", path));
+            }
+            for path in &hashes.mismatched {
+                problems.push(format!("{} does not match its hash", path));
+            }
+            for path in &hashes.missing {
+                problems.push(format!("{} is in file_hashes but not in the package", path));
+            }
+        }
+
+        match info.trust {
+            SignatureTrust::Trusted => info!(
+                "Signed by {}",
+                info.signer.as_deref().unwrap_or("unknown signer")
+            ),
+            SignatureTrust::Unsigned => warn!("Package is not signed"),
+            SignatureTrust::Untrusted | SignatureTrust::UnknownKey => warn!(
+                "Signature is good but not trusted: {}",
+                info.detail.as_deref().unwrap_or("unknown key")
+            ),
+            SignatureTrust::Invalid => problems.push(format!(
+                "Invalid signature: {}",
+                info.detail.as_deref().unwrap_or("bad signature")
+            )),
+        }
+
+        let files: BTreeSet<String> = entries
+            .iter()
+            .filter(|entry| matches!(entry.kind, EntryKind::File | EntryKind::Symlink))
+            .map(|entry| entry.path.to_string_lossy().into_owned())
+            .collect();
+        for path in declared_files(&manifest) {
+            if !files.contains(&path) {
+                problems.push(format!(
+                    "{} is declared in the manifest but not packaged",
+                    path
+                ));
+            }
+        }
+
+        if !problems.is_empty() {
+            bail!(
+                "Package validation failed:\n  - {}",
+                problems.join("\n  - ")
+            );
+        }
+
+        info!(
+            "✓ Package validation passed: {} ({}), {} files verified",
+            manifest.name, manifest.package_version, info.hashes.verified_files
+        );
+        Ok(())
+    }
+}
+
+/// Paths inside the package of the files the manifest refers to
+fn declared_files(manifest: &Manifest) -> Vec<String> {
+    let mut paths: Vec<String> = manifest
+        .executable_entries()
+        .into_iter()
+        .map(|entry| format!("payload/bin/{}", entry))
+        .collect();
+    for hook in ScriptHook::ALL {
+        if let Some(script) = manifest.script(hook) {
+            paths.push(script.path.to_string_lossy().into_owned());
+        }
+    }
+    if manifest.service {
+        for unit in manifest.service_units().iter().chain(&manifest.tmpfiles) {
+            paths.push(format!("services/{}", unit));
+        }
+    }
+    for file in manifest.changelog_file.iter().chain(&manifest.eula) {
+        paths.push(file.to_string_lossy().into_owned());
+    }
+    paths
+}